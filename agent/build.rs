@@ -1,4 +1,5 @@
 /// This generates Device Plugin code (in v1beta1.rs) from pluginapi.proto
+/// and DRA kubelet plugin code (in v1alpha3.rs and pluginregistration.rs) from dra.proto and pluginregistration.proto
 fn main() {
     tonic_build::configure()
         .build_client(true)
@@ -8,4 +9,12 @@ fn main() {
             &["./proto"],
         )
         .expect("failed to compile protos");
+    tonic_build::configure()
+        .build_client(true)
+        .out_dir("./src/dra_plugin_manager")
+        .compile(
+            &["./proto/dra.proto", "./proto/pluginregistration.proto"],
+            &["./proto"],
+        )
+        .expect("failed to compile protos");
 }
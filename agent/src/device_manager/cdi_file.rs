@@ -0,0 +1,199 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use super::{cdi, DeviceManager, InMemoryManager};
+use akri_shared::akri::AKRI_PREFIX;
+use tokio::{sync::watch, task::JoinHandle};
+
+/// Default directory for CDI spec files generated at runtime
+pub const DEFAULT_CDI_DIR: &str = "/var/run/cdi";
+
+/// This device manager resolves devices like the [InMemoryManager] and also persists every
+/// Configuration's CDI kind as a spec file in a CDI directory. This allows container runtimes
+/// to inject the devices by their CDI fully qualified name.
+pub struct CdiFileManager {
+    inner: InMemoryManager,
+}
+
+impl CdiFileManager {
+    /// Creates the manager and spawns the task that keeps the spec files in sync with the discovered devices,
+    /// the task ends when the state sender is dropped.
+    pub fn new(
+        state: watch::Receiver<HashMap<String, cdi::Kind>>,
+        cdi_dir: PathBuf,
+    ) -> (Self, JoinHandle<()>) {
+        let task = tokio::spawn(write_specs(state.clone(), cdi_dir));
+        (
+            Self {
+                inner: InMemoryManager::new(state),
+            },
+            task,
+        )
+    }
+}
+
+impl DeviceManager for CdiFileManager {
+    fn get(&self, fqdn: &str) -> Option<cdi::Device> {
+        self.inner.get(fqdn)
+    }
+
+    fn has_device(&self, fqdn: String) -> bool {
+        self.inner.has_device(fqdn)
+    }
+}
+
+/// Get the spec file path for a CDI kind, following the `<vendor>-<class>.json` naming convention
+fn spec_path(cdi_dir: &Path, kind: &str) -> PathBuf {
+    cdi_dir.join(format!("{}.json", kind.replace('/', "-")))
+}
+
+/// Atomically writes the spec, by writing to a temporary file that is renamed over the previous spec.
+/// The temporary file is ignored by the container runtimes as it doesn't have a spec extension.
+async fn write_spec(cdi_dir: &Path, kind: &cdi::Kind) -> std::io::Result<()> {
+    let path = spec_path(cdi_dir, &kind.kind);
+    let tmp_path = path.with_extension("json.tmp");
+    let content = serde_json::to_vec_pretty(kind)?;
+    tokio::fs::write(&tmp_path, content).await?;
+    tokio::fs::rename(&tmp_path, &path).await
+}
+
+async fn remove_spec(path: &Path) {
+    match tokio::fs::remove_file(path).await {
+        Ok(()) => trace!("remove_spec - removed CDI spec {:?}", path),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => error!("remove_spec - unable to remove CDI spec {:?}: {}", path, e),
+    }
+}
+
+/// Removes the Akri spec files left over by a previous run of the agent
+async fn remove_stale_specs(cdi_dir: &Path) -> std::io::Result<()> {
+    let prefix = format!("{}-", AKRI_PREFIX);
+    let mut entries = tokio::fs::read_dir(cdi_dir).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        if entry.file_name().to_string_lossy().starts_with(&prefix) {
+            remove_spec(&entry.path()).await;
+        }
+    }
+    Ok(())
+}
+
+async fn write_specs(mut state: watch::Receiver<HashMap<String, cdi::Kind>>, cdi_dir: PathBuf) {
+    if let Err(e) = tokio::fs::create_dir_all(&cdi_dir).await {
+        error!(
+            "write_specs - unable to create CDI directory {:?}: {}",
+            cdi_dir, e
+        );
+        return;
+    }
+    if let Err(e) = remove_stale_specs(&cdi_dir).await {
+        warn!("write_specs - unable to clean up CDI directory: {}", e);
+    }
+    let mut written: HashMap<String, cdi::Kind> = HashMap::new();
+    loop {
+        let current = state.borrow_and_update().clone();
+        for (name, kind) in current.iter() {
+            if written.get(name) == Some(kind) {
+                continue;
+            }
+            match write_spec(&cdi_dir, kind).await {
+                Ok(()) => {
+                    trace!("write_specs - wrote CDI spec for {}", name);
+                    written.insert(name.clone(), kind.clone());
+                }
+                Err(e) => error!("write_specs - unable to write CDI spec for {}: {}", name, e),
+            }
+        }
+        let removed: Vec<String> = written
+            .keys()
+            .filter(|name| !current.contains_key(*name))
+            .cloned()
+            .collect();
+        for name in removed {
+            remove_spec(&spec_path(&cdi_dir, &name)).await;
+            written.remove(&name);
+        }
+        if state.changed().await.is_err() {
+            trace!("write_specs - state sender dropped, stop writing CDI specs");
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn kind(name: &str, device: &str) -> cdi::Kind {
+        cdi::Kind {
+            kind: format!("akri.sh/{}", name),
+            annotations: Default::default(),
+            devices: vec![cdi::Device {
+                name: device.to_string(),
+                annotations: Default::default(),
+                container_edits: cdi::ContainerEdit {
+                    env: vec!["FOO=bar".to_string()],
+                    ..Default::default()
+                },
+            }],
+            container_edits: vec![],
+        }
+    }
+
+    fn read_spec(path: &Path) -> cdi::Kind {
+        serde_json::from_slice(&std::fs::read(path).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_spec_path() {
+        assert_eq!(
+            spec_path(Path::new("/var/run/cdi"), "akri.sh/my-config"),
+            PathBuf::from("/var/run/cdi/akri.sh-my-config.json")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_write_specs() {
+        let dir = tempfile::tempdir().unwrap();
+        let cdi_dir = dir.path().join("cdi");
+        std::fs::create_dir_all(&cdi_dir).unwrap();
+        let stale = cdi_dir.join("akri.sh-stale.json");
+        let foreign = cdi_dir.join("vendor.com-device.json");
+        std::fs::write(&stale, "{}").unwrap();
+        std::fs::write(&foreign, "{}").unwrap();
+
+        let (sender, receiver) = watch::channel(HashMap::new());
+        let (manager, task) = CdiFileManager::new(receiver, cdi_dir.clone());
+
+        sender.send_modify(|kinds| {
+            kinds.insert("akri.sh/config-a".to_string(), kind("config-a", "dev-1"));
+        });
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        let spec = cdi_dir.join("akri.sh-config-a.json");
+        assert_eq!(read_spec(&spec), kind("config-a", "dev-1"));
+        assert!(!stale.exists());
+        assert!(foreign.exists());
+        assert!(manager.has_device("akri.sh/config-a=dev-1".to_string()));
+
+        sender.send_modify(|kinds| {
+            kinds.insert("akri.sh/config-a".to_string(), kind("config-a", "dev-2"));
+        });
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert_eq!(read_spec(&spec), kind("config-a", "dev-2"));
+        assert!(!cdi_dir.join("akri.sh-config-a.json.tmp").exists());
+
+        sender.send_modify(|kinds| {
+            kinds.remove("akri.sh/config-a");
+        });
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert!(!spec.exists());
+
+        drop(sender);
+        assert!(tokio::time::timeout(Duration::from_millis(500), task)
+            .await
+            .is_ok());
+    }
+}
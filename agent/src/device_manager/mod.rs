@@ -1,6 +1,8 @@
 pub mod cdi;
+mod cdi_file;
 mod in_memory;
 
+pub use cdi_file::{CdiFileManager, DEFAULT_CDI_DIR};
 pub use in_memory::InMemoryManager;
 
 #[cfg(test)]
@@ -17,22 +17,38 @@
 //! A Discovery Handler's instance/endpoint sends a new list of discovered devices for a Request:
 #![doc=simple_mermaid::mermaid!("diagrams/dh_device.mmd")]
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
 
-use akri_discovery_utils::discovery::v0::{ByteData, Device, DiscoverRequest};
-use akri_shared::akri::configuration::{Configuration, DiscoveryProperty};
+use akri_discovery_utils::discovery::{
+    control::DiscoveryUpdate,
+    v0::{
+        device_health, ByteData, Device, DeviceHealth, DiscoverRequest, DiscoverResponse,
+        DiscoverySchedule,
+    },
+};
+use akri_shared::akri::configuration::{
+    CompiledDeviceSelector, Configuration, DeviceSelector, DiscoveryHandlerInfo, DiscoveryProperty,
+};
 use akri_shared::akri::instance::Instance;
 
-use akri_shared::akri::instance::InstanceSpec;
+use akri_shared::akri::discovery_handler::{
+    capabilities_config_map_key, capabilities_config_map_name, DiscoveryHandlerCapabilities,
+};
+use akri_shared::akri::instance::{
+    HealthState, InstanceHealth, InstanceSpec, InstanceStatus, NodeInstanceStatus,
+};
 use akri_shared::akri::AKRI_PREFIX;
+use akri_shared::k8s::api::Api;
 use async_trait::async_trait;
 use blake2::digest::{Update, VariableOutput};
 use blake2::VarBlake2b;
 use futures::future::select_all;
 use futures::future::try_join_all;
 use futures::FutureExt;
+use futures::StreamExt;
 use itertools::Itertools;
+use k8s_openapi::api::core::v1::ConfigMap;
 use kube::core::ObjectMeta;
 use kube_runtime::reflector::ObjectRef;
 use tokio::select;
@@ -41,7 +57,7 @@ use tokio::sync::watch;
 use tokio::sync::RwLock;
 use tokio::sync::{broadcast, Mutex, Notify};
 
-use super::discovery_property_solver::PropertySolver;
+use super::discovery_property_solver::{watch_property_sources, PropertySolver};
 use super::{DiscoveryError, DiscoveryManagerKubeInterface};
 use crate::device_manager::cdi::ContainerEdit;
 
@@ -89,6 +105,13 @@ impl DiscoveredDevice {
             DiscoveredDevice::SharedDevice(d) => d,
         }
     }
+
+    fn properties(&self) -> &HashMap<String, String> {
+        match self {
+            DiscoveredDevice::LocalDevice(d, _) => &d.properties,
+            DiscoveredDevice::SharedDevice(d) => &d.properties,
+        }
+    }
 }
 
 impl From<DiscoveredDevice> for crate::device_manager::cdi::Device {
@@ -112,18 +135,102 @@ impl From<DiscoveredDevice> for crate::device_manager::cdi::Device {
     }
 }
 
+/// Device list of a discovery, as built from the responses of a Discovery Handler. Responses are either full device
+/// lists, or deltas that get applied to the list once it got a snapshot.
+pub(super) struct DiscoveredDevices {
+    node_name: String,
+    shared: bool,
+    devices: BTreeMap<String, Arc<DiscoveredDevice>>,
+    /// Sequence number of the last applied delta, None when waiting for a snapshot
+    sequence: Option<u64>,
+}
+
+impl DiscoveredDevices {
+    pub(super) fn new(node_name: String, shared: bool) -> Self {
+        DiscoveredDevices {
+            node_name,
+            shared,
+            devices: Default::default(),
+            sequence: None,
+        }
+    }
+
+    fn to_discovered_device(&self, device: Device) -> Arc<DiscoveredDevice> {
+        Arc::new(match self.shared {
+            true => DiscoveredDevice::SharedDevice(device),
+            false => DiscoveredDevice::LocalDevice(device, self.node_name.clone()),
+        })
+    }
+
+    /// Applies a response of the Discovery Handler, returning the new device list if it changed.
+    pub(super) fn apply(
+        &mut self,
+        response: DiscoverResponse,
+    ) -> Option<Vec<Arc<DiscoveredDevice>>> {
+        let Some(delta) = response.delta else {
+            self.sequence = None;
+            return Some(
+                response
+                    .devices
+                    .into_iter()
+                    .map(|d| self.to_discovered_device(d))
+                    .collect(),
+            );
+        };
+        if delta.snapshot {
+            self.devices = response
+                .devices
+                .into_iter()
+                .map(|d| (d.id.clone(), self.to_discovered_device(d)))
+                .collect();
+        } else {
+            match self.sequence {
+                Some(sequence) if delta.sequence == sequence + 1 => {}
+                Some(sequence) => {
+                    warn!(
+                        "Received device delta {} after {}, ignoring deltas until the next snapshot",
+                        delta.sequence, sequence
+                    );
+                    self.sequence = None;
+                    return None;
+                }
+                None => return None,
+            }
+            if response.devices.is_empty() && delta.removed_ids.is_empty() {
+                self.sequence = Some(delta.sequence);
+                return None;
+            }
+            for id in delta.removed_ids.iter() {
+                self.devices.remove(id);
+            }
+            for device in response.devices {
+                let device_id = device.id.clone();
+                let device = self.to_discovered_device(device);
+                self.devices.insert(device_id, device);
+            }
+        }
+        self.sequence = Some(delta.sequence);
+        Some(self.devices.values().cloned().collect())
+    }
+}
+
 /// This trait represents a discovery handler, no matter if it is an embedded or remote one
 #[async_trait]
 #[cfg_attr(test, automock)]
 pub trait DiscoveryHandlerEndpoint: Send + Sync {
+    /// Starts a discovery, the discovered devices get sent through `sender` until all its receivers are dropped.
+    /// Returns a channel to update the running discovery if the endpoint supports it, otherwise the discovery
+    /// has to be queried again to apply any change.
     async fn query(
         &self,
         sender: watch::Sender<Vec<Arc<DiscoveredDevice>>>,
         query_body: DiscoverRequest,
-    ) -> Result<(), DiscoveryError>;
+    ) -> Result<Option<mpsc::Sender<DiscoveryUpdate>>, DiscoveryError>;
 
     fn get_name(&self) -> String;
     fn get_uid(&self) -> String;
+    /// Capabilities advertised by the Discovery Handler when registering
+    fn get_capabilities(&self) -> DiscoveryHandlerCapabilities;
 
     async fn closed(&self);
     fn is_closed(&self) -> bool;
@@ -138,6 +245,10 @@ pub trait DiscoveryHandlerEndpoint: Send + Sync {
 pub trait DiscoveryHandlerRequest: Sync + Send {
     async fn get_instances(&self) -> Result<Vec<Instance>, DiscoveryError>;
     async fn set_extra_device_properties(&self, extra_device_properties: HashMap<String, String>);
+    async fn set_selector(&self, selector: Option<DeviceSelector>);
+    async fn set_discovery_details(&self, discovery_details: String);
+    /// Get the unique identifiers of the Discovery Handler endpoints currently answering the request
+    async fn get_endpoints(&self) -> Vec<String>;
 }
 
 /// This trait is here to help with testing for code that interract with the discovery handler registry
@@ -151,9 +262,7 @@ pub trait DiscoveryHandlerRegistry: Sync + Send {
     async fn new_request(
         &self,
         key: &str,
-        dh_name: &str,
-        dh_details: &str,
-        dh_properties: &[DiscoveryProperty],
+        dh_info: &DiscoveryHandlerInfo,
         extra_device_properties: HashMap<String, String>,
         namespace: &str,
     ) -> Result<(), DiscoveryError>;
@@ -166,31 +275,58 @@ pub trait DiscoveryHandlerRegistry: Sync + Send {
 
     /// Register a new endpoint to make it available to all current and future queries
     async fn register_endpoint(&self, endpoint: Arc<dyn DiscoveryHandlerEndpoint>);
+
+    /// Get the capabilities last advertised by a Discovery Handler with this name
+    async fn get_capabilities(&self, name: &str) -> Option<DiscoveryHandlerCapabilities>;
+}
+
+/// A query running against a single Discovery Handler endpoint
+#[derive(Clone)]
+struct EndpointQuery {
+    /// Unique identifier of the queried endpoint
+    uid: String,
+    devices: watch::Receiver<Vec<Arc<DiscoveredDevice>>>,
+    /// Channel to update the running discovery, None if the endpoint doesn't support it
+    updates: Option<mpsc::Sender<DiscoveryUpdate>>,
 }
 
 /// Real world implementation of the Discovery Handler Request
 struct DHRequestImpl {
-    endpoints: RwLock<Vec<watch::Receiver<Vec<Arc<DiscoveredDevice>>>>>,
+    endpoints: RwLock<Vec<EndpointQuery>>,
     notifier: watch::Sender<crate::device_manager::cdi::Kind>,
     key: String,
     handler_name: String,
-    details: String,
+    details: RwLock<String>,
     properties: Vec<DiscoveryProperty>,
+    schedule: Option<DiscoverySchedule>,
     extra_device_properties: RwLock<HashMap<String, String>>,
+    selector: RwLock<Option<CompiledDeviceSelector>>,
     kube_client: Arc<dyn DiscoveryManagerKubeInterface>,
     termination_notifier: Arc<Notify>,
+    /// Discover request as last sent to the handlers, used to detect changes of the discovery details or
+    /// referenced Secrets/ConfigMaps
+    current_request: RwLock<DiscoverRequest>,
+    /// Asks for the running discovery to be brought up to date with the discovery details and properties
+    refresh_sender: mpsc::Sender<()>,
+    handlers: LockedMap<HashMap<String, Arc<dyn DiscoveryHandlerEndpoint>>>,
 }
 
 #[async_trait]
 impl DiscoveryHandlerRequest for DHRequestImpl {
     async fn get_instances(&self) -> Result<Vec<Instance>, DiscoveryError> {
         let properties = self.extra_device_properties.read().await;
+        let selector = self.selector.read().await;
         Ok(self
             .endpoints
             .read()
             .await
             .iter()
-            .flat_map(|r| r.borrow().clone().into_iter())
+            .flat_map(|r| r.devices.borrow().clone().into_iter())
+            .filter(|d| {
+                selector
+                    .as_ref()
+                    .map_or(true, |s| s.matches(d.properties()))
+            })
             .map(|i| self.device_to_instance(i.as_ref(), &properties))
             .collect())
     }
@@ -207,9 +343,58 @@ impl DiscoveryHandlerRequest for DHRequestImpl {
                 .send_modify(|k| k.container_edits.first_mut().unwrap().env = edit);
         }
     }
+
+    async fn set_selector(&self, selector: Option<DeviceSelector>) {
+        let selector = selector.as_ref().map(DeviceSelector::compile);
+        {
+            let mut current = self.selector.write().await;
+            if selector == *current {
+                return;
+            }
+            *current = selector;
+        }
+        self.notify_devices().await;
+    }
+
+    async fn set_discovery_details(&self, discovery_details: String) {
+        let mut current = self.details.write().await;
+        if discovery_details != *current {
+            *current = discovery_details;
+            // A refresh is already pending if the channel is full
+            let _ = self.refresh_sender.try_send(());
+        }
+    }
+
+    async fn get_endpoints(&self) -> Vec<String> {
+        self.endpoints
+            .read()
+            .await
+            .iter()
+            .map(|e| e.uid.clone())
+            .collect()
+    }
+}
+
+/// Converts the health reported by a Discovery Handler to the one stored in the Instance,
+/// a device that doesn't report any health is considered healthy
+fn get_instance_health(health: Option<&DeviceHealth>) -> InstanceHealth {
+    let Some(health) = health else {
+        return Default::default();
+    };
+    let state = match health.state() {
+        device_health::State::Healthy => HealthState::Healthy,
+        device_health::State::Unhealthy => HealthState::Unhealthy,
+        device_health::State::Degraded => HealthState::Degraded,
+    };
+    InstanceHealth {
+        state,
+        reason: Some(health.reason.clone()).filter(|r| !r.is_empty()),
+    }
 }
 
 impl DHRequestImpl {
+    /// Builds the Instance of a discovered device, its health is reported in a status entry
+    /// whose node is left for the caller to set
     fn device_to_instance(
         &self,
         dev: &DiscoveredDevice,
@@ -235,6 +420,13 @@ impl DHRequestImpl {
                 device_usage: Default::default(),
                 capacity: Default::default(),
             },
+            status: Some(InstanceStatus {
+                nodes: vec![NodeInstanceStatus {
+                    health: get_instance_health(rdev.health.as_ref()),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }),
             metadata: ObjectMeta {
                 name: Some(format!("{}-{}", self.key, dev.device_hash())),
                 ..Default::default()
@@ -249,10 +441,13 @@ impl DHRequestImpl {
     async fn watch_devices(
         &self,
         mut new_dh_receiver: broadcast::Receiver<Arc<dyn DiscoveryHandlerEndpoint>>,
+        mut refresh_receiver: mpsc::Receiver<()>,
     ) {
         loop {
             let mut local_endpoints = self.endpoints.write().await.clone();
-            let futures = local_endpoints.iter_mut().map(|e| e.changed().boxed());
+            let futures = local_endpoints
+                .iter_mut()
+                .map(|e| e.devices.changed().boxed());
             select! {
                 (a, index, _) = select_all(futures) => {
                     if a.is_err() {
@@ -272,51 +467,157 @@ impl DHRequestImpl {
                         self.endpoints.write().await.push(q);
                     }
                 },
+                Some(()) = refresh_receiver.recv() => {
+                    if let Err(e) = self.refresh_discovery().await {
+                        error!("Unable to refresh discovery for {}: {:?}", self.key, e);
+                    }
+                    // Updated or new streams will notify us when the handlers send a new list of devices
+                    continue
+                },
                 _ = self.notifier.closed() => {
                     return;
                 },
             }
-            let devices: Vec<Arc<DiscoveredDevice>> = self
-                .endpoints
-                .write()
-                .await
-                .iter_mut()
-                .flat_map(|r| r.borrow_and_update().clone().into_iter())
-                .unique_by(|d| self.get_device_cdi_fqdn(d))
-                .collect();
-            self.notifier
-                .send_replace(crate::device_manager::cdi::Kind {
-                    kind: format!("{}/{}", AKRI_PREFIX, self.key),
-                    annotations: Default::default(),
-                    devices: devices
-                        .into_iter()
-                        .map(|d| d.as_ref().clone().into())
-                        .collect(),
-                    container_edits: vec![ContainerEdit {
-                        env: self
-                            .extra_device_properties
-                            .read()
-                            .await
-                            .iter()
-                            .map(|(k, v)| format!("{}={}", k, v))
-                            .collect(),
-                        ..Default::default()
-                    }],
-                });
+            self.notify_devices().await;
         }
     }
 
+    /// Sends the CDI kind of the selected devices
+    async fn notify_devices(&self) {
+        let selector = self.selector.read().await;
+        let devices: Vec<Arc<DiscoveredDevice>> = self
+            .endpoints
+            .write()
+            .await
+            .iter_mut()
+            .flat_map(|r| r.devices.borrow_and_update().clone().into_iter())
+            .filter(|d| {
+                selector
+                    .as_ref()
+                    .map_or(true, |s| s.matches(d.properties()))
+            })
+            .unique_by(|d| self.get_device_cdi_fqdn(d))
+            .collect();
+        self.notifier
+            .send_replace(crate::device_manager::cdi::Kind {
+                kind: format!("{}/{}", AKRI_PREFIX, self.key),
+                annotations: Default::default(),
+                devices: devices
+                    .into_iter()
+                    .map(|d| d.as_ref().clone().into())
+                    .collect(),
+                container_edits: vec![ContainerEdit {
+                    env: self
+                        .extra_device_properties
+                        .read()
+                        .await
+                        .iter()
+                        .map(|(k, v)| format!("{}={}", k, v))
+                        .collect(),
+                    ..Default::default()
+                }],
+            });
+    }
+
     async fn query(
         &self,
         discovery_handler: Arc<dyn DiscoveryHandlerEndpoint>,
-    ) -> Result<watch::Receiver<Vec<Arc<DiscoveredDevice>>>, DiscoveryError> {
+    ) -> Result<EndpointQuery, DiscoveryError> {
+        let query_body = self.build_discover_request().await?;
+        self.query_with(discovery_handler, query_body).await
+    }
+
+    async fn query_with(
+        &self,
+        discovery_handler: Arc<dyn DiscoveryHandlerEndpoint>,
+        query_body: DiscoverRequest,
+    ) -> Result<EndpointQuery, DiscoveryError> {
         let (q_sender, q_receiver) = watch::channel(vec![]);
-        let query_body = DiscoverRequest {
-            discovery_details: self.details.clone(),
+        let updates = discovery_handler.query(q_sender, query_body).await?;
+        Ok(EndpointQuery {
+            uid: discovery_handler.get_uid(),
+            devices: q_receiver,
+            updates,
+        })
+    }
+
+    async fn build_discover_request(&self) -> Result<DiscoverRequest, DiscoveryError> {
+        Ok(DiscoverRequest {
+            discovery_details: self.details.read().await.clone(),
             discovery_properties: self.solve_discovery_properties().await?,
-        };
-        discovery_handler.query(q_sender, query_body).await?;
-        Ok(q_receiver)
+            schedule: self.schedule.clone(),
+            accept_deltas: true,
+        })
+    }
+
+    /// Brings the running discovery up to date with the discovery details and properties, if any of them
+    /// changed since the last Discover call.
+    async fn refresh_discovery(&self) -> Result<(), DiscoveryError> {
+        let request = self.build_discover_request().await?;
+        let mut updates = Vec::new();
+        {
+            let mut current = self.current_request.write().await;
+            if request.discovery_details != current.discovery_details {
+                updates.push(DiscoveryUpdate::Details(request.discovery_details.clone()));
+            }
+            if request.discovery_properties != current.discovery_properties {
+                updates.push(DiscoveryUpdate::Properties(
+                    request.discovery_properties.clone(),
+                ));
+            }
+            if updates.is_empty() {
+                return Ok(());
+            }
+            *current = request.clone();
+        }
+        trace!("Discovery of {} changed, updating it", self.key);
+        let res = self.update_discovery(request, updates).await;
+        if res.is_err() {
+            // Forget what was sent so the next refresh tries again
+            *self.current_request.write().await = Default::default();
+        }
+        res
+    }
+
+    /// Sends the updates to the running discovery. The endpoints that can't be updated only support the v0
+    /// protocol, if there is any, the Discover call is re-issued to all registered handlers and the previous
+    /// streams get dropped, which stops the ongoing discovery on the handlers' side.
+    async fn update_discovery(
+        &self,
+        request: DiscoverRequest,
+        updates: Vec<DiscoveryUpdate>,
+    ) -> Result<(), DiscoveryError> {
+        let update_senders: Option<Vec<mpsc::Sender<DiscoveryUpdate>>> = self
+            .endpoints
+            .read()
+            .await
+            .iter()
+            .map(|e| e.updates.clone())
+            .collect();
+        if let Some(update_senders) = update_senders.filter(|u| !u.is_empty()) {
+            for sender in update_senders {
+                for update in updates.iter() {
+                    // A closed channel means the endpoint is gone, it will be removed with its stream
+                    let _ = sender.send(update.clone()).await;
+                }
+            }
+            return Ok(());
+        }
+        let handlers: Vec<Arc<dyn DiscoveryHandlerEndpoint>> = self
+            .handlers
+            .read()
+            .await
+            .get(&self.handler_name)
+            .map(|h| h.values().cloned().collect())
+            .unwrap_or_default();
+        if handlers.is_empty() {
+            return Ok(());
+        }
+        let dh_futures = handlers
+            .into_iter()
+            .map(|handler| self.query_with(handler, request.clone()));
+        *self.endpoints.write().await = try_join_all(dh_futures).await?;
+        Ok(())
     }
 
     async fn solve_discovery_properties(
@@ -334,19 +635,35 @@ impl DHRequestImpl {
     }
 }
 
+/// Builds the schedule sent to Discovery Handlers from the Configuration's discovery interval and timeout,
+/// if any of them is set.
+fn get_discovery_schedule(dh_info: &DiscoveryHandlerInfo) -> Option<DiscoverySchedule> {
+    if dh_info.discovery_interval_seconds.is_none() && dh_info.discovery_timeout_seconds.is_none() {
+        return None;
+    }
+    Some(DiscoverySchedule {
+        interval_seconds: dh_info.discovery_interval_seconds,
+        timeout_seconds: dh_info.discovery_timeout_seconds,
+    })
+}
+
 pub(super) type LockedMap<T> = Arc<RwLock<HashMap<String, T>>>;
 
 pub(super) struct DHRegistryImpl {
+    node_name: String,
     requests: LockedMap<Arc<DHRequestImpl>>,
     handlers: LockedMap<HashMap<String, Arc<dyn DiscoveryHandlerEndpoint>>>,
     endpoint_notifier: broadcast::Sender<Arc<dyn DiscoveryHandlerEndpoint>>,
     configuration_notifier: mpsc::Sender<ObjectRef<Configuration>>,
     cdi_notifier: Arc<Mutex<watch::Sender<HashMap<String, crate::device_manager::cdi::Kind>>>>,
     kube_client: Arc<dyn DiscoveryManagerKubeInterface>,
+    /// Capabilities last advertised by the Discovery Handlers, by name
+    capabilities: LockedMap<DiscoveryHandlerCapabilities>,
 }
 
 impl DHRegistryImpl {
     pub(super) fn new(
+        node_name: String,
         kube_client: Arc<dyn DiscoveryManagerKubeInterface>,
         cdi_notifier: watch::Sender<HashMap<String, crate::device_manager::cdi::Kind>>,
         configuration_notifier: mpsc::Sender<ObjectRef<Configuration>>,
@@ -354,12 +671,49 @@ impl DHRegistryImpl {
         let (endpoint_notifier, _) = broadcast::channel(10);
 
         Self {
+            node_name,
             requests: Default::default(),
             handlers: Default::default(),
             endpoint_notifier,
             configuration_notifier,
             cdi_notifier: Arc::new(Mutex::new(cdi_notifier)),
             kube_client,
+            capabilities: Default::default(),
+        }
+    }
+
+    /// Stores the capabilities advertised by a Discovery Handler, and publishes them under the node's key of a
+    /// ConfigMap of the Agent's namespace when they changed, so that the webhook can validate Configurations
+    /// against them. The node's own field manager is used, so that the Agents don't take each other's keys over.
+    async fn update_capabilities(&self, name: &str, capabilities: DiscoveryHandlerCapabilities) {
+        let previous = self
+            .capabilities
+            .write()
+            .await
+            .insert(name.to_string(), capabilities.clone());
+        if previous.as_ref() == Some(&capabilities)
+            || capabilities == DiscoveryHandlerCapabilities::default()
+        {
+            return;
+        }
+        let config_map = ConfigMap {
+            metadata: ObjectMeta {
+                name: Some(capabilities_config_map_name(name)),
+                ..Default::default()
+            },
+            data: Some(BTreeMap::from([(
+                capabilities_config_map_key(&self.node_name),
+                serde_json::to_string(&capabilities).unwrap(),
+            )])),
+            ..Default::default()
+        };
+        let api: Box<dyn Api<ConfigMap>> = self.kube_client.default_namespaced();
+        let field_manager = format!("akri-agent-{}", self.node_name);
+        if let Err(e) = api.apply(config_map, &field_manager).await {
+            warn!(
+                "Failed to publish the capabilities of discovery handler {}: {}",
+                name, e
+            );
         }
     }
 }
@@ -409,33 +763,40 @@ impl DiscoveryHandlerRegistry for DHRegistryImpl {
     async fn new_request(
         &self,
         key: &str,
-        dh_name: &str,
-        dh_details: &str,
-        dh_properties: &[DiscoveryProperty],
+        dh_info: &DiscoveryHandlerInfo,
         extra_device_properties: HashMap<String, String>,
         namespace: &str,
     ) -> Result<(), DiscoveryError> {
+        let dh_name = &dh_info.name;
         match self.handlers.read().await.get(dh_name) {
             Some(handlers) => {
                 let (notifier, _) = watch::channel(Default::default());
                 let terminated = Arc::new(Notify::new());
+                // Only one pending refresh is needed, as it will solve all properties at once
+                let (refresh_sender, refresh_receiver) = mpsc::channel(1);
                 let mut dh_req = DHRequestImpl {
                     endpoints: Default::default(),
                     notifier,
                     key: key.to_string(),
                     handler_name: dh_name.to_string(),
-                    details: dh_details.to_string(),
-                    properties: dh_properties.to_vec(),
+                    details: RwLock::new(dh_info.discovery_details.clone()),
+                    properties: dh_info.discovery_properties.clone().unwrap_or_default(),
+                    schedule: get_discovery_schedule(dh_info),
                     extra_device_properties: RwLock::new(extra_device_properties),
+                    selector: Default::default(),
                     kube_client: self.kube_client.clone(),
                     termination_notifier: terminated.clone(),
+                    current_request: Default::default(),
+                    refresh_sender: refresh_sender.clone(),
+                    handlers: self.handlers.clone(),
                 };
+                let query_body = dh_req.build_discover_request().await?;
                 let dh_futures = handlers
                     .iter()
-                    .map(|(_, handler)| dh_req.query(handler.clone()));
-                let dh_streams: Vec<watch::Receiver<Vec<Arc<DiscoveredDevice>>>> =
-                    try_join_all(dh_futures).await?;
+                    .map(|(_, handler)| dh_req.query_with(handler.clone(), query_body.clone()));
+                let dh_streams: Vec<EndpointQuery> = try_join_all(dh_futures).await?;
                 dh_req.endpoints = RwLock::new(dh_streams);
+                dh_req.current_request = RwLock::new(query_body);
                 {
                     let mut req_w = self.requests.write().await;
                     req_w.insert(key.to_string(), Arc::new(dh_req));
@@ -464,13 +825,28 @@ impl DiscoveryHandlerRegistry for DHRegistryImpl {
                     .await
                 });
 
+                if let Some(mut changes) =
+                    watch_property_sources(&dh_req_ref.properties, self.kube_client.as_ref())
+                {
+                    tokio::spawn(async move {
+                        loop {
+                            select! {
+                                Some(()) = changes.next() => {
+                                    let _ = refresh_sender.try_send(());
+                                },
+                                _ = refresh_sender.closed() => return,
+                            }
+                        }
+                    });
+                }
+
                 let local_key = key.to_owned();
                 let notifier_receiver = self.endpoint_notifier.subscribe();
                 let local_req = self.requests.clone();
                 tokio::spawn(async move {
                     select! {
                         _ = dh_req_ref
-                        .watch_devices(notifier_receiver) => {},
+                        .watch_devices(notifier_receiver, refresh_receiver) => {},
                         _ = terminated.notified() => {},
                     }
                     local_req.write().await.remove(&local_key);
@@ -498,6 +874,8 @@ impl DiscoveryHandlerRegistry for DHRegistryImpl {
     async fn register_endpoint(&self, endpoint: Arc<dyn DiscoveryHandlerEndpoint>) {
         let name = endpoint.get_name();
         let uid = endpoint.get_uid();
+        self.update_capabilities(&name, endpoint.get_capabilities())
+            .await;
         let _ = self.endpoint_notifier.send(endpoint.clone());
         {
             let mut w_handlers = self.handlers.write().await;
@@ -530,6 +908,10 @@ impl DiscoveryHandlerRegistry for DHRegistryImpl {
             }
         });
     }
+
+    async fn get_capabilities(&self, name: &str) -> Option<DiscoveryHandlerCapabilities> {
+        self.capabilities.read().await.get(name).cloned()
+    }
 }
 
 #[cfg(test)]
@@ -541,6 +923,12 @@ mod tests {
         discovery_handler_manager::mock::MockDiscoveryManagerKubeInterface,
     };
     use akri_discovery_utils::discovery::v0 as discovery_utils;
+    use akri_shared::akri::configuration::{
+        DeviceSelectorOperator, DeviceSelectorRequirement, DiscoveryPropertyKeySelector,
+        DiscoveryPropertySource,
+    };
+    use akri_shared::akri::discovery_handler::DiscoveryHandlerFeature;
+    use akri_shared::k8s::api::MockApi;
 
     use super::*;
 
@@ -552,6 +940,7 @@ mod tests {
                 properties: Default::default(),
                 mounts: Default::default(),
                 device_specs: Default::default(),
+                health: None,
             },
             "my_node".to_owned(),
         );
@@ -561,6 +950,7 @@ mod tests {
                 properties: Default::default(),
                 mounts: Default::default(),
                 device_specs: Default::default(),
+                health: None,
             },
             "my_other_node".to_owned(),
         );
@@ -577,6 +967,7 @@ mod tests {
                 host_path: "host".to_owned(),
                 permissions: "perms".to_owned(),
             }],
+            health: None,
         });
 
         assert_eq!(
@@ -630,6 +1021,128 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_discovered_devices_apply() {
+        let device = |id: &str, value: &str| Device {
+            id: id.to_owned(),
+            properties: HashMap::from([("KEY".to_owned(), value.to_owned())]),
+            ..Default::default()
+        };
+        let delta = |sequence: u64, snapshot: bool, removed_ids: Vec<&str>| {
+            Some(discovery_utils::DevicesDelta {
+                sequence,
+                snapshot,
+                removed_ids: removed_ids.into_iter().map(String::from).collect(),
+            })
+        };
+        let ids = |devices: Vec<Arc<DiscoveredDevice>>| {
+            devices
+                .iter()
+                .map(|d| {
+                    let d = d.as_ref().clone().inner();
+                    format!("{}={}", d.id, d.properties["KEY"])
+                })
+                .collect::<Vec<String>>()
+        };
+        let mut devices = DiscoveredDevices::new("node-a".to_owned(), true);
+
+        // Deltas are ignored until a snapshot is received
+        assert!(devices
+            .apply(DiscoverResponse {
+                devices: vec![device("a", "1")],
+                delta: delta(1, false, vec![]),
+            })
+            .is_none());
+        let snapshot = devices.apply(DiscoverResponse {
+            devices: vec![device("b", "1"), device("a", "1")],
+            delta: delta(2, true, vec![]),
+        });
+        assert_eq!(ids(snapshot.unwrap()), vec!["a=1", "b=1"]);
+        let updated = devices.apply(DiscoverResponse {
+            devices: vec![device("b", "2"), device("c", "1")],
+            delta: delta(3, false, vec!["a"]),
+        });
+        assert_eq!(ids(updated.unwrap()), vec!["b=2", "c=1"]);
+        assert!(devices
+            .apply(DiscoverResponse {
+                devices: vec![],
+                delta: delta(4, false, vec![]),
+            })
+            .is_none());
+
+        // A gap in the sequence makes the list out of date until the next snapshot
+        assert!(devices
+            .apply(DiscoverResponse {
+                devices: vec![device("d", "1")],
+                delta: delta(6, false, vec![]),
+            })
+            .is_none());
+        assert!(devices
+            .apply(DiscoverResponse {
+                devices: vec![device("d", "1")],
+                delta: delta(7, false, vec![]),
+            })
+            .is_none());
+        let snapshot = devices.apply(DiscoverResponse {
+            devices: vec![device("d", "1")],
+            delta: delta(8, true, vec![]),
+        });
+        assert_eq!(ids(snapshot.unwrap()), vec!["d=1"]);
+
+        // Full device lists are used as is
+        let full = devices.apply(DiscoverResponse {
+            devices: vec![device("f", "1"), device("e", "1")],
+            delta: None,
+        });
+        assert_eq!(ids(full.unwrap()), vec!["f=1", "e=1"]);
+    }
+
+    #[test]
+    fn test_get_discovery_schedule() {
+        let mut dh_info = DiscoveryHandlerInfo {
+            name: "mock_handler".to_string(),
+            discovery_details: String::new(),
+            discovery_properties: None,
+            discovery_interval_seconds: None,
+            discovery_timeout_seconds: None,
+        };
+        assert_eq!(get_discovery_schedule(&dh_info), None);
+
+        dh_info.discovery_timeout_seconds = Some(5);
+        assert_eq!(
+            get_discovery_schedule(&dh_info),
+            Some(DiscoverySchedule {
+                interval_seconds: None,
+                timeout_seconds: Some(5),
+            })
+        );
+    }
+
+    #[test]
+    fn test_get_instance_health() {
+        assert_eq!(get_instance_health(None), InstanceHealth::default());
+        assert_eq!(
+            get_instance_health(Some(&DeviceHealth {
+                state: device_health::State::Degraded.into(),
+                reason: String::new(),
+            })),
+            InstanceHealth {
+                state: HealthState::Degraded,
+                reason: None,
+            }
+        );
+        assert_eq!(
+            get_instance_health(Some(&DeviceHealth {
+                state: device_health::State::Unhealthy.into(),
+                reason: "not answering".to_string(),
+            })),
+            InstanceHealth {
+                state: HealthState::Unhealthy,
+                reason: Some("not answering".to_string()),
+            }
+        );
+    }
+
     #[tokio::test]
     async fn test_dh_request_impl_get_instances() {
         let (_, notifier) = watch::channel(vec![Arc::new(DiscoveredDevice::LocalDevice(
@@ -641,10 +1154,15 @@ mod tests {
                 )]),
                 mounts: Default::default(),
                 device_specs: Default::default(),
+                health: None,
             },
             "my_node".to_owned(),
         ))]);
-        let endpoints = RwLock::new(vec![notifier]);
+        let endpoints = RwLock::new(vec![EndpointQuery {
+            uid: "mock_handler_local".to_owned(),
+            devices: notifier,
+            updates: None,
+        }]);
         let (cdi_notifier, _) = watch::channel(Default::default());
         let req = DHRequestImpl {
             endpoints,
@@ -653,12 +1171,17 @@ mod tests {
             handler_name: "mock_handler".to_string(),
             details: Default::default(),
             properties: Default::default(),
+            schedule: None,
             extra_device_properties: RwLock::new(HashMap::from([(
                 "MY_EXTRA_KEY".to_owned(),
                 "value".to_owned(),
             )])),
+            selector: Default::default(),
             kube_client: Arc::new(MockDiscoveryManagerKubeInterface::new()),
             termination_notifier: Arc::new(Notify::new()),
+            current_request: Default::default(),
+            refresh_sender: mpsc::channel(1).0,
+            handlers: Default::default(),
         };
 
         assert_eq!(
@@ -679,38 +1202,118 @@ mod tests {
                     shared: false,
                     nodes: Default::default(),
                     device_usage: Default::default(),
-                }
+                },
+                status: Some(InstanceStatus {
+                    nodes: vec![Default::default()],
+                    ..Default::default()
+                }),
             }]
         );
     }
 
+    #[tokio::test]
+    async fn test_dh_request_impl_get_instances_selector() {
+        let device = |id: &str, mac: &str| {
+            Arc::new(DiscoveredDevice::SharedDevice(Device {
+                id: id.to_owned(),
+                properties: HashMap::from([(
+                    "ONVIF_DEVICE_MAC_ADDRESS".to_owned(),
+                    mac.to_owned(),
+                )]),
+                mounts: Default::default(),
+                device_specs: Default::default(),
+                health: None,
+            }))
+        };
+        let (_, notifier) = watch::channel(vec![
+            device("device_a", "00:11:22:33:44:55"),
+            device("device_b", "66:77:88:99:aa:bb"),
+        ]);
+        let (cdi_notifier, cdi_receiver) = watch::channel(Default::default());
+        let req = DHRequestImpl {
+            endpoints: RwLock::new(vec![EndpointQuery {
+                uid: "mock_handler_local".to_owned(),
+                devices: notifier,
+                updates: None,
+            }]),
+            notifier: cdi_notifier,
+            key: "my_config".to_owned(),
+            handler_name: "mock_handler".to_string(),
+            details: Default::default(),
+            properties: Default::default(),
+            schedule: None,
+            extra_device_properties: Default::default(),
+            selector: Default::default(),
+            kube_client: Arc::new(MockDiscoveryManagerKubeInterface::new()),
+            termination_notifier: Arc::new(Notify::new()),
+            current_request: Default::default(),
+            refresh_sender: mpsc::channel(1).0,
+            handlers: Default::default(),
+        };
+        assert_eq!(req.get_instances().await.unwrap().len(), 2);
+
+        req.set_selector(Some(DeviceSelector {
+            match_expressions: vec![DeviceSelectorRequirement {
+                key: "ONVIF_DEVICE_MAC_ADDRESS".to_string(),
+                operator: DeviceSelectorOperator::Matches,
+                values: vec!["^00:11:".to_string()],
+            }],
+        }))
+        .await;
+        let instances = req.get_instances().await.unwrap();
+        assert_eq!(instances.len(), 1);
+        assert_eq!(
+            instances[0].spec.broker_properties["ONVIF_DEVICE_MAC_ADDRESS"],
+            "00:11:22:33:44:55"
+        );
+        // The CDI kind only holds the selected devices
+        assert_eq!(cdi_receiver.borrow().devices.len(), 1);
+
+        req.set_selector(None).await;
+        assert_eq!(req.get_instances().await.unwrap().len(), 2);
+        assert_eq!(cdi_receiver.borrow().devices.len(), 2);
+    }
+
     #[tokio::test]
     async fn test_dh_request_impl_watch_devices() {
         let (notifier, mut n_rec) = watch::channel(Default::default());
         let (dh_send, dh_rec) = watch::channel(Default::default());
         let req = Arc::new(DHRequestImpl {
-            endpoints: RwLock::new(vec![dh_rec]),
+            endpoints: RwLock::new(vec![EndpointQuery {
+                uid: "mock_handler_local".to_owned(),
+                devices: dh_rec,
+                updates: None,
+            }]),
             notifier,
             key: "my_config".to_owned(),
             handler_name: "mock_handler".to_string(),
-            details: "discovery details".to_string(),
+            details: RwLock::new("discovery details".to_string()),
             properties: vec![DiscoveryProperty {
                 name: "property_1".to_string(),
                 value: Some("value_1".to_string()),
                 value_from: None,
             }],
+            schedule: Some(DiscoverySchedule {
+                interval_seconds: Some(30),
+                timeout_seconds: None,
+            }),
             extra_device_properties: RwLock::new(HashMap::from([(
                 "MY_EXTRA_KEY".to_owned(),
                 "value".to_owned(),
             )])),
+            selector: Default::default(),
             kube_client: Arc::new(MockDiscoveryManagerKubeInterface::new()),
             termination_notifier: Arc::new(Notify::new()),
+            current_request: Default::default(),
+            refresh_sender: mpsc::channel(1).0,
+            handlers: Default::default(),
         });
         let req_ref = req.clone();
 
         let (new_dh_sen, rec) = broadcast::channel(1);
+        let (_refresh_sender, refresh_receiver) = mpsc::channel(1);
 
-        let task = tokio::spawn(async move { req_ref.watch_devices(rec).await });
+        let task = tokio::spawn(async move { req_ref.watch_devices(rec, refresh_receiver).await });
         assert!(n_rec.borrow_and_update().devices.is_empty());
 
         let new_device = Arc::new(DiscoveredDevice::SharedDevice(Device {
@@ -718,6 +1321,7 @@ mod tests {
             properties: HashMap::from([("ENV_KEY".to_owned(), "env_value".to_owned())]),
             mounts: vec![],
             device_specs: vec![],
+            health: None,
         }));
         dh_send.send(vec![new_device.clone()]).unwrap();
 
@@ -733,6 +1337,9 @@ mod tests {
         new_dh
             .expect_get_name()
             .returning(|| "mock_handler".to_string());
+        new_dh
+            .expect_get_uid()
+            .returning(|| "mock_handler_new".to_string());
         new_dh
             .expect_query()
             .with(
@@ -745,11 +1352,16 @@ mod tests {
                             vec: Some(b"value_1".to_vec()),
                         },
                     )]),
+                    schedule: Some(DiscoverySchedule {
+                        interval_seconds: Some(30),
+                        timeout_seconds: None,
+                    }),
+                    accept_deltas: true,
                 }),
             )
             .returning(move |s, _| {
                 senders_vec.lock().unwrap().push(s);
-                async { Ok(()) }.boxed()
+                async { Ok(None) }.boxed()
             });
         assert!(new_dh_sen.send(Arc::new(new_dh)).is_ok());
         tokio::time::sleep(Duration::from_millis(500)).await;
@@ -761,12 +1373,197 @@ mod tests {
         assert!(task.await.is_ok())
     }
 
+    #[tokio::test]
+    async fn test_dh_request_impl_refresh_discovery() {
+        let secret_values = Arc::new(std::sync::Mutex::new(vec!["value_2", "value_2", "value_1"]));
+        let mut kube_client = MockDiscoveryManagerKubeInterface::new();
+        kube_client
+            .secret
+            .expect_namespaced()
+            .with(mockall::predicate::eq("namespace"))
+            .returning(move |_| {
+                let value = secret_values.lock().unwrap().pop().unwrap();
+                let mut api = akri_shared::k8s::api::MockApi::new();
+                api.expect_get()
+                    .with(mockall::predicate::eq("my-secret"))
+                    .return_once(move |_| {
+                        Ok(Some(k8s_openapi::api::core::v1::Secret {
+                            data: Some(std::collections::BTreeMap::from([(
+                                "key".to_owned(),
+                                k8s_openapi::ByteString(value.into()),
+                            )])),
+                            ..Default::default()
+                        }))
+                    });
+                Box::new(api)
+            });
+
+        let queried_values = Arc::new(std::sync::Mutex::new(vec![]));
+        let dh_senders = Arc::new(std::sync::Mutex::new(vec![]));
+        let mut endpoint = MockDiscoveryHandlerEndpoint::new();
+        endpoint.expect_get_uid().return_const("mock_handler_local");
+        let local_queried_values = queried_values.clone();
+        let local_senders = dh_senders.clone();
+        endpoint.expect_query().returning(move |s, q| {
+            local_queried_values
+                .lock()
+                .unwrap()
+                .push(q.discovery_properties["property_1"].clone());
+            local_senders.lock().unwrap().push(s);
+            async { Ok(None) }.boxed()
+        });
+        let endpoint: Arc<dyn DiscoveryHandlerEndpoint> = Arc::new(endpoint);
+
+        let (notifier, _) = watch::channel(Default::default());
+        let req = DHRequestImpl {
+            endpoints: Default::default(),
+            notifier,
+            key: "my_config".to_owned(),
+            handler_name: "mock_handler".to_string(),
+            details: RwLock::new("discovery details".to_string()),
+            properties: vec![DiscoveryProperty {
+                name: "property_1".to_string(),
+                value: None,
+                value_from: Some(DiscoveryPropertySource::SecretKeyRef(
+                    DiscoveryPropertyKeySelector {
+                        key: "key".to_owned(),
+                        name: "my-secret".to_owned(),
+                        namespace: "namespace".to_owned(),
+                        optional: None,
+                    },
+                )),
+            }],
+            schedule: None,
+            extra_device_properties: Default::default(),
+            selector: Default::default(),
+            kube_client: Arc::new(kube_client),
+            termination_notifier: Arc::new(Notify::new()),
+            current_request: Default::default(),
+            refresh_sender: mpsc::channel(1).0,
+            handlers: Arc::new(RwLock::new(HashMap::from([(
+                "mock_handler".to_owned(),
+                HashMap::from([("mock_handler_local".to_owned(), endpoint.clone())]),
+            )]))),
+        };
+        let first_request = req.build_discover_request().await.unwrap();
+        let first_query = req
+            .query_with(endpoint, first_request.clone())
+            .await
+            .unwrap();
+        *req.endpoints.write().await = vec![first_query];
+        *req.current_request.write().await = first_request;
+
+        // The secret changed, the request gets re-issued and the previous stream dropped
+        req.refresh_discovery().await.unwrap();
+        assert_eq!(req.endpoints.read().await.len(), 1);
+        assert!(dh_senders.lock().unwrap()[0].is_closed());
+        assert!(!dh_senders.lock().unwrap()[1].is_closed());
+
+        // Nothing changed, nothing gets re-issued
+        req.refresh_discovery().await.unwrap();
+        assert_eq!(dh_senders.lock().unwrap().len(), 2);
+        assert_eq!(
+            *queried_values.lock().unwrap(),
+            vec![
+                ByteData {
+                    vec: Some(b"value_1".to_vec())
+                },
+                ByteData {
+                    vec: Some(b"value_2".to_vec())
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_dh_request_impl_update_discovery() {
+        let mut endpoint = MockDiscoveryHandlerEndpoint::new();
+        endpoint.expect_get_uid().return_const("mock_handler_local");
+        let (update_sender, mut update_receiver) = mpsc::channel(4);
+        let dh_senders = Arc::new(std::sync::Mutex::new(vec![]));
+        let local_senders = dh_senders.clone();
+        endpoint.expect_query().once().return_once(move |s, _| {
+            local_senders.lock().unwrap().push(s);
+            async move { Ok(Some(update_sender)) }.boxed()
+        });
+        let endpoint: Arc<dyn DiscoveryHandlerEndpoint> = Arc::new(endpoint);
+
+        let (notifier, _) = watch::channel(Default::default());
+        let (refresh_sender, mut refresh_receiver) = mpsc::channel(1);
+        let req = DHRequestImpl {
+            endpoints: Default::default(),
+            notifier,
+            key: "my_config".to_owned(),
+            handler_name: "mock_handler".to_string(),
+            details: RwLock::new("discovery details".to_string()),
+            properties: Default::default(),
+            schedule: None,
+            extra_device_properties: Default::default(),
+            selector: Default::default(),
+            kube_client: Arc::new(MockDiscoveryManagerKubeInterface::new()),
+            termination_notifier: Arc::new(Notify::new()),
+            current_request: Default::default(),
+            refresh_sender,
+            handlers: Arc::new(RwLock::new(HashMap::from([(
+                "mock_handler".to_owned(),
+                HashMap::from([("mock_handler_local".to_owned(), endpoint.clone())]),
+            )]))),
+        };
+        let first_request = req.build_discover_request().await.unwrap();
+        let first_query = req
+            .query_with(endpoint, first_request.clone())
+            .await
+            .unwrap();
+        *req.endpoints.write().await = vec![first_query];
+        *req.current_request.write().await = first_request;
+
+        // Same details, nothing to refresh
+        req.set_discovery_details("discovery details".to_string())
+            .await;
+        assert!(refresh_receiver.try_recv().is_err());
+
+        // The endpoint supports updates, the running discovery gets updated instead of queried again
+        req.set_discovery_details("new details".to_string()).await;
+        assert!(refresh_receiver.try_recv().is_ok());
+        req.refresh_discovery().await.unwrap();
+        assert_eq!(
+            update_receiver.try_recv().unwrap(),
+            DiscoveryUpdate::Details("new details".to_string())
+        );
+        assert!(update_receiver.try_recv().is_err());
+        assert_eq!(
+            req.current_request.read().await.discovery_details,
+            "new details"
+        );
+        assert!(!dh_senders.lock().unwrap()[0].is_closed());
+    }
+
     #[tokio::test]
     async fn test_dh_reg_register_endpoint() {
         let (cdi_notifier, _) = watch::channel(Default::default());
         let (configuration_notifier, _) = mpsc::channel(2);
+        let capabilities = DiscoveryHandlerCapabilities::new("1.0.0")
+            .with_feature(DiscoveryHandlerFeature::DeltaUpdates);
+        let expected_data = serde_json::to_string(&capabilities).unwrap();
+        // The capabilities are only published once, as the second endpoint advertises the same ones
+        let mut config_map_api = MockApi::<ConfigMap>::new();
+        config_map_api
+            .expect_apply()
+            .once()
+            .withf(move |cm, field_manager| {
+                cm.metadata.name.as_deref() == Some("akri-dh-capabilities-mock-handler")
+                    && cm.data.as_ref().unwrap()["node-a.capabilities.json"] == expected_data
+                    && field_manager == "akri-agent-node-a"
+            })
+            .returning(|cm, _| Ok(cm));
+        let mut kube_client = MockDiscoveryManagerKubeInterface::new();
+        kube_client
+            .config
+            .expect_default_namespaced()
+            .return_once(|| Box::new(config_map_api));
         let dh_reg = DHRegistryImpl::new(
-            Arc::new(MockDiscoveryManagerKubeInterface::new()),
+            "node-a".to_owned(),
+            Arc::new(kube_client),
             cdi_notifier,
             configuration_notifier,
         );
@@ -774,6 +1571,9 @@ mod tests {
         let (close_1, closed) = tokio::sync::oneshot::channel::<()>();
         endpoint.expect_get_name().return_const("mock_handler");
         endpoint.expect_get_uid().return_const("mock_handler_local");
+        endpoint
+            .expect_get_capabilities()
+            .return_const(capabilities.clone());
         endpoint.expect_closed().return_once(|| {
             Box::pin(async {
                 let _ = closed.await;
@@ -796,6 +1596,9 @@ mod tests {
         endpoint
             .expect_get_uid()
             .return_const("mock_handler_local_2");
+        endpoint
+            .expect_get_capabilities()
+            .return_const(capabilities.clone());
         endpoint.expect_closed().return_once(|| {
             Box::pin(async {
                 let _ = closed.await;
@@ -812,6 +1615,10 @@ mod tests {
             .unwrap()
             .get("mock_handler_local_2")
             .is_some());
+        assert_eq!(
+            dh_reg.get_capabilities("mock_handler").await,
+            Some(capabilities)
+        );
 
         close_1.send(()).unwrap();
         tokio::time::sleep(Duration::from_millis(500)).await;
@@ -842,7 +1649,12 @@ mod tests {
         let (cdi_notifier, _) = watch::channel(Default::default());
         let (configuration_notifier, _) = mpsc::channel(2);
         let kube_client = Arc::new(MockDiscoveryManagerKubeInterface::new());
-        let dh_reg = DHRegistryImpl::new(kube_client.clone(), cdi_notifier, configuration_notifier);
+        let dh_reg = DHRegistryImpl::new(
+            "node-a".to_owned(),
+            kube_client.clone(),
+            cdi_notifier,
+            configuration_notifier,
+        );
         let (req_not, _) = watch::channel(Default::default());
         let request = Arc::new(DHRequestImpl {
             endpoints: Default::default(),
@@ -851,9 +1663,14 @@ mod tests {
             handler_name: Default::default(),
             details: Default::default(),
             properties: Default::default(),
+            schedule: None,
             extra_device_properties: Default::default(),
+            selector: Default::default(),
             kube_client,
             termination_notifier: Arc::new(Notify::new()),
+            current_request: Default::default(),
+            refresh_sender: mpsc::channel(1).0,
+            handlers: Default::default(),
         });
         dh_reg
             .requests
@@ -883,14 +1700,23 @@ mod tests {
         let (cdi_notifier, mut cdi_rec) = watch::channel(Default::default());
         let (configuration_notifier, mut config_rec) = mpsc::channel(2);
         let kube_client = Arc::new(MockDiscoveryManagerKubeInterface::new());
-        let dh_reg = DHRegistryImpl::new(kube_client.clone(), cdi_notifier, configuration_notifier);
+        let dh_reg = DHRegistryImpl::new(
+            "node-a".to_owned(),
+            kube_client.clone(),
+            cdi_notifier,
+            configuration_notifier,
+        );
 
         assert!(dh_reg
             .new_request(
                 "my-config",
-                "mock_handler",
-                "discovery details",
-                &[],
+                &DiscoveryHandlerInfo {
+                    name: "mock_handler".to_string(),
+                    discovery_details: "discovery details".to_string(),
+                    discovery_properties: None,
+                    discovery_interval_seconds: None,
+                    discovery_timeout_seconds: None,
+                },
                 HashMap::from([]),
                 "namespace"
             )
@@ -908,6 +1734,9 @@ mod tests {
         let local_senders = dev_senders.clone();
         endpoint.expect_get_name().return_const("mock_handler");
         endpoint.expect_get_uid().return_const("mock_handler_local");
+        endpoint
+            .expect_get_capabilities()
+            .returning(Default::default);
         endpoint.expect_closed().return_once(|| {
             Box::pin(async {
                 let _ = closed.await;
@@ -916,7 +1745,7 @@ mod tests {
         endpoint.expect_is_closed().return_const(true);
         endpoint.expect_query().returning(move |s, _| {
             local_senders.lock().unwrap().push(s);
-            async { Ok(()) }.boxed()
+            async { Ok(None) }.boxed()
         });
         dh_reg.register_endpoint(Arc::new(endpoint)).await;
         let mut endpoint = MockDiscoveryHandlerEndpoint::new();
@@ -926,6 +1755,9 @@ mod tests {
         endpoint
             .expect_get_uid()
             .return_const("mock_handler_local_2");
+        endpoint
+            .expect_get_capabilities()
+            .returning(Default::default);
         endpoint.expect_closed().return_once(|| {
             Box::pin(async {
                 let _ = closed.await;
@@ -934,16 +1766,20 @@ mod tests {
         endpoint.expect_is_closed().return_const(true);
         endpoint.expect_query().returning(move |s, _| {
             local_senders.lock().unwrap().push(s);
-            async { Ok(()) }.boxed()
+            async { Ok(None) }.boxed()
         });
         dh_reg.register_endpoint(Arc::new(endpoint)).await;
 
         assert!(dh_reg
             .new_request(
                 "my-config",
-                "mock_handler",
-                "discovery details",
-                &[],
+                &DiscoveryHandlerInfo {
+                    name: "mock_handler".to_string(),
+                    discovery_details: "discovery details".to_string(),
+                    discovery_properties: None,
+                    discovery_interval_seconds: None,
+                    discovery_timeout_seconds: None,
+                },
                 HashMap::from([]),
                 "namespace"
             )
@@ -963,6 +1799,7 @@ mod tests {
                 properties: Default::default(),
                 mounts: Default::default(),
                 device_specs: Default::default(),
+                health: None,
             }))])
             .unwrap();
         tokio::time::sleep(Duration::from_millis(500)).await;
@@ -981,6 +1818,7 @@ mod tests {
                 properties: Default::default(),
                 mounts: Default::default(),
                 device_specs: Default::default(),
+                health: None,
             }))])
             .unwrap();
         tokio::time::sleep(Duration::from_millis(500)).await;
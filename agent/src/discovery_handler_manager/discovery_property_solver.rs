@@ -2,8 +2,15 @@ use akri_discovery_utils::discovery::v0::ByteData;
 use akri_shared::akri::configuration::{
     DiscoveryProperty, DiscoveryPropertyKeySelector, DiscoveryPropertySource,
 };
+use akri_shared::k8s::api::Api;
 use async_trait::async_trait;
+use futures::stream::{select_all, BoxStream};
+use futures::StreamExt;
 use k8s_openapi::api::core::v1::{ConfigMap, Secret};
+use kube::Resource;
+use kube_runtime::{watcher, WatchStreamExt};
+use serde::de::DeserializeOwned;
+use std::fmt::Debug;
 use std::sync::Arc;
 
 use super::{DiscoveryError, DiscoveryManagerKubeInterface};
@@ -42,6 +49,55 @@ impl PropertySolver for DiscoveryProperty {
     }
 }
 
+/// Watches the Secrets and ConfigMaps referenced by the given discovery properties. The returned stream yields
+/// every time one of them gets (re)listed, created, modified or deleted, it is then up to the caller to solve
+/// the properties again and check if they actually changed.
+/// Returns None if no property references a Secret or a ConfigMap.
+pub(super) fn watch_property_sources(
+    properties: &[DiscoveryProperty],
+    client: &dyn DiscoveryManagerKubeInterface,
+) -> Option<BoxStream<'static, ()>> {
+    let streams: Vec<BoxStream<'static, ()>> = properties
+        .iter()
+        .filter_map(|p| p.value_from.as_ref())
+        .map(|source| match source {
+            DiscoveryPropertySource::ConfigMapKeyRef(val) => {
+                watch_object::<ConfigMap>(client.namespaced(&val.namespace), &val.name)
+            }
+            DiscoveryPropertySource::SecretKeyRef(val) => {
+                watch_object::<Secret>(client.namespaced(&val.namespace), &val.name)
+            }
+        })
+        .collect();
+    if streams.is_empty() {
+        return None;
+    }
+    Some(select_all(streams).boxed())
+}
+
+fn watch_object<T>(api: Box<dyn Api<T>>, name: &str) -> BoxStream<'static, ()>
+where
+    T: Resource + Clone + DeserializeOwned + Debug + Send + Sync + 'static,
+{
+    let name = name.to_owned();
+    watcher(
+        api.as_inner(),
+        watcher::Config::default().fields(&format!("metadata.name={}", name)),
+    )
+    .default_backoff()
+    .filter_map(move |event| {
+        let res = match event {
+            Ok(_) => Some(()),
+            Err(e) => {
+                warn!("Error while watching {}: {:?}", name, e);
+                None
+            }
+        };
+        async move { res }
+    })
+    .boxed()
+}
+
 async fn solve_value_from_config_map(
     config_map_key_selector: &DiscoveryPropertyKeySelector,
     client: &dyn DiscoveryManagerKubeInterface,
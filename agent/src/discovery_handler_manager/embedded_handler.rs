@@ -1,12 +1,19 @@
 use std::sync::Arc;
 
 use akri_discovery_utils::discovery::{
+    control::DiscoveryUpdate,
     v0::{discovery_handler_server::DiscoveryHandler, DiscoverRequest, DiscoverResponse},
     DiscoverStream,
 };
-use akri_shared::os::env_var::{ActualEnvVarQuery, EnvVarQuery};
+use akri_shared::{
+    akri::discovery_handler::DiscoveryHandlerCapabilities,
+    os::env_var::{ActualEnvVarQuery, EnvVarQuery},
+};
 use async_trait::async_trait;
-use tokio::{select, sync::watch};
+use tokio::{
+    select,
+    sync::{mpsc, watch},
+};
 use tokio_stream::{wrappers::ReceiverStream, StreamExt};
 use tonic::IntoRequest;
 
@@ -16,7 +23,7 @@ pub const ENABLE_DEBUG_ECHO_LABEL: &str = "ENABLE_DEBUG_ECHO";
 
 use super::{
     discovery_handler_registry::{
-        DiscoveredDevice, DiscoveryHandlerEndpoint, DiscoveryHandlerRegistry,
+        DiscoveredDevice, DiscoveredDevices, DiscoveryHandlerEndpoint, DiscoveryHandlerRegistry,
     },
     DiscoveryError,
 };
@@ -26,6 +33,7 @@ struct EmbeddedHandlerEndpoint {
     shared: bool,
     handler: Box<dyn DiscoveryHandler<DiscoverStream = DiscoverStream>>,
     node_name: String,
+    capabilities: DiscoveryHandlerCapabilities,
 }
 
 impl EmbeddedHandlerEndpoint {
@@ -36,6 +44,7 @@ impl EmbeddedHandlerEndpoint {
         sender: watch::Sender<Vec<Arc<DiscoveredDevice>>>,
         mut stream: ReceiverStream<Result<DiscoverResponse, tonic::Status>>,
     ) {
+        let mut discovered_devices = DiscoveredDevices::new(node_name, shared);
         loop {
             let msg = select! {
                 _ = sender.closed() => return,
@@ -51,17 +60,9 @@ impl EmbeddedHandlerEndpoint {
                     },
                 },
             };
-            let devices = msg
-                .devices
-                .into_iter()
-                .map(|d| {
-                    Arc::new(match shared {
-                        true => DiscoveredDevice::SharedDevice(d),
-                        false => DiscoveredDevice::LocalDevice(d, node_name.clone()),
-                    })
-                })
-                .collect();
-            sender.send_replace(devices);
+            if let Some(devices) = discovered_devices.apply(msg) {
+                sender.send_replace(devices);
+            }
         }
     }
 }
@@ -72,7 +73,7 @@ impl DiscoveryHandlerEndpoint for EmbeddedHandlerEndpoint {
         &self,
         sender: watch::Sender<Vec<Arc<DiscoveredDevice>>>,
         query_body: DiscoverRequest,
-    ) -> Result<(), DiscoveryError> {
+    ) -> Result<Option<mpsc::Sender<DiscoveryUpdate>>, DiscoveryError> {
         let stream = match self.handler.discover(query_body.into_request()).await {
             Ok(r) => r.into_inner(),
             Err(e) => {
@@ -96,7 +97,8 @@ impl DiscoveryHandlerEndpoint for EmbeddedHandlerEndpoint {
             sender,
             stream,
         ));
-        Ok(())
+        // Embedded handlers only speak v0, changes are applied by querying them again
+        Ok(None)
     }
 
     fn get_name(&self) -> String {
@@ -105,6 +107,9 @@ impl DiscoveryHandlerEndpoint for EmbeddedHandlerEndpoint {
     fn get_uid(&self) -> String {
         format!("embedded-{}", self.name)
     }
+    fn get_capabilities(&self) -> DiscoveryHandlerCapabilities {
+        self.capabilities.clone()
+    }
 
     async fn closed(&self) {
         std::future::pending().await
@@ -135,6 +140,7 @@ async fn inner_register_discovery_handlers(
             shared,
             handler: Box::new(akri_debug_echo::discovery_handler::DiscoveryHandlerImpl::new(None)),
             node_name: node_name.clone(),
+            capabilities: akri_debug_echo::capabilities(),
         }))
         .await;
     }
@@ -146,6 +152,7 @@ async fn inner_register_discovery_handlers(
             None,
         )),
         node_name: node_name.clone(),
+        capabilities: akri_onvif::capabilities(),
     }))
     .await;
     #[cfg(feature = "udev-feat")]
@@ -156,6 +163,7 @@ async fn inner_register_discovery_handlers(
             None,
         )),
         node_name: node_name.clone(),
+        capabilities: akri_udev::capabilities(),
     }))
     .await;
     #[cfg(feature = "opcua-feat")]
@@ -166,6 +174,7 @@ async fn inner_register_discovery_handlers(
             None,
         )),
         node_name: node_name.clone(),
+        capabilities: akri_opcua::capabilities(),
     }))
     .await;
 }
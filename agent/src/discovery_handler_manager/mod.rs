@@ -39,6 +39,7 @@ pub enum DiscoveryError {
 }
 
 pub fn new_registry(
+    node_name: String,
     kube_client: Arc<dyn DiscoveryManagerKubeInterface>,
 ) -> (
     watch::Receiver<HashMap<String, crate::device_manager::cdi::Kind>>,
@@ -47,7 +48,7 @@ pub fn new_registry(
 ) {
     let (sender, receiver) = watch::channel(Default::default());
     let (configuration_notifier, notifier) = mpsc::channel(10);
-    let registry = DHRegistryImpl::new(kube_client, sender, configuration_notifier);
+    let registry = DHRegistryImpl::new(node_name, kube_client, sender, configuration_notifier);
     (receiver, registry, notifier)
 }
 
@@ -1,22 +1,34 @@
 use std::{convert::TryFrom, pin::Pin, sync::Arc};
 
-use akri_discovery_utils::discovery::v0::{
-    discovery_handler_client::DiscoveryHandlerClient,
-    register_discovery_handler_request::EndpointType, registration_server::Registration,
-    DiscoverRequest, DiscoverResponse, Empty, RegisterDiscoveryHandlerRequest,
+use akri_discovery_utils::discovery::{
+    control::{DiscoveryUpdate, DISCOVERY_UPDATES_CHANNEL_CAPACITY},
+    discovery_handler::get_capabilities,
+    v0::{
+        discovery_handler_client::DiscoveryHandlerClient,
+        register_discovery_handler_request::{EndpointType, ProtocolVersion},
+        registration_server::Registration,
+        DiscoverRequest, DiscoverResponse, Empty, RegisterDiscoveryHandlerRequest,
+    },
+    v1::{
+        discovery_handler_client::DiscoveryHandlerClient as DiscoveryHandlerClientV1,
+        DiscoverControl,
+    },
 };
-use akri_shared::uds::unix_stream;
+use akri_shared::{akri::discovery_handler::DiscoveryHandlerCapabilities, uds::unix_stream};
 use async_trait::async_trait;
 use futures::{Stream, StreamExt, TryFutureExt};
-use tokio::{select, sync::watch};
-use tokio_stream::StreamExt as _;
+use tokio::{
+    select,
+    sync::{mpsc, watch},
+};
+use tokio_stream::{wrappers::ReceiverStream, StreamExt as _};
 use tonic::{transport::Channel, Request, Response, Status};
 
 use crate::util::stopper::Stopper;
 
 use super::{
     discovery_handler_registry::{
-        DiscoveredDevice, DiscoveryHandlerEndpoint, DiscoveryHandlerRegistry,
+        DiscoveredDevice, DiscoveredDevices, DiscoveryHandlerEndpoint, DiscoveryHandlerRegistry,
     },
     DiscoveryError,
 };
@@ -28,10 +40,21 @@ struct NetworkEndpoint {
     stopped: Stopper,
     shared: bool,
     node_name: String,
+    /// Whether the Discovery Handler supports the v1 protocol, allowing to update a running discovery
+    supports_v1: bool,
+    /// Capabilities advertised by the Discovery Handler when registering
+    capabilities: DiscoveryHandlerCapabilities,
+}
+
+/// Control channel of a v1 discovery, passing the Agent's updates to the Discovery Handler
+struct DiscoveryControl {
+    updates: mpsc::Receiver<DiscoveryUpdate>,
+    control: mpsc::Sender<DiscoverControl>,
 }
 
 impl NetworkEndpoint {
     fn new(req: RegisterDiscoveryHandlerRequest, node_name: String) -> Self {
+        let capabilities = get_capabilities(&req);
         NetworkEndpoint {
             name: req.name,
             endpoint: req.endpoint,
@@ -39,26 +62,64 @@ impl NetworkEndpoint {
             shared: req.shared,
             endpoint_type: EndpointType::try_from(req.endpoint_type).unwrap(),
             node_name,
+            supports_v1: capabilities
+                .protocol_versions
+                .contains(&(ProtocolVersion::V1 as u32)),
+            capabilities,
         }
     }
 
-    async fn get_client(&self) -> Result<DiscoveryHandlerClient<Channel>, tonic::transport::Error> {
+    async fn get_channel(&self) -> Result<Channel, tonic::transport::Error> {
         match self.endpoint_type {
             EndpointType::Uds => {
                 let socket = self.endpoint.clone();
-                Ok(DiscoveryHandlerClient::new(
-                    tonic::transport::Endpoint::try_from("http://[::1]:50051")
-                        .unwrap()
-                        .connect_with_connector(tower::service_fn(move |_: hyper::Uri| {
-                            tokio::net::UnixStream::connect(socket.clone())
-                        }))
-                        .await?,
-                ))
+                tonic::transport::Endpoint::try_from("http://[::1]:50051")
+                    .unwrap()
+                    .connect_with_connector(tower::service_fn(move |_: hyper::Uri| {
+                        tokio::net::UnixStream::connect(socket.clone())
+                    }))
+                    .await
+            }
+            EndpointType::Network => {
+                tonic::transport::Endpoint::new(self.endpoint.clone())?
+                    .connect()
+                    .await
             }
-            EndpointType::Network => DiscoveryHandlerClient::connect(self.endpoint.clone()).await,
         }
     }
 
+    /// Calls Discover on the Discovery Handler, over v1 if it supports it, in which case the sender of the
+    /// control channel gets returned along with the stream of discovered devices.
+    async fn discover(
+        &self,
+        channel: Channel,
+        query_body: DiscoverRequest,
+    ) -> Result<
+        (
+            tonic::Streaming<DiscoverResponse>,
+            Option<mpsc::Sender<DiscoverControl>>,
+        ),
+        Status,
+    > {
+        if !self.supports_v1 {
+            let stream = DiscoveryHandlerClient::new(channel)
+                .discover(query_body)
+                .await?
+                .into_inner();
+            return Ok((stream, None));
+        }
+        let (control, control_receiver) = mpsc::channel(DISCOVERY_UPDATES_CHANNEL_CAPACITY);
+        // The discovery must be started by the first message of the control channel
+        control
+            .try_send(DiscoverControl::start(query_body))
+            .expect("Channel is new and empty");
+        let stream = DiscoveryHandlerClientV1::new(channel)
+            .discover(ReceiverStream::new(control_receiver))
+            .await?
+            .into_inner();
+        Ok((stream, Some(control)))
+    }
+
     async fn handle_stream(
         stopper: Stopper,
         uid: String,
@@ -66,13 +127,25 @@ impl NetworkEndpoint {
         shared: bool,
         sender: watch::Sender<Vec<Arc<DiscoveredDevice>>>,
         mut stream: Pin<Box<dyn Stream<Item = Result<DiscoverResponse, tonic::Status>> + Send>>,
+        mut control: Option<DiscoveryControl>,
     ) {
+        let mut discovered_devices = DiscoveredDevices::new(node_name, shared);
         loop {
             let msg = select! {
                 // This means all queries for this endpoint must end.
-                _ = stopper.stopped() => return,
+                _ = stopper.stopped() => break,
                 // This means all receiver dropped (i.e no one cares about this query anymore)
-                _ = sender.closed() => return,
+                _ = sender.closed() => break,
+                Some(update) = next_update(&mut control) => {
+                    trace!("Sending discovery update to discovery handler {}: {:?}", uid, update);
+                    if let Some(c) = control.as_ref() {
+                        if c.control.send(update.into()).await.is_err() {
+                            error!("Control channel of discovery handler {} closed unexpectedly", uid);
+                            return
+                        }
+                    }
+                    continue
+                },
                 msg = stream.try_next() => match msg {
                     Ok(Some(msg)) => msg,
                     Ok(None) => {
@@ -86,39 +159,43 @@ impl NetworkEndpoint {
                 },
             };
             trace!("Received new message from discovery handler: {:?}", msg);
-            let devices = msg
-                .devices
-                .into_iter()
-                .map(|d| {
-                    Arc::new(match shared {
-                        true => DiscoveredDevice::SharedDevice(d),
-                        false => DiscoveredDevice::LocalDevice(d, node_name.clone()),
-                    })
-                })
-                .collect();
-            sender.send_replace(devices);
+            if let Some(devices) = discovered_devices.apply(msg) {
+                sender.send_replace(devices);
+            }
+        }
+        if let Some(c) = control {
+            // Best effort, the Discovery Handler also stops the discovery when the control channel closes
+            let _ = c.control.try_send(DiscoverControl::stop());
         }
     }
 }
 
+/// Waits for the next update of a v1 discovery, never resolves for a v0 discovery
+async fn next_update(control: &mut Option<DiscoveryControl>) -> Option<DiscoveryUpdate> {
+    match control {
+        Some(c) => c.updates.recv().await,
+        None => futures::future::pending().await,
+    }
+}
+
 #[async_trait]
 impl DiscoveryHandlerEndpoint for NetworkEndpoint {
     async fn query(
         &self,
         sender: watch::Sender<Vec<Arc<DiscoveredDevice>>>,
         query_body: DiscoverRequest,
-    ) -> Result<(), DiscoveryError> {
+    ) -> Result<Option<mpsc::Sender<DiscoveryUpdate>>, DiscoveryError> {
         if self.stopped.is_stopped() {
             return Err(DiscoveryError::UnavailableDiscoveryHandler(self.get_uid()));
         }
-        let stream = match self.get_client().await {
-            Ok(mut discovery_handler_client) => {
+        let (stream, control) = match self.get_channel().await {
+            Ok(channel) => {
                 trace!(
                     "NetworkEndpoint::query - connecting to external {} discovery handler over network",
                     self.name
                 );
-                match discovery_handler_client.discover(query_body).await {
-                    Ok(device_update_receiver) => device_update_receiver.into_inner(),
+                match self.discover(channel, query_body).await {
+                    Ok(discovery) => discovery,
                     Err(e) => {
                         match e.code() {
                             tonic::Code::InvalidArgument => {
@@ -143,6 +220,16 @@ impl DiscoveryHandlerEndpoint for NetworkEndpoint {
                 return Err(DiscoveryError::UnavailableDiscoveryHandler(self.get_uid()));
             }
         };
+        let (update_sender, control) = match control {
+            Some(control) => {
+                let (update_sender, updates) = mpsc::channel(DISCOVERY_UPDATES_CHANNEL_CAPACITY);
+                (
+                    Some(update_sender),
+                    Some(DiscoveryControl { updates, control }),
+                )
+            }
+            None => (None, None),
+        };
         tokio::spawn(Self::handle_stream(
             self.stopped.to_owned(),
             self.get_uid(),
@@ -150,8 +237,9 @@ impl DiscoveryHandlerEndpoint for NetworkEndpoint {
             self.shared.to_owned(),
             sender,
             stream.boxed(),
+            control,
         ));
-        Ok(())
+        Ok(update_sender)
     }
 
     fn get_name(&self) -> String {
@@ -160,6 +248,9 @@ impl DiscoveryHandlerEndpoint for NetworkEndpoint {
     fn get_uid(&self) -> String {
         format!("{}@{}", self.name, self.endpoint)
     }
+    fn get_capabilities(&self) -> DiscoveryHandlerCapabilities {
+        self.capabilities.clone()
+    }
 
     async fn closed(&self) {
         self.stopped.stopped().await
@@ -258,13 +349,15 @@ mod tests {
             shared,
             sender,
             stream.boxed(),
+            None,
         ));
         assert!(st_sender
             .send(Ok(DiscoverResponse {
                 devices: vec![Device {
                     id: "bar".to_string(),
                     ..Default::default()
-                }]
+                }],
+                delta: None,
             }))
             .await
             .is_ok());
@@ -308,13 +401,15 @@ mod tests {
             shared,
             sender,
             stream.boxed(),
+            None,
         ));
         assert!(st_sender
             .send(Ok(DiscoverResponse {
                 devices: vec![Device {
                     id: "bar".to_string(),
                     ..Default::default()
-                }]
+                }],
+                delta: None,
             }))
             .await
             .is_ok());
@@ -337,4 +432,41 @@ mod tests {
             .await
             .is_ok());
     }
+
+    #[tokio::test]
+    async fn test_handle_stream_control() {
+        let stopper = Stopper::new();
+        let (sender, receiver) = watch::channel(Default::default());
+        let (_st_sender, st_rec) = mpsc::channel(1);
+        let stream = tokio_stream::wrappers::ReceiverStream::new(st_rec);
+        let (update_sender, updates) = mpsc::channel(1);
+        let (control, mut control_rec) = mpsc::channel(2);
+
+        let task = tokio::spawn(NetworkEndpoint::handle_stream(
+            stopper,
+            "foo".to_owned(),
+            "node-a".to_owned(),
+            false,
+            sender,
+            stream.boxed(),
+            Some(DiscoveryControl { updates, control }),
+        ));
+        update_sender
+            .send(DiscoveryUpdate::Details("new details".to_string()))
+            .await
+            .unwrap();
+        assert_eq!(
+            tokio::time::timeout(Duration::from_millis(500), control_rec.recv())
+                .await
+                .unwrap(),
+            Some(DiscoveryUpdate::Details("new details".to_string()).into())
+        );
+
+        // No one cares about the query anymore, the discovery gets stopped
+        drop(receiver);
+        assert!(tokio::time::timeout(Duration::from_millis(500), task)
+            .await
+            .is_ok());
+        assert_eq!(control_rec.recv().await, Some(DiscoverControl::stop()));
+    }
 }
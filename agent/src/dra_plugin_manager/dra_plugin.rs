@@ -0,0 +1,648 @@
+//! This module implements a DRA (Dynamic Resource Allocation) kubelet plugin. Instances are advertised
+//! in a ResourceSlice, and when the scheduler allocates one of them to a ResourceClaim, kubelet asks the
+//! plugin to prepare the claim. Preparation claims a slot of the Instance, the same way the device plugin
+//! does, and returns the CDI fully qualified name of the Instance's device, that the container runtime
+//! injects using the CDI spec files written by the device manager.
+use std::{collections::HashMap, path::Path, sync::Arc};
+
+use akri_shared::{
+    akri::instance::{Instance, SlotUsageKind},
+    uds::unix_stream,
+};
+use futures::TryFutureExt;
+use kube::ResourceExt;
+use kube_runtime::reflector::Store;
+use thiserror::Error;
+use tokio::{net::UnixListener, sync::Mutex};
+use tonic::{transport::Server, Request, Response, Status};
+
+use super::{
+    pluginregistration::{
+        registration_server::{Registration, RegistrationServer},
+        InfoRequest, PluginInfo, RegistrationStatus, RegistrationStatusResponse,
+    },
+    v1alpha3::{
+        node_server::{Node, NodeServer},
+        Claim, NodePrepareResourceResponse, NodePrepareResourcesRequest,
+        NodePrepareResourcesResponse, NodeUnprepareResourceResponse, NodeUnprepareResourcesRequest,
+        NodeUnprepareResourcesResponse,
+    },
+};
+use crate::device_manager::DeviceManager;
+use crate::plugin_manager::device_plugin_instance_controller::DraSlotClaimer;
+
+/// Name of the DRA driver, used in the ResourceSlices and ResourceClasses
+pub const DRA_DRIVER_NAME: &str = "akri.sh";
+
+/// Directory where kubelet watches for plugin registration sockets
+pub const KUBELET_PLUGINS_REGISTRY_PATH: &str = "/var/lib/kubelet/plugins_registry";
+
+/// Directory where kubelet plugins expose their service sockets
+pub const KUBELET_PLUGINS_PATH: &str = "/var/lib/kubelet/plugins";
+
+/// Plugin type expected by kubelet for DRA plugins
+const DRA_PLUGIN_TYPE: &str = "DRAPlugin";
+
+/// Version of the kubelet DRA plugin service served by the plugin
+const DRA_PLUGIN_VERSION: &str = "1.0.0";
+
+#[derive(Error, Debug)]
+pub enum DraPluginError {
+    #[error("Unable to create plugin socket: {0}")]
+    SocketError(#[from] std::io::Error),
+
+    #[error(transparent)]
+    ServerError(#[from] tonic::transport::Error),
+}
+
+/// A claim prepared by the plugin
+struct PreparedClaim {
+    cdi_devices: Vec<String>,
+    /// Device IDs of the Instance slots held for the claim
+    slots: Vec<String>,
+}
+
+/// The DRA kubelet plugin, it keeps track of the prepared claims so that kubelet can call
+/// NodePrepareResources and NodeUnprepareResources multiple times for the same claim.
+pub struct DraPlugin {
+    node_name: String,
+    instances: Store<Instance>,
+    device_manager: Arc<dyn DeviceManager>,
+    slot_claimer: Arc<dyn DraSlotClaimer>,
+    prepared_claims: Mutex<HashMap<String, PreparedClaim>>,
+}
+
+impl DraPlugin {
+    pub fn new(
+        node_name: String,
+        instances: Store<Instance>,
+        device_manager: Arc<dyn DeviceManager>,
+        slot_claimer: Arc<dyn DraSlotClaimer>,
+    ) -> Self {
+        Self {
+            node_name,
+            instances,
+            device_manager,
+            slot_claimer,
+            prepared_claims: Default::default(),
+        }
+    }
+
+    /// Rebuilds the prepared claims from the DRA slots this node holds on the Instances, so that
+    /// claims prepared by a previous run of the agent can be unprepared
+    pub async fn restore_prepared_claims(&self) {
+        // Waiting for the initial listing, an empty cache would restore nothing
+        if self.instances.wait_until_ready().await.is_err() {
+            return;
+        }
+        let mut prepared_claims = self.prepared_claims.lock().await;
+        for instance in self.instances.state() {
+            for (slot, usage) in instance.spec.device_usage.iter() {
+                let Some(claim_uid) = usage.claim_uid.as_ref() else {
+                    continue;
+                };
+                if usage.kind != SlotUsageKind::Dra || !usage.is_owned_by(&self.node_name) {
+                    continue;
+                }
+                trace!(
+                    "restore_prepared_claims - slot {} is prepared for claim {}",
+                    slot,
+                    claim_uid
+                );
+                let prepared =
+                    prepared_claims
+                        .entry(claim_uid.clone())
+                        .or_insert_with(|| PreparedClaim {
+                            cdi_devices: vec![],
+                            slots: vec![],
+                        });
+                prepared.cdi_devices.push(instance.spec.cdi_name.clone());
+                prepared.slots.push(slot.clone());
+            }
+        }
+    }
+
+    /// Resolves the Instances allocated to the claim, with their CDI device
+    fn get_claim_devices(&self, claim: &Claim) -> Result<Vec<(String, String)>, String> {
+        let allocated: Vec<&String> = claim
+            .structured_resource_handle
+            .iter()
+            .filter(|h| h.node_name.as_ref().map_or(true, |n| n == &self.node_name))
+            .flat_map(|h| h.results.iter())
+            .filter_map(|r| {
+                r.allocation_result_model
+                    .as_ref()?
+                    .named_resources
+                    .as_ref()?
+                    .name
+                    .as_ref()
+            })
+            .collect();
+        if allocated.is_empty() {
+            return Err(format!(
+                "no {} resource allocated to claim {}/{} on node {}",
+                DRA_DRIVER_NAME, claim.namespace, claim.name, self.node_name
+            ));
+        }
+        let instances = self.instances.state();
+        allocated
+            .into_iter()
+            .map(|name| {
+                let instance = instances
+                    .iter()
+                    .find(|i| i.name_any() == *name && i.spec.nodes.contains(&self.node_name))
+                    .ok_or_else(|| format!("instance {} is not available on this node", name))?;
+                if !instance.node_health(&self.node_name).is_usable() {
+                    return Err(format!("instance {} is unhealthy", name));
+                }
+                if !self
+                    .device_manager
+                    .has_device(instance.spec.cdi_name.clone())
+                {
+                    return Err(format!("device {} not found", instance.spec.cdi_name));
+                }
+                Ok((name.clone(), instance.spec.cdi_name.clone()))
+            })
+            .collect()
+    }
+
+    /// Claims a slot of every Instance allocated to the claim, releasing the already claimed
+    /// slots if one of them can't be claimed
+    async fn prepare_claim(&self, claim: &Claim) -> Result<PreparedClaim, String> {
+        let devices = self.get_claim_devices(claim)?;
+        let mut prepared = PreparedClaim {
+            cdi_devices: Vec::with_capacity(devices.len()),
+            slots: Vec::with_capacity(devices.len()),
+        };
+        for (instance, cdi_device) in devices {
+            match self
+                .slot_claimer
+                .claim_dra_slot(&instance, &claim.uid)
+                .await
+            {
+                Ok(slot) => {
+                    prepared.slots.push(slot);
+                    prepared.cdi_devices.push(cdi_device);
+                }
+                Err(e) => {
+                    self.free_slots(&prepared.slots).await;
+                    return Err(format!(
+                        "unable to claim a slot of instance {}: {}",
+                        instance, e
+                    ));
+                }
+            }
+        }
+        Ok(prepared)
+    }
+
+    /// Frees the given slots, returns the ones that couldn't be freed
+    async fn free_slots(&self, slots: &[String]) -> Vec<String> {
+        let mut remaining = Vec::new();
+        for slot in slots {
+            if let Err(e) = self.slot_claimer.free_dra_slot(slot).await {
+                warn!("free_slots - unable to free slot {}: {}", slot, e);
+                remaining.push(slot.clone());
+            }
+        }
+        remaining
+    }
+}
+
+#[tonic::async_trait]
+impl Node for DraPlugin {
+    async fn node_prepare_resources(
+        &self,
+        request: Request<NodePrepareResourcesRequest>,
+    ) -> Result<Response<NodePrepareResourcesResponse>, Status> {
+        let request = request.into_inner();
+        let mut prepared_claims = self.prepared_claims.lock().await;
+        let mut claims = HashMap::new();
+        for claim in request.claims {
+            trace!(
+                "node_prepare_resources - preparing claim {}/{} ({})",
+                claim.namespace,
+                claim.name,
+                claim.uid
+            );
+            let response = match prepared_claims.get(&claim.uid) {
+                Some(prepared) => NodePrepareResourceResponse {
+                    cdi_devices: prepared.cdi_devices.clone(),
+                    error: String::new(),
+                },
+                None => match self.prepare_claim(&claim).await {
+                    Ok(prepared) => {
+                        let cdi_devices = prepared.cdi_devices.clone();
+                        prepared_claims.insert(claim.uid.clone(), prepared);
+                        NodePrepareResourceResponse {
+                            cdi_devices,
+                            error: String::new(),
+                        }
+                    }
+                    Err(e) => {
+                        warn!(
+                            "node_prepare_resources - unable to prepare claim {}: {}",
+                            claim.uid, e
+                        );
+                        NodePrepareResourceResponse {
+                            cdi_devices: vec![],
+                            error: e,
+                        }
+                    }
+                },
+            };
+            claims.insert(claim.uid, response);
+        }
+        Ok(Response::new(NodePrepareResourcesResponse { claims }))
+    }
+
+    async fn node_unprepare_resources(
+        &self,
+        request: Request<NodeUnprepareResourcesRequest>,
+    ) -> Result<Response<NodeUnprepareResourcesResponse>, Status> {
+        let request = request.into_inner();
+        let mut prepared_claims = self.prepared_claims.lock().await;
+        let mut claims = HashMap::new();
+        for claim in request.claims {
+            trace!(
+                "node_unprepare_resources - unpreparing claim {}/{} ({})",
+                claim.namespace,
+                claim.name,
+                claim.uid
+            );
+            // Unpreparing an unknown claim is not an error, the claims prepared by a previous run
+            // of the agent are restored from the Instances, so there is nothing to release for it.
+            let mut response = NodeUnprepareResourceResponse::default();
+            if let Some(mut prepared) = prepared_claims.remove(&claim.uid) {
+                prepared.slots = self.free_slots(&prepared.slots).await;
+                if !prepared.slots.is_empty() {
+                    // Keep the claim so that kubelet retries to release the remaining slots
+                    response.error = format!("unable to free slots {:?}", prepared.slots);
+                    prepared_claims.insert(claim.uid.clone(), prepared);
+                }
+            }
+            claims.insert(claim.uid, response);
+        }
+        Ok(Response::new(NodeUnprepareResourcesResponse { claims }))
+    }
+}
+
+/// Serves the kubelet plugin registration service, pointing kubelet to the DRA plugin socket.
+struct PluginRegistration {
+    endpoint: String,
+}
+
+#[tonic::async_trait]
+impl Registration for PluginRegistration {
+    async fn get_info(
+        &self,
+        _request: Request<InfoRequest>,
+    ) -> Result<Response<PluginInfo>, Status> {
+        Ok(Response::new(PluginInfo {
+            r#type: DRA_PLUGIN_TYPE.to_string(),
+            name: DRA_DRIVER_NAME.to_string(),
+            endpoint: self.endpoint.clone(),
+            supported_versions: vec![DRA_PLUGIN_VERSION.to_string()],
+        }))
+    }
+
+    async fn notify_registration_status(
+        &self,
+        request: Request<RegistrationStatus>,
+    ) -> Result<Response<RegistrationStatusResponse>, Status> {
+        let status = request.into_inner();
+        match status.plugin_registered {
+            true => info!("notify_registration_status - DRA plugin registered to kubelet"),
+            false => error!(
+                "notify_registration_status - DRA plugin registration failed: {}",
+                status.error
+            ),
+        }
+        Ok(Response::new(RegistrationStatusResponse {}))
+    }
+}
+
+/// Binds a unix socket, removing any socket left over by a previous run
+async fn bind_socket(socket_path: &Path) -> Result<UnixListener, DraPluginError> {
+    if let Some(parent) = socket_path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    match tokio::fs::remove_file(socket_path).await {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+        _ => {}
+    }
+    Ok(UnixListener::bind(socket_path)?)
+}
+
+fn incoming(
+    uds: UnixListener,
+) -> impl futures::Stream<Item = std::io::Result<unix_stream::UnixStream>> {
+    async_stream::stream! {
+        loop {
+            let item = uds.accept().map_ok(|(st, _)| unix_stream::UnixStream(st)).await;
+            yield item;
+        }
+    }
+}
+
+/// Serves the DRA plugin service
+async fn serve_plugin(plugin: Arc<DraPlugin>, socket_path: &Path) -> Result<(), DraPluginError> {
+    let uds = bind_socket(socket_path).await?;
+    info!(
+        "serve_plugin - DRA plugin listening at: {}",
+        socket_path.display()
+    );
+    Server::builder()
+        .add_service(NodeServer::from_arc(plugin))
+        .serve_with_incoming(incoming(uds))
+        .await?;
+    Ok(())
+}
+
+/// Serves the DRA plugin and registers it to kubelet through the plugin registration directory
+pub async fn serve_and_register_plugin(
+    plugin: Arc<DraPlugin>,
+    plugins_path: &Path,
+    registry_path: &Path,
+) -> Result<(), DraPluginError> {
+    // Restore the claims before kubelet can reach the plugin
+    plugin.restore_prepared_claims().await;
+    let plugin_socket = plugins_path.join(DRA_DRIVER_NAME).join("plugin.sock");
+    let registration_socket = registry_path.join(format!("{}-reg.sock", DRA_DRIVER_NAME));
+    let registration = RegistrationServer::new(PluginRegistration {
+        endpoint: plugin_socket.to_string_lossy().to_string(),
+    });
+    let uds = bind_socket(&registration_socket).await?;
+    let registration_server = Server::builder()
+        .add_service(registration)
+        .serve_with_incoming(incoming(uds))
+        .map_err(DraPluginError::from);
+    futures::try_join!(serve_plugin(plugin, &plugin_socket), registration_server)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use akri_shared::akri::instance::{HealthState, InstanceSpec, SlotUsage};
+    use tokio::net::UnixStream;
+    use tonic::transport::{Channel, Endpoint, Uri};
+    use tower::service_fn;
+
+    use super::*;
+    use crate::{
+        device_manager::MockDeviceManager,
+        dra_plugin_manager::{
+            pluginregistration::registration_client::RegistrationClient,
+            v1alpha3::{
+                node_client::NodeClient, AllocationResultModel, DriverAllocationResult,
+                NamedResourcesAllocationResult, StructuredResourceHandle,
+            },
+        },
+        plugin_manager::device_plugin_instance_controller::{
+            DevicePluginError, MockDraSlotClaimer,
+        },
+    };
+
+    fn instance(name: &str, health: HealthState) -> Instance {
+        let mut instance = Instance::new(
+            name,
+            InstanceSpec {
+                configuration_name: "config-a".to_string(),
+                cdi_name: format!("akri.sh/config-a={}", name),
+                capacity: 1,
+                broker_properties: Default::default(),
+                shared: false,
+                nodes: vec!["node-a".to_string()],
+                device_usage: Default::default(),
+            },
+        );
+        instance.metadata.namespace = Some("default".to_string());
+        instance.status = Some(akri_shared::akri::instance::InstanceStatus {
+            nodes: vec![akri_shared::akri::instance::NodeInstanceStatus {
+                node: "node-a".to_string(),
+                health: akri_shared::akri::instance::InstanceHealth {
+                    state: health,
+                    reason: None,
+                },
+                ..Default::default()
+            }],
+            ..Default::default()
+        });
+        instance
+    }
+
+    fn claim(uid: &str, instances: Vec<&str>) -> Claim {
+        Claim {
+            namespace: "default".to_string(),
+            uid: uid.to_string(),
+            name: format!("claim-{}", uid),
+            resource_handle: String::new(),
+            structured_resource_handle: vec![StructuredResourceHandle {
+                node_name: Some("node-a".to_string()),
+                results: instances
+                    .into_iter()
+                    .map(|name| DriverAllocationResult {
+                        allocation_result_model: Some(AllocationResultModel {
+                            named_resources: Some(NamedResourcesAllocationResult {
+                                name: Some(name.to_string()),
+                            }),
+                        }),
+                    })
+                    .collect(),
+            }],
+        }
+    }
+
+    async fn connect(socket_path: &Path) -> Channel {
+        let socket_path = socket_path.to_path_buf();
+        // Wait for the server to be listening
+        for _ in 0..50 {
+            if socket_path.exists() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        Endpoint::try_from("http://[::1]:50051")
+            .unwrap()
+            .connect_with_connector(service_fn(move |_: Uri| {
+                UnixStream::connect(socket_path.clone())
+            }))
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_prepare_unprepare_resources() {
+        let _ = env_logger::builder().is_test(true).try_init();
+        let (store, mut writer) = kube_runtime::reflector::store();
+        writer.apply_watcher_event(&kube_runtime::watcher::Event::Restarted(vec![
+            instance("instance-a", HealthState::Healthy),
+            instance("instance-b", HealthState::Unhealthy),
+            instance("instance-c", HealthState::Healthy),
+        ]));
+        let mut device_manager = MockDeviceManager::new();
+        device_manager
+            .expect_has_device()
+            .returning(|fqdn| fqdn == "akri.sh/config-a=instance-a");
+        let mut slot_claimer = MockDraSlotClaimer::new();
+        slot_claimer
+            .expect_claim_dra_slot()
+            .withf(|instance, uid| instance == "instance-a" && uid == "uid-a")
+            .times(1)
+            .returning(|_, _| Ok("instance-a-0".to_string()));
+        slot_claimer
+            .expect_claim_dra_slot()
+            .withf(|instance, uid| instance == "instance-a" && uid == "uid-f")
+            .times(1)
+            .returning(|_, _| Err(DevicePluginError::NoSlot));
+        slot_claimer
+            .expect_free_dra_slot()
+            .withf(|slot| slot == "instance-a-0")
+            .times(1)
+            .returning(|_| Ok(()));
+        let plugin = Arc::new(DraPlugin::new(
+            "node-a".to_string(),
+            store,
+            Arc::new(device_manager),
+            Arc::new(slot_claimer),
+        ));
+
+        let dir = tempfile::tempdir().unwrap();
+        let plugins_path = dir.path().join("plugins");
+        let registry_path = dir.path().join("plugins_registry");
+        let task_plugins_path = plugins_path.clone();
+        let task_registry_path = registry_path.clone();
+        let task = tokio::spawn(async move {
+            serve_and_register_plugin(plugin, &task_plugins_path, &task_registry_path).await
+        });
+
+        let mut registration_client =
+            RegistrationClient::new(connect(&registry_path.join("akri.sh-reg.sock")).await);
+        let info = registration_client
+            .get_info(InfoRequest {})
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(info.r#type, "DRAPlugin");
+        assert_eq!(info.name, "akri.sh");
+        let plugin_socket = plugins_path.join("akri.sh/plugin.sock");
+        assert_eq!(info.endpoint, plugin_socket.to_string_lossy());
+
+        let mut client = NodeClient::new(connect(&plugin_socket).await);
+        let response = client
+            .node_prepare_resources(NodePrepareResourcesRequest {
+                claims: vec![
+                    claim("uid-a", vec!["instance-a"]),
+                    claim("uid-b", vec!["instance-b"]),
+                    claim("uid-c", vec!["instance-c"]),
+                    claim("uid-d", vec!["unknown"]),
+                    claim("uid-e", vec![]),
+                    claim("uid-f", vec!["instance-a"]),
+                ],
+            })
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(response.claims.len(), 6);
+        assert_eq!(
+            response.claims["uid-a"],
+            NodePrepareResourceResponse {
+                cdi_devices: vec!["akri.sh/config-a=instance-a".to_string()],
+                error: String::new(),
+            }
+        );
+        for uid in ["uid-b", "uid-c", "uid-d", "uid-e", "uid-f"] {
+            assert!(response.claims[uid].cdi_devices.is_empty());
+            assert!(!response.claims[uid].error.is_empty());
+        }
+
+        // Preparing an already prepared claim returns the same devices
+        let response = client
+            .node_prepare_resources(NodePrepareResourcesRequest {
+                claims: vec![claim("uid-a", vec!["instance-a"])],
+            })
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(
+            response.claims["uid-a"].cdi_devices,
+            vec!["akri.sh/config-a=instance-a".to_string()]
+        );
+
+        // Unprepare is idempotent
+        for _ in 0..2 {
+            let response = client
+                .node_unprepare_resources(NodeUnprepareResourcesRequest {
+                    claims: vec![claim("uid-a", vec!["instance-a"])],
+                })
+                .await
+                .unwrap()
+                .into_inner();
+            assert_eq!(response.claims["uid-a"].error, "");
+        }
+        task.abort();
+    }
+
+    #[tokio::test]
+    async fn test_unprepare_resources_after_restart() {
+        let _ = env_logger::builder().is_test(true).try_init();
+        let dra_slot = |node: &str, claim_uid: &str| SlotUsage {
+            kind: SlotUsageKind::Dra,
+            node: Some(node.to_string()),
+            claim_uid: Some(claim_uid.to_string()),
+            ..Default::default()
+        };
+        let mut instance_a = instance("instance-a", HealthState::Healthy);
+        instance_a.spec.capacity = 3;
+        instance_a.spec.device_usage = HashMap::from([
+            ("instance-a-0".to_string(), dra_slot("node-a", "uid-a")),
+            ("instance-a-1".to_string(), dra_slot("node-b", "uid-b")),
+            ("instance-a-2".to_string(), SlotUsage::from_legacy("node-a")),
+        ]);
+        let (store, mut writer) = kube_runtime::reflector::store();
+        writer.apply_watcher_event(&kube_runtime::watcher::Event::Restarted(vec![instance_a]));
+        let mut slot_claimer = MockDraSlotClaimer::new();
+        slot_claimer.expect_claim_dra_slot().never();
+        // Only the slot this node holds for the claim is freed
+        slot_claimer
+            .expect_free_dra_slot()
+            .withf(|slot| slot == "instance-a-0")
+            .times(1)
+            .returning(|_| Ok(()));
+        let plugin = DraPlugin::new(
+            "node-a".to_string(),
+            store,
+            Arc::new(MockDeviceManager::new()),
+            Arc::new(slot_claimer),
+        );
+        plugin.restore_prepared_claims().await;
+
+        // The restored claim is prepared with the same devices
+        let response = plugin
+            .node_prepare_resources(Request::new(NodePrepareResourcesRequest {
+                claims: vec![claim("uid-a", vec!["instance-a"])],
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(
+            response.claims["uid-a"],
+            NodePrepareResourceResponse {
+                cdi_devices: vec!["akri.sh/config-a=instance-a".to_string()],
+                error: String::new(),
+            }
+        );
+
+        let response = plugin
+            .node_unprepare_resources(Request::new(NodeUnprepareResourcesRequest {
+                claims: vec![claim("uid-a", vec!["instance-a"]), claim("uid-b", vec![])],
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(response.claims["uid-a"].error, "");
+        assert_eq!(response.claims["uid-b"].error, "");
+    }
+}
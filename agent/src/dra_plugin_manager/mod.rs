@@ -0,0 +1,5 @@
+pub mod pluginregistration; // Prost generated kubelet plugin registration module
+pub mod v1alpha3; // Prost generated DRA kubelet plugin module
+
+pub mod dra_plugin;
+pub mod resource_slice;
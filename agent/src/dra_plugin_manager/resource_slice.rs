@@ -0,0 +1,317 @@
+//! This module publishes the Instances available on the node as a DRA ResourceSlice, using the named
+//! resources structured model of `resource.k8s.io/v1alpha2`. The types are defined here as they are not
+//! part of the supported k8s-openapi version.
+use std::{borrow::Cow, sync::Arc, time::Duration};
+
+use akri_shared::{akri::instance::Instance, k8s::api::IntoApi};
+use kube::{
+    core::{ObjectMeta, TypeMeta},
+    Resource, ResourceExt,
+};
+use kube_runtime::reflector::Store;
+
+use super::dra_plugin::DRA_DRIVER_NAME;
+
+/// Number of seconds between two synchronizations of the node's ResourceSlice
+const RESOURCE_SLICE_SYNC_INTERVAL_SECS: u64 = 5;
+
+/// ResourceSlice provides information about available resources on individual nodes
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourceSlice {
+    #[serde(flatten, default, skip_serializing_if = "Option::is_none")]
+    pub types: Option<TypeMeta>,
+    pub metadata: ObjectMeta,
+    pub node_name: String,
+    pub driver_name: String,
+    pub named_resources: NamedResourcesResources,
+}
+
+/// NamedResourcesResources is used in ResourceSlice to describe the available resource instances
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct NamedResourcesResources {
+    pub instances: Vec<NamedResourcesInstance>,
+}
+
+/// NamedResourcesInstance represents one individual hardware instance that can be selected based
+/// on its attributes
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct NamedResourcesInstance {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attributes: Vec<NamedResourcesAttribute>,
+}
+
+/// NamedResourcesAttribute is a combination of an attribute name and its value
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct NamedResourcesAttribute {
+    pub name: String,
+    #[serde(flatten)]
+    pub value: NamedResourcesAttributeValue,
+}
+
+/// The value of an attribute, only one of the field must be set
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum NamedResourcesAttributeValue {
+    Bool(bool),
+    Int(i64),
+    String(String),
+}
+
+impl Resource for ResourceSlice {
+    type DynamicType = ();
+    type Scope = k8s_openapi::ClusterResourceScope;
+
+    fn kind(_: &()) -> Cow<'_, str> {
+        "ResourceSlice".into()
+    }
+
+    fn group(_: &()) -> Cow<'_, str> {
+        "resource.k8s.io".into()
+    }
+
+    fn version(_: &()) -> Cow<'_, str> {
+        "v1alpha2".into()
+    }
+
+    fn plural(_: &()) -> Cow<'_, str> {
+        "resourceslices".into()
+    }
+
+    fn meta(&self) -> &ObjectMeta {
+        &self.metadata
+    }
+
+    fn meta_mut(&mut self) -> &mut ObjectMeta {
+        &mut self.metadata
+    }
+}
+
+/// Attribute names must be DNS subdomains, broker properties are usually in the form `SOME_PROPERTY`
+fn property_to_attribute_name(property: &str) -> String {
+    property
+        .to_lowercase()
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | '0'..='9' | '.' | '-' => c,
+            _ => '-',
+        })
+        .collect::<String>()
+        .trim_matches(|c| c == '-' || c == '.')
+        .to_string()
+}
+
+fn property_to_attribute_value(value: &str) -> NamedResourcesAttributeValue {
+    if let Ok(int) = value.parse::<i64>() {
+        return NamedResourcesAttributeValue::Int(int);
+    }
+    match value {
+        "true" => NamedResourcesAttributeValue::Bool(true),
+        "false" => NamedResourcesAttributeValue::Bool(false),
+        _ => NamedResourcesAttributeValue::String(value.to_string()),
+    }
+}
+
+fn instance_to_named_resource(instance: &Instance) -> NamedResourcesInstance {
+    let mut attributes = vec![NamedResourcesAttribute {
+        name: "configuration".to_string(),
+        value: NamedResourcesAttributeValue::String(instance.spec.configuration_name.clone()),
+    }];
+    for (key, value) in instance.spec.broker_properties.iter() {
+        let name = property_to_attribute_name(key);
+        if name.is_empty() || attributes.iter().any(|a| a.name == name) {
+            trace!(
+                "instance_to_named_resource - skipping broker property {}",
+                key
+            );
+            continue;
+        }
+        attributes.push(NamedResourcesAttribute {
+            name,
+            value: property_to_attribute_value(value),
+        });
+    }
+    attributes.sort_by(|a, b| a.name.cmp(&b.name));
+    NamedResourcesInstance {
+        name: instance.name_any(),
+        attributes,
+    }
+}
+
+/// Builds the ResourceSlice of the node from the usable Instances visible by the node
+fn instances_to_resource_slice(node_name: &str, instances: &[Arc<Instance>]) -> ResourceSlice {
+    let mut named_instances: Vec<NamedResourcesInstance> = instances
+        .iter()
+        .filter(|i| {
+            i.spec.nodes.iter().any(|n| n == node_name) && i.node_health(node_name).is_usable()
+        })
+        .map(|i| instance_to_named_resource(i))
+        .collect();
+    named_instances.sort_by(|a, b| a.name.cmp(&b.name));
+    ResourceSlice {
+        types: Some(TypeMeta {
+            api_version: ResourceSlice::api_version(&()).to_string(),
+            kind: ResourceSlice::kind(&()).to_string(),
+        }),
+        metadata: ObjectMeta {
+            name: Some(format!("{}-{}", node_name, DRA_DRIVER_NAME)),
+            ..Default::default()
+        },
+        node_name: node_name.to_string(),
+        driver_name: DRA_DRIVER_NAME.to_string(),
+        named_resources: NamedResourcesResources {
+            instances: named_instances,
+        },
+    }
+}
+
+/// Keeps the node's ResourceSlice in sync with the Instances cache
+pub async fn publish_resource_slices(
+    node_name: String,
+    instances: Store<Instance>,
+    client: Arc<dyn IntoApi<ResourceSlice>>,
+) {
+    let api = client.all();
+    let field_manager = format!("akri-agent-{}", node_name);
+    let mut published: Option<ResourceSlice> = None;
+    loop {
+        let slice = instances_to_resource_slice(&node_name, &instances.state());
+        if published.as_ref() != Some(&slice) {
+            match api.apply(slice.clone(), &field_manager).await {
+                Ok(_) => {
+                    trace!(
+                        "publish_resource_slices - published {} resource instances",
+                        slice.named_resources.instances.len()
+                    );
+                    published = Some(slice);
+                }
+                Err(e) => error!(
+                    "publish_resource_slices - unable to apply ResourceSlice: {:?}",
+                    e
+                ),
+            }
+        }
+        tokio::time::sleep(Duration::from_secs(RESOURCE_SLICE_SYNC_INTERVAL_SECS)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use akri_shared::akri::instance::{
+        HealthState, InstanceHealth, InstanceSpec, InstanceStatus, NodeInstanceStatus,
+    };
+
+    use super::*;
+
+    fn instance(name: &str, nodes: Vec<&str>, health: HealthState) -> Arc<Instance> {
+        let mut instance = Instance::new(
+            name,
+            InstanceSpec {
+                configuration_name: "config-a".to_string(),
+                cdi_name: format!("akri.sh/config-a={}", name),
+                capacity: 1,
+                broker_properties: HashMap::from([
+                    ("ONVIF_RESOLUTION_HEIGHT".to_string(), "1080".to_string()),
+                    ("ONVIF_DEVICE_UUID".to_string(), "uuid-1".to_string()),
+                    ("PTZ".to_string(), "true".to_string()),
+                ]),
+                shared: true,
+                nodes: nodes.iter().map(|n| n.to_string()).collect(),
+                device_usage: Default::default(),
+            },
+        );
+        instance.metadata.namespace = Some("default".to_string());
+        instance.status = Some(InstanceStatus {
+            nodes: nodes
+                .iter()
+                .map(|n| NodeInstanceStatus {
+                    node: n.to_string(),
+                    health: InstanceHealth {
+                        state: health,
+                        reason: None,
+                    },
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        });
+        Arc::new(instance)
+    }
+
+    #[test]
+    fn test_property_to_attribute() {
+        assert_eq!(
+            property_to_attribute_name("ONVIF_DEVICE_UUID"),
+            "onvif-device-uuid"
+        );
+        assert_eq!(property_to_attribute_name("_weird key_"), "weird-key");
+        assert_eq!(
+            property_to_attribute_value("1080"),
+            NamedResourcesAttributeValue::Int(1080)
+        );
+        assert_eq!(
+            property_to_attribute_value("false"),
+            NamedResourcesAttributeValue::Bool(false)
+        );
+        assert_eq!(
+            property_to_attribute_value("/dev/video0"),
+            NamedResourcesAttributeValue::String("/dev/video0".to_string())
+        );
+    }
+
+    #[test]
+    fn test_instances_to_resource_slice() {
+        let instances = vec![
+            instance(
+                "instance-b",
+                vec!["node-a", "node-b"],
+                HealthState::Degraded,
+            ),
+            instance("instance-a", vec!["node-a"], HealthState::Healthy),
+            instance("instance-c", vec!["node-b"], HealthState::Healthy),
+            instance("instance-d", vec!["node-a"], HealthState::Unhealthy),
+        ];
+        let slice = instances_to_resource_slice("node-a", &instances);
+        assert_eq!(slice.metadata.name, Some("node-a-akri.sh".to_string()));
+        assert_eq!(
+            slice
+                .named_resources
+                .instances
+                .iter()
+                .map(|i| i.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["instance-a", "instance-b"]
+        );
+
+        let serialized = serde_json::to_value(&slice).unwrap();
+        assert_eq!(
+            serialized,
+            serde_json::json!({
+                "apiVersion": "resource.k8s.io/v1alpha2",
+                "kind": "ResourceSlice",
+                "metadata": {"name": "node-a-akri.sh"},
+                "nodeName": "node-a",
+                "driverName": "akri.sh",
+                "namedResources": {"instances": [
+                    {"name": "instance-a", "attributes": [
+                        {"name": "configuration", "string": "config-a"},
+                        {"name": "onvif-device-uuid", "string": "uuid-1"},
+                        {"name": "onvif-resolution-height", "int": 1080},
+                        {"name": "ptz", "bool": true},
+                    ]},
+                    {"name": "instance-b", "attributes": [
+                        {"name": "configuration", "string": "config-a"},
+                        {"name": "onvif-device-uuid", "string": "uuid-1"},
+                        {"name": "onvif-resolution-height", "int": 1080},
+                        {"name": "ptz", "bool": true},
+                    ]},
+                ]},
+            })
+        );
+    }
+}
@@ -5,10 +5,14 @@ extern crate log;
 extern crate serde_derive;
 mod device_manager;
 mod discovery_handler_manager;
+mod dra_plugin_manager;
 mod plugin_manager;
 mod util;
 
-use akri_shared::akri::{metrics::run_metrics_server, API_NAMESPACE};
+use akri_shared::{
+    akri::{metrics::run_metrics_server, API_NAMESPACE},
+    k8s::event::{EventRecorder, KubeEventRecorder},
+};
 use log::{info, trace};
 use std::{
     collections::HashMap,
@@ -34,10 +38,16 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>
     );
 
     let mut tasks = Vec::new();
+    let mut dra_plugin_task = None;
     let node_name = env::var("AGENT_NODE_NAME")?;
 
     {
         let kube_client = Arc::new(kube::Client::try_default().await?);
+        let recorder: Arc<dyn EventRecorder> = Arc::new(KubeEventRecorder::new(
+            kube_client.clone(),
+            "akri-agent",
+            &node_name,
+        ));
 
         // Start server for Prometheus metrics
         tasks.push(tokio::spawn(async move {
@@ -45,7 +55,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>
         }));
 
         let (device_notifier, discovery_handler_registry, config_notifier) =
-            discovery_handler_manager::new_registry(kube_client.clone());
+            discovery_handler_manager::new_registry(node_name.clone(), kube_client.clone());
 
         let dh_registry = Arc::new(discovery_handler_registry);
         let local_dh_reg = dh_registry.clone();
@@ -61,13 +71,45 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>
             .unwrap()
         }));
 
-        let im_device_manager = Arc::new(device_manager::InMemoryManager::new(device_notifier));
+        // The DRA plugin relies on container runtimes injecting devices by their CDI name, so it also enables CDI specs
+        let enable_dra_plugin = env::var("ENABLE_DRA_PLUGIN").is_ok_and(|v| v == "true");
+
+        // Optionally persist CDI specs so that container runtimes can inject devices by their CDI name
+        let device_manager: Arc<dyn device_manager::DeviceManager> =
+            match env::var("CDI_SPEC_DIRECTORY")
+                .ok()
+                .or_else(|| enable_dra_plugin.then(String::new))
+            {
+                Some(cdi_dir) => {
+                    let cdi_dir = match cdi_dir.is_empty() {
+                        true => device_manager::DEFAULT_CDI_DIR.into(),
+                        false => cdi_dir.into(),
+                    };
+                    let (manager, task) =
+                        device_manager::CdiFileManager::new(device_notifier, cdi_dir);
+                    tasks.push(task);
+                    Arc::new(manager)
+                }
+                None => Arc::new(device_manager::InMemoryManager::new(device_notifier)),
+            };
+
+        // Slots are written in their legacy string encoding until every Agent and Controller of the cluster reads
+        // their structured form
+        let structured_slot_usage = env::var("STRUCTURED_SLOT_USAGE").is_ok_and(|v| v == "true");
+
+        let config_controller =
+            util::discovery_configuration_controller::new_controller(kube_client.as_ref());
+        let configurations_cache = config_controller.store();
 
         let device_plugin_manager = Arc::new(
             plugin_manager::device_plugin_instance_controller::DevicePluginManager::new(
                 node_name.clone(),
                 kube_client.clone(),
-                im_device_manager.clone(),
+                configurations_cache,
+                kube_client.clone(),
+                device_manager.clone(),
+                recorder.clone(),
+                structured_slot_usage,
             ),
         );
 
@@ -77,6 +119,29 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>
             );
         tasks.push(device_plugin_controller_task);
 
+        if enable_dra_plugin {
+            let dra_plugin = Arc::new(dra_plugin_manager::dra_plugin::DraPlugin::new(
+                node_name.clone(),
+                instances_cache.clone(),
+                device_manager,
+                device_plugin_manager.clone(),
+            ));
+            dra_plugin_task = Some(tokio::spawn(
+                dra_plugin_manager::dra_plugin::serve_and_register_plugin(
+                    dra_plugin,
+                    dra_plugin_manager::dra_plugin::KUBELET_PLUGINS_PATH.as_ref(),
+                    dra_plugin_manager::dra_plugin::KUBELET_PLUGINS_REGISTRY_PATH.as_ref(),
+                ),
+            ));
+            tasks.push(tokio::spawn(
+                dra_plugin_manager::resource_slice::publish_resource_slices(
+                    node_name.clone(),
+                    instances_cache.clone(),
+                    kube_client.clone(),
+                ),
+            ));
+        }
+
         tasks.push(tokio::spawn(
             plugin_manager::device_plugin_slot_reclaimer::start_reclaimer(device_plugin_manager),
         ));
@@ -88,11 +153,13 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>
                 client: kube_client.clone(),
                 agent_identifier: node_name.clone(),
                 error_backoffs: Mutex::new(HashMap::new()),
+                recorder,
             },
         );
 
         tasks.push(tokio::spawn(async {
             util::discovery_configuration_controller::start_controller(
+                config_controller,
                 config_controller_context,
                 config_notifier,
             )
@@ -100,7 +167,19 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>
         }));
     }
 
-    futures::future::try_join_all(tasks).await?;
+    let tasks = futures::future::try_join_all(tasks);
+    match dra_plugin_task {
+        // A failure of the DRA plugin stops the agent with its error
+        Some(dra_plugin_task) => {
+            tokio::try_join!(
+                async { Ok::<_, Box<dyn std::error::Error + Send + Sync>>(tasks.await?) },
+                async { Ok(dra_plugin_task.await??) },
+            )?;
+        }
+        None => {
+            tasks.await?;
+        }
+    }
     info!("{} Agent end", API_NAMESPACE);
     Ok(())
 }
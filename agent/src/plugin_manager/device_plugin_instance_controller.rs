@@ -1,24 +1,36 @@
 use std::collections::HashSet;
-use std::fmt::Display;
-use std::str::FromStr;
 use std::{collections::HashMap, sync::Arc, time::Duration};
 
-use akri_shared::{akri::instance::Instance, k8s::api::IntoApi};
+use akri_shared::{
+    akri::{
+        configuration::{AllocationPolicy, AllocationStrategy, Configuration},
+        instance::{AnySlotUsage, Instance, InstanceHealth, SlotUsage, SlotUsageKind},
+    },
+    k8s::{
+        api::IntoApi,
+        event::{EventInfo, EventRecorder},
+    },
+};
 use anyhow::Context;
 use async_trait::async_trait;
 use futures::StreamExt;
 use itertools::Itertools;
+use k8s_openapi::api::core::v1::{ObjectReference, Pod};
+use k8s_openapi::{apimachinery::pkg::apis::meta::v1::Time, chrono::Utc};
 use kube::api::{Patch, PatchParams};
 use kube::core::{NotUsed, Object, ObjectMeta, TypeMeta};
 use kube::{Resource, ResourceExt};
 use kube_runtime::controller::Action;
-use kube_runtime::reflector::Store;
+use kube_runtime::reflector::{ObjectRef, Store};
 use kube_runtime::Controller;
 use thiserror::Error;
 use tokio::sync::{watch, Mutex, RwLock};
 use tokio::task::JoinHandle;
 use tonic::Request;
 
+#[cfg(test)]
+use mockall::automock;
+
 use crate::device_manager::{cdi, DeviceManager};
 use crate::plugin_manager::v1beta1::ContainerAllocateResponse;
 use crate::util::stopper::Stopper;
@@ -26,7 +38,10 @@ use crate::util::stopper::Stopper;
 use super::device_plugin_runner::{
     serve_and_register_plugin, DeviceUsageStream, InternalDevicePlugin,
 };
-use super::v1beta1::{AllocateRequest, AllocateResponse, ListAndWatchResponse};
+use super::v1beta1::{
+    AllocateRequest, AllocateResponse, ContainerPreferredAllocationResponse, ListAndWatchResponse,
+    PreferredAllocationRequest, PreferredAllocationResponse,
+};
 
 pub const DP_SLOT_PREFIX: &str = "akri.sh/";
 
@@ -38,6 +53,9 @@ pub enum DevicePluginError {
     #[error("No slots left for device")]
     NoSlot,
 
+    #[error("Device is unhealthy")]
+    Unhealthy,
+
     #[error("Device usage parse error")]
     UsageParseError,
 
@@ -55,7 +73,15 @@ pub enum DevicePluginError {
 enum DeviceUsage {
     Unused,
     Node(String),
-    Configuration { vdev: String, node: String },
+    Configuration {
+        vdev: String,
+        node: String,
+    },
+    /// Claimed by the DRA plugin for the resource claim with the given UID
+    Dra {
+        node: String,
+        claim_uid: String,
+    },
 }
 
 impl DeviceUsage {
@@ -63,118 +89,212 @@ impl DeviceUsage {
         match self {
             Self::Node(n) if n == node => true,
             Self::Configuration { node: n, .. } if n == node => true,
+            Self::Dra { node: n, .. } if n == node => true,
             _ => false,
         }
     }
 }
 
-impl Display for DeviceUsage {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            DeviceUsage::Unused => write!(f, ""),
-            DeviceUsage::Node(node) => write!(f, "{}", node),
-            DeviceUsage::Configuration { vdev, node } => write!(f, "C:{}:{}", vdev, node),
+/// Pod a slot is allocated to, as reported by the kubelet
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SlotPod {
+    pub namespace: String,
+    pub name: String,
+    /// The kubelet doesn't report the UID of the Pod, it is looked up when the Pod is recorded
+    pub uid: Option<String>,
+}
+
+/// A slot of an Instance, what holds it and since when
+#[derive(Debug, Clone, PartialEq)]
+struct Slot {
+    usage: DeviceUsage,
+    claim_time: Option<Time>,
+    pod: Option<SlotPod>,
+}
+
+impl From<DeviceUsage> for Slot {
+    fn from(usage: DeviceUsage) -> Self {
+        Self {
+            usage,
+            claim_time: None,
+            pod: None,
         }
     }
 }
 
-impl FromStr for DeviceUsage {
-    type Err = DevicePluginError;
-    fn from_str(val: &str) -> Result<Self, DevicePluginError> {
-        if val.is_empty() {
-            Ok(Self::Unused)
-        } else {
-            match val.split(':').collect_vec()[..] {
-                ["C", vdev, node] => Ok(Self::Configuration {
+impl TryFrom<&SlotUsage> for Slot {
+    type Error = DevicePluginError;
+    fn try_from(val: &SlotUsage) -> Result<Self, DevicePluginError> {
+        let usage = match (val.kind, &val.node, &val.vdev, &val.claim_uid) {
+            (SlotUsageKind::Free, _, _, _) => DeviceUsage::Unused,
+            (SlotUsageKind::Instance, Some(node), _, _) => DeviceUsage::Node(node.to_owned()),
+            (SlotUsageKind::Configuration, Some(node), Some(vdev), _) => {
+                DeviceUsage::Configuration {
                     vdev: vdev.to_owned(),
                     node: node.to_owned(),
-                }),
-                [node] => Ok(Self::Node(node.to_owned())),
-                _ => Err(DevicePluginError::UsageParseError),
+                }
             }
+            (SlotUsageKind::Dra, Some(node), _, Some(claim_uid)) => DeviceUsage::Dra {
+                node: node.to_owned(),
+                claim_uid: claim_uid.to_owned(),
+            },
+            _ => return Err(DevicePluginError::UsageParseError),
+        };
+        let pod = match (&val.pod_namespace, &val.pod_name) {
+            (Some(namespace), Some(name)) => Some(SlotPod {
+                namespace: namespace.to_owned(),
+                name: name.to_owned(),
+                uid: val.pod_uid.clone(),
+            }),
+            _ => None,
+        };
+        Ok(Self {
+            usage,
+            claim_time: val.claim_time.clone(),
+            pod,
+        })
+    }
+}
+
+impl From<&Slot> for SlotUsage {
+    fn from(val: &Slot) -> Self {
+        let (kind, node, vdev, claim_uid) = match &val.usage {
+            DeviceUsage::Unused => (SlotUsageKind::Free, None, None, None),
+            DeviceUsage::Node(node) => (SlotUsageKind::Instance, Some(node.to_owned()), None, None),
+            DeviceUsage::Configuration { vdev, node } => (
+                SlotUsageKind::Configuration,
+                Some(node.to_owned()),
+                Some(vdev.to_owned()),
+                None,
+            ),
+            DeviceUsage::Dra { node, claim_uid } => (
+                SlotUsageKind::Dra,
+                Some(node.to_owned()),
+                None,
+                Some(claim_uid.to_owned()),
+            ),
+        };
+        let pod = val.pod.as_ref();
+        Self {
+            kind,
+            node,
+            vdev,
+            pod_namespace: pod.map(|p| p.namespace.clone()),
+            pod_name: pod.map(|p| p.name.clone()),
+            pod_uid: pod.and_then(|p| p.uid.clone()),
+            claim_time: val.claim_time.clone(),
+            claim_uid,
         }
     }
 }
 
-fn parse_slot_id(st: &str) -> Result<usize, DevicePluginError> {
-    usize::from_str(
-        st.rsplit_once('-')
-            .ok_or(DevicePluginError::UsageParseError)?
-            .1,
-    )
-    .or(Err(DevicePluginError::UsageParseError))
+/// Gets the index of a slot from its `<instance name>-<index>` identifier
+fn parse_slot_id(instance_name: &str, st: &str) -> Result<usize, DevicePluginError> {
+    st.strip_prefix(instance_name)
+        .and_then(|id| id.strip_prefix('-'))
+        .ok_or(DevicePluginError::UsageParseError)?
+        .parse()
+        .or(Err(DevicePluginError::UsageParseError))
 }
 
 fn construct_slots_map(
-    slots: &HashMap<String, String>,
-) -> Result<HashMap<usize, DeviceUsage>, DevicePluginError> {
+    instance_name: &str,
+    slots: &HashMap<String, SlotUsage>,
+) -> Result<HashMap<usize, Slot>, DevicePluginError> {
     slots
         .iter()
-        .map(|(k, v)| Ok((parse_slot_id(k)?, DeviceUsage::from_str(v)?)))
+        .map(|(k, v)| Ok((parse_slot_id(instance_name, k)?, Slot::try_from(v)?)))
         .try_collect()
 }
 
 fn construct_slots_vec(
-    slots: &HashMap<String, String>,
+    instance_name: &str,
+    slots: &HashMap<String, SlotUsage>,
     capacity: usize,
-) -> Result<Vec<DeviceUsage>, DevicePluginError> {
-    let mut out_vec = vec![DeviceUsage::Unused; capacity];
+) -> Result<Vec<Slot>, DevicePluginError> {
+    let mut out_vec = vec![Slot::from(DeviceUsage::Unused); capacity];
     for (k, v) in slots.iter() {
-        let index = parse_slot_id(k)?;
+        let index = parse_slot_id(instance_name, k)?;
         if index >= capacity {
             return Err(DevicePluginError::UsageParseError);
         }
-        out_vec[index] = DeviceUsage::from_str(v)?;
+        out_vec[index] = Slot::try_from(v)?;
     }
     Ok(out_vec)
 }
 
+/// Slots of an Instance along with the health of the underlying device, an unhealthy
+/// device has all its slots reported as unhealthy to the kubelet
+#[derive(Debug, Clone, PartialEq)]
+struct InstanceSlots {
+    slots: Vec<Slot>,
+    healthy: bool,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 #[serde(rename_all = "camelCase")]
 struct PartialInstanceSlotUsage {
-    device_usage: HashMap<String, String>,
+    device_usage: HashMap<String, AnySlotUsage>,
 }
 
 struct InstanceDevicePlugin {
     device: cdi::Device,
-    slots_status: Mutex<watch::Sender<Vec<DeviceUsage>>>,
+    slots_status: Mutex<watch::Sender<InstanceSlots>>,
     node_name: String,
     instance_name: String,
     instance_namespace: String,
+    /// Reference to the Instance, events about its slots are recorded on it
+    instance_ref: ObjectReference,
     kube_client: Arc<dyn IntoApi<Instance>>,
+    recorder: Arc<dyn EventRecorder>,
     stopper: Stopper,
+    /// Whether the slots are written in their structured form rather than their legacy string
+    /// encoding, which is the only one Akri versions prior to the structured usage read
+    structured_slot_usage: bool,
 }
 
 impl InstanceDevicePlugin {
     fn new(
         node_name: String,
-        plugin_name: String,
-        namespace: String,
+        instance: &Instance,
         device: cdi::Device,
-        slots: &HashMap<String, String>,
-        capacity: usize,
         client: Arc<dyn IntoApi<Instance>>,
+        recorder: Arc<dyn EventRecorder>,
+        structured_slot_usage: bool,
     ) -> Result<Self, DevicePluginError> {
-        let (slots_status, _) = watch::channel(construct_slots_vec(slots, capacity)?);
+        let (slots_status, _) = watch::channel(InstanceSlots {
+            slots: construct_slots_vec(
+                &instance.name_any(),
+                &instance.spec.device_usage,
+                instance.spec.capacity,
+            )?,
+            healthy: instance.node_health(&node_name).is_usable(),
+        });
         Ok(Self {
             device,
             slots_status: Mutex::new(slots_status),
             node_name,
-            instance_name: plugin_name,
+            instance_name: instance.name_any(),
             kube_client: client,
+            recorder,
             stopper: Stopper::new(),
-            instance_namespace: namespace,
+            instance_namespace: instance.namespace().unwrap_or("default".to_string()),
+            instance_ref: instance.object_ref(&()),
+            structured_slot_usage,
         })
     }
 
-    async fn update_slots(&self, slots: &HashMap<String, String>) -> Result<(), DevicePluginError> {
+    async fn update_slots(
+        &self,
+        slots: &HashMap<String, SlotUsage>,
+    ) -> Result<(), DevicePluginError> {
         let my_slots = self.slots_status.lock().await;
-        let new_slots = construct_slots_map(slots)?;
+        let new_slots = construct_slots_map(&self.instance_name, slots)?;
         my_slots.send_if_modified(|current| {
             let mut modified = false;
             for (k, v) in new_slots.iter() {
-                if current[*k] != *v {
-                    v.clone_into(&mut current[*k]);
+                if current.slots[*k] != *v {
+                    v.clone_into(&mut current.slots[*k]);
                     modified = true;
                 }
             }
@@ -183,6 +303,36 @@ impl InstanceDevicePlugin {
         Ok(())
     }
 
+    /// Get the value of a broker property of the device
+    fn get_property(&self, name: &str) -> Option<String> {
+        self.device.container_edits.env.iter().find_map(|e| {
+            e.split_once('=')
+                .filter(|(k, _)| *k == name)
+                .map(|(_, v)| v.to_string())
+        })
+    }
+
+    async fn used_slots_count(&self) -> usize {
+        self.slots_status
+            .lock()
+            .await
+            .borrow()
+            .slots
+            .iter()
+            .filter(|s| s.usage != DeviceUsage::Unused)
+            .count()
+    }
+
+    async fn update_health(&self, health: &InstanceHealth) {
+        let healthy = health.is_usable();
+        self.slots_status.lock().await.send_if_modified(|current| {
+            let modified = current.healthy != healthy;
+            current.healthy = healthy;
+            modified
+        });
+    }
+
+    /// Claims the given slot, or the first free one
     async fn claim_slot(
         &self,
         id: Option<usize>,
@@ -192,91 +342,181 @@ impl InstanceDevicePlugin {
             return Err(anyhow::anyhow!("Should never happen").into());
         }
         let slots_status = self.slots_status.lock().await;
+        if !slots_status.borrow().healthy {
+            return Err(DevicePluginError::Unhealthy);
+        }
         let id = match id {
-            Some(id) => match &slots_status.borrow()[id] {
-                DeviceUsage::Unused => id,
-                // The kubelet asks for the same slot, it knows best
-                d if *d == wanted_state => id,
-                _ => {
+            Some(id) => {
+                let in_use = match &slots_status.borrow().slots[id].usage {
+                    DeviceUsage::Unused => false,
+                    // The kubelet asks for the same slot, it knows best
+                    d => *d != wanted_state,
+                };
+                if in_use {
                     trace!("Trying to claim already used slot");
+                    self.record_slot_conflict(id).await;
                     return Err(DevicePluginError::SlotInUse);
                 }
-            },
+                id
+            }
             None => slots_status
                 .borrow()
+                .slots
                 .iter()
-                .position(|v| *v == DeviceUsage::Unused)
+                .position(|s| s.usage == DeviceUsage::Unused)
                 .ok_or(DevicePluginError::NoSlot)?,
         };
+        let claimed_message = match &wanted_state {
+            DeviceUsage::Configuration { vdev, .. } => {
+                format!("Slot {} claimed on node {} as {}", id, self.node_name, vdev)
+            }
+            DeviceUsage::Dra { claim_uid, .. } => format!(
+                "Slot {} claimed on node {} for resource claim {}",
+                id, self.node_name, claim_uid
+            ),
+            _ => format!("Slot {} claimed on node {}", id, self.node_name),
+        };
         slots_status.send_modify(|slots| {
-            slots[id] = wanted_state;
+            slots.slots[id] = Slot {
+                usage: wanted_state,
+                claim_time: Some(Time(Utc::now())),
+                pod: None,
+            };
         });
-        let device_usage = slots_status
-            .borrow()
-            .iter()
-            .enumerate()
-            .filter_map(|(i, v)| match v {
-                v if v.is_owned_by(&self.node_name) => {
-                    Some((format!("{}-{}", self.instance_name, i), v.to_string()))
-                }
-                _ => None,
-            })
-            .collect();
-        let api = self.kube_client.namespaced(&self.instance_namespace);
-        let patch = Patch::Apply(
-            serde_json::to_value(Object {
-                types: Some(TypeMeta {
-                    api_version: Instance::api_version(&()).to_string(),
-                    kind: Instance::kind(&()).to_string(),
-                }),
-                status: None::<NotUsed>,
-                spec: PartialInstanceSlotUsage { device_usage },
-                metadata: ObjectMeta {
-                    name: Some(self.instance_name.to_owned()),
-                    ..Default::default()
-                },
-            })
-            .context("Could not create instance patch")?,
-        );
-        api.raw_patch(
-            &self.instance_name,
-            &patch,
-            &PatchParams::apply(&format!("dp-{}", &self.node_name)),
-        )
-        .await
-        .map_err(|e| match e {
-            kube::Error::Api(ae) => match ae.code {
-                409 => {
-                    trace!("Conflict on apply {:?}", ae);
-                    DevicePluginError::SlotInUse
-                }
-                _ => DevicePluginError::Other(ae.into()),
-            },
-            e => DevicePluginError::Other(e.into()),
-        })?;
+        let device_usage = self.owned_device_usage(&slots_status.borrow());
+        let result = self.apply_device_usage(device_usage).await;
+        if let Err(DevicePluginError::SlotInUse) = result {
+            self.record_slot_conflict(id).await;
+        }
+        result?;
+        self.recorder
+            .record(
+                &self.instance_ref,
+                EventInfo::normal("SlotClaimed", "Allocate", claimed_message),
+            )
+            .await;
         Ok(id)
     }
 
     async fn free_slot(&self, id: usize) -> Result<(), DevicePluginError> {
         let slots_status = self.slots_status.lock().await;
-        slots_status.send_if_modified(|slots| {
-            if id >= slots.len() {
+        let freed = slots_status.send_if_modified(|slots| {
+            if id >= slots.slots.len() {
                 // We try to free a slot that doesn't exists, probably already freed
                 false
             } else {
-                slots[id] = DeviceUsage::Unused;
+                slots.slots[id] = DeviceUsage::Unused.into();
                 true
             }
         });
-        let device_usage = slots_status
-            .borrow()
+        let device_usage = self.owned_device_usage(&slots_status.borrow());
+        let result = self.apply_device_usage(device_usage).await;
+        if let Err(DevicePluginError::SlotInUse) = result {
+            self.record_slot_conflict(id).await;
+        }
+        result?;
+        if freed {
+            self.recorder
+                .record(
+                    &self.instance_ref,
+                    EventInfo::normal(
+                        "SlotFreed",
+                        "Deallocate",
+                        format!("Slot {} freed on node {}", id, self.node_name),
+                    ),
+                )
+                .await;
+        }
+        Ok(())
+    }
+
+    /// Applies again the slots held by this node, this rewrites them in the form this Agent writes,
+    /// so that they follow a switch to, or back from, the structured form
+    async fn reapply_slots(&self) -> Result<(), DevicePluginError> {
+        let slots_status = self.slots_status.lock().await;
+        let device_usage = self.owned_device_usage(&slots_status.borrow());
+        if device_usage.is_empty() {
+            return Ok(());
+        }
+        self.apply_device_usage(device_usage).await
+    }
+
+    /// Records the Pods the slots held by this node are allocated to, the Pods being keyed by the
+    /// device ID the kubelet allocated to them. Slots whose device ID isn't in use keep their Pod.
+    async fn record_pods(
+        &self,
+        pods: &HashMap<String, SlotPod>,
+        pod_client: &dyn IntoApi<Pod>,
+    ) -> Result<(), DevicePluginError> {
+        let slots_status = self.slots_status.lock().await;
+        let slots = slots_status.borrow().slots.clone();
+        let mut recorded = HashMap::new();
+        for (i, slot) in slots.iter().enumerate() {
+            let device_id = match &slot.usage {
+                DeviceUsage::Node(node) if *node == self.node_name => {
+                    format!("{}-{}", self.instance_name, i)
+                }
+                DeviceUsage::Configuration { vdev, node } if *node == self.node_name => {
+                    vdev.to_owned()
+                }
+                _ => continue,
+            };
+            let Some(pod) = pods.get(&device_id) else {
+                continue;
+            };
+            if slot
+                .pod
+                .as_ref()
+                .is_some_and(|p| p.namespace == pod.namespace && p.name == pod.name)
+            {
+                continue;
+            }
+            let uid = pod_client
+                .namespaced(&pod.namespace)
+                .get(&pod.name)
+                .await
+                .map_err(|e| DevicePluginError::Other(e.into()))?
+                .and_then(|p| p.metadata.uid);
+            recorded.insert(i, SlotPod { uid, ..pod.clone() });
+        }
+        if recorded.is_empty() {
+            return Ok(());
+        }
+        slots_status.send_modify(|slots| {
+            for (i, pod) in recorded {
+                slots.slots[i].pod = Some(pod);
+            }
+        });
+        let device_usage = self.owned_device_usage(&slots_status.borrow());
+        self.apply_device_usage(device_usage).await
+    }
+
+    /// Returns the usage of the slots held by this node, keyed by their identifier
+    fn owned_device_usage(&self, slots: &InstanceSlots) -> HashMap<String, SlotUsage> {
+        slots
+            .slots
             .iter()
             .enumerate()
-            .filter_map(|(i, v)| match v {
-                v if v.is_owned_by(&self.node_name) => {
-                    Some((format!("{}-{}", self.instance_name, i), v.to_string()))
+            .filter(|(_, s)| s.usage.is_owned_by(&self.node_name))
+            .map(|(i, s)| (format!("{}-{}", self.instance_name, i), s.into()))
+            .collect()
+    }
+
+    /// Server side applies the slots held by this node, the slots it no longer holds are
+    /// thus removed from the Instance
+    async fn apply_device_usage(
+        &self,
+        device_usage: HashMap<String, SlotUsage>,
+    ) -> Result<(), DevicePluginError> {
+        // The legacy encoding can't hold the resource claim of a DRA slot, which is needed to free
+        // it once the claim is unprepared, those are always written in their structured form
+        let device_usage = device_usage
+            .into_iter()
+            .map(|(slot, usage)| {
+                match self.structured_slot_usage || usage.kind == SlotUsageKind::Dra {
+                    true => (slot, AnySlotUsage::Structured(usage)),
+                    false => (slot, AnySlotUsage::Legacy(usage.to_legacy())),
                 }
-                _ => None,
             })
             .collect();
         let api = self.kube_client.namespaced(&self.instance_namespace);
@@ -303,28 +543,53 @@ impl InstanceDevicePlugin {
         .await
         .map_err(|e| match e {
             kube::Error::Api(ae) => match ae.code {
-                409 => DevicePluginError::SlotInUse,
+                409 => {
+                    trace!("Conflict on apply {:?}", ae);
+                    DevicePluginError::SlotInUse
+                }
                 _ => DevicePluginError::Other(ae.into()),
             },
             e => DevicePluginError::Other(e.into()),
         })?;
         Ok(())
     }
+
+    /// Records a warning for a slot that is used by another node or device
+    async fn record_slot_conflict(&self, id: usize) {
+        self.recorder
+            .record(
+                &self.instance_ref,
+                EventInfo::warning(
+                    "SlotConflict",
+                    "Allocate",
+                    format!(
+                        "Slot {} is already in use, node {} cannot use it",
+                        id, self.node_name
+                    ),
+                ),
+            )
+            .await;
+    }
 }
 
 fn instance_device_usage_to_device(
     device_name: &str,
     node_name: &str,
-    devices: Vec<DeviceUsage>,
+    devices: InstanceSlots,
 ) -> Result<ListAndWatchResponse, tonic::Status> {
+    let healthy = devices.healthy;
     let devices = devices
+        .slots
         .into_iter()
         .enumerate()
         .map(|(id, dev)| super::v1beta1::Device {
             id: format!("{}-{}", device_name, id),
-            health: match dev {
+            health: match dev.usage {
+                _ if !healthy => "Unhealthy",
                 DeviceUsage::Unused => "Healthy",
-                DeviceUsage::Configuration { .. } => "Unhealthy",
+                // Slots claimed by the DRA plugin are reported as unhealthy so that the kubelet
+                // doesn't allocate them
+                DeviceUsage::Configuration { .. } | DeviceUsage::Dra { .. } => "Unhealthy",
                 DeviceUsage::Node(n) => match n == node_name {
                     true => "Healthy",
                     false => "Unhealthy",
@@ -340,7 +605,7 @@ fn instance_device_usage_to_device(
 
 #[async_trait]
 impl InternalDevicePlugin for InstanceDevicePlugin {
-    type DeviceStore = Vec<DeviceUsage>;
+    type DeviceStore = InstanceSlots;
 
     fn get_name(&self) -> String {
         self.instance_name.clone()
@@ -392,11 +657,7 @@ impl InternalDevicePlugin for InstanceDevicePlugin {
         for allocate_request in reqs {
             let devices = allocate_request.devices_i_ds;
             for device in devices {
-                let (_, id) = device
-                    .rsplit_once('-')
-                    .ok_or(tonic::Status::unknown("Invalid device id"))?;
-                let id = id
-                    .parse::<usize>()
+                let id = parse_slot_id(&self.instance_name, &device)
                     .or(Err(tonic::Status::unknown("Invalid device id")))?;
                 self.claim_slot(Some(id), DeviceUsage::Node(self.node_name.to_owned()))
                     .await
@@ -474,25 +735,146 @@ enum ConfigurationSlot {
     DeviceUsed { device: String, slot_id: usize },
 }
 
+/// Virtual device considered for a preferred allocation
+#[derive(Clone, Debug, PartialEq)]
+struct AllocationCandidate {
+    id: String,
+    instance: String,
+    used_slots: usize,
+    property: Option<String>,
+}
+
+/// Chooses `size` virtual devices among the candidates according to the allocation policy,
+/// devices from distinct Instances are always preferred over several devices of the same Instance.
+fn preferred_allocation(
+    policy: &AllocationPolicy,
+    must_include: Vec<AllocationCandidate>,
+    mut available: Vec<AllocationCandidate>,
+    size: usize,
+) -> Vec<String> {
+    available.retain(|c| !must_include.iter().any(|m| m.id == c.id));
+    let strategy = match (policy.strategy, &policy.property) {
+        (AllocationStrategy::Affinity, None) => {
+            warn!("Affinity allocation policy without a property, falling back to spread");
+            AllocationStrategy::Spread
+        }
+        (strategy, _) => strategy,
+    };
+    match strategy {
+        AllocationStrategy::Spread => {
+            available.sort_by(|a, b| a.used_slots.cmp(&b.used_slots).then(a.id.cmp(&b.id)))
+        }
+        AllocationStrategy::Pack => {
+            available.sort_by(|a, b| b.used_slots.cmp(&a.used_slots).then(a.id.cmp(&b.id)))
+        }
+        AllocationStrategy::Affinity => {
+            // Prefer the group the mandatory devices belong to, or the one with the most
+            // Instances otherwise
+            let mut groups = must_include
+                .iter()
+                .unique_by(|c| &c.instance)
+                .filter_map(|c| c.property.clone())
+                .counts();
+            if groups.is_empty() {
+                groups = available
+                    .iter()
+                    .unique_by(|c| &c.instance)
+                    .filter_map(|c| c.property.clone())
+                    .counts();
+            }
+            let preferred_group = groups
+                .into_iter()
+                .min_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then(a.cmp(b)))
+                .map(|(group, _)| group);
+            available.sort_by(|a, b| {
+                let a_out = a.property.is_none() || a.property != preferred_group;
+                let b_out = b.property.is_none() || b.property != preferred_group;
+                a_out
+                    .cmp(&b_out)
+                    .then(a.used_slots.cmp(&b.used_slots))
+                    .then(a.id.cmp(&b.id))
+            })
+        }
+    }
+    let mut chosen_instances: HashSet<String> =
+        must_include.iter().map(|c| c.instance.clone()).collect();
+    let mut chosen: Vec<String> = must_include.into_iter().map(|c| c.id).collect();
+    let remaining = size.saturating_sub(chosen.len());
+    let (distinct, same_instance): (Vec<_>, Vec<_>) = available
+        .into_iter()
+        .partition(|c| chosen_instances.insert(c.instance.clone()));
+    chosen.extend(
+        distinct
+            .into_iter()
+            .chain(same_instance)
+            .map(|c| c.id)
+            .take(remaining),
+    );
+    chosen
+}
+
 struct ConfigurationDevicePlugin {
     instances: RwLock<HashMap<String, Arc<InstanceDevicePlugin>>>,
     slots: Arc<RwLock<watch::Sender<HashMap<String, ConfigurationSlot>>>>,
+    config_cache: Store<Configuration>,
     config_name: String,
+    config_namespace: String,
     node_name: String,
     stopper: Stopper,
 }
 
 impl ConfigurationDevicePlugin {
-    fn new(config_name: String, node_name: String) -> Self {
+    fn new(
+        config_name: String,
+        config_namespace: String,
+        node_name: String,
+        config_cache: Store<Configuration>,
+    ) -> Self {
         let (slots, _) = watch::channel(Default::default());
         Self {
             instances: Default::default(),
             slots: Arc::new(RwLock::new(slots)),
+            config_cache,
             config_name,
+            config_namespace,
             node_name,
             stopper: Stopper::new(),
         }
     }
+
+    /// Reads the allocation policy of the cached Configuration, so that a change of the policy applies
+    /// to the next allocation
+    fn allocation_policy(&self) -> AllocationPolicy {
+        let config_ref =
+            ObjectRef::<Configuration>::new(&self.config_name).within(&self.config_namespace);
+        self.config_cache
+            .get(&config_ref)
+            .and_then(|config| config.spec.allocation_policy.clone())
+            .unwrap_or_default()
+    }
+
+    async fn get_allocation_candidate(
+        &self,
+        id: &str,
+        slots: &HashMap<String, ConfigurationSlot>,
+        policy: &AllocationPolicy,
+    ) -> Option<AllocationCandidate> {
+        let instance = match slots.get(id)? {
+            ConfigurationSlot::DeviceFree(instance) => instance,
+            ConfigurationSlot::DeviceUsed { device, .. } => device,
+        };
+        let plugin = self.instances.read().await.get(instance).cloned()?;
+        let property = match &policy.property {
+            Some(name) => plugin.get_property(name),
+            None => None,
+        };
+        Some(AllocationCandidate {
+            id: id.to_owned(),
+            instance: instance.to_owned(),
+            used_slots: plugin.used_slots_count().await,
+            property,
+        })
+    }
     async fn add_plugin(&self, name: String, plugin: Arc<InstanceDevicePlugin>) {
         self.instances
             .write()
@@ -508,11 +890,14 @@ impl ConfigurationDevicePlugin {
                 {
                     let (has_free, used_config_slots) = {
                         let values = receiver.borrow_and_update();
-                        let has_free = values.contains(&DeviceUsage::Unused);
+                        // An unhealthy device doesn't offer any free slot to the Configuration
+                        let has_free = values.healthy
+                            && values.slots.iter().any(|s| s.usage == DeviceUsage::Unused);
                         let used_config_slots: HashMap<String, ConfigurationSlot> = values
+                            .slots
                             .iter()
                             .enumerate()
-                            .filter_map(|(slot, du)| match du {
+                            .filter_map(|(slot, s)| match &s.usage {
                                 DeviceUsage::Configuration { vdev, node } if *node == node_name => {
                                     Some((
                                         vdev.clone(),
@@ -705,6 +1090,57 @@ impl InternalDevicePlugin for ConfigurationDevicePlugin {
             container_responses,
         }))
     }
+
+    fn preferred_allocation_available(&self) -> bool {
+        true
+    }
+
+    /// Kubelet calls get_preferred_allocation when a container requests several virtual devices of the
+    /// Configuration, this orders the available devices according to the Configuration's allocation policy.
+    async fn get_preferred_allocation(
+        &self,
+        requests: Request<PreferredAllocationRequest>,
+    ) -> Result<tonic::Response<PreferredAllocationResponse>, tonic::Status> {
+        info!(
+            "get_preferred_allocation - kubelet called get_preferred_allocation for Configuration {}",
+            self.config_name
+        );
+        let slots = self.slots.read().await.borrow().clone();
+        let policy = self.allocation_policy();
+        let mut container_responses = Vec::new();
+        for request in requests.into_inner().container_requests {
+            let mut must_include = Vec::new();
+            for id in request.must_include_device_i_ds.iter() {
+                must_include.push(
+                    self.get_allocation_candidate(id, &slots, &policy)
+                        .await
+                        .unwrap_or(AllocationCandidate {
+                            id: id.to_owned(),
+                            instance: id.to_owned(),
+                            used_slots: 0,
+                            property: None,
+                        }),
+                );
+            }
+            let mut available = Vec::new();
+            for id in request.available_device_i_ds.iter() {
+                if let Some(candidate) = self.get_allocation_candidate(id, &slots, &policy).await {
+                    available.push(candidate);
+                }
+            }
+            container_responses.push(ContainerPreferredAllocationResponse {
+                device_i_ds: preferred_allocation(
+                    &policy,
+                    must_include,
+                    available,
+                    request.allocation_size.max(0) as usize,
+                ),
+            });
+        }
+        Ok(tonic::Response::new(PreferredAllocationResponse {
+            container_responses,
+        }))
+    }
 }
 
 fn config_device_usage_to_device(
@@ -726,13 +1162,32 @@ fn config_device_usage_to_device(
 
 /// This module implements a controller for Instance resources that will ensure device plugins are correctly created with the correct health status
 
+/// Claims Instance slots on behalf of the DRA plugin, so that a slot can't be handed out by both the
+/// DRA plugin and the device plugin
+#[cfg_attr(test, automock)]
+#[async_trait]
+pub trait DraSlotClaimer: Send + Sync {
+    /// Claims a free slot of the Instance for the given resource claim, returns the slot's device ID
+    async fn claim_dra_slot(
+        &self,
+        instance_name: &str,
+        claim_uid: &str,
+    ) -> Result<String, DevicePluginError>;
+    /// Frees a slot claimed with `claim_dra_slot`
+    async fn free_dra_slot(&self, device_id: &str) -> Result<(), DevicePluginError>;
+}
+
 pub struct DevicePluginManager {
     instance_plugins: Mutex<HashMap<String, Arc<InstanceDevicePlugin>>>,
     configuration_plugins: Mutex<HashMap<String, Arc<ConfigurationDevicePlugin>>>,
     node_name: String,
     kube_client: Arc<dyn IntoApi<Instance>>,
+    config_cache: Store<Configuration>,
+    pod_client: Arc<dyn IntoApi<Pod>>,
     device_manager: Arc<dyn DeviceManager>,
+    recorder: Arc<dyn EventRecorder>,
     error_backoffs: std::sync::Mutex<HashMap<String, Duration>>,
+    structured_slot_usage: bool,
 }
 
 const SUCCESS_REQUEUE: Duration = Duration::from_secs(600);
@@ -741,15 +1196,23 @@ impl DevicePluginManager {
     pub fn new(
         node_name: String,
         kube_client: Arc<dyn IntoApi<Instance>>,
+        config_cache: Store<Configuration>,
+        pod_client: Arc<dyn IntoApi<Pod>>,
         device_manager: Arc<dyn DeviceManager>,
+        recorder: Arc<dyn EventRecorder>,
+        structured_slot_usage: bool,
     ) -> Self {
         Self {
             instance_plugins: Mutex::new(HashMap::default()),
             configuration_plugins: Mutex::new(HashMap::default()),
             node_name,
             kube_client,
+            config_cache,
+            pod_client,
             device_manager,
+            recorder,
             error_backoffs: std::sync::Mutex::new(HashMap::default()),
+            structured_slot_usage,
         }
     }
 
@@ -783,15 +1246,16 @@ impl DevicePluginManager {
     pub async fn get_used_slots(&self) -> HashSet<String> {
         let mut slots: HashSet<String> = Default::default();
         for (instance, plugin) in self.instance_plugins.lock().await.iter() {
+            let slots_status = plugin.slots_status.lock().await;
+            let instance_slots = slots_status.borrow();
             slots.extend(
-                plugin
-                    .slots_status
-                    .lock()
-                    .await
-                    .borrow()
+                instance_slots
+                    .slots
                     .iter()
                     .enumerate()
-                    .filter_map(|(i, u)| match u {
+                    // Slots claimed by the DRA plugin are freed when their claim is unprepared,
+                    // so they are not listed
+                    .filter_map(|(i, s)| match &s.usage {
                         DeviceUsage::Node(n) if *n == self.node_name => {
                             Some(format!("{}{}-{}", DP_SLOT_PREFIX, instance, i))
                         }
@@ -804,6 +1268,60 @@ impl DevicePluginManager {
         }
         slots
     }
+
+    /// Records on the slots held by this node the Pods they are allocated to, keyed by device ID
+    pub async fn record_slot_pods(&self, pods: &HashMap<String, SlotPod>) {
+        // The legacy encoding of the slots can't hold their Pod
+        if !self.structured_slot_usage {
+            return;
+        }
+        let plugins = self
+            .instance_plugins
+            .lock()
+            .await
+            .values()
+            .cloned()
+            .collect_vec();
+        for plugin in plugins {
+            if let Err(e) = plugin.record_pods(pods, self.pod_client.as_ref()).await {
+                warn!(
+                    "Unable to record the Pods using Instance {}: {:?}",
+                    plugin.instance_name, e
+                );
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl DraSlotClaimer for DevicePluginManager {
+    async fn claim_dra_slot(
+        &self,
+        instance_name: &str,
+        claim_uid: &str,
+    ) -> Result<String, DevicePluginError> {
+        let plugin = self
+            .instance_plugins
+            .lock()
+            .await
+            .get(instance_name)
+            .cloned()
+            .ok_or_else(|| DevicePluginError::UnknownDevice(instance_name.to_owned()))?;
+        let id = plugin
+            .claim_slot(
+                None,
+                DeviceUsage::Dra {
+                    node: self.node_name.clone(),
+                    claim_uid: claim_uid.to_owned(),
+                },
+            )
+            .await?;
+        Ok(format!("{}-{}", instance_name, id))
+    }
+
+    async fn free_dra_slot(&self, device_id: &str) -> Result<(), DevicePluginError> {
+        self.free_slot(device_id.to_owned()).await
+    }
 }
 
 pub fn start_dpm(dpm: Arc<DevicePluginManager>) -> (Store<Instance>, JoinHandle<()>) {
@@ -860,13 +1378,14 @@ pub async fn reconcile(
                 None => {
                     let plugin = Arc::new(InstanceDevicePlugin::new(
                         ctx.node_name.to_owned(),
-                        instance.name_any(),
-                        instance.namespace().unwrap_or("default".to_string()),
+                        &instance,
                         device,
-                        &instance.spec.device_usage,
-                        instance.spec.capacity,
                         ctx.kube_client.clone(),
+                        ctx.recorder.clone(),
+                        ctx.structured_slot_usage,
                     )?);
+                    // Slots held before a restart may be written in another form
+                    plugin.reapply_slots().await?;
                     serve_and_register_plugin(plugin.clone()).await?;
                     instance_plugins.insert(instance.name_any(), plugin.clone());
                     plugin
@@ -874,6 +1393,9 @@ pub async fn reconcile(
                 Some(plugin) => {
                     // TODO: Add a way to handle a change in the instance's capacity.
                     plugin.update_slots(&instance.spec.device_usage).await?;
+                    plugin
+                        .update_health(&instance.node_health(&ctx.node_name))
+                        .await;
                     plugin.clone()
                 }
             }
@@ -884,7 +1406,9 @@ pub async fn reconcile(
                 None => {
                     let plugin = Arc::new(ConfigurationDevicePlugin::new(
                         instance.spec.configuration_name.to_owned(),
+                        instance.namespace().unwrap_or("default".to_string()),
                         ctx.node_name.to_owned(),
+                        ctx.config_cache.clone(),
                     ));
                     serve_and_register_plugin(plugin.clone()).await?;
                     configuration_plugins
@@ -933,8 +1457,12 @@ mod tests {
 
     use akri_shared::{
         akri::instance::InstanceSpec,
-        k8s::api::{MockApi, MockIntoApi},
+        k8s::{
+            api::{MockApi, MockIntoApi},
+            event::MockEventRecorder,
+        },
     };
+    use kube_runtime::{reflector, watcher};
     use tokio_stream::StreamExt;
 
     use crate::plugin_manager::v1beta1::ContainerAllocateRequest;
@@ -943,64 +1471,196 @@ mod tests {
 
     use super::*;
 
+    fn configuration_plugin(config_cache: Store<Configuration>) -> ConfigurationDevicePlugin {
+        ConfigurationDevicePlugin::new(
+            "config-a".to_owned(),
+            "default".to_owned(),
+            "node-a".to_owned(),
+            config_cache,
+        )
+    }
+
+    fn configuration(name: &str, allocation_policy: AllocationPolicy) -> Configuration {
+        let mut configuration: Configuration = serde_json::from_value(serde_json::json!({
+            "apiVersion": "akri.sh/v0",
+            "kind": "Configuration",
+            "metadata": {"name": name, "namespace": "default"},
+            "spec": {"discoveryHandler": {"name": "debugEcho", "discoveryDetails": ""}},
+        }))
+        .unwrap();
+        configuration.spec.allocation_policy = Some(allocation_policy);
+        configuration
+    }
+
+    fn recorder() -> Arc<dyn EventRecorder> {
+        let mut recorder = MockEventRecorder::new();
+        recorder.expect_record().returning(|_, _| ());
+        Arc::new(recorder)
+    }
+
+    fn instance(
+        name: &str,
+        device_usage: &HashMap<String, SlotUsage>,
+        capacity: usize,
+    ) -> Instance {
+        Instance {
+            metadata: ObjectMeta {
+                name: Some(name.to_owned()),
+                namespace: Some("namespace-a".to_owned()),
+                ..Default::default()
+            },
+            spec: InstanceSpec {
+                configuration_name: "config-a".to_owned(),
+                cdi_name: Default::default(),
+                capacity,
+                broker_properties: Default::default(),
+                shared: false,
+                nodes: Default::default(),
+                device_usage: device_usage.clone(),
+            },
+            status: None,
+        }
+    }
+
+    fn instance_slots(usages: impl IntoIterator<Item = DeviceUsage>) -> InstanceSlots {
+        InstanceSlots {
+            slots: usages.into_iter().map(Slot::from).collect(),
+            healthy: true,
+        }
+    }
+
     #[test]
-    fn test_device_usage() -> Result<(), DevicePluginError> {
+    fn test_slot_usage() -> Result<(), DevicePluginError> {
+        for (legacy, usage) in [
+            ("", DeviceUsage::Unused),
+            ("node-a", DeviceUsage::Node("node-a".to_string())),
+            ("node:a", DeviceUsage::Node("node:a".to_string())),
+            (
+                "C:vdev1:node-a",
+                DeviceUsage::Configuration {
+                    vdev: "vdev1".to_string(),
+                    node: "node-a".to_string(),
+                },
+            ),
+            (
+                "C:vdev1:node:a",
+                DeviceUsage::Configuration {
+                    vdev: "vdev1".to_string(),
+                    node: "node:a".to_string(),
+                },
+            ),
+        ] {
+            let slot = Slot::try_from(&SlotUsage::from_legacy(legacy))?;
+            assert_eq!(slot, Slot::from(usage));
+            assert_eq!(SlotUsage::from(&slot), SlotUsage::from_legacy(legacy));
+        }
+
+        let claimed = SlotUsage {
+            kind: SlotUsageKind::Instance,
+            node: Some("node-a".to_string()),
+            pod_namespace: Some("default".to_string()),
+            pod_name: Some("broker-a".to_string()),
+            pod_uid: Some("8d7e6f".to_string()),
+            claim_time: Some(Time(Utc::now())),
+            ..Default::default()
+        };
+        let slot = Slot::try_from(&claimed)?;
         assert_eq!(
-            DeviceUsage::from_str("node-a")?,
-            DeviceUsage::Node("node-a".to_string())
+            slot.pod,
+            Some(SlotPod {
+                namespace: "default".to_string(),
+                name: "broker-a".to_string(),
+                uid: Some("8d7e6f".to_string()),
+            })
         );
+        assert_eq!(slot.claim_time, claimed.claim_time);
+        assert_eq!(SlotUsage::from(&slot), claimed);
+
+        let dra = SlotUsage {
+            kind: SlotUsageKind::Dra,
+            node: Some("node-a".to_string()),
+            claim_uid: Some("claim-uid".to_string()),
+            ..Default::default()
+        };
+        let slot = Slot::try_from(&dra)?;
         assert_eq!(
-            DeviceUsage::from_str("C:vdev1:node-a")?,
-            DeviceUsage::Configuration {
-                vdev: "vdev1".to_string(),
-                node: "node-a".to_string()
-            },
+            slot.usage,
+            DeviceUsage::Dra {
+                node: "node-a".to_string(),
+                claim_uid: "claim-uid".to_string(),
+            }
         );
-        assert_eq!(DeviceUsage::from_str("")?, DeviceUsage::Unused,);
-        assert!(DeviceUsage::from_str("C:node-a").is_err());
+        assert_eq!(SlotUsage::from(&slot), dra);
 
+        assert!(Slot::try_from(&SlotUsage {
+            kind: SlotUsageKind::Instance,
+            ..Default::default()
+        })
+        .is_err());
+        assert!(Slot::try_from(&SlotUsage {
+            kind: SlotUsageKind::Dra,
+            node: Some("node-a".to_string()),
+            ..Default::default()
+        })
+        .is_err());
+        assert!(Slot::try_from(&SlotUsage {
+            kind: SlotUsageKind::Configuration,
+            node: Some("node-a".to_string()),
+            ..Default::default()
+        })
+        .is_err());
         Ok(())
     }
 
     #[test]
     fn test_parse_slot_id() -> Result<(), DevicePluginError> {
-        assert_eq!(parse_slot_id("slot-1")?, 1);
-        assert_eq!(parse_slot_id("my-other-slot-2")?, 2);
-        assert!(parse_slot_id("not-a-slot-id").is_err());
+        assert_eq!(parse_slot_id("slot", "slot-1")?, 1);
+        assert_eq!(parse_slot_id("my-other-slot", "my-other-slot-2")?, 2);
+        assert!(parse_slot_id("slot", "not-a-slot-id").is_err());
+        assert!(parse_slot_id("slot", "other-slot-1").is_err());
+        assert!(parse_slot_id("slot", "slot1").is_err());
         Ok(())
     }
 
     #[test]
     fn test_construct_slots_map() -> Result<(), DevicePluginError> {
         let slots = HashMap::from([
-            ("slot-1".to_owned(), "node-a".to_owned()),
-            ("slot-3".to_owned(), "C:vdev1:node-a".to_owned()),
+            ("slot-1".to_owned(), SlotUsage::from_legacy("node-a")),
+            (
+                "slot-3".to_owned(),
+                SlotUsage::from_legacy("C:vdev1:node-a"),
+            ),
         ]);
         assert_eq!(
-            construct_slots_map(&slots)?,
+            construct_slots_map("slot", &slots)?,
             HashMap::from([
-                (1, DeviceUsage::Node("node-a".to_owned())),
+                (1, DeviceUsage::Node("node-a".to_owned()).into()),
                 (
                     3,
                     DeviceUsage::Configuration {
                         vdev: "vdev1".to_owned(),
                         node: "node-a".to_owned()
                     }
+                    .into()
                 )
             ])
         );
+        assert!(construct_slots_map("other", &slots).is_err());
         Ok(())
     }
 
     #[test]
     fn test_construct_slots_vec() -> Result<(), DevicePluginError> {
         let slots = HashMap::from([
-            ("slot-1".to_owned(), "node-a".to_owned()),
-            ("slot-3".to_owned(), "C:vdev1:node-a".to_owned()),
+            ("slot-1".to_owned(), SlotUsage::from_legacy("node-a")),
+            (
+                "slot-3".to_owned(),
+                SlotUsage::from_legacy("C:vdev1:node-a"),
+            ),
         ]);
         assert_eq!(
-            construct_slots_vec(&slots, 4)?,
-            vec![
+            construct_slots_vec("slot", &slots, 4)?,
+            instance_slots([
                 DeviceUsage::Unused,
                 DeviceUsage::Node("node-a".to_string()),
                 DeviceUsage::Unused,
@@ -1008,9 +1668,10 @@ mod tests {
                     vdev: "vdev1".to_owned(),
                     node: "node-a".to_owned()
                 }
-            ]
+            ])
+            .slots
         );
-        assert!(construct_slots_vec(&slots, 1).is_err());
+        assert!(construct_slots_vec("slot", &slots, 1).is_err());
         Ok(())
     }
 
@@ -1018,8 +1679,7 @@ mod tests {
     async fn test_instance_plugin_update_slots() {
         let plugin = InstanceDevicePlugin::new(
             "node-a".to_owned(),
-            "my-device".to_owned(),
-            "namespace-a".to_owned(),
+            &instance("my-device", &HashMap::new(), 3),
             Device {
                 name: "my-device".to_owned(),
                 annotations: Default::default(),
@@ -1027,23 +1687,251 @@ mod tests {
                     ..Default::default()
                 },
             },
-            &HashMap::new(),
-            3,
             Arc::new(MockIntoApi::new()),
+            recorder(),
+            true,
         )
         .unwrap();
 
         assert!(plugin
-            .update_slots(&HashMap::from([("slot-1".to_owned(), "node-a".to_owned())]))
+            .update_slots(&HashMap::from([(
+                "my-device-1".to_owned(),
+                SlotUsage::from_legacy("node-a"),
+            )]))
             .await
             .is_ok(),);
 
         assert_eq!(
-            plugin.slots_status.lock().await.borrow()[1],
+            plugin.slots_status.lock().await.borrow().slots[1].usage,
             DeviceUsage::Node("node-a".to_owned())
         );
     }
 
+    #[tokio::test]
+    async fn test_instance_plugin_reapply_slots() {
+        let device = Device {
+            name: "my-device".to_owned(),
+            annotations: Default::default(),
+            container_edits: Default::default(),
+        };
+        // Nothing is applied when the node holds no slot
+        let plugin = InstanceDevicePlugin::new(
+            "node-a".to_owned(),
+            &instance(
+                "instance-a",
+                &HashMap::from([("instance-a-0".to_owned(), SlotUsage::from_legacy("node-b"))]),
+                2,
+            ),
+            device.clone(),
+            Arc::new(MockIntoApi::new()),
+            recorder(),
+            true,
+        )
+        .unwrap();
+        assert!(plugin.reapply_slots().await.is_ok());
+
+        // Held slots are rewritten in the form the Agent writes
+        for (structured_slot_usage, expected) in [
+            (
+                true,
+                serde_json::json!({
+                    "instance-a-1": {"kind": "Configuration", "node": "node-a", "vdev": "vdev0"}
+                }),
+            ),
+            (false, serde_json::json!({"instance-a-1": "C:vdev0:node-a"})),
+        ] {
+            let mut kube_client = MockIntoApi::new();
+            kube_client.expect_namespaced().returning(move |_| {
+                let expected = expected.clone();
+                let mut api = MockApi::new();
+                api.expect_raw_patch()
+                    .with(
+                        mockall::predicate::eq("instance-a"),
+                        mockall::predicate::function(move |a: &Patch<serde_json::Value>| match a {
+                            Patch::Apply(v) => v["spec"]["deviceUsage"] == expected,
+                            _ => false,
+                        }),
+                        mockall::predicate::always(),
+                    )
+                    .times(1)
+                    .returning(|_, _, _| Ok(instance("instance-a", &HashMap::new(), 2)));
+                Box::new(api)
+            });
+            let plugin = InstanceDevicePlugin::new(
+                "node-a".to_owned(),
+                &instance(
+                    "instance-a",
+                    &HashMap::from([
+                        ("instance-a-0".to_owned(), SlotUsage::from_legacy("node-b")),
+                        (
+                            "instance-a-1".to_owned(),
+                            SlotUsage::from_legacy("C:vdev0:node-a"),
+                        ),
+                    ]),
+                    2,
+                ),
+                device.clone(),
+                Arc::new(kube_client),
+                recorder(),
+                structured_slot_usage,
+            )
+            .unwrap();
+            assert!(plugin.reapply_slots().await.is_ok());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_instance_plugin_record_pods() {
+        let mut kube_client = MockIntoApi::new();
+        kube_client.expect_namespaced().returning(|_| {
+            let mut api = MockApi::new();
+            api.expect_raw_patch()
+                .with(
+                    mockall::predicate::eq("instance-a"),
+                    mockall::predicate::function(|a: &Patch<serde_json::Value>| match a {
+                        Patch::Apply(v) => {
+                            v["spec"]["deviceUsage"]
+                                == serde_json::json!({
+                                    "instance-a-0": {"kind": "Instance", "node": "node-a", "podNamespace": "namespace-a", "podName": "pod-a", "podUid": "uid-pod-a"},
+                                    "instance-a-1": {"kind": "Configuration", "node": "node-a", "vdev": "config-a-0", "podNamespace": "namespace-a", "podName": "pod-b", "podUid": "uid-pod-b"},
+                                    "instance-a-3": {"kind": "Instance", "node": "node-a"},
+                                })
+                        }
+                        _ => false,
+                    }),
+                    mockall::predicate::always(),
+                )
+                .times(1)
+                .returning(|_, _, _| Ok(instance("instance-a", &HashMap::new(), 4)));
+            Box::new(api)
+        });
+        let mut pod_client = MockIntoApi::<Pod>::new();
+        pod_client
+            .expect_namespaced()
+            .with(mockall::predicate::eq("namespace-a"))
+            .times(2)
+            .returning(|_| {
+                let mut api = MockApi::new();
+                api.expect_get().times(1).returning(|name| {
+                    Ok(Some(Pod {
+                        metadata: ObjectMeta {
+                            name: Some(name.to_owned()),
+                            uid: Some(format!("uid-{}", name)),
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    }))
+                });
+                Box::new(api)
+            });
+        let (s, _) = watch::channel(instance_slots([
+            DeviceUsage::Node("node-a".to_owned()),
+            DeviceUsage::Configuration {
+                vdev: "config-a-0".to_owned(),
+                node: "node-a".to_owned(),
+            },
+            DeviceUsage::Node("node-b".to_owned()),
+            DeviceUsage::Node("node-a".to_owned()),
+        ]));
+        let instance_plugin = InstanceDevicePlugin {
+            device: Device {
+                name: "my-device".to_owned(),
+                annotations: Default::default(),
+                container_edits: Default::default(),
+            },
+            slots_status: Mutex::new(s),
+            node_name: "node-a".to_owned(),
+            instance_name: "instance-a".to_owned(),
+            instance_namespace: "namespace-a".to_owned(),
+            instance_ref: Default::default(),
+            kube_client: Arc::new(kube_client),
+            recorder: recorder(),
+            stopper: Stopper::new(),
+            structured_slot_usage: true,
+        };
+        let pod = |name: &str| SlotPod {
+            namespace: "namespace-a".to_owned(),
+            name: name.to_owned(),
+            uid: None,
+        };
+        // Slots of other nodes are left alone, and the Pod of the last slot is not known yet
+        let pods = HashMap::from([
+            ("instance-a-0".to_owned(), pod("pod-a")),
+            ("config-a-0".to_owned(), pod("pod-b")),
+            ("instance-a-2".to_owned(), pod("pod-c")),
+        ]);
+        instance_plugin
+            .record_pods(&pods, &pod_client)
+            .await
+            .unwrap();
+        assert_eq!(
+            instance_plugin.slots_status.lock().await.borrow().slots[0].pod,
+            Some(SlotPod {
+                uid: Some("uid-pod-a".to_owned()),
+                ..pod("pod-a")
+            })
+        );
+        assert_eq!(
+            instance_plugin.slots_status.lock().await.borrow().slots[2].pod,
+            None
+        );
+        // Pods that are already recorded are neither looked up nor applied again
+        instance_plugin
+            .record_pods(&pods, &pod_client)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_record_slot_pods_legacy_slot_usage() {
+        // Neither the Pods nor the Instances are queried or patched with the legacy encoding
+        let kube_client = Arc::new(MockIntoApi::new());
+        let dpm = DevicePluginManager::new(
+            "node-a".to_owned(),
+            kube_client.clone(),
+            reflector::store().0,
+            Arc::new(MockIntoApi::<Pod>::new()),
+            Arc::new(crate::device_manager::MockDeviceManager::new()),
+            recorder(),
+            false,
+        );
+        let (s, _) = watch::channel(instance_slots([DeviceUsage::Node("node-a".to_owned())]));
+        let instance_plugin = Arc::new(InstanceDevicePlugin {
+            device: Device {
+                name: "my-device".to_owned(),
+                annotations: Default::default(),
+                container_edits: Default::default(),
+            },
+            slots_status: Mutex::new(s),
+            node_name: "node-a".to_owned(),
+            instance_name: "instance-a".to_owned(),
+            instance_namespace: "namespace-a".to_owned(),
+            instance_ref: Default::default(),
+            kube_client,
+            recorder: recorder(),
+            stopper: Stopper::new(),
+            structured_slot_usage: false,
+        });
+        dpm.instance_plugins
+            .lock()
+            .await
+            .insert("instance-a".to_owned(), instance_plugin.clone());
+
+        dpm.record_slot_pods(&HashMap::from([(
+            "instance-a-0".to_owned(),
+            SlotPod {
+                namespace: "namespace-a".to_owned(),
+                name: "pod-a".to_owned(),
+                uid: None,
+            },
+        )]))
+        .await;
+        assert_eq!(
+            instance_plugin.slots_status.lock().await.borrow().slots[0].pod,
+            None
+        );
+    }
+
     #[tokio::test]
     async fn test_free_slot() {
         let dm = crate::device_manager::MockDeviceManager::new();
@@ -1076,23 +1964,42 @@ mod tests {
                             nodes: Default::default(),
                             device_usage: Default::default(),
                         },
+                        status: None,
                     })
                 });
             Box::new(api)
         });
         let kube_client = Arc::new(kube_client);
-        let dpm = DevicePluginManager::new("node-a".to_owned(), kube_client.clone(), Arc::new(dm));
+        let dpm = DevicePluginManager::new(
+            "node-a".to_owned(),
+            kube_client.clone(),
+            reflector::store().0,
+            Arc::new(MockIntoApi::<Pod>::new()),
+            Arc::new(dm),
+            recorder(),
+            true,
+        );
 
         let stopper = Stopper::new();
 
-        let (s, _) = watch::channel(vec![
+        let (s, _) = watch::channel(instance_slots([
             DeviceUsage::Configuration {
                 vdev: "config-a-1".to_owned(),
                 node: "node-a".to_owned(),
             },
             DeviceUsage::Node("node-b".to_owned()),
-        ]);
-
+        ]));
+
+        let mut recorder = MockEventRecorder::new();
+        recorder
+            .expect_record()
+            .withf(|regarding, event| {
+                regarding.name.as_deref() == Some("instance-a")
+                    && event.reason == "SlotFreed"
+                    && event.message == "Slot 0 freed on node node-a"
+            })
+            .times(1)
+            .returning(|_, _| ());
         let instance_plugin = Arc::new(InstanceDevicePlugin {
             device: Device {
                 name: "my-device".to_owned(),
@@ -1105,8 +2012,14 @@ mod tests {
             node_name: "node-a".to_owned(),
             instance_name: "instance-a".to_owned(),
             instance_namespace: "namespace-a".to_owned(),
+            instance_ref: ObjectReference {
+                name: Some("instance-a".to_owned()),
+                ..Default::default()
+            },
             kube_client,
+            recorder: Arc::new(recorder),
             stopper: stopper.clone(),
+            structured_slot_usage: true,
         });
         dpm.instance_plugins
             .lock()
@@ -1126,7 +2039,9 @@ mod tests {
             Arc::new(ConfigurationDevicePlugin {
                 instances: RwLock::new(HashMap::from([("instance-a".to_owned(), instance_plugin)])),
                 slots: Arc::new(RwLock::new(s)),
+                config_cache: reflector::store().0,
                 config_name: "config-a".to_owned(),
+                config_namespace: "default".to_owned(),
                 node_name: "node-a".to_string(),
                 stopper,
             }),
@@ -1141,11 +2056,19 @@ mod tests {
         let dm = crate::device_manager::MockDeviceManager::new();
         let kube_client = Arc::new(MockIntoApi::new());
         let stopper = Stopper::new();
-        let dpm = DevicePluginManager::new("node-a".to_owned(), kube_client.clone(), Arc::new(dm));
+        let dpm = DevicePluginManager::new(
+            "node-a".to_owned(),
+            kube_client.clone(),
+            reflector::store().0,
+            Arc::new(MockIntoApi::<Pod>::new()),
+            Arc::new(dm),
+            recorder(),
+            true,
+        );
 
         assert!(dpm.get_used_slots().await.is_empty());
 
-        let (s, _) = watch::channel(vec![
+        let slots = instance_slots([
             DeviceUsage::Configuration {
                 vdev: "akri.sh/config-a-1".to_owned(),
                 node: "node-a".to_owned(),
@@ -1153,7 +2076,13 @@ mod tests {
             DeviceUsage::Node("node-a".to_owned()),
             DeviceUsage::Node("node-b".to_owned()),
             DeviceUsage::Unused,
+            // Slots claimed by the DRA plugin are not reclaimed
+            DeviceUsage::Dra {
+                node: "node-a".to_owned(),
+                claim_uid: "claim-uid".to_owned(),
+            },
         ]);
+        let (s, _) = watch::channel(slots);
         let instance_plugin = Arc::new(InstanceDevicePlugin {
             device: Device {
                 name: "my-device".to_owned(),
@@ -1166,8 +2095,11 @@ mod tests {
             node_name: "node-a".to_owned(),
             instance_name: "instance-a".to_owned(),
             instance_namespace: "namespace-a".to_owned(),
+            instance_ref: Default::default(),
             kube_client,
+            recorder: recorder(),
             stopper: stopper.clone(),
+            structured_slot_usage: true,
         });
         dpm.instance_plugins
             .lock()
@@ -1186,7 +2118,7 @@ mod tests {
     async fn test_config_plugin_add_remove_plugin() {
         let kube_client = Arc::new(MockIntoApi::new());
         let stopper = Stopper::new();
-        let (s, mut r) = watch::channel(vec![
+        let (s, mut r) = watch::channel(instance_slots([
             DeviceUsage::Configuration {
                 vdev: "akri.sh/config-a-1".to_owned(),
                 node: "node-a".to_owned(),
@@ -1194,7 +2126,7 @@ mod tests {
             DeviceUsage::Node("node-a".to_owned()),
             DeviceUsage::Node("node-b".to_owned()),
             DeviceUsage::Unused,
-        ]);
+        ]));
         let instance_plugin = Arc::new(InstanceDevicePlugin {
             device: Device {
                 name: "my-device".to_owned(),
@@ -1207,12 +2139,14 @@ mod tests {
             node_name: "node-a".to_owned(),
             instance_name: "instance-a".to_owned(),
             instance_namespace: "namespace-a".to_owned(),
+            instance_ref: Default::default(),
             kube_client,
+            recorder: recorder(),
             stopper: stopper.clone(),
+            structured_slot_usage: true,
         });
 
-        let config_plugin =
-            ConfigurationDevicePlugin::new("config-a".to_owned(), "node-a".to_owned());
+        let config_plugin = configuration_plugin(reflector::store().0);
         config_plugin
             .add_plugin("instance-a".to_owned(), instance_plugin.clone())
             .await;
@@ -1242,7 +2176,7 @@ mod tests {
             .slots_status
             .lock()
             .await
-            .send_modify(|slots| slots[3] = DeviceUsage::Node("node-a".to_string()));
+            .send_modify(|slots| slots.slots[3] = DeviceUsage::Node("node-a".to_string()).into());
         drop(instance_plugin);
 
         tokio::time::sleep(Duration::from_millis(500)).await;
@@ -1288,13 +2222,14 @@ mod tests {
                         nodes: Default::default(),
                         device_usage: Default::default(),
                     },
+                    status: None,
                 })
             });
             Box::new(api)
         });
         let kube_client = Arc::new(kube_client);
         let stopper = Stopper::new();
-        let (s, _) = watch::channel(vec![
+        let (s, _) = watch::channel(instance_slots([
             DeviceUsage::Configuration {
                 vdev: "akri.sh/config-a-1".to_owned(),
                 node: "node-a".to_owned(),
@@ -1302,7 +2237,7 @@ mod tests {
             DeviceUsage::Node("node-a".to_owned()),
             DeviceUsage::Node("node-b".to_owned()),
             DeviceUsage::Unused,
-        ]);
+        ]));
         let instance_plugin = Arc::new(InstanceDevicePlugin {
             device: Device {
                 name: "my-device".to_owned(),
@@ -1315,12 +2250,14 @@ mod tests {
             node_name: "node-a".to_owned(),
             instance_name: "instance-a".to_owned(),
             instance_namespace: "namespace-a".to_owned(),
+            instance_ref: Default::default(),
             kube_client,
+            recorder: recorder(),
             stopper: stopper.clone(),
+            structured_slot_usage: true,
         });
 
-        let config_plugin =
-            ConfigurationDevicePlugin::new("config-a".to_owned(), "node-a".to_owned());
+        let config_plugin = configuration_plugin(reflector::store().0);
         config_plugin
             .add_plugin("instance-a".to_owned(), instance_plugin)
             .await;
@@ -1356,15 +2293,34 @@ mod tests {
                             let su: Object<PartialInstanceSlotUsage, NotUsed> =
                                 serde_json::from_value(v.clone()).unwrap();
                             error!("{:?}", su.spec.device_usage);
-                            su.spec.device_usage
-                                == HashMap::from([
-                                    (
-                                        "instance-a-0".to_string(),
-                                        "C:akri.sh/config-a-1:node-a".to_owned(),
-                                    ),
-                                    ("instance-a-1".to_owned(), "node-a".to_owned()),
-                                    ("instance-a-3".to_owned(), "node-a".to_owned()),
-                                ])
+                            let device_usage: HashMap<String, SlotUsage> = su
+                                .spec
+                                .device_usage
+                                .into_iter()
+                                .map(|(k, v)| (k, v.into()))
+                                .collect();
+                            device_usage["instance-a-3"].claim_time.is_some()
+                                && device_usage
+                                    .iter()
+                                    .map(|(k, v)| (k.clone(), Slot::try_from(v).unwrap().usage))
+                                    .collect::<HashMap<_, _>>()
+                                    == HashMap::from([
+                                        (
+                                            "instance-a-0".to_string(),
+                                            DeviceUsage::Configuration {
+                                                vdev: "akri.sh/config-a-1".to_owned(),
+                                                node: "node-a".to_owned(),
+                                            },
+                                        ),
+                                        (
+                                            "instance-a-1".to_owned(),
+                                            DeviceUsage::Node("node-a".to_owned()),
+                                        ),
+                                        (
+                                            "instance-a-3".to_owned(),
+                                            DeviceUsage::Node("node-a".to_owned()),
+                                        ),
+                                    ])
                         }
                         _ => false,
                     }),
@@ -1382,13 +2338,14 @@ mod tests {
                             nodes: Default::default(),
                             device_usage: Default::default(),
                         },
+                        status: None,
                     })
                 });
             Box::new(api)
         });
         let kube_client = Arc::new(kube_client);
         let stopper = Stopper::new();
-        let (s, _) = watch::channel(vec![
+        let (s, _) = watch::channel(instance_slots([
             DeviceUsage::Configuration {
                 vdev: "akri.sh/config-a-1".to_owned(),
                 node: "node-a".to_owned(),
@@ -1396,7 +2353,7 @@ mod tests {
             DeviceUsage::Node("node-a".to_owned()),
             DeviceUsage::Node("node-b".to_owned()),
             DeviceUsage::Unused,
-        ]);
+        ]));
         let instance_plugin = Arc::new(InstanceDevicePlugin {
             device: Device {
                 name: "my-device".to_owned(),
@@ -1409,8 +2366,11 @@ mod tests {
             node_name: "node-a".to_owned(),
             instance_name: "instance-a".to_owned(),
             instance_namespace: "namespace-a".to_owned(),
+            instance_ref: Default::default(),
             kube_client,
+            recorder: recorder(),
             stopper: stopper.clone(),
+            structured_slot_usage: true,
         });
 
         assert!(instance_plugin
@@ -1437,21 +2397,23 @@ mod tests {
         let instance_plugin = Arc::new(
             InstanceDevicePlugin::new(
                 "node-a".to_owned(),
-                "instance-a".to_owned(),
-                "namespace-a".to_owned(),
+                &instance(
+                    "instance-a",
+                    &HashMap::from([("instance-a-1".to_owned(), SlotUsage::from_legacy("node-b"))]),
+                    3,
+                ),
                 Device {
                     name: "my-device".to_string(),
                     annotations: Default::default(),
                     container_edits: Default::default(),
                 },
-                &HashMap::from([("instance-a-1".to_owned(), "node-b".to_owned())]),
-                3,
                 kube_client,
+                recorder(),
+                true,
             )
             .unwrap(),
         );
-        let config_plugin =
-            ConfigurationDevicePlugin::new("config-a".to_owned(), "node-a".to_owned());
+        let config_plugin = configuration_plugin(reflector::store().0);
         config_plugin
             .add_plugin("instance-a".to_owned(), instance_plugin.clone())
             .await;
@@ -1540,4 +2502,245 @@ mod tests {
             }
         );
     }
+
+    #[tokio::test]
+    async fn test_list_and_watch_unhealthy() {
+        let kube_client = Arc::new(MockIntoApi::new());
+        let instance_plugin = Arc::new(
+            InstanceDevicePlugin::new(
+                "node-a".to_owned(),
+                &instance("instance-a", &HashMap::new(), 2),
+                Device {
+                    name: "my-device".to_string(),
+                    annotations: Default::default(),
+                    container_edits: Default::default(),
+                },
+                kube_client,
+                recorder(),
+                true,
+            )
+            .unwrap(),
+        );
+        let config_plugin = configuration_plugin(reflector::store().0);
+        config_plugin
+            .add_plugin("instance-a".to_owned(), instance_plugin.clone())
+            .await;
+
+        let mut instance_stream = instance_plugin.list_and_watch().await.unwrap().into_inner();
+        let mut config_stream = config_plugin.list_and_watch().await.unwrap().into_inner();
+        assert_eq!(
+            instance_stream.next().await.unwrap().unwrap().devices.len(),
+            2
+        );
+        assert_eq!(
+            config_stream.next().await.unwrap().unwrap().devices.len(),
+            0
+        );
+        assert_eq!(
+            config_stream.next().await.unwrap().unwrap().devices.len(),
+            1
+        );
+
+        instance_plugin
+            .update_health(&InstanceHealth {
+                state: akri_shared::akri::instance::HealthState::Unhealthy,
+                reason: Some("unreachable".to_owned()),
+            })
+            .await;
+
+        assert_eq!(
+            instance_stream.next().await.unwrap().unwrap(),
+            ListAndWatchResponse {
+                devices: vec![
+                    crate::plugin_manager::v1beta1::Device {
+                        id: "instance-a-0".to_owned(),
+                        health: "Unhealthy".to_owned(),
+                        topology: None,
+                    },
+                    crate::plugin_manager::v1beta1::Device {
+                        id: "instance-a-1".to_owned(),
+                        health: "Unhealthy".to_owned(),
+                        topology: None,
+                    },
+                ]
+            }
+        );
+        // The free slot offered to the Configuration is withdrawn
+        assert_eq!(
+            config_stream.next().await.unwrap().unwrap(),
+            ListAndWatchResponse { devices: vec![] }
+        );
+        assert!(matches!(
+            instance_plugin
+                .claim_slot(Some(0), DeviceUsage::Node("node-a".to_owned()))
+                .await,
+            Err(DevicePluginError::Unhealthy)
+        ));
+    }
+
+    fn candidate(id: &str, instance: &str, used_slots: usize, bus: &str) -> AllocationCandidate {
+        AllocationCandidate {
+            id: id.to_owned(),
+            instance: instance.to_owned(),
+            used_slots,
+            property: Some(bus.to_owned()),
+        }
+    }
+
+    #[test]
+    fn test_preferred_allocation() {
+        let available = vec![
+            candidate("config-a-0", "instance-a", 2, "bus-1"),
+            candidate("config-a-1", "instance-b", 0, "bus-2"),
+            candidate("config-a-2", "instance-c", 1, "bus-1"),
+            candidate("config-a-3", "instance-b", 0, "bus-2"),
+        ];
+        let spread = AllocationPolicy::default();
+        assert_eq!(
+            preferred_allocation(&spread, vec![], available.clone(), 2),
+            vec!["config-a-1", "config-a-2"]
+        );
+        // Devices of the same Instance are only used when there is no other choice
+        assert_eq!(
+            preferred_allocation(&spread, vec![], available.clone(), 4),
+            vec!["config-a-1", "config-a-2", "config-a-0", "config-a-3"]
+        );
+
+        let pack = AllocationPolicy {
+            strategy: AllocationStrategy::Pack,
+            property: None,
+        };
+        assert_eq!(
+            preferred_allocation(&pack, vec![], available.clone(), 2),
+            vec!["config-a-0", "config-a-2"]
+        );
+
+        let affinity = AllocationPolicy {
+            strategy: AllocationStrategy::Affinity,
+            property: Some("BUS".to_owned()),
+        };
+        assert_eq!(
+            preferred_allocation(&affinity, vec![], available.clone(), 2),
+            vec!["config-a-2", "config-a-0"]
+        );
+        assert_eq!(
+            preferred_allocation(
+                &affinity,
+                vec![candidate("config-a-0", "instance-a", 2, "bus-1")],
+                available.clone(),
+                2
+            ),
+            vec!["config-a-0", "config-a-2"]
+        );
+        // Affinity without property behaves like spread
+        let affinity = AllocationPolicy {
+            strategy: AllocationStrategy::Affinity,
+            property: None,
+        };
+        assert_eq!(
+            preferred_allocation(&affinity, vec![], available, 2),
+            vec!["config-a-1", "config-a-2"]
+        );
+    }
+
+    #[test]
+    fn test_config_plugin_allocation_policy_follows_cache() {
+        let (config_cache, mut config_writer) = reflector::store();
+        let config_plugin = configuration_plugin(config_cache);
+        // The policy defaults to spread until the Configuration is cached
+        assert_eq!(
+            config_plugin.allocation_policy(),
+            AllocationPolicy::default()
+        );
+
+        let pack = AllocationPolicy {
+            strategy: AllocationStrategy::Pack,
+            property: None,
+        };
+        config_writer.apply_watcher_event(&watcher::Event::Applied(configuration(
+            "config-a",
+            pack.clone(),
+        )));
+        assert_eq!(config_plugin.allocation_policy(), pack);
+
+        // A change of the Configuration applies without reconciling its Instances
+        let affinity = AllocationPolicy {
+            strategy: AllocationStrategy::Affinity,
+            property: Some("BUS".to_owned()),
+        };
+        config_writer.apply_watcher_event(&watcher::Event::Applied(configuration(
+            "config-a",
+            affinity.clone(),
+        )));
+        assert_eq!(config_plugin.allocation_policy(), affinity);
+    }
+
+    #[tokio::test]
+    async fn test_config_plugin_get_preferred_allocation() {
+        let kube_client = Arc::new(MockIntoApi::new());
+        let (config_cache, mut config_writer) = reflector::store();
+        config_writer.apply_watcher_event(&watcher::Event::Applied(configuration(
+            "config-a",
+            AllocationPolicy {
+                strategy: AllocationStrategy::Affinity,
+                property: Some("BUS".to_owned()),
+            },
+        )));
+        let config_plugin = configuration_plugin(config_cache);
+        for (name, bus) in [
+            ("instance-a", "1"),
+            ("instance-b", "2"),
+            ("instance-c", "2"),
+        ] {
+            let instance_plugin = Arc::new(
+                InstanceDevicePlugin::new(
+                    "node-a".to_owned(),
+                    &instance(name, &HashMap::new(), 1),
+                    Device {
+                        name: name.to_owned(),
+                        annotations: Default::default(),
+                        container_edits: ContainerEdit {
+                            env: vec![format!("BUS={}", bus)],
+                            ..Default::default()
+                        },
+                    },
+                    kube_client.clone(),
+                    recorder(),
+                    true,
+                )
+                .unwrap(),
+            );
+            config_plugin
+                .add_plugin(name.to_owned(), instance_plugin)
+                .await;
+        }
+        tokio::time::sleep(Duration::from_millis(500)).await;
+
+        let slots = config_plugin.slots.read().await.borrow().clone();
+        let mut preferred = config_plugin
+            .get_preferred_allocation(Request::new(PreferredAllocationRequest {
+                container_requests: vec![
+                    crate::plugin_manager::v1beta1::ContainerPreferredAllocationRequest {
+                        available_device_i_ds: slots.keys().cloned().collect(),
+                        must_include_device_i_ds: vec![],
+                        allocation_size: 2,
+                    },
+                ],
+            }))
+            .await
+            .unwrap()
+            .into_inner()
+            .container_responses
+            .pop()
+            .unwrap()
+            .device_i_ds
+            .into_iter()
+            .map(|id| match slots.get(&id) {
+                Some(ConfigurationSlot::DeviceFree(instance)) => instance.clone(),
+                _ => panic!("Unexpected slot {}", id),
+            })
+            .collect_vec();
+        preferred.sort();
+        assert_eq!(preferred, vec!["instance-b", "instance-c"]);
+    }
 }
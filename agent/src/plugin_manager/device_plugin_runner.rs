@@ -24,7 +24,7 @@ pub const KUBELET_SOCKET: &str = "/var/lib/kubelet/device-plugins/kubelet.sock";
 use super::v1beta1::{
     device_plugin_server::{DevicePlugin, DevicePluginServer},
     registration_client, AllocateRequest, AllocateResponse, DevicePluginOptions, Empty,
-    ListAndWatchResponse, RegisterRequest,
+    ListAndWatchResponse, PreferredAllocationRequest, PreferredAllocationResponse, RegisterRequest,
 };
 
 #[async_trait]
@@ -38,6 +38,20 @@ pub(super) trait InternalDevicePlugin: Sync + Send {
         requests: Request<AllocateRequest>,
     ) -> Result<tonic::Response<AllocateResponse>, tonic::Status>;
 
+    /// Whether the plugin implements `get_preferred_allocation`, advertised to the kubelet
+    fn preferred_allocation_available(&self) -> bool {
+        false
+    }
+    async fn get_preferred_allocation(
+        &self,
+        _requests: Request<PreferredAllocationRequest>,
+    ) -> Result<tonic::Response<PreferredAllocationResponse>, tonic::Status> {
+        error!("get_preferred_allocation - kubelet called get_prefered_allocation",);
+        Err(tonic::Status::unimplemented(
+            "Get preferred allocation is not implemented for this plugin",
+        ))
+    }
+
     fn get_name(&self) -> String;
 
     async fn stopped(&self);
@@ -86,7 +100,7 @@ impl<T: Clone + 'static + Send + Sync> DevicePlugin for DevicePluginImpl<T> {
     ) -> Result<tonic::Response<DevicePluginOptions>, tonic::Status> {
         Ok(tonic::Response::new(DevicePluginOptions {
             pre_start_required: false,
-            get_preferred_allocation_available: false,
+            get_preferred_allocation_available: self.inner.preferred_allocation_available(),
         }))
     }
 
@@ -119,12 +133,10 @@ impl<T: Clone + 'static + Send + Sync> DevicePlugin for DevicePluginImpl<T> {
 
     async fn get_preferred_allocation(
         &self,
-        _request: tonic::Request<super::v1beta1::PreferredAllocationRequest>,
-    ) -> Result<tonic::Response<super::v1beta1::PreferredAllocationResponse>, tonic::Status> {
-        error!("get_preferred_allocation - kubelet called get_prefered_allocation",);
-        Err(tonic::Status::unimplemented(
-            "Get preferred allocation is not implemented for this plugin",
-        ))
+        requests: tonic::Request<PreferredAllocationRequest>,
+    ) -> Result<tonic::Response<PreferredAllocationResponse>, tonic::Status> {
+        trace!("kubelet called get_preferred_allocation {:?}", requests);
+        self.inner.get_preferred_allocation(requests).await
     }
 }
 
@@ -190,7 +202,12 @@ pub(super) async fn serve_and_register_plugin<T: Clone + 'static + Send + Sync>(
         std::fs::remove_file(socket_to_delete).unwrap_or(());
     });
 
-    if let Err(e) = register_plugin(device_plugin_name, device_endpoint, socket_path).await {
+    let options = DevicePluginOptions {
+        pre_start_required: false,
+        get_preferred_allocation_available: plugin.preferred_allocation_available(),
+    };
+    if let Err(e) = register_plugin(device_plugin_name, device_endpoint, socket_path, options).await
+    {
         plugin.stop();
         return Err(e);
     }
@@ -201,6 +218,7 @@ async fn register_plugin(
     device_plugin_name: String,
     device_endpoint: String,
     socket_path: String,
+    options: DevicePluginOptions,
 ) -> Result<(), RunnerError> {
     let capability_id: String = format!("akri.sh/{}", device_plugin_name);
 
@@ -212,11 +230,6 @@ async fn register_plugin(
         "register - entered for Instance {} and socket_name: {}",
         capability_id, device_endpoint
     );
-    let op = DevicePluginOptions {
-        pre_start_required: false,
-        get_preferred_allocation_available: false,
-    };
-
     // We will ignore this dummy uri because UDS does not use it.
     // Some servers will check the uri content so the uri needs to
     // be in valid format even it's not used, the scheme part is used
@@ -235,7 +248,7 @@ async fn register_plugin(
         version: K8S_DEVICE_PLUGIN_VERSION.into(),
         endpoint: device_endpoint.to_string(),
         resource_name: capability_id.to_string(),
-        options: Some(op),
+        options: Some(options),
     });
     trace!(
         "register - before call to register with the kubelet at socket {}",
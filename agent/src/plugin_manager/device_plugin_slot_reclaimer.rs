@@ -1,5 +1,5 @@
 use std::{
-    collections::{HashMap, HashSet},
+    collections::HashMap,
     convert::TryFrom,
     sync::Arc,
     time::{Duration, Instant},
@@ -14,7 +14,7 @@ use crate::plugin_manager::{
 };
 
 use super::{
-    device_plugin_instance_controller::DevicePluginManager,
+    device_plugin_instance_controller::{DevicePluginManager, SlotPod},
     v1::pod_resources_lister_client as podresources,
 };
 
@@ -24,10 +24,10 @@ const SLOT_GRACE_PERIOD: Duration = Duration::from_secs(20);
 const SLOT_RECLAIM_INTERVAL: Duration = Duration::from_secs(10);
 
 /// This function connects to kubelet's resource monitoring interface and extracts
-/// the set of resources currently used by pods on the node.
+/// the set of resources currently used by pods on the node, along with the pod using each of them.
 /// It uses this Kubelet interface:
 ///  <https://kubernetes.io/docs/concepts/extend-kubernetes/compute-storage-net/device-plugins/#grpc-endpoint-list>
-async fn get_used_slots() -> Result<HashSet<String>, anyhow::Error> {
+async fn get_used_slots() -> Result<HashMap<String, SlotPod>, anyhow::Error> {
     // We will ignore this dummy uri because UDS does not use it.
     // Some servers will check the uri content so the uri needs to
     // be in valid format even it's not used, the scheme part is used
@@ -55,13 +55,22 @@ async fn get_used_slots() -> Result<HashSet<String>, anyhow::Error> {
         .pod_resources
         .into_iter()
         .flat_map(|pr| {
-            pr.containers.into_iter().flat_map(|cr| {
-                cr.devices.into_iter().flat_map(|cd| {
+            let pod = SlotPod {
+                namespace: pr.namespace,
+                name: pr.name,
+                uid: None,
+            };
+            pr.containers.into_iter().flat_map(move |cr| {
+                let pod = pod.clone();
+                cr.devices.into_iter().flat_map(move |cd| {
+                    let pod = pod.clone();
                     if cd.resource_name.starts_with(DP_SLOT_PREFIX) {
                         cd.device_ids
                     } else {
                         vec![]
                     }
+                    .into_iter()
+                    .map(move |id| (id, pod.clone()))
                 })
             })
         })
@@ -75,10 +84,14 @@ pub async fn start_reclaimer(dp_manager: Arc<DevicePluginManager>) {
     loop {
         trace!("reclaiming unused slots - start");
         if let Ok(used_slots) = get_used_slots().await {
+            dp_manager.record_slot_pods(&used_slots).await;
             let theoretical_slots = dp_manager.get_used_slots().await;
             let mut new_stalled_slots: HashMap<String, Instant> = HashMap::new();
             let reclaim_iteration_start = Instant::now();
-            for slot_to_reclaim in theoretical_slots.difference(&used_slots) {
+            for slot_to_reclaim in theoretical_slots
+                .iter()
+                .filter(|s| !used_slots.contains_key(*s))
+            {
                 // See if slot was already stalled at previous iteration
                 if let Some(at) = stalled_slots.get(slot_to_reclaim) {
                     if reclaim_iteration_start.saturating_duration_since(*at) >= SLOT_GRACE_PERIOD {
@@ -6,12 +6,18 @@ use std::{
 
 use akri_shared::{
     akri::{
-        configuration::{Configuration, DiscoveryProperty},
-        instance::Instance,
+        configuration::{Configuration, NodeDiscoveryStatus},
+        instance::{Instance, InstanceHealth},
+        status::{set_condition, DISCOVERY_ACTIVE_CONDITION, HANDLER_REGISTERED_CONDITION},
+    },
+    k8s::{
+        api::IntoApi,
+        event::{EventInfo, EventRecorder},
     },
-    k8s::api::IntoApi,
 };
 use futures::StreamExt;
+use k8s_openapi::{apimachinery::pkg::apis::meta::v1::Time, chrono::Utc};
+use serde_json::json;
 use tokio::sync::mpsc;
 
 use crate::discovery_handler_manager::{
@@ -36,6 +42,20 @@ pub enum Error {
 
 const SUCCESS_REQUEUE: Duration = Duration::from_secs(600);
 
+/// Unchanged status entries are rewritten after this delay, so their timestamps show discovery
+/// is still running without rewriting them on every reconciliation
+const STATUS_REFRESH: Duration = Duration::from_secs(300);
+
+/// Outcome of a reconciliation, as reported in the status of the Configuration
+enum DiscoveryOutcome<'a> {
+    Started,
+    Running {
+        endpoints: Vec<String>,
+        discovered_instances: usize,
+    },
+    Failed(&'a DiscoveryError),
+}
+
 pub trait DiscoveryConfigurationKubeClient: IntoApi<Configuration> + IntoApi<Instance> {}
 
 impl<T: IntoApi<Configuration> + IntoApi<Instance>> DiscoveryConfigurationKubeClient for T {}
@@ -46,17 +66,22 @@ pub struct ControllerContext {
     pub client: Arc<dyn DiscoveryConfigurationKubeClient>,
     pub agent_identifier: String,
     pub error_backoffs: Mutex<HashMap<String, Duration>>,
+    pub recorder: Arc<dyn EventRecorder>,
+}
+
+/// Creates the Configuration controller. Its store caches the Configurations for the rest of the Agent,
+/// such as the allocation policy of the device plugins, without another watch on the Configurations.
+pub fn new_controller(client: &dyn IntoApi<Configuration>) -> Controller<Configuration> {
+    Controller::new(client.all().as_inner(), Default::default())
 }
 
-/// This function starts the reconciling loop for the Configuration controller.
+/// This function starts the reconciling loop for the Configuration controller created with `new_controller`.
 /// It is expected to run this as a task.
 pub async fn start_controller(
+    controller: Controller<Configuration>,
     ctx: Arc<ControllerContext>,
     rec: mpsc::Receiver<ObjectRef<Configuration>>,
 ) {
-    let api = ctx.client.all().as_inner();
-    let controller = Controller::new(api, Default::default());
-
     controller
         // Reconcile the Configuration when the discovery handler manager signals a change
         .reconcile_on(tokio_stream::wrappers::ReceiverStream::new(rec))
@@ -76,6 +101,7 @@ pub async fn start_controller(
 ///  - Start discovery if not already started
 ///  - Get discovery results (empty list if just started)
 ///  - Create/Delete Instances according to discovery results
+///  - Report the discovery state of this node in the Configuration and Instances status
 pub async fn reconcile(
     dc: Arc<Configuration>,
     ctx: Arc<ControllerContext>,
@@ -103,14 +129,6 @@ pub async fn reconcile(
             .map_err(|e| Error::Other(e.into()))?
     }
 
-    let dh_name = &dc.spec.discovery_handler.name;
-    let dh_details = &dc.spec.discovery_handler.discovery_details;
-    let dh_properties: &[DiscoveryProperty] = dc
-        .spec
-        .discovery_handler
-        .discovery_properties
-        .as_deref()
-        .unwrap_or_default();
     let dh_extra_device_properties = dc.spec.broker_properties.clone();
 
     let discovered_instances: Vec<Instance> =
@@ -118,8 +136,17 @@ pub async fn reconcile(
             Some(req) => {
                 req.set_extra_device_properties(dc.spec.broker_properties.clone())
                     .await;
-                req.get_instances()
-                    .await?
+                req.set_selector(dc.spec.selector.clone()).await;
+                req.set_discovery_details(dc.spec.discovery_handler.discovery_details.clone())
+                    .await;
+                let instances = match req.get_instances().await {
+                    Ok(instances) => instances,
+                    Err(e) => {
+                        record_discovery_error(&dc, &ctx, &e).await;
+                        return Err(e.into());
+                    }
+                };
+                let instances: Vec<Instance> = instances
                     .into_iter()
                     .map(|mut instance| {
                         // Add
@@ -128,19 +155,30 @@ pub async fn reconcile(
                         instance.spec.capacity = dc.spec.capacity;
                         instance
                     })
-                    .collect()
+                    .collect();
+                let outcome = DiscoveryOutcome::Running {
+                    endpoints: req.get_endpoints().await,
+                    discovered_instances: instances.len(),
+                };
+                update_discovery_status(&dc, &ctx, outcome).await;
+                instances
             }
             None => {
-                ctx.dh_registry
+                if let Err(e) = ctx
+                    .dh_registry
                     .new_request(
                         &dc.name_any(),
-                        dh_name,
-                        dh_details,
-                        dh_properties,
+                        &dc.spec.discovery_handler,
                         dh_extra_device_properties,
                         &dc.namespace().unwrap_or("default".to_string()),
                     )
-                    .await?;
+                    .await
+                {
+                    update_discovery_status(&dc, &ctx, DiscoveryOutcome::Failed(&e)).await;
+                    record_discovery_error(&dc, &ctx, &e).await;
+                    return Err(e.into());
+                }
+                update_discovery_status(&dc, &ctx, DiscoveryOutcome::Started).await;
                 vec![]
             }
         };
@@ -156,16 +194,61 @@ pub async fn reconcile(
                 instance.as_ref(),
                 &ctx.agent_identifier,
             )
-            .await?
+            .await?;
+            if instance.spec.nodes.contains(&ctx.agent_identifier) {
+                ctx.recorder
+                    .record(
+                        &dc.object_ref(&()),
+                        EventInfo::normal(
+                            "InstanceRemoved",
+                            "Discover",
+                            format!(
+                                "Instance {} is no longer discovered on node {}",
+                                instance.name_any(),
+                                ctx.agent_identifier
+                            ),
+                        ),
+                    )
+                    .await;
+            }
         }
     }
 
-    for instance in discovered_instances {
-        ctx.client
+    for mut instance in discovered_instances {
+        // The health of the device is part of the status of this node, written separately
+        let health = instance
+            .status
+            .take()
+            .and_then(|s| s.nodes.into_iter().next())
+            .map(|n| n.health)
+            .unwrap_or_default();
+        let known = ctx
+            .instances_cache
+            .get(&ObjectRef::from_obj(&instance))
+            .is_some_and(|known| known.spec.nodes.contains(&ctx.agent_identifier));
+        let instance: Instance = ctx
+            .client
             .namespaced(&namespace)
             .apply(instance, &ctx.agent_identifier)
             .await
             .map_err(|e| Error::Other(e.into()))?;
+        if !known {
+            ctx.recorder
+                .record(
+                    &dc.object_ref(&()),
+                    EventInfo::normal(
+                        "InstanceCreated",
+                        "Discover",
+                        format!(
+                            "Discovered Instance {} on node {}",
+                            instance.name_any(),
+                            ctx.agent_identifier
+                        ),
+                    ),
+                )
+                .await;
+        }
+        update_node_status(&instance, health, &ctx).await;
     }
 
     ctx.error_backoffs.lock().unwrap().remove(&dc.name_any());
@@ -190,6 +273,219 @@ pub fn error_policy(dc: Arc<Configuration>, error: &Error, ctx: Arc<ControllerCo
     Action::requeue(next_duration)
 }
 
+/// Records a warning on the Configuration explaining why discovery cannot run on this node
+async fn record_discovery_error(
+    dc: &Configuration,
+    ctx: &ControllerContext,
+    error: &DiscoveryError,
+) {
+    let reason = match error {
+        DiscoveryError::NoHandler(_) | DiscoveryError::UnavailableDiscoveryHandler(_) => {
+            "DiscoveryHandlerUnavailable"
+        }
+        DiscoveryError::UnsolvableProperty(_) => "UnsolvableProperty",
+        _ => "DiscoveryFailed",
+    };
+    ctx.recorder
+        .record(
+            &dc.object_ref(&()),
+            EventInfo::warning(
+                reason,
+                "Discover",
+                format!("{} on node {}", error, ctx.agent_identifier),
+            ),
+        )
+        .await;
+}
+
+/// Builds the status entry of this node for the Configuration, starting from the current one so
+/// conditions keep their last transition time
+fn node_discovery_status(
+    dc: &Configuration,
+    node: &str,
+    outcome: DiscoveryOutcome,
+) -> NodeDiscoveryStatus {
+    let mut status = current_node_discovery_status(dc, node)
+        .cloned()
+        .unwrap_or_else(|| NodeDiscoveryStatus {
+            node: node.to_string(),
+            ..Default::default()
+        });
+    let generation = dc.metadata.generation;
+    let handler_name = &dc.spec.discovery_handler.name;
+    match outcome {
+        DiscoveryOutcome::Started => {
+            set_condition(
+                &mut status.conditions,
+                DISCOVERY_ACTIVE_CONDITION,
+                true,
+                "DiscoveryStarted",
+                "",
+                generation,
+            );
+            set_condition(
+                &mut status.conditions,
+                HANDLER_REGISTERED_CONDITION,
+                true,
+                "HandlerRegistered",
+                "",
+                generation,
+            );
+            status.handler_endpoints = vec![];
+            status.discovered_instances = 0;
+        }
+        DiscoveryOutcome::Running {
+            endpoints,
+            discovered_instances,
+        } => {
+            set_condition(
+                &mut status.conditions,
+                DISCOVERY_ACTIVE_CONDITION,
+                true,
+                "Discovering",
+                "",
+                generation,
+            );
+            if endpoints.is_empty() {
+                set_condition(
+                    &mut status.conditions,
+                    HANDLER_REGISTERED_CONDITION,
+                    false,
+                    "NoEndpoint",
+                    &format!(
+                        "No {} Discovery Handler endpoint is answering",
+                        handler_name
+                    ),
+                    generation,
+                );
+            } else {
+                set_condition(
+                    &mut status.conditions,
+                    HANDLER_REGISTERED_CONDITION,
+                    true,
+                    "HandlerRegistered",
+                    "",
+                    generation,
+                );
+            }
+            status.handler_endpoints = endpoints;
+            status.discovered_instances = discovered_instances;
+        }
+        DiscoveryOutcome::Failed(error) => {
+            set_condition(
+                &mut status.conditions,
+                DISCOVERY_ACTIVE_CONDITION,
+                false,
+                "DiscoveryFailed",
+                &error.to_string(),
+                generation,
+            );
+            if let DiscoveryError::NoHandler(_) = error {
+                set_condition(
+                    &mut status.conditions,
+                    HANDLER_REGISTERED_CONDITION,
+                    false,
+                    "NoHandler",
+                    &error.to_string(),
+                    generation,
+                );
+            }
+            status.handler_endpoints = vec![];
+            status.discovered_instances = 0;
+        }
+    }
+    status
+}
+
+fn current_node_discovery_status<'a>(
+    dc: &'a Configuration,
+    node: &str,
+) -> Option<&'a NodeDiscoveryStatus> {
+    dc.status
+        .as_ref()
+        .and_then(|s| s.nodes.iter().find(|n| n.node == node))
+}
+
+/// Returns true if the timestamp is missing or older than `STATUS_REFRESH`
+fn is_stale(time: Option<&Time>) -> bool {
+    time.map_or(true, |t| {
+        (Utc::now() - t.0)
+            .to_std()
+            .map_or(false, |age| age >= STATUS_REFRESH)
+    })
+}
+
+/// Writes the status entry of this node in the Configuration.
+/// As every write triggers a new reconciliation, the entry is only written when it changed or
+/// when its last discovery time is stale. Errors are only logged, the status is informative.
+async fn update_discovery_status(
+    dc: &Configuration,
+    ctx: &ControllerContext,
+    outcome: DiscoveryOutcome<'_>,
+) {
+    let running = matches!(outcome, DiscoveryOutcome::Running { .. });
+    let current = current_node_discovery_status(dc, &ctx.agent_identifier);
+    let mut status = node_discovery_status(dc, &ctx.agent_identifier, outcome);
+    if Some(&status) == current && !(running && is_stale(status.last_discovery_time.as_ref())) {
+        return;
+    }
+    if running {
+        status.last_discovery_time = Some(Time(Utc::now()));
+    }
+    let api = IntoApi::<Configuration>::namespaced(
+        ctx.client.as_ref(),
+        &dc.namespace().unwrap_or_default(),
+    );
+    if let Err(e) = api
+        .apply_status(
+            &dc.name_any(),
+            json!({ "nodes": [status] }),
+            &ctx.agent_identifier,
+        )
+        .await
+    {
+        warn!(
+            "Unable to update status of Configuration {}: {:?}",
+            dc.name_any(),
+            e
+        );
+    }
+}
+
+/// Records in the Instance status that this node just discovered the device and the health it
+/// sees the device in, only when the health changed or the previous record is stale to avoid
+/// writing it on every reconciliation
+async fn update_node_status(instance: &Instance, health: InstanceHealth, ctx: &ControllerContext) {
+    let current = instance
+        .status
+        .as_ref()
+        .and_then(|s| s.nodes.iter().find(|n| n.node == ctx.agent_identifier));
+    if current.is_some_and(|n| n.health == health && !is_stale(n.last_seen_time.as_ref())) {
+        return;
+    }
+    let api = IntoApi::<Instance>::namespaced(
+        ctx.client.as_ref(),
+        &instance.namespace().unwrap_or_default(),
+    );
+    let status = json!({
+        "nodes": [{
+            "node": ctx.agent_identifier,
+            "lastSeenTime": Time(Utc::now()),
+            "health": health,
+        }]
+    });
+    if let Err(e) = api
+        .apply_status(&instance.name_any(), status, &ctx.agent_identifier)
+        .await
+    {
+        warn!(
+            "Unable to update status of Instance {}: {:?}",
+            instance.name_any(),
+            e
+        );
+    }
+}
+
 async fn delete_instance(
     client: &dyn DiscoveryConfigurationKubeClient,
     instance: &Instance,
@@ -208,6 +504,10 @@ async fn delete_instance(
         api.apply(new_instance, agent_instance_name)
             .await
             .map_err(|e| Error::Other(e.into()))?;
+        // Applying an empty status drops the entry this node owns
+        api.apply_status(&instance.name_any(), json!({}), agent_instance_name)
+            .await
+            .map_err(|e| Error::Other(e.into()))?;
     }
     Ok(())
 }
@@ -217,9 +517,12 @@ mod tests {
     use akri_shared::{
         akri::{
             configuration::{ConfigurationSpec, DiscoveryHandlerInfo},
-            instance::InstanceSpec,
+            instance::{HealthState, InstanceSpec, InstanceStatus, NodeInstanceStatus},
+        },
+        k8s::{
+            api::{Api, MockApi, MockIntoApi},
+            event::{EventType, MockEventRecorder},
         },
-        k8s::api::{Api, MockApi, MockIntoApi},
     };
     use k8s_openapi::apimachinery::pkg::apis::meta::v1::OwnerReference;
     use kube::core::{ObjectMeta, Status};
@@ -230,6 +533,13 @@ mod tests {
     };
 
     use super::*;
+    use akri_shared::akri::configuration::ConfigurationStatus;
+
+    fn permissive_recorder() -> Arc<dyn EventRecorder> {
+        let mut recorder = MockEventRecorder::new();
+        recorder.expect_record().returning(|_, _| ());
+        Arc::new(recorder)
+    }
 
     #[derive(Default)]
     pub struct MockDiscoveryConfigurationKubeClient {
@@ -278,13 +588,18 @@ mod tests {
                     name: "debugEcho".to_string(),
                     discovery_details: String::default(),
                     discovery_properties: None,
+                    discovery_interval_seconds: None,
+                    discovery_timeout_seconds: None,
                 },
                 capacity: 1,
                 broker_spec: None,
                 instance_service_spec: None,
                 configuration_service_spec: None,
+                allocation_policy: None,
+                selector: None,
                 broker_properties: Default::default(),
             },
+            status: None,
         });
         let config_2 = Arc::new(Configuration {
             metadata: ObjectMeta {
@@ -296,13 +611,18 @@ mod tests {
                     name: "debugEcho".to_string(),
                     discovery_details: String::default(),
                     discovery_properties: None,
+                    discovery_interval_seconds: None,
+                    discovery_timeout_seconds: None,
                 },
                 capacity: 1,
                 broker_spec: None,
                 instance_service_spec: None,
                 configuration_service_spec: None,
+                allocation_policy: None,
+                selector: None,
                 broker_properties: Default::default(),
             },
+            status: None,
         });
 
         let (store, _) = kube_runtime::reflector::store();
@@ -313,6 +633,7 @@ mod tests {
             client: Arc::new(MockDiscoveryConfigurationKubeClient::default()),
             agent_identifier: "node-a".to_string(),
             error_backoffs: Default::default(),
+            recorder: permissive_recorder(),
         });
 
         assert_eq!(
@@ -355,6 +676,76 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_update_node_status() {
+        let instance = Instance {
+            metadata: ObjectMeta {
+                name: Some("instance-1".to_string()),
+                namespace: Some("namespace-a".to_string()),
+                ..Default::default()
+            },
+            spec: InstanceSpec {
+                capacity: 1,
+                configuration_name: Default::default(),
+                cdi_name: Default::default(),
+                broker_properties: Default::default(),
+                shared: true,
+                nodes: vec!["node-a".to_string(), "node-b".to_string()],
+                device_usage: Default::default(),
+            },
+            status: Some(InstanceStatus {
+                nodes: vec![NodeInstanceStatus {
+                    node: "node-a".to_string(),
+                    last_seen_time: Some(Time(Utc::now())),
+                    health: Default::default(),
+                }],
+                ..Default::default()
+            }),
+        };
+        let unhealthy = InstanceHealth {
+            state: HealthState::Unhealthy,
+            reason: Some("unreachable".to_string()),
+        };
+        let ctx = |client| ControllerContext {
+            instances_cache: kube_runtime::reflector::store().0,
+            dh_registry: Arc::new(MockDiscoveryHandlerRegistry::new()),
+            client: Arc::new(client),
+            agent_identifier: "node-a".to_string(),
+            error_backoffs: Default::default(),
+            recorder: permissive_recorder(),
+        };
+
+        // Nothing is written while the health is the same and the record is recent
+        update_node_status(
+            &instance,
+            Default::default(),
+            &ctx(MockDiscoveryConfigurationKubeClient::default()),
+        )
+        .await;
+
+        let mut mock_client = MockDiscoveryConfigurationKubeClient::default();
+        let mut mock_api = MockApi::new();
+        let local_instance = instance.clone();
+        mock_api
+            .expect_apply_status()
+            .withf(|name, status, field_manager| {
+                let node = &status["nodes"][0];
+                name == "instance-1"
+                    && field_manager == "node-a"
+                    && node["node"] == "node-a"
+                    && node["lastSeenTime"].is_string()
+                    && node["health"] == json!({"state": "Unhealthy", "reason": "unreachable"})
+            })
+            .times(1)
+            .returning(move |_, _, _| Ok(local_instance.clone()));
+        mock_client
+            .instance
+            .expect_namespaced()
+            .with(eq("namespace-a"))
+            .return_once(|_| Box::new(mock_api));
+        update_node_status(&instance, unhealthy, &ctx(mock_client)).await;
+    }
+
     #[tokio::test]
     async fn test_delete_instance_delete() {
         let instance = Instance {
@@ -372,6 +763,7 @@ mod tests {
                 nodes: vec!["node-a".to_string()],
                 device_usage: Default::default(),
             },
+            status: None,
         };
 
         let mut mock_client = MockDiscoveryConfigurationKubeClient::default();
@@ -411,6 +803,7 @@ mod tests {
                 nodes: vec!["node-a".to_string(), "node-b".to_string()],
                 device_usage: Default::default(),
             },
+            status: None,
         };
 
         let mut mock_client = MockDiscoveryConfigurationKubeClient::default();
@@ -419,6 +812,13 @@ mod tests {
         mock_api
             .expect_apply()
             .returning(move |_, _| Ok(local_instance.clone()));
+        let local_instance = instance.clone();
+        mock_api
+            .expect_apply_status()
+            .withf(|name, status, field_manager| {
+                name == "instance-1" && status == &json!({}) && field_manager == "node-a"
+            })
+            .returning(move |_, _, _| Ok(local_instance.clone()));
         mock_client
             .instance
             .expect_namespaced()
@@ -449,6 +849,7 @@ mod tests {
                 nodes: vec!["node-b".to_string()],
                 device_usage: Default::default(),
             },
+            status: None,
         };
 
         let mut mock_client = MockDiscoveryConfigurationKubeClient::default();
@@ -468,9 +869,52 @@ mod tests {
 
     #[tokio::test]
     async fn test_reconcile_nothing_to_do() {
+        let dc = Arc::new(Configuration {
+            metadata: ObjectMeta {
+                name: Some("config-1".to_string()),
+                namespace: Some("namespace-a".to_string()),
+                uid: Some("00112233-4455-6677-8899-aabbccddeeff".to_string()),
+                finalizers: Some(vec!["node-a".to_string()]),
+                ..Default::default()
+            },
+            spec: ConfigurationSpec {
+                discovery_handler: DiscoveryHandlerInfo {
+                    name: "debugEcho".to_string(),
+                    discovery_details: String::new(),
+                    discovery_properties: None,
+                    discovery_interval_seconds: None,
+                    discovery_timeout_seconds: None,
+                },
+                capacity: 1,
+                broker_spec: None,
+                instance_service_spec: None,
+                configuration_service_spec: None,
+                allocation_policy: None,
+                selector: None,
+                broker_properties: Default::default(),
+            },
+            status: None,
+        });
+
         let (store, _) = kube_runtime::reflector::store();
         let mut client = MockDiscoveryConfigurationKubeClient::default();
-        let api = MockApi::new();
+        let mut api = MockApi::new();
+        let local_dc = dc.as_ref().clone();
+        api.expect_apply_status()
+            .withf(|name, status, field_manager| {
+                let node = &status["nodes"][0];
+                name == "config-1"
+                    && field_manager == "node-a"
+                    && node["node"] == "node-a"
+                    && node["handlerEndpoints"] == json!(["debugEcho@local"])
+                    && node["discoveredInstances"] == 0
+                    && node["lastDiscoveryTime"].is_string()
+                    && node["conditions"][0]["type"] == "DiscoveryActive"
+                    && node["conditions"][0]["status"] == "True"
+                    && node["conditions"][1]["type"] == "HandlerRegistered"
+                    && node["conditions"][1]["status"] == "True"
+            })
+            .return_once(move |_, _, _| Ok(local_dc));
         client
             .config
             .expect_namespaced()
@@ -481,7 +925,12 @@ mod tests {
         request
             .expect_set_extra_device_properties()
             .returning(|_| {});
+        request.expect_set_selector().returning(|_| {});
+        request.expect_set_discovery_details().returning(|_| {});
         request.expect_get_instances().returning(|| Ok(vec![]));
+        request
+            .expect_get_endpoints()
+            .returning(|| vec!["debugEcho@local".to_string()]);
         registry
             .expect_get_request()
             .return_once(|_| Some(Arc::new(request)));
@@ -492,8 +941,37 @@ mod tests {
             client: Arc::new(client),
             agent_identifier: "node-a".to_string(),
             error_backoffs: Default::default(),
+            recorder: permissive_recorder(),
         });
 
+        assert!(reconcile(dc, ctx).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_status_unchanged() {
+        let mut status = NodeDiscoveryStatus {
+            node: "node-a".to_string(),
+            handler_endpoints: vec!["debugEcho@local".to_string()],
+            discovered_instances: 0,
+            last_discovery_time: Some(Time(Utc::now())),
+            ..Default::default()
+        };
+        set_condition(
+            &mut status.conditions,
+            DISCOVERY_ACTIVE_CONDITION,
+            true,
+            "Discovering",
+            "",
+            None,
+        );
+        set_condition(
+            &mut status.conditions,
+            HANDLER_REGISTERED_CONDITION,
+            true,
+            "HandlerRegistered",
+            "",
+            None,
+        );
         let dc = Arc::new(Configuration {
             metadata: ObjectMeta {
                 name: Some("config-1".to_string()),
@@ -507,18 +985,133 @@ mod tests {
                     name: "debugEcho".to_string(),
                     discovery_details: String::new(),
                     discovery_properties: None,
+                    discovery_interval_seconds: None,
+                    discovery_timeout_seconds: None,
                 },
                 capacity: 1,
                 broker_spec: None,
                 instance_service_spec: None,
                 configuration_service_spec: None,
+                allocation_policy: None,
+                selector: None,
                 broker_properties: Default::default(),
             },
+            status: Some(ConfigurationStatus {
+                nodes: vec![status],
+            }),
+        });
+
+        // The status of the node is up to date, so it doesn't get written
+        let (store, _) = kube_runtime::reflector::store();
+        let client = MockDiscoveryConfigurationKubeClient::default();
+        let mut registry = MockDiscoveryHandlerRegistry::new();
+        let mut request = MockDiscoveryHandlerRequest::new();
+        request
+            .expect_set_extra_device_properties()
+            .returning(|_| {});
+        request.expect_set_selector().returning(|_| {});
+        request.expect_set_discovery_details().returning(|_| {});
+        request.expect_get_instances().returning(|| Ok(vec![]));
+        request
+            .expect_get_endpoints()
+            .returning(|| vec!["debugEcho@local".to_string()]);
+        registry
+            .expect_get_request()
+            .return_once(|_| Some(Arc::new(request)));
+
+        let ctx = Arc::new(ControllerContext {
+            instances_cache: store,
+            dh_registry: Arc::new(registry),
+            client: Arc::new(client),
+            agent_identifier: "node-a".to_string(),
+            error_backoffs: Default::default(),
+            recorder: permissive_recorder(),
         });
 
         assert!(reconcile(dc, ctx).await.is_ok());
     }
 
+    #[tokio::test]
+    async fn test_reconcile_no_handler() {
+        let dc = Arc::new(Configuration {
+            metadata: ObjectMeta {
+                name: Some("config-1".to_string()),
+                namespace: Some("namespace-a".to_string()),
+                uid: Some("00112233-4455-6677-8899-aabbccddeeff".to_string()),
+                finalizers: Some(vec!["node-a".to_string()]),
+                ..Default::default()
+            },
+            spec: ConfigurationSpec {
+                discovery_handler: DiscoveryHandlerInfo {
+                    name: "debugEcho".to_string(),
+                    discovery_details: String::new(),
+                    discovery_properties: None,
+                    discovery_interval_seconds: None,
+                    discovery_timeout_seconds: None,
+                },
+                capacity: 1,
+                broker_spec: None,
+                instance_service_spec: None,
+                configuration_service_spec: None,
+                allocation_policy: None,
+                selector: None,
+                broker_properties: Default::default(),
+            },
+            status: None,
+        });
+
+        let (store, _) = kube_runtime::reflector::store();
+        let mut client = MockDiscoveryConfigurationKubeClient::default();
+        let mut api = MockApi::new();
+        let local_dc = dc.as_ref().clone();
+        api.expect_apply_status()
+            .withf(|_, status, _| {
+                let node = &status["nodes"][0];
+                node["conditions"][0]["type"] == "DiscoveryActive"
+                    && node["conditions"][0]["status"] == "False"
+                    && node["conditions"][1]["type"] == "HandlerRegistered"
+                    && node["conditions"][1]["status"] == "False"
+                    && node["conditions"][1]["reason"] == "NoHandler"
+                    && node.get("lastDiscoveryTime").is_none()
+            })
+            .return_once(move |_, _, _| Ok(local_dc));
+        client
+            .config
+            .expect_namespaced()
+            .return_once(|_| Box::new(api));
+
+        let mut registry = MockDiscoveryHandlerRegistry::new();
+        registry.expect_get_request().return_once(|_| None);
+        registry
+            .expect_new_request()
+            .returning(|_, _, _, _| Err(DiscoveryError::NoHandler("debugEcho".to_string())));
+
+        let mut recorder = MockEventRecorder::new();
+        recorder
+            .expect_record()
+            .withf(|regarding, event| {
+                regarding.name.as_deref() == Some("config-1")
+                    && event.type_ == EventType::Warning
+                    && event.reason == "DiscoveryHandlerUnavailable"
+            })
+            .times(1)
+            .returning(|_, _| ());
+
+        let ctx = Arc::new(ControllerContext {
+            instances_cache: store,
+            dh_registry: Arc::new(registry),
+            client: Arc::new(client),
+            agent_identifier: "node-a".to_string(),
+            error_backoffs: Default::default(),
+            recorder: Arc::new(recorder),
+        });
+
+        assert!(matches!(
+            reconcile(dc, ctx).await,
+            Err(Error::DiscoveryError(DiscoveryError::NoHandler(_)))
+        ));
+    }
+
     #[tokio::test]
     async fn test_reconcile_no_request_existing_instances() {
         let (store, mut writer) = kube_runtime::reflector::store();
@@ -546,6 +1139,7 @@ mod tests {
                     nodes: vec!["node-a".to_string()],
                     device_usage: Default::default(),
                 },
+                status: None,
             },
             Instance {
                 metadata: ObjectMeta {
@@ -570,6 +1164,7 @@ mod tests {
                     nodes: vec!["node-b".to_string()],
                     device_usage: Default::default(),
                 },
+                status: None,
             },
             Instance {
                 metadata: ObjectMeta {
@@ -594,15 +1189,52 @@ mod tests {
                     nodes: vec!["node-a".to_string()],
                     device_usage: Default::default(),
                 },
+                status: None,
             },
         ]));
+        let dc = Arc::new(Configuration {
+            metadata: ObjectMeta {
+                name: Some("config-1".to_string()),
+                namespace: Some("namespace-a".to_string()),
+                uid: Some("00112233-4455-6677-8899-aabbccddeeff".to_string()),
+                ..Default::default()
+            },
+            spec: ConfigurationSpec {
+                discovery_handler: DiscoveryHandlerInfo {
+                    name: "debugEcho".to_string(),
+                    discovery_details: String::new(),
+                    discovery_properties: None,
+                    discovery_interval_seconds: None,
+                    discovery_timeout_seconds: None,
+                },
+                capacity: 1,
+                broker_spec: None,
+                instance_service_spec: None,
+                configuration_service_spec: None,
+                allocation_policy: None,
+                selector: None,
+                broker_properties: Default::default(),
+            },
+            status: None,
+        });
+
         let mut client = MockDiscoveryConfigurationKubeClient::default();
-        let mut api = MockApi::new();
-        api.expect_add_finalizer().returning(|_, _| Ok(()));
+        let local_dc = dc.as_ref().clone();
         client
             .config
             .expect_namespaced()
-            .return_once(|_| Box::new(api));
+            .times(2)
+            .returning(move |_| {
+                let mut api = MockApi::new();
+                api.expect_add_finalizer().returning(|_, _| Ok(()));
+                let local_dc = local_dc.clone();
+                api.expect_apply_status()
+                    .withf(|_, status, _| {
+                        status["nodes"][0]["conditions"][0]["reason"] == "DiscoveryStarted"
+                    })
+                    .return_once(move |_, _, _| Ok(local_dc));
+                Box::new(api)
+            });
 
         let mut instance_api = MockApi::new();
         instance_api
@@ -617,9 +1249,7 @@ mod tests {
         let mut registry = MockDiscoveryHandlerRegistry::new();
         registry.expect_get_request().return_once(|_| None);
         //TODO: check arguments here
-        registry
-            .expect_new_request()
-            .returning(|_, _, _, _, _, _| Ok(()));
+        registry.expect_new_request().returning(|_, _, _, _| Ok(()));
 
         let ctx = Arc::new(ControllerContext {
             instances_cache: store,
@@ -627,27 +1257,7 @@ mod tests {
             client: Arc::new(client),
             agent_identifier: "node-a".to_string(),
             error_backoffs: Default::default(),
-        });
-
-        let dc = Arc::new(Configuration {
-            metadata: ObjectMeta {
-                name: Some("config-1".to_string()),
-                namespace: Some("namespace-a".to_string()),
-                uid: Some("00112233-4455-6677-8899-aabbccddeeff".to_string()),
-                ..Default::default()
-            },
-            spec: ConfigurationSpec {
-                discovery_handler: DiscoveryHandlerInfo {
-                    name: "debugEcho".to_string(),
-                    discovery_details: String::new(),
-                    discovery_properties: None,
-                },
-                capacity: 1,
-                broker_spec: None,
-                instance_service_spec: None,
-                configuration_service_spec: None,
-                broker_properties: Default::default(),
-            },
+            recorder: permissive_recorder(),
         });
 
         assert!(reconcile(dc, ctx).await.is_ok());
@@ -2,11 +2,14 @@
 extern crate lazy_static;
 mod util;
 
-use akri_shared::akri::{metrics::run_metrics_server, API_NAMESPACE};
+use akri_shared::{
+    akri::{metrics::run_metrics_server, API_NAMESPACE},
+    k8s::event::{EventRecorder, KubeEventRecorder},
+};
 use async_std::sync::Mutex;
 use prometheus::IntGaugeVec;
 use std::sync::Arc;
-use util::{instance_action, node_watcher, pod_watcher};
+use util::{configuration_watcher, instance_action, node_watcher, pod_watcher};
 
 /// Length of time to sleep between controller system validation checks
 pub const SYSTEM_CHECK_DELAY_SECS: u64 = 30;
@@ -33,6 +36,12 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>
 
     log::info!("{} Controller logging started", API_NAMESPACE);
 
+    // Events are reported as coming from this Controller Pod
+    let recorder: Arc<dyn EventRecorder> = Arc::new(KubeEventRecorder::new(
+        Arc::new(kube::Client::try_default().await?),
+        "akri-controller",
+        &std::env::var("HOSTNAME").unwrap_or_default(),
+    ));
     let synchronization = Arc::new(Mutex::new(()));
     let instance_watch_synchronization = synchronization.clone();
     let mut tasks = Vec::new();
@@ -44,14 +53,18 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>
 
     // Handle existing instances
     tasks.push(tokio::spawn({
+        let recorder = recorder.clone();
         async move {
-            instance_action::handle_existing_instances().await.unwrap();
+            instance_action::handle_existing_instances(recorder)
+                .await
+                .unwrap();
         }
     }));
     // Handle instance changes
     tasks.push(tokio::spawn({
+        let recorder = recorder.clone();
         async move {
-            instance_action::do_instance_watch(instance_watch_synchronization)
+            instance_action::do_instance_watch(instance_watch_synchronization, recorder)
                 .await
                 .unwrap();
         }
@@ -63,10 +76,18 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>
             node_watcher.watch().await.unwrap();
         }
     }));
+    // Release deleted Configurations once the Agents are done with them
+    tasks.push(tokio::spawn({
+        async move {
+            configuration_watcher::do_configuration_watch()
+                .await
+                .unwrap();
+        }
+    }));
     // Watch for broker Pod state changes
     tasks.push(tokio::spawn({
         async move {
-            let mut broker_pod_watcher = pod_watcher::BrokerPodWatcher::new();
+            let mut broker_pod_watcher = pod_watcher::BrokerPodWatcher::new(recorder);
             broker_pod_watcher.watch().await.unwrap();
         }
     }));
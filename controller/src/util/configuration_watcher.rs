@@ -0,0 +1,161 @@
+use akri_shared::{
+    akri::configuration::{Configuration, CONFIGURATION_FINALIZER},
+    k8s::{self, api::IntoApi},
+};
+use futures::{StreamExt, TryStreamExt};
+use kube::{
+    api::{Patch, PatchParams},
+    ResourceExt,
+};
+use kube_runtime::watcher::{watcher, Config, Event};
+use kube_runtime::WatchStreamExt;
+use log::{error, info, trace};
+use serde_json::json;
+
+/// This releases Configurations from the finalizer the webhook adds on their creation.
+///
+/// The finalizer keeps a Configuration around until the Agents added theirs, so a
+/// Configuration deleted right after its creation cannot vanish before the Agents
+/// cleaned up after it. It is removed once it is the last finalizer of a deleted
+/// Configuration.
+pub async fn do_configuration_watch(
+) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    trace!("do_configuration_watch - enter");
+    let kube_interface = k8s::KubeImpl::new().await?;
+    let resource = IntoApi::<Configuration>::all(&kube_interface).as_inner();
+    let mut informer = watcher(resource, Config::default())
+        .default_backoff()
+        .boxed();
+    loop {
+        let configurations = match informer.try_next().await {
+            Err(e) => {
+                error!("Error during watch: {}", e);
+                continue;
+            }
+            Ok(None) => break,
+            Ok(Some(Event::Applied(configuration))) => vec![configuration],
+            Ok(Some(Event::Restarted(configurations))) => configurations,
+            Ok(Some(Event::Deleted(_))) => continue,
+        };
+        for configuration in configurations {
+            if let Err(e) = release_configuration(&configuration, &kube_interface).await {
+                error!(
+                    "do_configuration_watch - failed to release Configuration {}: {}",
+                    configuration.name_any(),
+                    e
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Removes the finalizer of a deleted Configuration once the Agents removed theirs.
+/// The patch is conditioned on the resource version, so no finalizer added meanwhile gets dropped.
+async fn release_configuration(
+    configuration: &Configuration,
+    kube_interface: &impl IntoApi<Configuration>,
+) -> anyhow::Result<()> {
+    if configuration.metadata.deletion_timestamp.is_none()
+        || configuration.finalizers() != [CONFIGURATION_FINALIZER]
+    {
+        return Ok(());
+    }
+    info!(
+        "release_configuration - removing finalizer of deleted Configuration {}",
+        configuration.name_any()
+    );
+    let patch = Patch::Merge(json!({
+        "metadata": {
+            "finalizers": null,
+            "resourceVersion": configuration.resource_version(),
+        }
+    }));
+    kube_interface
+        .namespaced(&configuration.namespace().unwrap_or_default())
+        .raw_patch(&configuration.name_any(), &patch, &PatchParams::default())
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use akri_shared::{
+        akri::configuration::{ConfigurationSpec, DiscoveryHandlerInfo},
+        k8s::api::{MockApi, MockIntoApi},
+    };
+    use k8s_openapi::apimachinery::pkg::apis::meta::v1::{ObjectMeta, Time};
+
+    fn configuration(deleted: bool, finalizers: &[&str]) -> Configuration {
+        let mut configuration = Configuration::new(
+            "config-a",
+            ConfigurationSpec {
+                discovery_handler: DiscoveryHandlerInfo {
+                    name: "debugEcho".to_string(),
+                    discovery_details: String::new(),
+                    discovery_properties: None,
+                    discovery_interval_seconds: None,
+                    discovery_timeout_seconds: None,
+                },
+                capacity: 1,
+                broker_spec: None,
+                instance_service_spec: None,
+                configuration_service_spec: None,
+                broker_properties: Default::default(),
+                allocation_policy: None,
+                selector: None,
+            },
+        );
+        configuration.metadata = ObjectMeta {
+            name: Some("config-a".to_string()),
+            namespace: Some("config-a-namespace".to_string()),
+            resource_version: Some("42".to_string()),
+            deletion_timestamp: deleted.then(|| Time(chrono::Utc::now())),
+            finalizers: Some(finalizers.iter().map(|f| f.to_string()).collect()),
+            ..Default::default()
+        };
+        configuration
+    }
+
+    #[tokio::test]
+    async fn test_release_configuration_not_ready() {
+        // Neither a Configuration that isn't deleted nor one the Agents still hold get patched
+        let kube_interface = MockIntoApi::<Configuration>::new();
+        release_configuration(&configuration(false, &["akri.sh"]), &kube_interface)
+            .await
+            .unwrap();
+        release_configuration(
+            &configuration(true, &["akri.sh", "node-a"]),
+            &kube_interface,
+        )
+        .await
+        .unwrap();
+        release_configuration(&configuration(true, &[]), &kube_interface)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_release_configuration() {
+        let mut kube_interface = MockIntoApi::<Configuration>::new();
+        kube_interface
+            .expect_namespaced()
+            .withf(|namespace| namespace == "config-a-namespace")
+            .returning(|_| {
+                let mut api = MockApi::<Configuration>::new();
+                api.expect_raw_patch()
+                    .withf(|name, patch, _| {
+                        name == "config-a"
+                            && matches!(patch, Patch::Merge(patch) if patch == &json!({
+                                "metadata": {"finalizers": null, "resourceVersion": "42"}
+                            }))
+                    })
+                    .returning(|_, _, _| Ok(configuration(true, &[])));
+                Box::new(api)
+            });
+        release_configuration(&configuration(true, &["akri.sh"]), &kube_interface)
+            .await
+            .unwrap();
+    }
+}
@@ -1,22 +1,31 @@
 use super::super::BROKER_POD_COUNT_METRIC;
 use super::{pod_action::PodAction, pod_action::PodActionInfo};
 use akri_shared::{
-    akri::{configuration::BrokerSpec, instance::Instance, AKRI_PREFIX},
+    akri::{
+        configuration::BrokerSpec,
+        instance::{HealthState, Instance, InstanceHealth},
+        status::{set_condition, BROKERS_READY_CONDITION, DEGRADED_CONDITION},
+        AKRI_PREFIX,
+    },
     k8s::{
-        self, job, pod,
+        self,
+        event::{EventInfo, EventRecorder},
+        job, pod,
         pod::{AKRI_INSTANCE_LABEL_NAME, AKRI_TARGET_NODE_LABEL_NAME},
         KubeInterface, OwnershipInfo, OwnershipType,
     },
 };
 use async_std::sync::Mutex;
 use futures::{StreamExt, TryStreamExt};
-use k8s_openapi::api::batch::v1::JobSpec;
+use k8s_openapi::api::batch::v1::{Job, JobSpec};
 use k8s_openapi::api::core::v1::{Pod, PodSpec};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::Condition;
 use kube::api::Api;
+use kube::{Resource, ResourceExt};
 use kube_runtime::watcher::{watcher, Config, Event};
 use kube_runtime::WatchStreamExt;
 use log::{error, info, trace};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 /// Length of time a Pod can be pending before we give up and retry
 pub const PENDING_POD_GRACE_PERIOD_MINUTES: i64 = 5;
@@ -55,32 +64,46 @@ pub enum InstanceAction {
 
 /// This invokes an internal method that watches for Instance events
 pub async fn handle_existing_instances(
+    recorder: Arc<dyn EventRecorder>,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
-    internal_handle_existing_instances(&k8s::KubeImpl::new().await?).await
+    internal_handle_existing_instances(&k8s::KubeImpl::new().await?, recorder).await
 }
 
 /// This invokes an internal method that watches for Instance events
 pub async fn do_instance_watch(
     synchronization: Arc<Mutex<()>>,
+    recorder: Arc<dyn EventRecorder>,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
     // Watch for instance changes
-    internal_do_instance_watch(&synchronization, &k8s::KubeImpl::new().await?).await
+    internal_do_instance_watch(
+        &synchronization,
+        &k8s::KubeImpl::new().await?,
+        recorder.as_ref(),
+    )
+    .await
 }
 
 /// This invokes an internal method that watches for Instance events
 async fn internal_handle_existing_instances(
     kube_interface: &impl KubeInterface,
+    recorder: Arc<dyn EventRecorder>,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
     let mut tasks = Vec::new();
 
     // Handle existing instances
     let pre_existing_instances = kube_interface.get_instances().await?;
     for instance in pre_existing_instances {
+        let recorder = recorder.clone();
         tasks.push(tokio::spawn(async move {
             let inner_kube_interface = k8s::KubeImpl::new().await.unwrap();
-            handle_instance_change(&instance, &InstanceAction::Update, &inner_kube_interface)
-                .await
-                .unwrap();
+            handle_instance_change(
+                &instance,
+                &InstanceAction::Update,
+                &inner_kube_interface,
+                recorder.as_ref(),
+            )
+            .await
+            .unwrap();
         }));
     }
     futures::future::try_join_all(tasks).await?;
@@ -90,6 +113,7 @@ async fn internal_handle_existing_instances(
 async fn internal_do_instance_watch(
     synchronization: &Arc<Mutex<()>>,
     kube_interface: &impl KubeInterface,
+    recorder: &dyn EventRecorder,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
     trace!("internal_do_instance_watch - enter");
     let resource = Api::<Instance>::all(kube_interface.get_kube_client());
@@ -111,7 +135,7 @@ async fn internal_do_instance_watch(
         // cannot execute at the same time.
         let _lock = synchronization.lock().await;
         trace!("internal_do_instance_watch - aquired sync lock");
-        handle_instance(event, kube_interface, &mut first_event).await?;
+        handle_instance(event, kube_interface, recorder, &mut first_event).await?;
     }
     Ok(())
 }
@@ -121,6 +145,7 @@ async fn internal_do_instance_watch(
 async fn handle_instance(
     event: Event<Instance>,
     kube_interface: &impl KubeInterface,
+    recorder: &dyn EventRecorder,
     first_event: &mut bool,
 ) -> anyhow::Result<()> {
     trace!("handle_instance - enter");
@@ -133,14 +158,16 @@ async fn handle_instance(
             // TODO: consider renaming `InstanceAction::Add` to `InstanceAction::AddOrUpdate`
             // to reflect that this could also be an Update event. Or as we do more specific
             // inspection in future, delineation may be useful.
-            handle_instance_change(&instance, &InstanceAction::Add, kube_interface).await?;
+            handle_instance_change(&instance, &InstanceAction::Add, kube_interface, recorder)
+                .await?;
         }
         Event::Deleted(instance) => {
             info!(
                 "handle_instance - deleted Akri Instance {:?}: {:?}",
                 instance.metadata.name, instance.spec
             );
-            handle_instance_change(&instance, &InstanceAction::Remove, kube_interface).await?;
+            handle_instance_change(&instance, &InstanceAction::Remove, kube_interface, recorder)
+                .await?;
         }
         Event::Restarted(_instances) => {
             if *first_event {
@@ -200,9 +227,14 @@ pub(crate) fn create_pod_context(k8s_pod: &Pod, action: PodAction) -> anyhow::Re
 /// This finds what to do with a given broker Pod based on its current state and
 /// the Instance event action.  If this method has enough information,
 /// it will update the nodes_to_act_on map with the required action.
+/// A Pod recorded as holding one of the Instance's slots (by its UID in
+/// slot_pod_uids) is never considered to be on an unknown node, so that
+/// it is not evicted while it still uses the device. Pods are only
+/// recorded by Agents writing structured slots.
 fn determine_action_for_pod(
     k8s_pod: &Pod,
     action: &InstanceAction,
+    slot_pod_uids: &HashSet<&str>,
     nodes_to_act_on: &mut HashMap<String, PodContext>,
 ) -> anyhow::Result<()> {
     let pod_name = k8s_pod.metadata.name.as_ref().unwrap();
@@ -219,6 +251,11 @@ fn determine_action_for_pod(
 
     // Early exits above ensure unwrap will not panic
     let pod_start_time = k8s_pod.status.as_ref().unwrap().start_time.clone();
+    let holds_slot = k8s_pod
+        .metadata
+        .uid
+        .as_deref()
+        .is_some_and(|uid| slot_pod_uids.contains(uid));
 
     let pod_action_info = PodActionInfo {
         pending_grace_time_in_minutes: PENDING_POD_GRACE_PERIOD_MINUTES,
@@ -226,7 +263,7 @@ fn determine_action_for_pod(
         phase: pod_phase.to_string(),
         instance_action: action.clone(),
         status_start_time: pod_start_time,
-        unknown_node: !nodes_to_act_on.contains_key(node_to_run_pod_on),
+        unknown_node: !nodes_to_act_on.contains_key(node_to_run_pod_on) && !holds_slot,
         trace_node_name: k8s_pod.metadata.name.clone().unwrap(),
     };
     update_pod_context.action = pod_action_info.select_pod_action()?;
@@ -288,6 +325,63 @@ async fn handle_deletion_work(
     Ok(())
 }
 
+#[cfg(test)]
+mod determine_action_for_pod_tests {
+    use super::*;
+
+    fn running_pod(uid: &str) -> Pod {
+        serde_json::from_value(serde_json::json!({
+            "metadata": {
+                "name": "broker-a",
+                "namespace": "namespace-a",
+                "uid": uid,
+                "labels": { AKRI_TARGET_NODE_LABEL_NAME: "node-a" }
+            },
+            "status": { "phase": "Running" }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_determine_action_for_pod_holding_slot() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        // node-a is no longer among the Instance nodes, but its broker still holds a slot
+        let slot_pod_uids = HashSet::from(["uid-a"]);
+        let mut nodes_to_act_on = HashMap::new();
+        determine_action_for_pod(
+            &running_pod("uid-a"),
+            &InstanceAction::Update,
+            &slot_pod_uids,
+            &mut nodes_to_act_on,
+        )
+        .unwrap();
+        assert_eq!(nodes_to_act_on["node-a"].action, PodAction::NoAction);
+
+        // A broker that holds no slot on an unknown node is removed
+        let mut nodes_to_act_on = HashMap::new();
+        determine_action_for_pod(
+            &running_pod("uid-b"),
+            &InstanceAction::Update,
+            &slot_pod_uids,
+            &mut nodes_to_act_on,
+        )
+        .unwrap();
+        assert_eq!(nodes_to_act_on["node-a"].action, PodAction::Remove);
+
+        // Brokers are still removed along with their Instance
+        let mut nodes_to_act_on = HashMap::new();
+        determine_action_for_pod(
+            &running_pod("uid-a"),
+            &InstanceAction::Remove,
+            &slot_pod_uids,
+            &mut nodes_to_act_on,
+        )
+        .unwrap();
+        assert_eq!(nodes_to_act_on["node-a"].action, PodAction::Remove);
+    }
+}
+
 #[cfg(test)]
 mod handle_deletion_work_tests {
     use super::*;
@@ -389,10 +483,12 @@ async fn handle_addition_work(
 /// Handle Instance change by
 /// 1) checking to make sure the Instance's Configuration exists
 /// 2) calling the appropriate handler depending on the broker type (Pod or Job) if any
+/// 3) updating the conditions of the Instance status unless it is removed
 pub async fn handle_instance_change(
     instance: &Instance,
     action: &InstanceAction,
     kube_interface: &impl KubeInterface,
+    recorder: &dyn EventRecorder,
 ) -> anyhow::Result<()> {
     trace!("handle_instance_change - enter {:?}", action);
     let instance_name = instance.metadata.name.clone().unwrap();
@@ -418,10 +514,13 @@ pub async fn handle_instance_change(
             return Ok(());
         }
     };
+    let mut broker_pods = None;
     if let Some(broker_spec) = &configuration.spec.broker_spec {
         let instance_change_result = match broker_spec {
             BrokerSpec::BrokerPodSpec(p) => {
-                handle_instance_change_pod(instance, p, action, kube_interface).await
+                handle_instance_change_pod(instance, p, action, kube_interface, recorder)
+                    .await
+                    .map(|pods| broker_pods = Some(pods))
             }
             BrokerSpec::BrokerJobSpec(j) => {
                 handle_instance_change_job(
@@ -430,6 +529,7 @@ pub async fn handle_instance_change(
                     j,
                     action,
                     kube_interface,
+                    recorder,
                 )
                 .await
             }
@@ -438,9 +538,216 @@ pub async fn handle_instance_change(
             error!("Unable to handle Broker action: {:?}", e);
         }
     }
+    if action != &InstanceAction::Remove {
+        update_instance_conditions(instance, broker_pods.as_deref(), kube_interface).await;
+    }
     Ok(())
 }
 
+/// Computes the conditions of the Instance status owned by the Controller:
+/// `BrokersReady` from the broker Pods, when the Instance has Pod brokers, and
+/// `Degraded` from the health of the device as seen by each of its nodes.
+fn instance_conditions(instance: &Instance, broker_pods: Option<&[Pod]>) -> Vec<Condition> {
+    let mut conditions = instance
+        .status
+        .as_ref()
+        .map(|s| s.conditions.clone())
+        .unwrap_or_default();
+    let generation = instance.metadata.generation;
+    if let Some(pods) = broker_pods {
+        let not_ready: Vec<String> = instance
+            .spec
+            .nodes
+            .iter()
+            .filter_map(|node| {
+                match pods
+                    .iter()
+                    .find(|p| p.labels().get(AKRI_TARGET_NODE_LABEL_NAME) == Some(node))
+                {
+                    Some(pod) => broker_pod_failure(pod).map(|f| format!("{}: {}", node, f)),
+                    None => Some(format!("{}: no broker Pod", node)),
+                }
+            })
+            .collect();
+        if not_ready.is_empty() {
+            set_condition(
+                &mut conditions,
+                BROKERS_READY_CONDITION,
+                true,
+                "BrokersReady",
+                "",
+                generation,
+            );
+        } else {
+            set_condition(
+                &mut conditions,
+                BROKERS_READY_CONDITION,
+                false,
+                "BrokersNotReady",
+                &not_ready.join("; "),
+                generation,
+            );
+        }
+    }
+    // The device is unhealthy if no node can use it, and degraded if some node can't use it
+    // or sees it degraded
+    let unhealthy_nodes: Vec<(&String, InstanceHealth)> = instance
+        .spec
+        .nodes
+        .iter()
+        .map(|node| (node, instance.node_health(node)))
+        .filter(|(_, health)| health.state != HealthState::Healthy)
+        .collect();
+    let health_reason = unhealthy_nodes
+        .iter()
+        .map(|(node, health)| match &health.reason {
+            Some(reason) => format!("{}: {}", node, reason),
+            None => format!("{}: {:?}", node, health.state),
+        })
+        .collect::<Vec<String>>()
+        .join("; ");
+    if unhealthy_nodes.is_empty() {
+        set_condition(
+            &mut conditions,
+            DEGRADED_CONDITION,
+            false,
+            "DeviceHealthy",
+            "",
+            generation,
+        );
+    } else if unhealthy_nodes.len() == instance.spec.nodes.len()
+        && unhealthy_nodes
+            .iter()
+            .all(|(_, health)| !health.is_usable())
+    {
+        set_condition(
+            &mut conditions,
+            DEGRADED_CONDITION,
+            true,
+            "DeviceUnhealthy",
+            &health_reason,
+            generation,
+        );
+    } else {
+        set_condition(
+            &mut conditions,
+            DEGRADED_CONDITION,
+            true,
+            "DeviceDegraded",
+            &health_reason,
+            generation,
+        );
+    }
+    conditions
+}
+
+/// Returns true if the Pod has a true Ready condition
+pub(crate) fn is_pod_ready(pod: &Pod) -> bool {
+    pod.status
+        .as_ref()
+        .and_then(|s| s.conditions.as_ref())
+        .map_or(false, |c| {
+            c.iter().any(|c| c.type_ == "Ready" && c.status == "True")
+        })
+}
+
+/// Describes why a broker Pod is not ready, using the reason its containers
+/// are waiting or terminated for, returns None if the Pod is ready
+fn broker_pod_failure(pod: &Pod) -> Option<String> {
+    let status = pod.status.as_ref();
+    let phase = status.and_then(|s| s.phase.as_deref()).unwrap_or("Unknown");
+    if phase == "Running" && is_pod_ready(pod) {
+        return None;
+    }
+    let container_failure = status
+        .and_then(|s| s.container_statuses.as_ref())
+        .into_iter()
+        .flatten()
+        .filter_map(|c| c.state.as_ref())
+        .find_map(|state| {
+            if let Some(waiting) = &state.waiting {
+                waiting.reason.clone().map(|r| (r, waiting.message.clone()))
+            } else {
+                state.terminated.as_ref().map(|terminated| {
+                    (
+                        terminated
+                            .reason
+                            .clone()
+                            .unwrap_or_else(|| format!("exit code {}", terminated.exit_code)),
+                        terminated.message.clone(),
+                    )
+                })
+            }
+        });
+    Some(match container_failure {
+        Some((reason, Some(message))) => {
+            format!(
+                "Pod {} is {} ({}: {})",
+                pod.name_any(),
+                phase,
+                reason,
+                message
+            )
+        }
+        Some((reason, None)) => format!("Pod {} is {} ({})", pod.name_any(), phase, reason),
+        None => format!("Pod {} is {}", pod.name_any(), phase),
+    })
+}
+
+/// Writes the conditions of the Instance status if they changed. Errors are only
+/// logged, as the status is informative.
+async fn update_instance_conditions(
+    instance: &Instance,
+    broker_pods: Option<&[Pod]>,
+    kube_interface: &impl KubeInterface,
+) {
+    let conditions = instance_conditions(instance, broker_pods);
+    let current = instance
+        .status
+        .as_ref()
+        .map(|s| s.conditions.as_slice())
+        .unwrap_or_default();
+    if conditions == current {
+        return;
+    }
+    if let Err(e) = kube_interface
+        .update_instance_conditions(
+            &conditions,
+            &instance.name_any(),
+            &instance.namespace().unwrap_or_default(),
+        )
+        .await
+    {
+        error!(
+            "Unable to update conditions of Instance {}: {:?}",
+            instance.name_any(),
+            e
+        );
+    }
+}
+
+/// Updates the `BrokersReady` condition of an Instance with Pod brokers from its current broker Pods
+pub(crate) async fn refresh_brokers_ready(
+    instance: &Instance,
+    kube_interface: &impl KubeInterface,
+) {
+    match kube_interface
+        .find_pods_with_label(&format!(
+            "{}={}",
+            AKRI_INSTANCE_LABEL_NAME,
+            instance.name_any()
+        ))
+        .await
+    {
+        Ok(pods) => update_instance_conditions(instance, Some(&pods.items), kube_interface).await,
+        Err(e) => error!(
+            "Unable to find broker Pods of Instance {}: {:?}",
+            instance.name_any(),
+            e
+        ),
+    }
+}
+
 /// Called when an Instance has changed that requires a Job broker. Action determined by InstanceAction.
 /// InstanceAction::Add =>  Deploy a Job with JobSpec from Configuration. Label with Instance name.
 /// InstanceAction::Remove => Delete all Jobs labeled with the Instance name
@@ -451,6 +758,7 @@ pub async fn handle_instance_change_job(
     job_spec: &JobSpec,
     action: &InstanceAction,
     kube_interface: &impl KubeInterface,
+    recorder: &dyn EventRecorder,
 ) -> anyhow::Result<()> {
     trace!("handle_instance_change_job - enter {:?}", action);
     // Create name for Job. Includes Configuration generation in the suffix
@@ -487,6 +795,16 @@ pub async fn handle_instance_change_job(
             kube_interface
                 .create_job(&new_job, instance_namespace)
                 .await?;
+            recorder
+                .record(
+                    &instance.object_ref(&()),
+                    EventInfo::normal(
+                        "BrokerDeployed",
+                        "Deploy",
+                        format!("Deployed broker Job {}", job_name),
+                    ),
+                )
+                .await;
         }
         InstanceAction::Remove => {
             trace!("handle_instance_change_job - instance removed");
@@ -501,13 +819,37 @@ pub async fn handle_instance_change_job(
                         j.metadata.namespace.as_ref().unwrap(),
                     )
                     .await
+                    .map(|_| j.name_any())
             });
 
-            futures::future::try_join_all(delete_tasks).await?;
+            let deleted_jobs = futures::future::try_join_all(delete_tasks).await?;
+            for job_name in deleted_jobs {
+                recorder
+                    .record(
+                        &instance.object_ref(&()),
+                        EventInfo::normal(
+                            "BrokerDeleted",
+                            "Delete",
+                            format!("Deleted broker Job {}", job_name),
+                        ),
+                    )
+                    .await;
+            }
         }
         InstanceAction::Update => {
             trace!("handle_instance_change_job - instance updated");
             // TODO: Broker could have encountered unexpected admission error and need to be removed and added
+            let instance_jobs = kube_interface
+                .find_jobs_with_label(&format!("{}={}", AKRI_INSTANCE_LABEL_NAME, instance_name))
+                .await?;
+            for failure in instance_jobs.iter().filter_map(broker_job_failure) {
+                recorder
+                    .record(
+                        &instance.object_ref(&()),
+                        EventInfo::warning("BrokerFailing", "Deploy", failure),
+                    )
+                    .await;
+            }
         }
     }
     Ok(())
@@ -519,12 +861,14 @@ pub async fn handle_instance_change_job(
 /// InstanceAction::Add =>  Deploy Pod to each Node on Instance's `nodes` list (up to `capacity` total)
 /// InstanceAction::Remove => Delete all Pods labeled with the Instance name
 /// InstanceAction::Update => Ensure that each Node on Instance's `nodes` list (up to `capacity` total) have a Pod
+/// Returns the broker Pods found before acting on them.
 pub async fn handle_instance_change_pod(
     instance: &Instance,
     podspec: &PodSpec,
     action: &InstanceAction,
     kube_interface: &impl KubeInterface,
-) -> anyhow::Result<()> {
+    recorder: &dyn EventRecorder,
+) -> anyhow::Result<Vec<Pod>> {
     trace!("handle_instance_change_pod - enter {:?}", action);
 
     let instance_name = instance.metadata.name.clone().unwrap();
@@ -572,19 +916,83 @@ pub async fn handle_instance_change_pod(
     // By default, assume any pod tracked by the instance need to be added.
     // Query the existing pods to see if some of these are already added, or
     // need to be removed
-    instance_pods
-        .items
-        .iter()
-        .try_for_each(|x| determine_action_for_pod(x, action, &mut nodes_to_act_on))?;
+    let slot_pod_uids: HashSet<&str> = instance
+        .spec
+        .device_usage
+        .values()
+        .filter_map(|usage| usage.pod_uid.as_deref())
+        .collect();
+    instance_pods.items.iter().try_for_each(|x| {
+        determine_action_for_pod(x, action, &slot_pod_uids, &mut nodes_to_act_on)
+    })?;
 
     trace!(
         "handle_instance_change - nodes tracked after querying existing pods={:?}",
         nodes_to_act_on
     );
+    let broker_events: Vec<EventInfo> = nodes_to_act_on
+        .iter()
+        .filter_map(|(node, context)| broker_pod_event(node, context, &instance_pods.items))
+        .collect();
     do_pod_action_for_nodes(nodes_to_act_on, instance, podspec, kube_interface).await?;
+    for event in broker_events {
+        recorder.record(&instance.object_ref(&()), event).await;
+    }
     trace!("handle_instance_change - exit");
 
-    Ok(())
+    Ok(instance_pods.items)
+}
+
+/// Describes the action taken on the broker Pod of a node as an event
+fn broker_pod_event(node: &str, context: &PodContext, pods: &[Pod]) -> Option<EventInfo> {
+    match context.action {
+        PodAction::Add => Some(EventInfo::normal(
+            "BrokerDeployed",
+            "Deploy",
+            format!("Deployed broker Pod on node {}", node),
+        )),
+        PodAction::Remove => Some(EventInfo::normal(
+            "BrokerDeleted",
+            "Delete",
+            format!("Deleted broker Pod on node {}", node),
+        )),
+        PodAction::RemoveAndAdd => {
+            let failure = pods
+                .iter()
+                .find(|p| {
+                    p.labels()
+                        .get(AKRI_TARGET_NODE_LABEL_NAME)
+                        .map(String::as_str)
+                        == Some(node)
+                })
+                .and_then(broker_pod_failure)
+                .unwrap_or_else(|| format!("Broker Pod on node {} is failing", node));
+            Some(EventInfo::warning(
+                "BrokerFailing",
+                "Redeploy",
+                format!("{}, redeploying it", failure),
+            ))
+        }
+        PodAction::NoAction => None,
+    }
+}
+
+/// Describes why a broker Job failed, returns None unless the Job failed
+fn broker_job_failure(job: &Job) -> Option<String> {
+    let failed = job
+        .status
+        .as_ref()?
+        .conditions
+        .as_ref()?
+        .iter()
+        .find(|c| c.type_ == "Failed" && c.status == "True")?;
+    Some(match (&failed.reason, &failed.message) {
+        (Some(reason), Some(message)) => {
+            format!("Job {} failed ({}: {})", job.name_any(), reason, message)
+        }
+        (Some(reason), None) => format!("Job {} failed ({})", job.name_any(), reason),
+        _ => format!("Job {} failed", job.name_any()),
+    })
 }
 
 pub(crate) async fn do_pod_action_for_nodes(
@@ -644,12 +1052,15 @@ mod handle_instance_tests {
     use super::super::shared_test_utils::config_for_tests::PodList;
     use super::*;
     use akri_shared::{
-        akri::instance::Instance,
-        k8s::{pod::AKRI_INSTANCE_LABEL_NAME, MockKubeInterface},
+        akri::instance::{Instance, NodeInstanceStatus, SlotUsage},
+        k8s::{event::MockEventRecorder, pod::AKRI_INSTANCE_LABEL_NAME, MockKubeInterface},
         os::file,
     };
     use chrono::prelude::*;
     use chrono::Utc;
+    use k8s_openapi::api::core::v1::{
+        ContainerState, ContainerStateWaiting, ContainerStatus, PodCondition,
+    };
     use mockall::predicate::*;
 
     fn configure_find_pods_with_phase(
@@ -804,6 +1215,9 @@ mod handle_instance_tests {
         if let Some(addition_work) = &work.addition_work {
             configure_for_handle_addition_work(mock, addition_work);
         }
+
+        mock.expect_update_instance_conditions()
+            .returning(|_, _, _| Ok(()));
     }
 
     #[derive(Clone)]
@@ -897,6 +1311,7 @@ mod handle_instance_tests {
                 InstanceAction::Remove => Event::Deleted(instance),
             },
             mock,
+            &config_for_tests::permissive_recorder(),
             &mut false,
         )
         .await
@@ -912,6 +1327,7 @@ mod handle_instance_tests {
         assert!(handle_instance(
             Event::Restarted(Vec::new()),
             &MockKubeInterface::new(),
+            &config_for_tests::permissive_recorder(),
             &mut first_event
         )
         .await
@@ -920,6 +1336,7 @@ mod handle_instance_tests {
         assert!(handle_instance(
             Event::Restarted(Vec::new()),
             &MockKubeInterface::new(),
+            &config_for_tests::permissive_recorder(),
             &mut first_event
         )
         .await
@@ -932,7 +1349,12 @@ mod handle_instance_tests {
 
         let mut mock = MockKubeInterface::new();
         config_for_tests::configure_get_instances(&mut mock, "../test/json/empty-list.json", false);
-        internal_handle_existing_instances(&mock).await.unwrap();
+        internal_handle_existing_instances(
+            &mock,
+            Arc::new(config_for_tests::permissive_recorder()),
+        )
+        .await
+        .unwrap();
     }
 
     #[tokio::test]
@@ -961,6 +1383,42 @@ mod handle_instance_tests {
         .await;
     }
 
+    #[tokio::test]
+    async fn test_handle_instance_change_records_broker_deployed() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let mut mock = MockKubeInterface::new();
+        configure_for_handle_instance_change(
+            &mut mock,
+            &HandleInstanceWork {
+                find_pods_selector: "akri.sh/instance=config-a-b494b6",
+                find_pods_result: "../test/json/empty-list.json",
+                find_pods_phase: None,
+                find_pods_start_time: None,
+                find_pods_delete_start_time: false,
+                config_work: get_config_work(),
+                deletion_work: None,
+                addition_work: Some(configure_add_local_config_a_b494b6(false)),
+            },
+        );
+        let mut recorder = MockEventRecorder::new();
+        recorder
+            .expect_record()
+            .withf(|regarding, event| {
+                regarding.kind.as_deref() == Some("Instance")
+                    && regarding.name.as_deref() == Some("config-a-b494b6")
+                    && event.reason == "BrokerDeployed"
+                    && event.message == "Deployed broker Pod on node node-a"
+            })
+            .times(1)
+            .returning(|_, _| ());
+        let instance_json = file::read_file_to_string("../test/json/local-instance.json");
+        let instance: Instance = serde_json::from_str(&instance_json).unwrap();
+        handle_instance_change(&instance, &InstanceAction::Add, &mock, &recorder)
+            .await
+            .unwrap();
+    }
+
     #[tokio::test]
     async fn test_handle_instance_change_for_add_new_local_instance_error() {
         let _ = env_logger::builder().is_test(true).try_init();
@@ -1119,13 +1577,13 @@ mod handle_instance_tests {
             .device_usage
             .iter()
             .map(|(k, v)| {
-                if v != deleted_node {
-                    (k.to_string(), v.to_string())
+                if !v.is_owned_by(deleted_node) {
+                    (k.to_string(), v.clone())
                 } else {
-                    (k.to_string(), "".to_string())
+                    (k.to_string(), SlotUsage::default())
                 }
             })
-            .collect::<HashMap<String, String>>();
+            .collect::<HashMap<String, SlotUsage>>();
 
         let mut mock = MockKubeInterface::new();
         configure_for_handle_instance_change(
@@ -1217,4 +1675,82 @@ mod handle_instance_tests {
             0
         );
     }
+
+    #[test]
+    fn test_instance_conditions() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let instance_json = file::read_file_to_string("../test/json/shared-instance.json");
+        let mut instance: Instance = serde_json::from_str(&instance_json).unwrap();
+        let pods_json =
+            file::read_file_to_string("../test/json/running-pod-list-for-config-a-shared.json");
+        let mut pods: PodList = serde_json::from_str(&pods_json).unwrap();
+        for pod in pods.items.iter_mut() {
+            pod.status.as_mut().unwrap().conditions = Some(vec![PodCondition {
+                type_: "Ready".to_string(),
+                status: "True".to_string(),
+                ..Default::default()
+            }]);
+        }
+        instance.spec.nodes = pods
+            .items
+            .iter()
+            .map(|p| p.labels()[AKRI_TARGET_NODE_LABEL_NAME].clone())
+            .collect();
+
+        // Without broker Pods, only the health of the device is reported
+        let conditions = instance_conditions(&instance, None);
+        assert_eq!(conditions.len(), 1);
+        assert_eq!(conditions[0].type_, "Degraded");
+        assert_eq!(conditions[0].status, "False");
+
+        let conditions = instance_conditions(&instance, Some(&pods.items));
+        let brokers_ready = conditions
+            .iter()
+            .find(|c| c.type_ == "BrokersReady")
+            .unwrap();
+        assert_eq!(brokers_ready.status, "True");
+
+        // A node without broker Pod and a crashing broker are reported
+        instance.spec.nodes.push("node-z".to_string());
+        instance.status.get_or_insert_with(Default::default).nodes = vec![NodeInstanceStatus {
+            node: "node-z".to_string(),
+            health: InstanceHealth {
+                state: HealthState::Degraded,
+                reason: Some("Low battery".to_string()),
+            },
+            ..Default::default()
+        }];
+        let status = pods.items[0].status.as_mut().unwrap();
+        status.phase = Some("Pending".to_string());
+        status.container_statuses = Some(vec![ContainerStatus {
+            state: Some(ContainerState {
+                waiting: Some(ContainerStateWaiting {
+                    reason: Some("ImagePullBackOff".to_string()),
+                    message: None,
+                }),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }]);
+        let conditions = instance_conditions(&instance, Some(&pods.items));
+        let brokers_ready = conditions
+            .iter()
+            .find(|c| c.type_ == "BrokersReady")
+            .unwrap();
+        assert_eq!(brokers_ready.status, "False");
+        assert!(brokers_ready.message.contains("(ImagePullBackOff)"));
+        assert!(brokers_ready.message.contains("node-z: no broker Pod"));
+        let degraded = conditions.iter().find(|c| c.type_ == "Degraded").unwrap();
+        assert_eq!(degraded.status, "True");
+        assert_eq!(degraded.reason, "DeviceDegraded");
+        assert_eq!(degraded.message, "node-z: Low battery");
+
+        // The device is unhealthy once no node can use it
+        instance.spec.nodes = vec!["node-z".to_string()];
+        instance.status.as_mut().unwrap().nodes[0].health.state = HealthState::Unhealthy;
+        let conditions = instance_conditions(&instance, None);
+        assert_eq!(conditions[0].reason, "DeviceUnhealthy");
+        assert_eq!(conditions[0].message, "node-z: Low battery");
+    }
 }
@@ -1,3 +1,4 @@
+pub mod configuration_watcher;
 pub mod instance_action;
 pub mod node_watcher;
 mod pod_action;
@@ -1,7 +1,6 @@
 use akri_shared::{
     akri::{
-        instance::device_usage::NodeUsage,
-        instance::{Instance, InstanceSpec},
+        instance::{Instance, InstanceSpec, SlotUsage},
         retry::{random_delay, MAX_INSTANCE_UPDATE_TRIES},
     },
     k8s,
@@ -14,7 +13,6 @@ use kube_runtime::watcher::{watcher, Config, Event};
 use kube_runtime::WatchStreamExt;
 use log::{error, info, trace};
 use std::collections::HashMap;
-use std::str::FromStr;
 
 /// Node states that NodeWatcher is interested in
 ///
@@ -298,21 +296,16 @@ impl NodeWatcher {
             .device_usage
             .iter()
             .map(|(slot, usage)| {
-                let same_node_name = match NodeUsage::from_str(usage) {
-                    Ok(node_usage) => node_usage.is_same_node(vanished_node_name),
-                    Err(_) => false,
-                };
-
                 (
                     slot.to_string(),
-                    if same_node_name {
-                        NodeUsage::default().to_string()
+                    if usage.is_owned_by(vanished_node_name) {
+                        SlotUsage::default()
                     } else {
-                        usage.into()
+                        usage.clone()
                     },
                 )
             })
-            .collect::<HashMap<String, String>>();
+            .collect::<HashMap<String, SlotUsage>>();
 
         // Save the instance
         let modified_instance = InstanceSpec {
@@ -457,7 +450,7 @@ mod tests {
         instance.nodes.clear();
         instance
             .device_usage
-            .insert("config-a-359973-2".to_string(), "".to_string());
+            .insert("config-a-359973-2".to_string(), SlotUsage::default());
 
         let mut mock = MockKubeInterface::new();
         configure_for_handle_node_disappearance(
@@ -526,7 +519,7 @@ mod tests {
         instance.nodes.clear();
         instance
             .device_usage
-            .insert("config-a-359973-2".to_string(), "".to_string());
+            .insert("config-a-359973-2".to_string(), SlotUsage::default());
 
         let mut mock = MockKubeInterface::new();
         configure_for_handle_node_disappearance(
@@ -619,19 +612,10 @@ mod tests {
                 n == "config-a"
                     && ns == "config-a-namespace"
                     && !ins.nodes.contains(&"node-b".to_string())
-                    && ins
+                    && !ins
                         .device_usage
-                        .iter()
-                        .filter_map(|(_slot, value)| {
-                            if value == &"node-b".to_string() {
-                                Some(value.to_string())
-                            } else {
-                                None
-                            }
-                        })
-                        .collect::<Vec<String>>()
-                        .first()
-                        .is_none()
+                        .values()
+                        .any(|usage| usage.is_owned_by("node-b"))
             })
             .returning(move |_, _, _| Ok(()));
 
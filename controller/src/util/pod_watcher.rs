@@ -5,6 +5,7 @@ use akri_shared::{
     },
     k8s,
     k8s::{
+        event::EventRecorder,
         pod::{AKRI_CONFIGURATION_LABEL_NAME, AKRI_INSTANCE_LABEL_NAME},
         service, KubeInterface, OwnershipInfo, OwnershipType,
     },
@@ -109,16 +110,30 @@ fn get_broker_pod_owner_kind(pod: &Pod) -> BrokerPodOwnerKind {
 /// still have other broker Pods supporting them.  If there
 /// are no other supporting broker Pods, delete one or both
 /// of the services.
-#[derive(Debug)]
 pub struct BrokerPodWatcher {
     known_pods: HashMap<String, PodState>,
+    /// Readiness of the Running broker Pods, to refresh the `BrokersReady` condition
+    /// of their Instance when it changes while they keep running
+    known_readiness: HashMap<String, bool>,
+    recorder: Arc<dyn EventRecorder>,
+}
+
+impl std::fmt::Debug for BrokerPodWatcher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BrokerPodWatcher")
+            .field("known_pods", &self.known_pods)
+            .field("known_readiness", &self.known_readiness)
+            .finish_non_exhaustive()
+    }
 }
 
 impl BrokerPodWatcher {
     /// Create new instance of BrokerPodWatcher
-    pub fn new() -> Self {
+    pub fn new(recorder: Arc<dyn EventRecorder>) -> Self {
         BrokerPodWatcher {
             known_pods: HashMap::new(),
+            known_readiness: HashMap::new(),
+            recorder,
         }
     }
 
@@ -246,13 +261,22 @@ impl BrokerPodWatcher {
             "handle_running_pod_if_needed - last_known_state: {:?}",
             &last_known_state
         );
+        let ready = super::instance_action::is_pod_ready(pod);
         // Ensure that, for each pod, handle_running_pod is called once
         // per transition into the Running state
         if last_known_state != &PodState::Running {
             trace!("handle_running_pod_if_needed - call handle_running_pod");
             self.handle_running_pod(pod, kube_interface).await?;
-            self.known_pods.insert(pod_name, PodState::Running);
+            self.known_pods.insert(pod_name.clone(), PodState::Running);
+        } else if matches!(self.known_readiness.get(&pod_name), Some(known) if *known != ready) {
+            trace!(
+                "handle_running_pod_if_needed - readiness changed to {}",
+                ready
+            );
+            self.refresh_instance_brokers_ready(pod, kube_interface)
+                .await?;
         }
+        self.known_readiness.insert(pod_name, ready);
         Ok(())
     }
 
@@ -281,6 +305,7 @@ impl BrokerPodWatcher {
         if last_known_state != &PodState::Ended {
             trace!("handle_ended_pod_if_needed - call handle_non_running_pod");
             self.handle_non_running_pod(pod, kube_interface).await?;
+            self.known_readiness.remove(&pod_name);
             self.known_pods.insert(pod_name, PodState::Ended);
         }
         Ok(())
@@ -311,6 +336,7 @@ impl BrokerPodWatcher {
         if last_known_state != &PodState::Deleted {
             trace!("handle_deleted_pod_if_needed - call handle_non_running_pod");
             self.handle_non_running_pod(pod, kube_interface).await?;
+            self.known_readiness.remove(&pod_name);
             self.known_pods.insert(pod_name, PodState::Deleted);
         }
         Ok(())
@@ -375,6 +401,7 @@ impl BrokerPodWatcher {
                     &instance,
                     &super::instance_action::InstanceAction::Update,
                     kube_interface,
+                    self.recorder.as_ref(),
                 )
                 .await?;
             }
@@ -523,6 +550,41 @@ impl BrokerPodWatcher {
         )
         .await?;
 
+        // Report the running broker in the status of the Instance managing it
+        if get_broker_pod_owner_kind(pod) == BrokerPodOwnerKind::Instance {
+            super::instance_action::refresh_brokers_ready(&instance, kube_interface).await;
+        }
+
+        Ok(())
+    }
+
+    /// Refreshes the `BrokersReady` condition of the Instance managing a running
+    /// broker Pod, as its readiness changed.
+    async fn refresh_instance_brokers_ready(
+        &self,
+        pod: &Pod,
+        kube_interface: &impl KubeInterface,
+    ) -> anyhow::Result<()> {
+        if get_broker_pod_owner_kind(pod) != BrokerPodOwnerKind::Instance {
+            return Ok(());
+        }
+        let namespace = pod.metadata.namespace.as_ref().ok_or_else(|| {
+            anyhow::anyhow!("Namespace not found for pod: {:?}", &pod.metadata.name)
+        })?;
+        let (instance_name, _) = self.get_instance_and_configuration_from_pod(pod)?;
+        match kube_interface
+            .find_instance(&instance_name, namespace)
+            .await
+        {
+            Ok(instance) => {
+                super::instance_action::refresh_brokers_ready(&instance, kube_interface).await
+            }
+            // The Instance has likely been deleted, along with its broker Pods
+            _ => trace!(
+                "refresh_instance_brokers_ready - no instance found for {}",
+                &instance_name
+            ),
+        }
         Ok(())
     }
 
@@ -787,7 +849,8 @@ mod tests {
     #[tokio::test]
     async fn test_handle_watcher_restart() {
         let _ = env_logger::builder().is_test(true).try_init();
-        let mut pod_watcher = BrokerPodWatcher::new();
+        let mut pod_watcher =
+            BrokerPodWatcher::new(Arc::new(config_for_tests::permissive_recorder()));
         let mut first_event = true;
         assert!(pod_watcher
             .handle_pod(
@@ -818,7 +881,8 @@ mod tests {
                 phase,
             );
             let pod = pod_list.items.first().unwrap().clone();
-            let mut pod_watcher = BrokerPodWatcher::new();
+            let mut pod_watcher =
+                BrokerPodWatcher::new(Arc::new(config_for_tests::permissive_recorder()));
             trace!(
                 "test_handle_pod_added_unready phase:{}, Event::Applied",
                 &phase
@@ -849,7 +913,8 @@ mod tests {
                 phase,
             );
             let pod = pod_list.items.first().unwrap().clone();
-            let mut pod_watcher = BrokerPodWatcher::new();
+            let mut pod_watcher =
+                BrokerPodWatcher::new(Arc::new(config_for_tests::permissive_recorder()));
             trace!(
                 "test_handle_pod_modified_unready phase:{}, Event::Applied",
                 &phase
@@ -878,7 +943,8 @@ mod tests {
             file::read_file_to_string("../test/json/running-pod-list-for-config-a-local.json");
         let pod_list: PodList = serde_json::from_str(&pods_json).unwrap();
         let pod = pod_list.items.first().unwrap().clone();
-        let mut pod_watcher = BrokerPodWatcher::new();
+        let mut pod_watcher =
+            BrokerPodWatcher::new(Arc::new(config_for_tests::permissive_recorder()));
         let mut mock = MockKubeInterface::new();
         configure_for_handle_pod(
             &mut mock,
@@ -891,6 +957,9 @@ mod tests {
 
                     find_instance_name: "config-a-b494b6",
                     find_instance_result: "../test/json/local-instance.json",
+                    find_broker_pods_selector: "akri.sh/instance=config-a-b494b6",
+                    find_broker_pods_result:
+                        "../test/json/running-pod-list-for-config-a-local.json",
 
                     find_instance_service: FindServices {
                         find_services_selector: "akri.sh/instance=config-a-b494b6",
@@ -933,7 +1002,8 @@ mod tests {
             file::read_file_to_string("../test/json/running-pod-list-for-config-a-local.json");
         let pod_list: PodList = serde_json::from_str(&pods_json).unwrap();
         let pod = pod_list.items.first().unwrap().clone();
-        let mut pod_watcher = BrokerPodWatcher::new();
+        let mut pod_watcher =
+            BrokerPodWatcher::new(Arc::new(config_for_tests::permissive_recorder()));
         let mut mock = MockKubeInterface::new();
         configure_for_handle_pod(
             &mut mock,
@@ -946,6 +1016,9 @@ mod tests {
 
                     find_instance_name: "config-a-b494b6",
                     find_instance_result: "../test/json/local-instance.json",
+                    find_broker_pods_selector: "akri.sh/instance=config-a-b494b6",
+                    find_broker_pods_result:
+                        "../test/json/running-pod-list-for-config-a-local.json",
 
                     find_instance_service: FindServices {
                         find_services_selector: "akri.sh/instance=config-a-b494b6",
@@ -989,7 +1062,8 @@ mod tests {
             "Failed",
         );
         let pod = pod_list.items.first().unwrap().clone();
-        let mut pod_watcher = BrokerPodWatcher::new();
+        let mut pod_watcher =
+            BrokerPodWatcher::new(Arc::new(config_for_tests::permissive_recorder()));
         let mut mock = MockKubeInterface::new();
         configure_for_handle_pod(
             &mut mock,
@@ -1048,7 +1122,8 @@ mod tests {
             "Failed",
         );
         let pod = pod_list.items.first().unwrap().clone();
-        let mut pod_watcher = BrokerPodWatcher::new();
+        let mut pod_watcher =
+            BrokerPodWatcher::new(Arc::new(config_for_tests::permissive_recorder()));
         let mut mock = MockKubeInterface::new();
         configure_for_handle_pod(
             &mut mock,
@@ -1107,7 +1182,8 @@ mod tests {
             "Succeeded",
         );
         let pod = pod_list.items.first().unwrap().clone();
-        let mut pod_watcher = BrokerPodWatcher::new();
+        let mut pod_watcher =
+            BrokerPodWatcher::new(Arc::new(config_for_tests::permissive_recorder()));
         let mut mock = MockKubeInterface::new();
         configure_for_handle_pod(
             &mut mock,
@@ -1168,7 +1244,8 @@ mod tests {
                 phase,
             );
             let pod = pod_list.items.first().unwrap().clone();
-            let mut pod_watcher = BrokerPodWatcher::new();
+            let mut pod_watcher =
+                BrokerPodWatcher::new(Arc::new(config_for_tests::permissive_recorder()));
             trace!(
                 "test_handle_pod_added_unready phase:{}, Event::Applied",
                 &phase
@@ -1189,7 +1266,8 @@ mod tests {
                 phase,
             );
             let pod = pod_list.items.first().unwrap().clone();
-            let mut pod_watcher = BrokerPodWatcher::new();
+            let mut pod_watcher =
+                BrokerPodWatcher::new(Arc::new(config_for_tests::permissive_recorder()));
             trace!(
                 "test_handle_pod_added_unready phase:{}, Event::Applied",
                 &phase
@@ -1215,7 +1293,8 @@ mod tests {
         let pod_list: PodList = serde_json::from_str(&pods_json).unwrap();
         let pod = pod_list.items.first().unwrap();
 
-        let mut pod_watcher = BrokerPodWatcher::new();
+        let mut pod_watcher =
+            BrokerPodWatcher::new(Arc::new(config_for_tests::permissive_recorder()));
         pod_watcher
             .known_pods
             .insert("config-a-b494b6-pod".to_string(), PodState::Running);
@@ -1230,6 +1309,65 @@ mod tests {
         )
     }
 
+    #[tokio::test]
+    async fn test_handle_running_pod_if_needed_readiness_changed() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let pods_json =
+            file::read_file_to_string("../test/json/running-pod-list-for-config-a-local.json");
+        let pod_list: PodList = serde_json::from_str(&pods_json).unwrap();
+        let mut pod = pod_list.items.first().unwrap().clone();
+        pod.status.as_mut().unwrap().conditions =
+            Some(vec![k8s_openapi::api::core::v1::PodCondition {
+                type_: "Ready".to_string(),
+                status: "True".to_string(),
+                ..Default::default()
+            }]);
+
+        let mut pod_watcher =
+            BrokerPodWatcher::new(Arc::new(config_for_tests::permissive_recorder()));
+        pod_watcher
+            .known_pods
+            .insert("config-a-b494b6-pod".to_string(), PodState::Running);
+        pod_watcher
+            .known_readiness
+            .insert("config-a-b494b6-pod".to_string(), false);
+        let mut mock = MockKubeInterface::new();
+        config_for_tests::configure_find_instance(
+            &mut mock,
+            "config-a-b494b6",
+            "config-a-namespace",
+            "../test/json/local-instance.json",
+            false,
+        );
+        config_for_tests::configure_find_pods(
+            &mut mock,
+            "akri.sh/instance=config-a-b494b6",
+            "../test/json/running-pod-list-for-config-a-local.json",
+            false,
+        );
+        mock.expect_update_instance_conditions()
+            .times(1)
+            .withf(|conditions, name, _| {
+                name == "config-a-b494b6" && conditions.iter().any(|c| c.type_ == "BrokersReady")
+            })
+            .returning(|_, _, _| Ok(()));
+        pod_watcher
+            .handle_running_pod_if_needed(&pod, &mock)
+            .await
+            .unwrap();
+        assert_eq!(
+            Some(&true),
+            pod_watcher.known_readiness.get("config-a-b494b6-pod")
+        );
+
+        // Same readiness, nothing to refresh
+        pod_watcher
+            .handle_running_pod_if_needed(&pod, &MockKubeInterface::new())
+            .await
+            .unwrap();
+    }
+
     #[tokio::test]
     async fn test_handle_ended_pod_if_needed_do_nothing() {
         let _ = env_logger::builder().is_test(true).try_init();
@@ -1239,7 +1377,8 @@ mod tests {
         let pod_list: PodList = serde_json::from_str(&pods_json).unwrap();
         let pod = pod_list.items.first().unwrap();
 
-        let mut pod_watcher = BrokerPodWatcher::new();
+        let mut pod_watcher =
+            BrokerPodWatcher::new(Arc::new(config_for_tests::permissive_recorder()));
         pod_watcher
             .known_pods
             .insert("config-a-b494b6-pod".to_string(), PodState::Ended);
@@ -1263,7 +1402,8 @@ mod tests {
         let pod_list: PodList = serde_json::from_str(&pods_json).unwrap();
         let pod = pod_list.items.first().unwrap();
 
-        let mut pod_watcher = BrokerPodWatcher::new();
+        let mut pod_watcher =
+            BrokerPodWatcher::new(Arc::new(config_for_tests::permissive_recorder()));
         pod_watcher
             .known_pods
             .insert("config-a-b494b6-pod".to_string(), PodState::Deleted);
@@ -1297,7 +1437,8 @@ mod tests {
                 phase,
             );
             let pod = pod_list.items.first().unwrap().clone();
-            let mut pod_watcher = BrokerPodWatcher::new();
+            let mut pod_watcher =
+                BrokerPodWatcher::new(Arc::new(config_for_tests::permissive_recorder()));
 
             assert_eq!(phase.to_string(), pod_watcher.get_pod_phase(&pod));
         }
@@ -1310,7 +1451,8 @@ mod tests {
             let mut pod = pod_list.items.first().unwrap().clone();
             pod.status = None;
 
-            let mut pod_watcher = BrokerPodWatcher::new();
+            let mut pod_watcher =
+                BrokerPodWatcher::new(Arc::new(config_for_tests::permissive_recorder()));
 
             assert_eq!("Unknown", pod_watcher.get_pod_phase(&pod));
         }
@@ -1319,7 +1461,7 @@ mod tests {
     #[tokio::test]
     async fn test_cleanup_svc_if_unsupported() {
         let _ = env_logger::builder().is_test(true).try_init();
-        let watcher = BrokerPodWatcher::new();
+        let watcher = BrokerPodWatcher::new(Arc::new(config_for_tests::permissive_recorder()));
         let pod_list = create_pods_with_phase(
             "../test/json/running-pod-list-for-config-a-local.json",
             "Succeeded",
@@ -1347,7 +1489,7 @@ mod tests {
         );
         let orig_pod = pod_list.items.first().unwrap();
 
-        let pod_watcher = BrokerPodWatcher::new();
+        let pod_watcher = BrokerPodWatcher::new(Arc::new(config_for_tests::permissive_recorder()));
         assert!(pod_watcher
             .get_instance_and_configuration_from_pod(orig_pod)
             .is_ok());
@@ -1382,7 +1524,7 @@ mod tests {
         let config_json = file::read_file_to_string("../test/json/config-a.json");
         let config: Configuration = serde_json::from_str(&config_json).unwrap();
 
-        let pod_watcher = BrokerPodWatcher::new();
+        let pod_watcher = BrokerPodWatcher::new(Arc::new(config_for_tests::permissive_recorder()));
         let mut mock = MockKubeInterface::new();
         config_for_tests::configure_find_services(
             &mut mock,
@@ -1424,7 +1566,7 @@ mod tests {
         let config_json = file::read_file_to_string("../test/json/config-a.json");
         let config: Configuration = serde_json::from_str(&config_json).unwrap();
 
-        let pod_watcher = BrokerPodWatcher::new();
+        let pod_watcher = BrokerPodWatcher::new(Arc::new(config_for_tests::permissive_recorder()));
         let mut mock = MockKubeInterface::new();
         config_for_tests::configure_find_services(
             &mut mock,
@@ -1467,7 +1609,7 @@ mod tests {
         let config_json = file::read_file_to_string("../test/json/config-a.json");
         let config: Configuration = serde_json::from_str(&config_json).unwrap();
 
-        let pod_watcher = BrokerPodWatcher::new();
+        let pod_watcher = BrokerPodWatcher::new(Arc::new(config_for_tests::permissive_recorder()));
         let mut mock = MockKubeInterface::new();
         config_for_tests::configure_find_services(
             &mut mock,
@@ -1511,7 +1653,7 @@ mod tests {
         let config_json = file::read_file_to_string("../test/json/config-a.json");
         let config: Configuration = serde_json::from_str(&config_json).unwrap();
 
-        let pod_watcher = BrokerPodWatcher::new();
+        let pod_watcher = BrokerPodWatcher::new(Arc::new(config_for_tests::permissive_recorder()));
         let mut mock = MockKubeInterface::new();
         config_for_tests::configure_find_services(
             &mut mock,
@@ -1618,6 +1760,8 @@ mod tests {
 
         find_instance_name: &'static str,
         find_instance_result: &'static str,
+        find_broker_pods_selector: &'static str,
+        find_broker_pods_result: &'static str,
 
         find_instance_service: FindServices,
         new_instance_svc_name: &'static str,
@@ -1688,6 +1832,20 @@ mod tests {
                     work.find_config_name,
                 );
             }
+
+            config_for_tests::configure_find_pods(
+                mock,
+                work.find_broker_pods_selector,
+                work.find_broker_pods_result,
+                false,
+            );
+            mock.expect_update_instance_conditions()
+                .times(1)
+                .withf(|conditions, name, _| {
+                    name == "config-a-b494b6"
+                        && conditions.iter().any(|c| c.type_ == "BrokersReady")
+                })
+                .returning(|_, _, _| Ok(()));
         }
     }
 
@@ -5,7 +5,7 @@ pub mod config_for_tests {
             configuration::Configuration,
             instance::{Instance, InstanceList, InstanceSpec},
         },
-        k8s::MockKubeInterface,
+        k8s::{event::MockEventRecorder, MockKubeInterface},
         os::file,
     };
     use k8s_openapi::api::core::v1::{Pod, Service};
@@ -15,6 +15,12 @@ pub mod config_for_tests {
     pub type PodList = ObjectList<Pod>;
     pub type ServiceList = ObjectList<Service>;
 
+    pub fn permissive_recorder() -> MockEventRecorder {
+        let mut recorder = MockEventRecorder::new();
+        recorder.expect_record().returning(|_, _| ());
+        recorder
+    }
+
     pub fn configure_find_instance(
         mock: &mut MockKubeInterface,
         instance_name: &'static str,
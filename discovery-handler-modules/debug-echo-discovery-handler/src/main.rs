@@ -1,9 +1,9 @@
 use akri_debug_echo::{
-    discovery_handler::DiscoveryHandlerImpl, DEBUG_ECHO_INSTANCES_SHARED_LABEL,
+    capabilities, discovery_handler::DiscoveryHandlerImpl, DEBUG_ECHO_INSTANCES_SHARED_LABEL,
     DISCOVERY_HANDLER_NAME,
 };
 use akri_discovery_utils::discovery::discovery_handler::{
-    run_discovery_handler, REGISTER_AGAIN_CHANNEL_CAPACITY,
+    run_discovery_handler_with_capabilities, REGISTER_AGAIN_CHANNEL_CAPACITY,
 };
 use log::info;
 #[tokio::main]
@@ -17,11 +17,12 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>
         .unwrap()
         .parse()
         .unwrap();
-    run_discovery_handler(
+    run_discovery_handler_with_capabilities(
         discovery_handler,
         register_receiver,
         DISCOVERY_HANDLER_NAME,
         shared,
+        capabilities(),
     )
     .await?;
     info!("main - debugEcho discovery handler ended");
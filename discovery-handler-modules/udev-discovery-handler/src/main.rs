@@ -1,7 +1,9 @@
 use akri_discovery_utils::discovery::discovery_handler::{
-    run_discovery_handler, REGISTER_AGAIN_CHANNEL_CAPACITY,
+    run_discovery_handler_with_capabilities, REGISTER_AGAIN_CHANNEL_CAPACITY,
+};
+use akri_udev::{
+    capabilities, discovery_handler::DiscoveryHandlerImpl, DISCOVERY_HANDLER_NAME, SHARED,
 };
-use akri_udev::{discovery_handler::DiscoveryHandlerImpl, DISCOVERY_HANDLER_NAME, SHARED};
 use log::info;
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
@@ -10,11 +12,12 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>
     let (register_sender, register_receiver) =
         tokio::sync::mpsc::channel(REGISTER_AGAIN_CHANNEL_CAPACITY);
     let discovery_handler = DiscoveryHandlerImpl::new(Some(register_sender));
-    run_discovery_handler(
+    run_discovery_handler_with_capabilities(
         discovery_handler,
         register_receiver,
         DISCOVERY_HANDLER_NAME,
         SHARED,
+        capabilities(),
     )
     .await?;
     info!("main - udev discovery handler ended");
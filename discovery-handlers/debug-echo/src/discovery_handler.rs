@@ -1,17 +1,23 @@
 use akri_discovery_utils::discovery::{
-    discovery_handler::{deserialize_discovery_details, DISCOVERED_DEVICES_CHANNEL_CAPACITY},
+    discovery_handler::{
+        deserialize_discovery_details, get_discovery_interval, get_discovery_timeout,
+        run_blocking_scan, DISCOVERED_DEVICES_CHANNEL_CAPACITY,
+    },
     v0::{discovery_handler_server::DiscoveryHandler, Device, DiscoverRequest, DiscoverResponse},
     DiscoverStream,
 };
 use async_trait::async_trait;
 use log::{error, info, trace};
+use schemars::JsonSchema;
 use std::time::Duration;
-use std::{collections::HashMap, fs};
+use std::{collections::HashMap, fs, path::PathBuf};
 use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
 use tokio::time::sleep;
 use tonic::{Response, Status};
 
-// TODO: make this configurable
+/// Default number of seconds between two discovery scans, used if the Configuration does not set
+/// `discoveryIntervalSeconds`
 pub const DISCOVERY_INTERVAL_SECS: u64 = 10;
 
 /// File acting as an environment variable for testing discovery.
@@ -23,10 +29,25 @@ pub const DEBUG_ECHO_AVAILABILITY_CHECK_PATH: &str = "/tmp/debug-echo-availabili
 /// String to write into DEBUG_ECHO_AVAILABILITY_CHECK_PATH to make Other devices undiscoverable
 pub const OFFLINE: &str = "OFFLINE";
 
+/// Checks whether the file at `availability_path` marks the devices as offline, waiting at most `timeout` for it
+/// to be read. Returns `None` if the check timed out, in which case it is awaited again by the next call.
+async fn check_offline(
+    in_flight_check: &mut Option<JoinHandle<bool>>,
+    timeout: Option<Duration>,
+    availability_path: PathBuf,
+) -> Option<bool> {
+    run_blocking_scan(in_flight_check, timeout, move || {
+        fs::read_to_string(availability_path)
+            .unwrap_or_default()
+            .contains(OFFLINE)
+    })
+    .await
+}
+
 /// DebugEchoDiscoveryDetails describes the necessary information needed to discover and filter debug echo devices.
 /// Specifically, it contains a list (`descriptions`) of fake devices to be discovered.
 /// This information is expected to be serialized in the discovery details map sent during Discover requests.
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct DebugEchoDiscoveryDetails {
     pub descriptions: Vec<String>,
@@ -61,11 +82,17 @@ impl DiscoveryHandler for DiscoveryHandlerImpl {
             deserialize_discovery_details(&discover_request.discovery_details)
                 .map_err(|e| tonic::Status::new(tonic::Code::InvalidArgument, format!("{}", e)))?;
         let descriptions = discovery_handler_config.descriptions;
+        let discovery_interval = get_discovery_interval(
+            discover_request,
+            Duration::from_secs(DISCOVERY_INTERVAL_SECS),
+        );
+        let discovery_timeout = get_discovery_timeout(discover_request);
         let mut offline = fs::read_to_string(DEBUG_ECHO_AVAILABILITY_CHECK_PATH)
             .unwrap_or_default()
             .contains(OFFLINE);
         let mut first_loop = true;
         tokio::spawn(async move {
+            let mut in_flight_check = None;
             loop {
                 // Before each iteration, check if receiver has dropped
                 if discovered_devices_sender.is_closed() {
@@ -76,13 +103,23 @@ impl DiscoveryHandler for DiscoveryHandlerImpl {
                     break;
                 }
 
-                let availability =
-                    fs::read_to_string(DEBUG_ECHO_AVAILABILITY_CHECK_PATH).unwrap_or_default();
-                trace!(
-                    "discover -- debugEcho devices are online? {}",
-                    !availability.contains(OFFLINE)
-                );
-                if (availability.contains(OFFLINE) && !offline) || offline && first_loop {
+                let Some(now_offline) = check_offline(
+                    &mut in_flight_check,
+                    discovery_timeout,
+                    PathBuf::from(DEBUG_ECHO_AVAILABILITY_CHECK_PATH),
+                )
+                .await
+                else {
+                    // Keep the devices in their current state until a check completes in time
+                    error!(
+                        "discover - for debugEcho, availability check did not complete within {:?}",
+                        discovery_timeout.unwrap_or_default()
+                    );
+                    sleep(discovery_interval).await;
+                    continue;
+                };
+                trace!("discover -- debugEcho devices are online? {}", !now_offline);
+                if (now_offline && !offline) || offline && first_loop {
                     if first_loop {
                         first_loop = false;
                     }
@@ -91,6 +128,7 @@ impl DiscoveryHandler for DiscoveryHandlerImpl {
                     if let Err(e) = discovered_devices_sender
                         .send(Ok(DiscoverResponse {
                             devices: Vec::new(),
+                            delta: None,
                         }))
                         .await
                     {
@@ -100,7 +138,7 @@ impl DiscoveryHandler for DiscoveryHandlerImpl {
                         }
                         break;
                     }
-                } else if (!availability.contains(OFFLINE) && offline) || !offline && first_loop {
+                } else if (!now_offline && offline) || !offline && first_loop {
                     if first_loop {
                         first_loop = false;
                     }
@@ -118,11 +156,15 @@ impl DiscoveryHandler for DiscoveryHandlerImpl {
                                 properties,
                                 mounts: Vec::default(),
                                 device_specs: Vec::default(),
+                                health: None,
                             }
                         })
                         .collect::<Vec<Device>>();
                     if let Err(e) = discovered_devices_sender
-                        .send(Ok(DiscoverResponse { devices }))
+                        .send(Ok(DiscoverResponse {
+                            devices,
+                            delta: None,
+                        }))
                         .await
                     {
                         // TODO: consider re-registering here
@@ -133,7 +175,7 @@ impl DiscoveryHandler for DiscoveryHandlerImpl {
                         break;
                     }
                 }
-                sleep(Duration::from_secs(DISCOVERY_INTERVAL_SECS)).await;
+                sleep(discovery_interval).await;
             }
         });
         Ok(Response::new(tokio_stream::wrappers::ReceiverStream::new(
@@ -173,6 +215,44 @@ mod tests {
         assert_eq!(&dh_config.descriptions[0], "foo1");
     }
 
+    #[tokio::test]
+    async fn test_check_offline_timeout() {
+        let availability_path = std::env::temp_dir().join(format!(
+            "debug-echo-availability-{}.fifo",
+            std::process::id()
+        ));
+        // Reading a FIFO blocks until it is opened for writing, mimicking a check that hangs
+        assert!(std::process::Command::new("mkfifo")
+            .arg(&availability_path)
+            .status()
+            .unwrap()
+            .success());
+        let mut in_flight_check = None;
+        assert_eq!(
+            check_offline(
+                &mut in_flight_check,
+                Some(Duration::from_millis(10)),
+                availability_path.clone(),
+            )
+            .await,
+            None
+        );
+        assert!(in_flight_check.is_some());
+
+        // The check that timed out completes once the file is written
+        fs::write(&availability_path, OFFLINE).unwrap();
+        assert_eq!(
+            check_offline(
+                &mut in_flight_check,
+                Some(Duration::from_secs(5)),
+                availability_path.clone(),
+            )
+            .await,
+            Some(true)
+        );
+        fs::remove_file(&availability_path).unwrap();
+    }
+
     #[tokio::test]
     async fn test_discover_online_devices() {
         // Make devices "online"
@@ -197,10 +277,13 @@ mod tests {
             properties,
             mounts: Vec::default(),
             device_specs: Vec::default(),
+            health: None,
         };
         let discover_request = tonic::Request::new(DiscoverRequest {
             discovery_details: deserialized.discovery_details.clone(),
             discovery_properties: HashMap::new(),
+            schedule: None,
+            accept_deltas: false,
         });
         let mut stream = discovery_handler
             .discover(discover_request)
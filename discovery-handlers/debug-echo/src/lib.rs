@@ -3,6 +3,8 @@ pub mod discovery_handler;
 #[macro_use]
 extern crate serde_derive;
 
+use akri_discovery_utils::discovery::discovery_handler::DiscoveryHandlerCapabilities;
+
 /// Name debugEcho discovery handlers use when registering with the Agent
 pub const DISCOVERY_HANDLER_NAME: &str = "debugEcho";
 /// Label of the environment variable in debugEcho discovery handlers that sets whether debug echo registers
@@ -11,3 +13,9 @@ pub const DEBUG_ECHO_INSTANCES_SHARED_LABEL: &str = "DEBUG_ECHO_INSTANCES_SHARED
 /// Name of environment variable that is set in debug echo brokers. Contains the description of
 /// the device.
 pub const DEBUG_ECHO_DESCRIPTION_LABEL: &str = "DEBUG_ECHO_DESCRIPTION";
+
+/// Capabilities debugEcho discovery handlers advertise when registering with the Agent
+pub fn capabilities() -> DiscoveryHandlerCapabilities {
+    DiscoveryHandlerCapabilities::new(env!("CARGO_PKG_VERSION"))
+        .with_discovery_details_schema::<discovery_handler::DebugEchoDiscoveryDetails>()
+}
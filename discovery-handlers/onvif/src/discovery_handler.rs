@@ -1,15 +1,21 @@
 use super::credential_store::CredentialStore;
 use super::discovery_impl::util;
 use super::discovery_utils::{
-    OnvifQuery, OnvifQueryImpl, ONVIF_DEVICE_IP_ADDRESS_LABEL_ID,
+    is_authentication_error, MediaProfile, OnvifQuery, OnvifQueryImpl,
+    ONVIF_DEVICE_AUTHENTICATION_FAILED_LABEL_ID, ONVIF_DEVICE_IP_ADDRESS_LABEL_ID,
     ONVIF_DEVICE_MAC_ADDRESS_LABEL_ID, ONVIF_DEVICE_SERVICE_URL_LABEL_ID,
     ONVIF_DEVICE_UUID_LABEL_ID,
 };
 use akri_discovery_utils::{
     discovery::{
-        discovery_handler::{deserialize_discovery_details, DISCOVERED_DEVICES_CHANNEL_CAPACITY},
+        control::{next_update, DiscoveryUpdate, UpdatableDiscoveryHandler},
+        discovery_handler::{
+            deserialize_discovery_details, get_discovery_interval, get_discovery_timeout,
+            DISCOVERED_DEVICES_CHANNEL_CAPACITY,
+        },
         v0::{
-            discovery_handler_server::DiscoveryHandler, Device, DiscoverRequest, DiscoverResponse,
+            device_health, discovery_handler_server::DiscoveryHandler, Device, DeviceHealth,
+            DiscoverRequest, DiscoverResponse,
         },
         DiscoverStream,
     },
@@ -17,19 +23,30 @@ use akri_discovery_utils::{
 };
 use async_trait::async_trait;
 use log::{error, info, trace};
-use std::{collections::HashMap, time::Duration};
-use tokio::{sync::mpsc, time::sleep};
+use schemars::JsonSchema;
+use serde::Deserializer;
+use std::{
+    collections::{HashMap, HashSet},
+    future::Future,
+    time::Duration,
+};
+use tokio::{
+    sync::mpsc,
+    time::{sleep_until, Instant},
+};
 use tonic::{Response, Status};
 
-// TODO: make this configurable
+/// Default number of seconds between two discovery scans, used if the Configuration does not set
+/// `discoveryIntervalSeconds`
 pub const DISCOVERY_INTERVAL_SECS: u64 = 10;
 
 /// This defines the ONVIF data stored in the Configuration
 /// CRD
 ///
 /// The ONVIF discovery handler is structured to store a filter list for
-/// ip addresses, mac addresses, and ONVIF scopes.
-#[derive(Serialize, Deserialize, Clone, Debug)]
+/// ip addresses, mac addresses, and ONVIF scopes. Cameras that multicast probes do not reach can
+/// be discovered with directed probes to `unicast_targets` or through a WS-Discovery proxy.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct OnvifDiscoveryDetails {
     #[serde(default, skip_serializing_if = "Option::is_none")]
@@ -42,12 +59,88 @@ pub struct OnvifDiscoveryDetails {
     pub uuids: Option<FilterList>,
     #[serde(default = "default_discovery_timeout_seconds")]
     pub discovery_timeout_seconds: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub media_profiles: Option<MediaProfilesDetails>,
+    /// IPv4 addresses or CIDR networks of cameras to send directed probes to, in addition to the
+    /// multicast probe. An address may have a port, else the WS-Discovery port is used.
+    #[serde(
+        default,
+        skip_serializing_if = "Vec::is_empty",
+        deserialize_with = "deserialize_unicast_targets"
+    )]
+    pub unicast_targets: Vec<String>,
+    /// Url of a WS-Discovery proxy to probe over HTTP (managed mode discovery)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub discovery_proxy: Option<String>,
 }
 
 fn default_discovery_timeout_seconds() -> i32 {
     1
 }
 
+/// Rejects the unicast targets the probes can't be sent to, as they are sent from an IPv4 socket
+fn deserialize_unicast_targets<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let targets: Vec<String> = serde::Deserialize::deserialize(deserializer)?;
+    for target in &targets {
+        util::validate_unicast_target(target).map_err(serde::de::Error::custom)?;
+    }
+    Ok(targets)
+}
+
+impl Default for OnvifDiscoveryDetails {
+    fn default() -> Self {
+        OnvifDiscoveryDetails {
+            ip_addresses: None,
+            mac_addresses: None,
+            scopes: None,
+            uuids: None,
+            discovery_timeout_seconds: default_discovery_timeout_seconds(),
+            media_profiles: None,
+            unicast_targets: Vec::new(),
+            discovery_proxy: None,
+        }
+    }
+}
+
+/// Enables querying the media profiles of the cameras with the Media or Media2 service. The
+/// properties of the profiles, such as their resolution and streaming uri, are added to the
+/// discovered devices, and the profiles are filtered on them.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaProfilesDetails {
+    /// Discovers a device per media profile instead of a device per camera, which then holds the
+    /// properties of its first profile
+    #[serde(default)]
+    pub device_per_profile: bool,
+    /// Filter on the video encoding of the profiles, such as `H264`, `H265` or `JPEG`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encodings: Option<FilterList>,
+    /// Filter on the resolution of the profiles, in the `WIDTHxHEIGHT` form
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resolutions: Option<FilterList>,
+    /// Filter on the frame rate limit of the profiles
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub frame_rates: Option<FilterList>,
+}
+
+impl MediaProfilesDetails {
+    fn should_include(&self, profile: &MediaProfile) -> bool {
+        !util::execute_filter(
+            self.encodings.as_ref(),
+            profile.encoding.clone().map(|e| vec![e]).as_ref(),
+        ) && !util::execute_filter(
+            self.resolutions.as_ref(),
+            profile.resolution().map(|r| vec![r]).as_ref(),
+        ) && !util::execute_filter(
+            self.frame_rates.as_ref(),
+            profile.frame_rate.clone().map(|f| vec![f]).as_ref(),
+        )
+    }
+}
+
 /// `DiscoveryHandlerImpl` discovers the onvif instances as described by the `OnvifDiscoveryDetails` filters `ip_addresses`,
 /// `mac_addresses`, and `scopes`.
 /// The instances it discovers are always shared.
@@ -69,18 +162,62 @@ impl DiscoveryHandler for DiscoveryHandlerImpl {
         request: tonic::Request<DiscoverRequest>,
     ) -> Result<Response<Self::DiscoverStream>, Status> {
         info!("discover - called for ONVIF protocol");
+        self.start_discovery(request.into_inner(), None)
+            .map(Response::new)
+    }
+}
+
+#[async_trait]
+impl UpdatableDiscoveryHandler for DiscoveryHandlerImpl {
+    async fn discover_with_updates(
+        &self,
+        request: DiscoverRequest,
+        updates: mpsc::Receiver<DiscoveryUpdate>,
+    ) -> Result<DiscoverStream, Status> {
+        info!("discover_with_updates - called for ONVIF protocol");
+        self.start_discovery(request, Some(updates))
+    }
+}
+
+impl DiscoveryHandlerImpl {
+    /// Runs the discovery, applying the updates of the Agent to it when started over the v1 protocol.
+    /// Changing the discovery details or properties re-evaluates all the cameras on the next probe.
+    fn start_discovery(
+        &self,
+        discover_request: DiscoverRequest,
+        mut updates: Option<mpsc::Receiver<DiscoveryUpdate>>,
+    ) -> Result<DiscoverStream, Status> {
         let register_sender = self.register_sender.clone();
-        let discover_request = request.get_ref();
         let (discovered_devices_sender, discovered_devices_receiver) =
             mpsc::channel(DISCOVERED_DEVICES_CHANNEL_CAPACITY);
-        let discovery_handler_config: OnvifDiscoveryDetails =
+        let mut discovery_handler_config: OnvifDiscoveryDetails =
             deserialize_discovery_details(&discover_request.discovery_details)
                 .map_err(|e| tonic::Status::new(tonic::Code::InvalidArgument, format!("{}", e)))?;
+        let discovery_interval = get_discovery_interval(
+            &discover_request,
+            Duration::from_secs(DISCOVERY_INTERVAL_SECS),
+        );
+        // A timeout set on the Configuration is a deadline for the whole scan, probe and camera queries,
+        // the probe listens for the time set in the discovery details within that deadline
+        let configured_timeout = get_discovery_timeout(&discover_request);
+        let get_timeout = move |config: &OnvifDiscoveryDetails| {
+            let listen_timeout = Duration::from_secs(config.discovery_timeout_seconds as u64);
+            configured_timeout.map_or(listen_timeout, |timeout| timeout.min(listen_timeout))
+        };
+        let mut discovery_timeout = get_timeout(&discovery_handler_config);
         let credential_store = CredentialStore::new(&discover_request.discovery_properties);
-        let onvif_query = OnvifQueryImpl::new(credential_store);
+        let mut onvif_query = OnvifQueryImpl::new(credential_store);
+        let mut unicast_targets =
+            util::get_unicast_targets(&discovery_handler_config.unicast_targets);
+        let mut announcement_receiver =
+            listen_for_announcements(discovery_handler_config.scopes.clone());
         tokio::spawn(async move {
             let mut previous_cameras = HashMap::new();
             let mut filtered_camera_devices = HashMap::new();
+            let mut next_probe = Instant::now();
+            // Cameras whose query did not complete before the deadline of the previous scan
+            let mut timed_out_cameras = HashSet::new();
+            let mut scan_start = Instant::now();
             loop {
                 // Before each iteration, check if receiver has dropped
                 if discovered_devices_sender.is_closed() {
@@ -92,16 +229,77 @@ impl DiscoveryHandler for DiscoveryHandlerImpl {
                 }
                 let mut changed_camera_list = false;
 
-                trace!("discover - filters:{:?}", &discovery_handler_config,);
-                let mut socket = util::get_discovery_response_socket().await.unwrap();
-                let latest_cameras = util::simple_onvif_discover(
-                    &mut socket,
-                    discovery_handler_config.scopes.as_ref(),
-                    Duration::from_secs(discovery_handler_config.discovery_timeout_seconds as u64),
-                )
-                .await
-                .unwrap();
+                // Probe the cameras periodically, and add or remove them on their announcements
+                // in between
+                let (latest_cameras, probed) = tokio::select! {
+                    _ = sleep_until(next_probe) => {
+                        trace!("discover - filters:{:?}", &discovery_handler_config,);
+                        scan_start = Instant::now();
+                        let mut socket = util::get_discovery_response_socket().await.unwrap();
+                        util::send_directed_probes(&socket, &unicast_targets).await;
+                        let latest_cameras = util::simple_onvif_discover(
+                            &mut socket,
+                            discovery_handler_config.discovery_proxy.as_deref(),
+                            discovery_handler_config.scopes.as_ref(),
+                            discovery_timeout,
+                        )
+                        .await
+                        .unwrap();
+                        next_probe = Instant::now() + discovery_interval;
+                        (latest_cameras, true)
+                    }
+                    Some(announcement) = announcement_receiver.recv() => {
+                        scan_start = Instant::now();
+                        let mut latest_cameras = previous_cameras.clone();
+                        match announcement {
+                            util::Announcement::Hello(uris) => latest_cameras.extend(uris),
+                            util::Announcement::Bye(uuid) => {
+                                latest_cameras.retain(|_, camera_uuid| *camera_uuid != uuid)
+                            }
+                        }
+                        (latest_cameras, false)
+                    }
+                    update = next_update(&mut updates) => {
+                        match update {
+                            Some(DiscoveryUpdate::Details(details)) => {
+                                match deserialize_discovery_details::<OnvifDiscoveryDetails>(&details) {
+                                    Ok(config) => {
+                                        // The listener filters the announcements on the scopes
+                                        announcement_receiver =
+                                            listen_for_announcements(config.scopes.clone());
+                                        unicast_targets =
+                                            util::get_unicast_targets(&config.unicast_targets);
+                                        discovery_timeout = get_timeout(&config);
+                                        discovery_handler_config = config;
+                                    }
+                                    Err(e) => {
+                                        error!(
+                                            "discover - ignoring invalid discovery details update: {}",
+                                            e
+                                        );
+                                        continue;
+                                    }
+                                }
+                            }
+                            Some(DiscoveryUpdate::Properties(properties)) => {
+                                onvif_query = OnvifQueryImpl::new(CredentialStore::new(&properties));
+                            }
+                            // The Agent asks for an immediate probe
+                            Some(DiscoveryUpdate::Rescan) => {}
+                            None => {
+                                // The discovery got stopped, which also closes the devices channel
+                                trace!("discover - control channel closed");
+                                updates = None;
+                                continue;
+                            }
+                        }
+                        // Probing right away queries all the cameras again with the update or rescan
+                        next_probe = Instant::now();
+                        continue;
+                    }
+                };
                 trace!("discover - discovered:{:?}", &latest_cameras);
+                onvif_query.retain_devices(latest_cameras.values());
                 // Remove cameras that have gone offline
                 previous_cameras.keys().for_each(|c| {
                     if !latest_cameras.contains_key(c) {
@@ -112,17 +310,51 @@ impl DiscoveryHandler for DiscoveryHandlerImpl {
 
                 let futures: Vec<_> = latest_cameras
                     .iter()
-                    .filter(|(k, _)| !previous_cameras.contains_key(*k))
+                    .filter(|(uri, _)| {
+                        needs_query(
+                            uri,
+                            probed,
+                            &previous_cameras,
+                            &filtered_camera_devices,
+                            &timed_out_cameras,
+                        )
+                    })
                     .map(|(uri, uuid)| {
-                        apply_filters(&discovery_handler_config, uri, uuid, &onvif_query)
+                        let filtered = discover_camera_devices(
+                            &discovery_handler_config,
+                            uri,
+                            uuid,
+                            &onvif_query,
+                        );
+                        (uri.clone(), filtered)
                     })
                     .collect();
-                let options = futures_util::future::join_all(futures).await;
-                // Insert newly discovered camera that are not filtered out
-                options.into_iter().for_each(|o| {
-                    if let Some((service_url, d)) = o {
-                        changed_camera_list = true;
-                        filtered_camera_devices.insert(service_url, d);
+                let (options, timed_out) = join_camera_queries(
+                    futures,
+                    configured_timeout.map(|timeout| scan_start + timeout),
+                )
+                .await;
+                // Cameras that did not answer in time keep their devices until queried again on the next scan
+                if !timed_out.is_empty() {
+                    error!(
+                        "discover - queries of cameras {:?} did not complete within {:?}",
+                        timed_out,
+                        configured_timeout.unwrap_or_default()
+                    );
+                }
+                timed_out_cameras = timed_out.into_iter().collect();
+                // Insert cameras that are not filtered out and remove the ones that now are
+                options.into_iter().for_each(|(uri, o)| match o {
+                    Some((service_url, d)) => {
+                        if filtered_camera_devices.get(&service_url) != Some(&d) {
+                            changed_camera_list = true;
+                            filtered_camera_devices.insert(service_url, d);
+                        }
+                    }
+                    None => {
+                        if filtered_camera_devices.remove(&uri).is_some() {
+                            changed_camera_list = true;
+                        }
                     }
                 });
 
@@ -131,7 +363,12 @@ impl DiscoveryHandler for DiscoveryHandlerImpl {
                     previous_cameras = latest_cameras;
                     if let Err(e) = discovered_devices_sender
                         .send(Ok(DiscoverResponse {
-                            devices: filtered_camera_devices.values().cloned().collect(),
+                            devices: filtered_camera_devices
+                                .values()
+                                .flatten()
+                                .cloned()
+                                .collect(),
+                            delta: None,
                         }))
                         .await
                     {
@@ -145,15 +382,175 @@ impl DiscoveryHandler for DiscoveryHandlerImpl {
                         break;
                     }
                 }
-                sleep(Duration::from_secs(DISCOVERY_INTERVAL_SECS)).await;
             }
         });
-        Ok(Response::new(tokio_stream::wrappers::ReceiverStream::new(
+        Ok(tokio_stream::wrappers::ReceiverStream::new(
             discovered_devices_receiver,
-        )))
+        ))
+    }
+}
+
+/// Listens for the Hello and Bye announcements of the cameras passing the scopes filter, the listener
+/// stops once the returned receiver gets dropped.
+fn listen_for_announcements(scopes: Option<FilterList>) -> mpsc::Receiver<util::Announcement> {
+    let (announcement_sender, announcement_receiver) =
+        mpsc::channel(DISCOVERED_DEVICES_CHANNEL_CAPACITY);
+    match util::get_announcement_socket() {
+        Ok(socket) => {
+            tokio::spawn(util::listen_for_announcements(
+                socket,
+                scopes,
+                announcement_sender,
+            ));
+        }
+        Err(e) => error!(
+            "discover - unable to listen for Hello and Bye announcements, relying on probes only: {}",
+            e
+        ),
+    }
+    announcement_receiver
+}
+
+/// Waits for the camera queries until the `deadline` of the scan. Returns the results of the queries that
+/// completed in time along with the uris of the cameras whose query got dropped at the deadline.
+async fn join_camera_queries<F, T>(
+    queries: Vec<(String, F)>,
+    deadline: Option<Instant>,
+) -> (Vec<(String, T)>, Vec<String>)
+where
+    F: Future<Output = T>,
+{
+    let queries = queries.into_iter().map(|(uri, query)| async move {
+        let result = match deadline {
+            Some(deadline) => tokio::time::timeout_at(deadline, query).await.ok(),
+            None => Some(query.await),
+        };
+        (uri, result)
+    });
+    let mut completed = Vec::new();
+    let mut timed_out = Vec::new();
+    for (uri, result) in futures_util::future::join_all(queries).await {
+        match result {
+            Some(result) => completed.push((uri, result)),
+            None => timed_out.push(uri),
+        }
+    }
+    (completed, timed_out)
+}
+
+/// Whether a camera is queried in this iteration of the discovery. All the cameras are queried again on
+/// each probe so that known cameras that stop answering are reported unhealthy, while announcements only
+/// query the new cameras, the unhealthy ones and the ones that timed out on the previous scan.
+fn needs_query(
+    uri: &str,
+    probed: bool,
+    previous_cameras: &HashMap<String, String>,
+    filtered_camera_devices: &HashMap<String, Vec<Device>>,
+    timed_out_cameras: &HashSet<String>,
+) -> bool {
+    probed
+        || !previous_cameras.contains_key(uri)
+        || timed_out_cameras.contains(uri)
+        || filtered_camera_devices
+            .get(uri)
+            .is_some_and(|devices| devices.iter().any(is_unhealthy))
+}
+
+fn is_unhealthy(device: &Device) -> bool {
+    device
+        .health
+        .as_ref()
+        .is_some_and(|h| h.state() == device_health::State::Unhealthy)
+}
+
+/// Evaluates the filters against a discovered camera, then splits it in a device per media profile
+/// if requested.
+async fn discover_camera_devices(
+    discovery_handler_config: &OnvifDiscoveryDetails,
+    device_service_uri: &str,
+    device_uuid: &str,
+    onvif_query: &impl OnvifQuery,
+) -> Option<(String, Vec<Device>)> {
+    let (service_url, device) = apply_filters(
+        discovery_handler_config,
+        device_service_uri,
+        device_uuid,
+        onvif_query,
+    )
+    .await?;
+    match discovery_handler_config.media_profiles.as_ref() {
+        // The profiles of a camera that does not answer are queried once it is healthy again
+        Some(media_profiles) if !is_unhealthy(&device) => {
+            let devices =
+                apply_media_profiles(media_profiles, device, device_uuid, onvif_query).await?;
+            Some((service_url, devices))
+        }
+        _ => Some((service_url, vec![device])),
     }
 }
 
+/// Adds the properties of the camera's media profiles to its device, filtering the profiles with the
+/// `MediaProfilesDetails`. A camera without any matching profile is filtered out.
+async fn apply_media_profiles(
+    media_profiles: &MediaProfilesDetails,
+    mut device: Device,
+    device_uuid: &str,
+    onvif_query: &impl OnvifQuery,
+) -> Option<Vec<Device>> {
+    let service_url = device.properties[ONVIF_DEVICE_SERVICE_URL_LABEL_ID].clone();
+    let profiles = match onvif_query
+        .get_media_profiles(&service_url, device_uuid)
+        .await
+    {
+        Ok(profiles) => profiles,
+        Err(e) => {
+            error!("apply_media_profiles - error getting media profiles: {}", e);
+            if is_authentication_error(&e) {
+                device.properties.insert(
+                    ONVIF_DEVICE_AUTHENTICATION_FAILED_LABEL_ID.to_string(),
+                    "true".to_string(),
+                );
+            }
+            device.health = Some(DeviceHealth {
+                state: device_health::State::Unhealthy.into(),
+                reason: format!("Unable to get media profiles: {}", e),
+            });
+            return Some(vec![device]);
+        }
+    };
+    let profiles: Vec<MediaProfile> = profiles
+        .into_iter()
+        .filter(|profile| media_profiles.should_include(profile))
+        .collect();
+    if profiles.is_empty() {
+        trace!(
+            "apply_media_profiles - no media profile of {} passes the filters",
+            service_url
+        );
+        return None;
+    }
+    if media_profiles.device_per_profile {
+        Some(
+            profiles
+                .iter()
+                .map(|profile| {
+                    let mut profile_device = device.clone();
+                    profile_device.id = format!("{}-{}", device.id, profile.token);
+                    profile_device.properties.extend(profile.properties());
+                    profile_device
+                })
+                .collect(),
+        )
+    } else {
+        device.properties.extend(profiles[0].properties());
+        Some(vec![device])
+    }
+}
+
+/// Evaluates the filters against a discovered camera. A camera that cannot be queried for its
+/// ip and mac addresses is filtered out if a filter applies to these addresses, otherwise it is
+/// kept as an unhealthy device. A camera rejecting the credentials is flagged with the
+/// `ONVIF_DEVICE_AUTHENTICATION_FAILED` property.
 async fn apply_filters(
     discovery_handler_config: &OnvifDiscoveryDetails,
     device_service_uri: &str,
@@ -168,26 +565,29 @@ async fn apply_filters(
     if util::execute_filter(
         discovery_handler_config.uuids.as_ref(),
         Some(vec![device_uuid.to_string()]).as_ref(),
-        |uuid, pattern| uuid.to_lowercase() == pattern.to_lowercase(),
     ) {
         return None;
     }
 
-    let ip_and_mac = onvif_query
+    let (ip_and_mac, health, authentication_failed) = match onvif_query
         .get_device_ip_and_mac_address(device_service_uri, device_uuid)
         .await
-        .map_err(|e| {
+    {
+        Ok(ip_and_mac) => (Some(ip_and_mac), None, false),
+        Err(e) => {
             error!("apply_filters - error getting ip and mac address: {}", e);
-            e
-        })
-        .ok();
+            let health = DeviceHealth {
+                state: device_health::State::Unhealthy.into(),
+                reason: format!("Unable to get ip and mac address: {}", e),
+            };
+            (None, Some(health), is_authentication_error(&e))
+        }
+    };
     // Evaluate camera ip address against ip filter if provided
-    // use case-insensitive comparison in case of IPv6 is used
     let ip_address_as_vec = ip_and_mac.as_ref().map(|(ip, _)| vec![ip.clone()]);
     if util::execute_filter(
         discovery_handler_config.ip_addresses.as_ref(),
         ip_address_as_vec.as_ref(),
-        |ip, pattern| ip.to_lowercase() == pattern.to_lowercase(),
     ) {
         return None;
     }
@@ -197,7 +597,6 @@ async fn apply_filters(
     if util::execute_filter(
         discovery_handler_config.mac_addresses.as_ref(),
         mac_address_as_vec.as_ref(),
-        |mac, pattern| mac.to_lowercase() == pattern.to_lowercase(),
     ) {
         return None;
     }
@@ -213,6 +612,12 @@ async fn apply_filters(
         properties.insert(ONVIF_DEVICE_IP_ADDRESS_LABEL_ID.into(), ip_address);
         properties.insert(ONVIF_DEVICE_MAC_ADDRESS_LABEL_ID.into(), mac_address);
     }
+    if authentication_failed {
+        properties.insert(
+            ONVIF_DEVICE_AUTHENTICATION_FAILED_LABEL_ID.into(),
+            "true".to_string(),
+        );
+    }
 
     Some((
         device_service_uri.to_string(),
@@ -221,6 +626,7 @@ async fn apply_filters(
             properties,
             mounts: Vec::default(),
             device_specs: Vec::default(),
+            health,
         },
     ))
 }
@@ -285,10 +691,126 @@ mod tests {
                 properties,
                 mounts: Vec::default(),
                 device_specs: Vec::default(),
+                health: None,
             },
         )
     }
 
+    fn expected_unhealthy_device(uri: &str, uuid: &str) -> (String, Device) {
+        let (uri, mut device) = expected_device(uri, uuid, None);
+        device.health = Some(DeviceHealth {
+            state: device_health::State::Unhealthy.into(),
+            reason: "Unable to get ip and mac address: mock get_device_ip_and_mac_address failure"
+                .to_string(),
+        });
+        (uri, device)
+    }
+
+    #[test]
+    fn test_is_unhealthy() {
+        let (_, mut device) = expected_device("device_uri", "device_uuid", None);
+        assert!(!is_unhealthy(&device));
+        device.health = Some(DeviceHealth {
+            state: device_health::State::Degraded.into(),
+            reason: String::new(),
+        });
+        assert!(!is_unhealthy(&device));
+        let (_, device) = expected_unhealthy_device("device_uri", "device_uuid");
+        assert!(is_unhealthy(&device));
+    }
+
+    #[tokio::test]
+    async fn test_known_camera_failing_becomes_unhealthy() {
+        let _ = env_logger::builder().is_test(true).try_init();
+        let (uri, uuid) = ("device_uri", "device_uuid");
+        let ip_and_mac = IpAndMac {
+            ip: "mock.ip",
+            mac: "mock:mac",
+        };
+        let previous_cameras = HashMap::from([(uri.to_string(), uuid.to_string())]);
+        let (_, healthy_device) = expected_device(uri, uuid, Some(ip_and_mac));
+        let filtered_camera_devices = HashMap::from([(uri.to_string(), vec![healthy_device])]);
+        // A known healthy camera is only queried again on probes
+        assert!(!needs_query(
+            uri,
+            false,
+            &previous_cameras,
+            &filtered_camera_devices,
+            &HashSet::new()
+        ));
+        assert!(needs_query(
+            uri,
+            true,
+            &previous_cameras,
+            &filtered_camera_devices,
+            &HashSet::new()
+        ));
+
+        // Its query now fails, which reports it unhealthy
+        let mut mock = MockOnvifQuery::new();
+        configure_scenario(
+            &mut mock,
+            uri,
+            Err("mock get_device_ip_and_mac_address failure".to_string()),
+        );
+        let (service_url, devices) =
+            discover_camera_devices(&OnvifDiscoveryDetails::default(), uri, uuid, &mock)
+                .await
+                .unwrap();
+        assert_eq!(service_url, uri);
+        assert_eq!(devices, vec![expected_unhealthy_device(uri, uuid).1]);
+
+        // Unhealthy and timed out cameras are also queried on announcements
+        let filtered_camera_devices = HashMap::from([(uri.to_string(), devices)]);
+        assert!(needs_query(
+            uri,
+            false,
+            &previous_cameras,
+            &filtered_camera_devices,
+            &HashSet::new()
+        ));
+        assert!(needs_query(
+            uri,
+            false,
+            &previous_cameras,
+            &HashMap::new(),
+            &HashSet::from([uri.to_string()])
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_join_camera_queries_deadline() {
+        let mut mock = MockOnvifQuery::new();
+        let ip_and_mac = IpAndMac {
+            ip: "mock.ip",
+            mac: "mock:mac",
+        };
+        configure_scenario(&mut mock, "device_uri", Ok(ip_and_mac));
+        let config = OnvifDiscoveryDetails::default();
+        let answering: std::pin::Pin<Box<dyn Future<Output = Option<(String, Vec<Device>)>>>> =
+            Box::pin(discover_camera_devices(
+                &config,
+                "device_uri",
+                "device_uuid",
+                &mock,
+            ));
+        // A camera that never answers must not hold the scan past its deadline
+        let hanging: std::pin::Pin<Box<dyn Future<Output = Option<(String, Vec<Device>)>>>> =
+            Box::pin(std::future::pending());
+        let (completed, timed_out) = join_camera_queries(
+            vec![
+                ("device_uri".to_string(), answering),
+                ("hanging_uri".to_string(), hanging),
+            ],
+            Some(Instant::now() + Duration::from_millis(100)),
+        )
+        .await;
+        assert_eq!(completed.len(), 1);
+        assert_eq!(completed[0].0, "device_uri");
+        assert!(completed[0].1.is_some());
+        assert_eq!(timed_out, vec!["hanging_uri".to_string()]);
+    }
+
     #[test]
     fn test_deserialize_discovery_details() {
         let _ = env_logger::builder().is_test(true).try_init();
@@ -297,6 +819,36 @@ mod tests {
         let serialized = serde_json::to_string(&dh_config).unwrap();
         let expected_deserialized = r#"{"discoveryTimeoutSeconds":1}"#;
         assert_eq!(expected_deserialized, serialized);
+        assert_eq!(
+            serialized,
+            serde_json::to_string(&OnvifDiscoveryDetails::default()).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_deserialize_discovery_details_directed_discovery() {
+        let yaml = r#"
+          unicastTargets:
+          - 10.1.0.10
+          - 10.2.0.0/24
+          discoveryProxy: http://discovery-proxy:5357/
+        "#;
+        let dh_config: OnvifDiscoveryDetails = deserialize_discovery_details(yaml).unwrap();
+        assert_eq!(dh_config.unicast_targets, vec!["10.1.0.10", "10.2.0.0/24"]);
+        assert_eq!(
+            dh_config.discovery_proxy.as_deref(),
+            Some("http://discovery-proxy:5357/")
+        );
+    }
+
+    #[test]
+    fn test_deserialize_discovery_details_ipv6_unicast_target() {
+        let yaml = r#"
+          unicastTargets:
+          - 10.1.0.10
+          - fd00::1
+        "#;
+        assert!(deserialize_discovery_details::<OnvifDiscoveryDetails>(yaml).is_err());
     }
 
     #[tokio::test]
@@ -319,6 +871,7 @@ mod tests {
             scopes: None,
             uuids: None,
             discovery_timeout_seconds: 1,
+            ..Default::default()
         };
         let instance = apply_filters(&onvif_config, mock_uri, mock_uuid, &mock)
             .await
@@ -350,12 +903,49 @@ mod tests {
             scopes: None,
             uuids: None,
             discovery_timeout_seconds: 1,
+            ..Default::default()
         };
         let instance = apply_filters(&onvif_config, mock_uri, mock_uuid, &mock)
             .await
             .unwrap();
 
-        assert_eq!(expected_device(mock_uri, mock_uuid, None), instance);
+        assert_eq!(expected_unhealthy_device(mock_uri, mock_uuid), instance);
+    }
+
+    #[tokio::test]
+    async fn test_apply_filters_get_ip_mac_address_authentication_fail() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let mock_uri = "device_uri";
+        let mock_uuid = "device_uuid";
+
+        let mut mock = MockOnvifQuery::new();
+        mock.expect_get_device_ip_and_mac_address()
+            .times(1)
+            .returning(|_, _| {
+                Err(crate::discovery_utils::AuthenticationError { challenges: vec![] }.into())
+            });
+
+        let onvif_config = OnvifDiscoveryDetails {
+            ip_addresses: None,
+            mac_addresses: None,
+            scopes: None,
+            uuids: None,
+            discovery_timeout_seconds: 1,
+            ..Default::default()
+        };
+        let (_, device) = apply_filters(&onvif_config, mock_uri, mock_uuid, &mock)
+            .await
+            .unwrap();
+
+        // The camera is kept as an unhealthy device flagged as failing authentication
+        assert!(is_unhealthy(&device));
+        assert_eq!(
+            device
+                .properties
+                .get(ONVIF_DEVICE_AUTHENTICATION_FAILED_LABEL_ID),
+            Some(&"true".to_string())
+        );
     }
 
     #[tokio::test]
@@ -377,11 +967,13 @@ mod tests {
             ip_addresses: Some(FilterList {
                 action: FilterType::Include,
                 items: vec![mock_ip.to_string()],
+                ..Default::default()
             }),
             mac_addresses: None,
             scopes: None,
             uuids: None,
             discovery_timeout_seconds: 1,
+            ..Default::default()
         };
         assert!(apply_filters(&onvif_config, mock_uri, mock_uuid, &mock)
             .await
@@ -407,11 +999,13 @@ mod tests {
             ip_addresses: Some(FilterList {
                 action: FilterType::Include,
                 items: vec![mock_ip.to_string()],
+                ..Default::default()
             }),
             mac_addresses: None,
             scopes: None,
             uuids: None,
             discovery_timeout_seconds: 1,
+            ..Default::default()
         };
         let instance = apply_filters(&onvif_config, mock_uri, mock_uuid, &mock)
             .await
@@ -441,11 +1035,13 @@ mod tests {
             ip_addresses: Some(FilterList {
                 action: FilterType::Include,
                 items: vec!["nonexist.ip".to_string()],
+                ..Default::default()
             }),
             mac_addresses: None,
             scopes: None,
             uuids: None,
             discovery_timeout_seconds: 1,
+            ..Default::default()
         };
         assert!(apply_filters(&onvif_config, mock_uri, mock_uuid, &mock)
             .await
@@ -470,11 +1066,13 @@ mod tests {
             ip_addresses: Some(FilterList {
                 action: FilterType::Include,
                 items: vec!["mock.i".to_string()],
+                ..Default::default()
             }),
             mac_addresses: None,
             scopes: None,
             uuids: None,
             discovery_timeout_seconds: 1,
+            ..Default::default()
         };
         assert!(apply_filters(&onvif_config, mock_uri, mock_uuid, &mock)
             .await
@@ -499,11 +1097,13 @@ mod tests {
             ip_addresses: Some(FilterList {
                 action: FilterType::Exclude,
                 items: vec!["nonexist.ip".to_string()],
+                ..Default::default()
             }),
             mac_addresses: None,
             scopes: None,
             uuids: None,
             discovery_timeout_seconds: 1,
+            ..Default::default()
         };
         let instance = apply_filters(&onvif_config, mock_uri, mock_uuid, &mock)
             .await
@@ -534,11 +1134,13 @@ mod tests {
             ip_addresses: Some(FilterList {
                 action: FilterType::Exclude,
                 items: vec![mock_ip.to_string()],
+                ..Default::default()
             }),
             mac_addresses: None,
             scopes: None,
             uuids: None,
             discovery_timeout_seconds: 1,
+            ..Default::default()
         };
         assert!(apply_filters(&onvif_config, mock_uri, mock_uuid, &mock)
             .await
@@ -563,11 +1165,13 @@ mod tests {
             ip_addresses: Some(FilterList {
                 action: FilterType::Exclude,
                 items: vec!["mock.i".to_string()],
+                ..Default::default()
             }),
             mac_addresses: None,
             scopes: None,
             uuids: None,
             discovery_timeout_seconds: 1,
+            ..Default::default()
         };
         let instance = apply_filters(&onvif_config, mock_uri, mock_uuid, &mock)
             .await
@@ -599,10 +1203,12 @@ mod tests {
             mac_addresses: Some(FilterList {
                 action: FilterType::Include,
                 items: vec![mock_mac.to_string()],
+                ..Default::default()
             }),
             scopes: None,
             uuids: None,
             discovery_timeout_seconds: 1,
+            ..Default::default()
         };
         assert!(apply_filters(&onvif_config, mock_uri, mock_uuid, &mock)
             .await
@@ -629,10 +1235,12 @@ mod tests {
             mac_addresses: Some(FilterList {
                 action: FilterType::Include,
                 items: vec![mock_mac.to_string()],
+                ..Default::default()
             }),
             scopes: None,
             uuids: None,
             discovery_timeout_seconds: 1,
+            ..Default::default()
         };
         let instance = apply_filters(&onvif_config, mock_uri, mock_uuid, &mock)
             .await
@@ -663,10 +1271,12 @@ mod tests {
             mac_addresses: Some(FilterList {
                 action: FilterType::Include,
                 items: vec!["nonexist:mac".to_string()],
+                ..Default::default()
             }),
             scopes: None,
             uuids: None,
             discovery_timeout_seconds: 1,
+            ..Default::default()
         };
         assert!(apply_filters(&onvif_config, mock_uri, mock_uuid, &mock)
             .await
@@ -692,10 +1302,12 @@ mod tests {
             mac_addresses: Some(FilterList {
                 action: FilterType::Exclude,
                 items: vec!["nonexist:mac".to_string()],
+                ..Default::default()
             }),
             scopes: None,
             uuids: None,
             discovery_timeout_seconds: 1,
+            ..Default::default()
         };
         let instance = apply_filters(&onvif_config, mock_uri, mock_uuid, &mock)
             .await
@@ -727,10 +1339,12 @@ mod tests {
             mac_addresses: Some(FilterList {
                 action: FilterType::Exclude,
                 items: vec![mock_mac.to_string()],
+                ..Default::default()
             }),
             scopes: None,
             uuids: None,
             discovery_timeout_seconds: 1,
+            ..Default::default()
         };
         assert!(apply_filters(&onvif_config, mock_uri, mock_uuid, &mock)
             .await
@@ -757,10 +1371,12 @@ mod tests {
             mac_addresses: Some(FilterList {
                 action: FilterType::Include,
                 items: vec![mock_mac.to_uppercase()],
+                ..Default::default()
             }),
             scopes: None,
             uuids: None,
             discovery_timeout_seconds: 1,
+            ..Default::default()
         };
         let instance = apply_filters(&onvif_config, mock_uri, mock_uuid, &mock)
             .await
@@ -792,10 +1408,12 @@ mod tests {
             mac_addresses: Some(FilterList {
                 action: FilterType::Exclude,
                 items: vec![mock_mac.to_uppercase()],
+                ..Default::default()
             }),
             scopes: None,
             uuids: None,
             discovery_timeout_seconds: 1,
+            ..Default::default()
         };
         assert!(apply_filters(&onvif_config, mock_uri, mock_uuid, &mock)
             .await
@@ -823,8 +1441,10 @@ mod tests {
             uuids: Some(FilterList {
                 action: FilterType::Include,
                 items: vec![mock_uuid.to_string()],
+                ..Default::default()
             }),
             discovery_timeout_seconds: 1,
+            ..Default::default()
         };
         let instance = apply_filters(&onvif_config, mock_uri, mock_uuid, &mock)
             .await
@@ -851,8 +1471,10 @@ mod tests {
             uuids: Some(FilterList {
                 action: FilterType::Include,
                 items: vec!["nonexist-uuid".to_string()],
+                ..Default::default()
             }),
             discovery_timeout_seconds: 1,
+            ..Default::default()
         };
         assert!(apply_filters(&onvif_config, mock_uri, mock_uuid, &mock)
             .await
@@ -874,8 +1496,10 @@ mod tests {
             uuids: Some(FilterList {
                 action: FilterType::Include,
                 items: vec!["device_uui".to_string()],
+                ..Default::default()
             }),
             discovery_timeout_seconds: 1,
+            ..Default::default()
         };
         assert!(apply_filters(&onvif_config, mock_uri, mock_uuid, &mock)
             .await
@@ -897,8 +1521,10 @@ mod tests {
             uuids: Some(FilterList {
                 action: FilterType::Exclude,
                 items: vec![mock_uuid.to_string()],
+                ..Default::default()
             }),
             discovery_timeout_seconds: 1,
+            ..Default::default()
         };
         assert!(apply_filters(&onvif_config, mock_uri, mock_uuid, &mock)
             .await
@@ -926,8 +1552,10 @@ mod tests {
             uuids: Some(FilterList {
                 action: FilterType::Exclude,
                 items: vec!["nonexist-uuid".to_string()],
+                ..Default::default()
             }),
             discovery_timeout_seconds: 1,
+            ..Default::default()
         };
         let instance = apply_filters(&onvif_config, mock_uri, mock_uuid, &mock)
             .await
@@ -960,8 +1588,10 @@ mod tests {
             uuids: Some(FilterList {
                 action: FilterType::Exclude,
                 items: vec!["device_uui".to_string()],
+                ..Default::default()
             }),
             discovery_timeout_seconds: 1,
+            ..Default::default()
         };
         let instance = apply_filters(&onvif_config, mock_uri, mock_uuid, &mock)
             .await
@@ -994,8 +1624,10 @@ mod tests {
             uuids: Some(FilterList {
                 action: FilterType::Include,
                 items: vec![mock_uuid.to_uppercase()],
+                ..Default::default()
             }),
             discovery_timeout_seconds: 1,
+            ..Default::default()
         };
         let instance = apply_filters(&onvif_config, mock_uri, mock_uuid, &mock)
             .await
@@ -1022,11 +1654,193 @@ mod tests {
             uuids: Some(FilterList {
                 action: FilterType::Exclude,
                 items: vec![mock_uuid.to_uppercase()],
+                ..Default::default()
             }),
             discovery_timeout_seconds: 1,
+            ..Default::default()
         };
         assert!(apply_filters(&onvif_config, mock_uri, mock_uuid, &mock)
             .await
             .is_none());
     }
+
+    fn media_profiles() -> Vec<MediaProfile> {
+        vec![
+            MediaProfile {
+                token: "main".to_string(),
+                name: "MainStream".to_string(),
+                encoding: Some("H265".to_string()),
+                width: Some(3840),
+                height: Some(2160),
+                frame_rate: Some("30".to_string()),
+                stream_uri: Some("rtsp://camera/main".to_string()),
+            },
+            MediaProfile {
+                token: "sub".to_string(),
+                name: "SubStream".to_string(),
+                encoding: Some("H264".to_string()),
+                width: Some(640),
+                height: Some(360),
+                frame_rate: Some("15".to_string()),
+                stream_uri: Some("rtsp://camera/sub".to_string()),
+            },
+        ]
+    }
+
+    fn media_profiles_config(media_profiles: MediaProfilesDetails) -> OnvifDiscoveryDetails {
+        OnvifDiscoveryDetails {
+            ip_addresses: None,
+            mac_addresses: None,
+            scopes: None,
+            uuids: None,
+            discovery_timeout_seconds: 1,
+            media_profiles: Some(media_profiles),
+            ..Default::default()
+        }
+    }
+
+    fn configure_get_media_profiles(mock: &mut MockOnvifQuery, uri: &'static str) {
+        mock.expect_get_media_profiles()
+            .times(1)
+            .withf(move |u, _uuid| u == uri)
+            .returning(|_, _| Ok(media_profiles()));
+    }
+
+    #[test]
+    fn test_deserialize_discovery_details_media_profiles() {
+        let yaml = r#"
+            mediaProfiles:
+              devicePerProfile: true
+              encodings:
+                items:
+                - H264
+              resolutions:
+                action: Exclude
+                itemKind: Glob
+                items:
+                - "640x*"
+        "#;
+        let dh_config: OnvifDiscoveryDetails = deserialize_discovery_details(yaml).unwrap();
+        let serialized = serde_json::to_string(&dh_config).unwrap();
+        let expected_serialized = r#"{"discoveryTimeoutSeconds":1,"mediaProfiles":{"devicePerProfile":true,"encodings":{"items":["H264"],"action":"Include"},"resolutions":{"items":["640x*"],"action":"Exclude","itemKind":"Glob"}}}"#;
+        assert_eq!(expected_serialized, serialized);
+    }
+
+    #[tokio::test]
+    async fn test_discover_camera_devices_device_per_profile() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let mock_uri = "device_uri";
+        let mock_uuid = "device_uuid";
+        let mock_ip_and_mac = IpAndMac {
+            ip: "mock.ip",
+            mac: "mock:mac",
+        };
+        let mut mock = MockOnvifQuery::new();
+        configure_scenario(&mut mock, mock_uri, Ok(mock_ip_and_mac.clone()));
+        configure_get_media_profiles(&mut mock, mock_uri);
+
+        let onvif_config = media_profiles_config(MediaProfilesDetails {
+            device_per_profile: true,
+            ..Default::default()
+        });
+        let (uri, devices) = discover_camera_devices(&onvif_config, mock_uri, mock_uuid, &mock)
+            .await
+            .unwrap();
+        assert_eq!(uri, mock_uri);
+        let (_, camera_device) = expected_device(mock_uri, mock_uuid, Some(mock_ip_and_mac));
+        let expected_devices = media_profiles()
+            .iter()
+            .map(|profile| {
+                let mut device = camera_device.clone();
+                device.id = format!("{}-{}", camera_device.id, profile.token);
+                device.properties.extend(profile.properties());
+                device
+            })
+            .collect::<Vec<Device>>();
+        assert_eq!(expected_devices, devices);
+    }
+
+    #[tokio::test]
+    async fn test_discover_camera_devices_profile_filters() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let mock_uri = "device_uri";
+        let mock_uuid = "device_uuid";
+        let mock_ip_and_mac = IpAndMac {
+            ip: "mock.ip",
+            mac: "mock:mac",
+        };
+        let mut mock = MockOnvifQuery::new();
+        configure_scenario(&mut mock, mock_uri, Ok(mock_ip_and_mac.clone()));
+        configure_get_media_profiles(&mut mock, mock_uri);
+
+        // The camera device holds the properties of its first profile passing the filters
+        let onvif_config = media_profiles_config(MediaProfilesDetails {
+            resolutions: Some(FilterList {
+                action: FilterType::Exclude,
+                items: vec!["3840x*".to_string()],
+                item_kind: akri_discovery_utils::filtering::FilterItemKind::Glob,
+            }),
+            ..Default::default()
+        });
+        let (_, devices) = discover_camera_devices(&onvif_config, mock_uri, mock_uuid, &mock)
+            .await
+            .unwrap();
+        let (_, mut expected) = expected_device(mock_uri, mock_uuid, Some(mock_ip_and_mac));
+        expected.properties.extend(media_profiles()[1].properties());
+        assert_eq!(vec![expected], devices);
+
+        // A camera without matching profiles is filtered out
+        let mut mock = MockOnvifQuery::new();
+        configure_scenario(
+            &mut mock,
+            mock_uri,
+            Ok(IpAndMac {
+                ip: "mock.ip",
+                mac: "mock:mac",
+            }),
+        );
+        configure_get_media_profiles(&mut mock, mock_uri);
+        let onvif_config = media_profiles_config(MediaProfilesDetails {
+            encodings: Some(FilterList {
+                items: vec!["JPEG".to_string()],
+                ..Default::default()
+            }),
+            ..Default::default()
+        });
+        assert!(
+            discover_camera_devices(&onvif_config, mock_uri, mock_uuid, &mock)
+                .await
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_discover_camera_devices_media_profiles_fail() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let mock_uri = "device_uri";
+        let mock_uuid = "device_uuid";
+        let mock_ip_and_mac = IpAndMac {
+            ip: "mock.ip",
+            mac: "mock:mac",
+        };
+        let mut mock = MockOnvifQuery::new();
+        configure_scenario(&mut mock, mock_uri, Ok(mock_ip_and_mac.clone()));
+        mock.expect_get_media_profiles()
+            .times(1)
+            .returning(|_, _| Err(anyhow::format_err!("mock get_media_profiles failure")));
+
+        let onvif_config = media_profiles_config(MediaProfilesDetails::default());
+        let (_, devices) = discover_camera_devices(&onvif_config, mock_uri, mock_uuid, &mock)
+            .await
+            .unwrap();
+        let (_, mut expected) = expected_device(mock_uri, mock_uuid, Some(mock_ip_and_mac));
+        expected.health = Some(DeviceHealth {
+            state: device_health::State::Unhealthy.into(),
+            reason: "Unable to get media profiles: mock get_media_profiles failure".to_string(),
+        });
+        assert_eq!(vec![expected], devices);
+    }
 }
@@ -73,6 +73,44 @@ pub mod to_deserialize {
         #[yaserde(prefix = "w", rename = "RelatesTo")]
         pub relates_to: String,
     }
+
+    /// Hello or Bye announcement multicast by a device joining or leaving the network
+    #[derive(Default, PartialEq, Debug, YaDeserialize)]
+    #[yaserde(prefix = "s", namespace = "s: http://www.w3.org/2003/05/soap-envelope")]
+    pub struct AnnouncementEnvelope {
+        #[yaserde(prefix = "s", rename = "Header")]
+        pub header: AnnouncementHeader,
+
+        #[yaserde(prefix = "s", rename = "Body")]
+        pub body: AnnouncementBody,
+    }
+
+    #[derive(Default, PartialEq, Debug, YaDeserialize)]
+    #[yaserde(
+        prefix = "s",
+        namespace = "s: http://www.w3.org/2003/05/soap-envelope",
+        namespace = "w: http://schemas.xmlsoap.org/ws/2004/08/addressing"
+    )]
+    pub struct AnnouncementHeader {
+        #[yaserde(prefix = "w", rename = "Action")]
+        pub action: String,
+    }
+
+    /// The Hello and Bye messages describe the device like a ProbeMatch does, a Bye only holding
+    /// its EndpointReference
+    #[derive(Default, PartialEq, Debug, YaDeserialize)]
+    #[yaserde(
+        prefix = "s",
+        namespace = "s: http://www.w3.org/2003/05/soap-envelope",
+        namespace = "d: http://schemas.xmlsoap.org/ws/2005/04/discovery"
+    )]
+    pub struct AnnouncementBody {
+        #[yaserde(prefix = "d", rename = "Hello")]
+        pub hello: Vec<ProbeMatch>,
+
+        #[yaserde(prefix = "d", rename = "Bye")]
+        pub bye: Vec<ProbeMatch>,
+    }
 }
 
 #[allow(dead_code)]
@@ -143,15 +181,23 @@ pub mod util {
     use super::super::discovery_utils::{OnvifQuery, OnvifQueryImpl};
     use super::{common, probe_types, to_deserialize, to_serialize};
     use akri_discovery_utils::filtering::{FilterList, FilterType};
-    use log::{error, info, trace};
-    use std::collections::HashMap;
+    use futures_util::stream::TryStreamExt;
+    use hyper::Request;
+    use log::{error, info, trace, warn};
+    use std::collections::{HashMap, HashSet};
     use std::net::{IpAddr, Ipv4Addr, SocketAddr};
     use tokio::{
         io::ErrorKind,
         net::UdpSocket,
+        sync::mpsc,
         time::{self, Duration, Instant},
     };
 
+    // WS-Discovery multicast ip and port selected from available standard
+    // options.  See https://en.wikipedia.org/wiki/WS-Discovery
+    const MULTI_IPV4_ADDR: Ipv4Addr = Ipv4Addr::new(239, 255, 255, 250);
+    const WS_DISCOVERY_PORT: u16 = 3702;
+
     fn create_onvif_discovery_message(uuid_string: &str) -> String {
         let probe_types: Vec<String> = vec![probe_types::NETWORK_VIDEO_TRANSMITTER.into()];
         let envelope = to_serialize::Envelope {
@@ -198,7 +244,16 @@ pub mod util {
         scopes: Option<&FilterList>,
     ) -> Vec<(String, String)> {
         let response_envelope =
-            yaserde::de::from_str::<to_deserialize::Envelope>(discovery_response);
+            match yaserde::de::from_str::<to_deserialize::Envelope>(discovery_response) {
+                Ok(response_envelope) => response_envelope,
+                Err(e) => {
+                    error!(
+                        "get_scope_filtered_uris_from_discovery_response - invalid response: {}",
+                        e
+                    );
+                    return Vec::new();
+                }
+            };
         // The response envelope follows this format:
         //   <Envelope><Body><ProbeMatches><ProbeMatch>
         //      <EndpointReference>
@@ -211,16 +266,24 @@ pub mod util {
         //          https://10.0.0.3:5357/svc
         //      </XAddrs>
         //   </ProbeMatch></ProbeMatches></Body></Envelope>
-        response_envelope
-            .unwrap()
-            .body
-            .probe_matches
-            .probe_match
+        get_scope_filtered_uris(&response_envelope.body.probe_matches.probe_match, scopes)
+    }
+
+    /// Lists the uris of the devices whose scopes pass the filter, along with their uuid
+    fn get_scope_filtered_uris(
+        probe_matches: &[common::ProbeMatch],
+        scopes: Option<&FilterList>,
+    ) -> Vec<(String, String)> {
+        probe_matches
             .iter()
             .filter(|probe_match| {
-                !execute_filter(scopes, Some(&probe_match.scopes), |scope, pattern| {
-                    scope.split_whitespace().any(|s| s == pattern)
-                })
+                let scopes_list = probe_match
+                    .scopes
+                    .iter()
+                    .flat_map(|scope| scope.split_whitespace())
+                    .map(|scope| scope.to_string())
+                    .collect();
+                !execute_filter(scopes, Some(&scopes_list))
             })
             .flat_map(|probe_match| {
                 probe_match
@@ -266,42 +329,29 @@ pub mod util {
         s.to_lowercase()
     }
 
-    pub(crate) fn execute_filter<P>(
+    /// Tests whether a device should be filtered out, according to its values for the filtered field.
+    /// A device without values for the field is filtered out, unless the filter accepts everything.
+    pub(crate) fn execute_filter(
         filter_list: Option<&FilterList>,
         filter_against: Option<&Vec<String>>,
-        predicate: P,
-    ) -> bool
-    where
-        P: Fn(&str, &str) -> bool,
-    {
-        if filter_list.is_none() {
+    ) -> bool {
+        let Some(filter_list) = filter_list else {
             return false;
-        }
-        let filter_list = filter_list.unwrap();
+        };
         if filter_list.items.is_empty() && filter_list.action == FilterType::Exclude {
             return false;
         }
-        if filter_against.is_none() {
+        let Some(filter_against) = filter_against else {
             return true;
-        }
-        let filter_against = filter_against.unwrap();
-        let filter_action = filter_list.action.clone();
-        let filter_count = filter_list
-            .items
+        };
+        let matched = filter_against
             .iter()
-            .filter(|pattern| {
-                filter_against
-                    .iter()
-                    .filter(|filter_against_item| predicate(filter_against_item, pattern))
-                    .count()
-                    > 0
-            })
-            .count();
+            .any(|filter_against_item| filter_list.matches(filter_against_item));
 
-        if FilterType::Include == filter_action {
-            filter_count == 0
+        if FilterType::Include == filter_list.action {
+            !matched
         } else {
-            filter_count != 0
+            matched
         }
     }
 
@@ -400,6 +450,7 @@ pub mod util {
             let filter_list = FilterList {
                 action: FilterType::Include,
                 items: vec!["onvif://www.onvif.org/name/NVT".to_string()],
+                ..Default::default()
             };
             let uris = vec!["uri_one".to_string(), "uri_two".to_string()];
             let device_uuid = "device_uuid";
@@ -424,6 +475,7 @@ pub mod util {
             let filter_list = FilterList {
                 action: FilterType::Exclude,
                 items: vec!["onvif://www.onvif.org/name/NVT".to_string()],
+                ..Default::default()
             };
             let uris = vec!["uri_one".to_string(), "uri_two".to_string()];
             let device_uuid = "device_uuid";
@@ -442,6 +494,7 @@ pub mod util {
             let filter_list = FilterList {
                 action: FilterType::Include,
                 items: vec!["onvif://www.onvif.org/name/NVT123".to_string()],
+                ..Default::default()
             };
             let uris = vec!["uri_one".to_string(), "uri_two".to_string()];
             let device_uuid = "device_uuid";
@@ -460,6 +513,7 @@ pub mod util {
             let filter_list = FilterList {
                 action: FilterType::Exclude,
                 items: vec!["onvif://www.onvif.org/name/NVT123".to_string()],
+                ..Default::default()
             };
             let uris = vec!["uri_one".to_string(), "uri_two".to_string()];
             let device_uuid = "device_uuid";
@@ -484,6 +538,7 @@ pub mod util {
             let filter_list = FilterList {
                 action: FilterType::Include,
                 items: vec!["onvif://www.onvif.org/name".to_string()],
+                ..Default::default()
             };
             let uris = vec!["uri_one".to_string(), "uri_two".to_string()];
             let device_uuid = "device_uuid";
@@ -502,6 +557,7 @@ pub mod util {
             let filter_list = FilterList {
                 action: FilterType::Exclude,
                 items: vec!["onvif://www.onvif.org/name".to_string()],
+                ..Default::default()
             };
             let uris = vec!["uri_one".to_string(), "uri_two".to_string()];
             let device_uuid = "device_uuid";
@@ -542,6 +598,7 @@ pub mod util {
     #[cfg(test)]
     mod filter_tests {
         use super::*;
+        use akri_discovery_utils::filtering::FilterItemKind;
 
         // execute_filter should return false (not filter out)
         // if filter_list is None
@@ -549,21 +606,12 @@ pub mod util {
         fn test_execute_filter_filter_list_is_none() {
             let _ = env_logger::builder().is_test(true).try_init();
 
-            let predicate_match = |_v: &str, _p: &str| true;
-            let predicate_not_match = |_v: &str, _p: &str| false;
             let filter_list: Option<&FilterList> = None;
 
-            assert!(!execute_filter(None, None, predicate_match,));
-            assert!(!execute_filter(None, None, predicate_not_match,));
-            assert!(!execute_filter(
-                filter_list,
-                Some(vec!["foo".to_string()]).as_ref(),
-                predicate_match,
-            ));
+            assert!(!execute_filter(None, None));
             assert!(!execute_filter(
                 filter_list,
                 Some(vec!["foo".to_string()]).as_ref(),
-                predicate_not_match,
             ));
         }
 
@@ -573,28 +621,16 @@ pub mod util {
         fn test_execute_filter_filter_list_is_exclude_nothing() {
             let _ = env_logger::builder().is_test(true).try_init();
 
-            let predicate_match = |_v: &str, _p: &str| true;
-            let predicate_not_match = |_v: &str, _p: &str| false;
             let filter_list = Some(FilterList {
                 action: FilterType::Exclude,
                 items: vec![],
+                ..Default::default()
             });
 
-            assert!(!execute_filter(filter_list.as_ref(), None, predicate_match,));
-            assert!(!execute_filter(
-                filter_list.as_ref(),
-                None,
-                predicate_not_match,
-            ));
+            assert!(!execute_filter(filter_list.as_ref(), None));
             assert!(!execute_filter(
                 filter_list.as_ref(),
                 Some(vec!["foo".to_string()]).as_ref(),
-                predicate_match,
-            ));
-            assert!(!execute_filter(
-                filter_list.as_ref(),
-                Some(vec!["foo".to_string()]).as_ref(),
-                predicate_not_match,
             ));
         }
 
@@ -604,76 +640,93 @@ pub mod util {
         fn test_execute_filter_filter_against_is_none() {
             let _ = env_logger::builder().is_test(true).try_init();
 
-            let predicate_match = |_v: &str, _p: &str| true;
-            let predicate_not_match = |_v: &str, _p: &str| false;
             let filter_list = Some(FilterList {
                 action: FilterType::Exclude,
                 items: vec!["foo".to_string(), "bar".to_string()],
+                ..Default::default()
             });
-
-            assert!(execute_filter(filter_list.as_ref(), None, predicate_match,));
-            assert!(execute_filter(
-                filter_list.as_ref(),
-                None,
-                predicate_not_match,
-            ));
+            assert!(execute_filter(filter_list.as_ref(), None));
 
             let filter_list = Some(FilterList {
                 action: FilterType::Include,
                 items: vec!["foo".to_string(), "bar".to_string()],
+                ..Default::default()
             });
-            assert!(execute_filter(filter_list.as_ref(), None, predicate_match,));
-            assert!(execute_filter(
-                filter_list.as_ref(),
-                None,
-                predicate_not_match,
-            ));
+            assert!(execute_filter(filter_list.as_ref(), None));
         }
 
         #[test]
         fn test_execute_filter() {
             let _ = env_logger::builder().is_test(true).try_init();
 
-            let predicate = |v: &str, p: &str| v == p;
             let filter_list = Some(FilterList {
                 action: FilterType::Exclude,
                 items: vec!["foo".to_string(), "bar".to_string()],
+                ..Default::default()
             });
 
             assert!(execute_filter(
                 filter_list.as_ref(),
                 Some(vec!["foo".to_string()]).as_ref(),
-                predicate,
             ));
             assert!(execute_filter(
                 filter_list.as_ref(),
-                Some(vec!["bar".to_string()]).as_ref(),
-                predicate,
+                Some(vec!["BAR".to_string()]).as_ref(),
             ));
             assert!(!execute_filter(
                 filter_list.as_ref(),
                 Some(vec!["foobar".to_string()]).as_ref(),
-                predicate,
             ));
 
             let filter_list = Some(FilterList {
                 action: FilterType::Include,
                 items: vec!["foo".to_string(), "bar".to_string()],
+                ..Default::default()
             });
             assert!(!execute_filter(
                 filter_list.as_ref(),
                 Some(vec!["foo".to_string()]).as_ref(),
-                predicate,
             ));
             assert!(!execute_filter(
                 filter_list.as_ref(),
-                Some(vec!["bar".to_string()]).as_ref(),
-                predicate,
+                Some(vec!["bar".to_string(), "baz".to_string()]).as_ref(),
             ));
             assert!(execute_filter(
                 filter_list.as_ref(),
                 Some(vec!["foobar".to_string()]).as_ref(),
-                predicate,
+            ));
+        }
+
+        #[test]
+        fn test_execute_filter_item_kind() {
+            let _ = env_logger::builder().is_test(true).try_init();
+
+            let filter_list = Some(FilterList {
+                action: FilterType::Include,
+                items: vec!["10.0.0.0/24".to_string()],
+                item_kind: FilterItemKind::Cidr,
+            });
+            assert!(!execute_filter(
+                filter_list.as_ref(),
+                Some(vec!["10.0.0.1".to_string()]).as_ref(),
+            ));
+            assert!(execute_filter(
+                filter_list.as_ref(),
+                Some(vec!["10.0.1.1".to_string()]).as_ref(),
+            ));
+
+            let filter_list = Some(FilterList {
+                action: FilterType::Exclude,
+                items: vec!["onvif://www.onvif.org/name/*".to_string()],
+                item_kind: FilterItemKind::Glob,
+            });
+            assert!(execute_filter(
+                filter_list.as_ref(),
+                Some(vec!["onvif://www.onvif.org/Name/NVT".to_string()]).as_ref(),
+            ));
+            assert!(!execute_filter(
+                filter_list.as_ref(),
+                Some(vec!["onvif://www.onvif.org/hardware/NVT".to_string()]).as_ref(),
             ));
         }
     }
@@ -685,11 +738,7 @@ pub mod util {
         const LOCAL_PORT: u16 = 0;
         let local_socket_addr = SocketAddr::new(IpAddr::V4(LOCAL_IPV4_ADDR), LOCAL_PORT);
 
-        // WS-Discovery multicast ip and port selected from available standard
-        // options.  See https://en.wikipedia.org/wiki/WS-Discovery
-        const MULTI_IPV4_ADDR: Ipv4Addr = Ipv4Addr::new(239, 255, 255, 250);
-        const MULTI_PORT: u16 = 3702;
-        let multi_socket_addr = SocketAddr::new(IpAddr::V4(MULTI_IPV4_ADDR), MULTI_PORT);
+        let multi_socket_addr = SocketAddr::new(IpAddr::V4(MULTI_IPV4_ADDR), WS_DISCOVERY_PORT);
 
         trace!(
             "get_discovery_response_socket - binding to: {:?}",
@@ -712,11 +761,200 @@ pub mod util {
         Ok(socket)
     }
 
+    /// Maximum number of addresses that directed probes are sent to
+    const MAX_UNICAST_TARGETS: usize = 4096;
+
+    /// Checks that a unicast target is an IPv4 socket address, IP address or CIDR network, as the
+    /// probes are sent from an IPv4 socket
+    pub fn validate_unicast_target(target: &str) -> Result<(), String> {
+        let target = target.trim();
+        let ip = if let Ok(address) = target.parse::<SocketAddr>() {
+            address.ip()
+        } else if let Ok(ip) = target.parse::<IpAddr>() {
+            ip
+        } else if let Some((ip, prefix)) = target.split_once('/') {
+            match (ip.trim().parse::<IpAddr>(), prefix.trim().parse::<u32>()) {
+                (Ok(IpAddr::V4(ip)), Ok(prefix)) if prefix <= 32 => IpAddr::V4(ip),
+                (Ok(IpAddr::V6(ip)), Ok(_)) => IpAddr::V6(ip),
+                _ => return Err(format!("invalid unicast target {}", target)),
+            }
+        } else {
+            return Err(format!("invalid unicast target {}", target));
+        };
+        match ip {
+            IpAddr::V4(_) => Ok(()),
+            IpAddr::V6(_) => Err(format!(
+                "unsupported IPv6 unicast target {}, only IPv4 targets are supported",
+                target
+            )),
+        }
+    }
+
+    /// Lists the addresses to send directed probes to from a list of IPv4 socket addresses, IP
+    /// addresses and CIDR networks. Invalid targets are skipped, and the number of addresses is
+    /// capped to `MAX_UNICAST_TARGETS`.
+    pub fn get_unicast_targets(unicast_targets: &[String]) -> Vec<SocketAddr> {
+        let mut addresses = Vec::new();
+        for target in unicast_targets.iter().map(|target| target.trim()) {
+            if let Err(e) = validate_unicast_target(target) {
+                error!("get_unicast_targets - {}", e);
+            } else if let Ok(address) = target.parse::<SocketAddr>() {
+                addresses.push(address);
+            } else if let Ok(ip) = target.parse::<IpAddr>() {
+                addresses.push(SocketAddr::new(ip, WS_DISCOVERY_PORT));
+            } else if let Some(ips) = get_network_hosts(target) {
+                addresses.extend(
+                    ips.take(MAX_UNICAST_TARGETS)
+                        .map(|ip| SocketAddr::new(IpAddr::V4(ip), WS_DISCOVERY_PORT)),
+                );
+            }
+        }
+        let mut seen = HashSet::new();
+        addresses.retain(|address| seen.insert(*address));
+        if addresses.len() > MAX_UNICAST_TARGETS {
+            warn!(
+                "get_unicast_targets - only the first {} addresses are probed",
+                MAX_UNICAST_TARGETS
+            );
+            addresses.truncate(MAX_UNICAST_TARGETS);
+        }
+        addresses
+    }
+
+    /// Lists the hosts of an IPv4 CIDR network. The network and broadcast addresses are excluded.
+    fn get_network_hosts(network: &str) -> Option<impl Iterator<Item = Ipv4Addr>> {
+        let (ip, prefix) = network.split_once('/')?;
+        let ip: Ipv4Addr = ip.trim().parse().ok()?;
+        let prefix: u32 = prefix.trim().parse().ok()?;
+        if prefix > 32 {
+            return None;
+        }
+        let host_mask = u32::MAX.checked_shr(prefix).unwrap_or(0);
+        let start = u32::from(ip) & !host_mask;
+        let end = start | host_mask;
+        let (start, end) = if prefix < 31 {
+            (start + 1, end - 1)
+        } else {
+            (start, end)
+        };
+        Some((start..=end).map(Ipv4Addr::from))
+    }
+
+    /// Sends a probe to each of the unicast targets, for cameras that multicast probes do not reach.
+    /// The cameras answer to the socket like they do for the multicast probe.
+    pub async fn send_directed_probes(socket: &UdpSocket, unicast_targets: &[SocketAddr]) {
+        let envelope_as_string =
+            create_onvif_discovery_message(&format!("uuid:{}", uuid::Uuid::new_v4()));
+        for target in unicast_targets {
+            trace!("send_directed_probes - probing {}", target);
+            if let Err(e) = socket.send_to(envelope_as_string.as_bytes(), target).await {
+                error!("send_directed_probes - failed to probe {}: {}", target, e);
+            }
+        }
+    }
+
+    /// Sends a probe to a WS-Discovery proxy over HTTP, which answers with the matches of the
+    /// cameras it knows of (managed mode discovery).
+    async fn probe_discovery_proxy(
+        discovery_proxy: &str,
+        timeout: Duration,
+    ) -> Result<String, anyhow::Error> {
+        let envelope_as_string =
+            create_onvif_discovery_message(&format!("uuid:{}", uuid::Uuid::new_v4()));
+        let request = Request::post(discovery_proxy)
+            .header("CONTENT-TYPE", "application/soap+xml; charset=utf-8")
+            .body(envelope_as_string.into())?;
+        let response = time::timeout(timeout, hyper::Client::new().request(request)).await??;
+        if response.status() != 200 {
+            return Err(anyhow::format_err!(
+                "Received a response status of {}, expected 200",
+                response.status()
+            ));
+        }
+        let response_body = response
+            .into_body()
+            .try_fold(bytes::BytesMut::new(), |mut acc, chunk| async {
+                acc.extend(chunk);
+                Ok(acc)
+            })
+            .await?
+            .freeze();
+        Ok(String::from_utf8_lossy(&response_body).to_string())
+    }
+
+    /// Merges the uris of the discovery responses, keeping the uris of a device from the first
+    /// response that matched it, as a camera answers both multicast and directed probes.
+    fn merge_discovered_uris(
+        discovered_uris: Vec<Vec<(String, String)>>,
+    ) -> HashMap<String, String> {
+        let mut merged_uris = HashMap::new();
+        let mut seen_uuids = HashSet::new();
+        for uris in discovered_uris {
+            let uuids: HashSet<String> = uris.iter().map(|(_, uuid)| uuid.clone()).collect();
+            merged_uris.extend(
+                uris.into_iter()
+                    .filter(|(_, uuid)| !seen_uuids.contains(uuid)),
+            );
+            seen_uuids.extend(uuids);
+        }
+        merged_uris
+    }
+
+    /// Collects the responses to the probes sent on the socket until the timeout, along with the
+    /// response of the WS-Discovery proxy if any, and returns the uris of the responsive devices
+    /// with their uuid.
     pub async fn simple_onvif_discover(
         socket: &mut UdpSocket,
+        discovery_proxy: Option<&str>,
         scopes_filters: Option<&FilterList>,
         timeout: Duration,
     ) -> Result<HashMap<String, String>, anyhow::Error> {
+        let (broadcast_responses, proxy_response) = tokio::join!(
+            collect_probe_responses(socket, timeout),
+            async {
+                match discovery_proxy {
+                    Some(discovery_proxy) => {
+                        match probe_discovery_proxy(discovery_proxy, timeout).await {
+                            Ok(response) => Some(response),
+                            Err(e) => {
+                                error!(
+                                    "simple_onvif_discover - failed to probe discovery proxy {}: {}",
+                                    discovery_proxy, e
+                                );
+                                None
+                            }
+                        }
+                    }
+                    None => None,
+                }
+            }
+        );
+        let mut broadcast_responses = broadcast_responses?;
+        trace!(
+            "simple_onvif_discover - uris discovered by udp broadcast {:?}",
+            broadcast_responses
+        );
+        broadcast_responses.extend(proxy_response);
+        let filtered_uris = merge_discovered_uris(
+            broadcast_responses
+                .iter()
+                .map(|r| get_scope_filtered_uris_from_discovery_response(r, scopes_filters))
+                .collect(),
+        );
+        trace!(
+            "simple_onvif_discover - uris after filtering by scopes {:?}",
+            filtered_uris
+        );
+        let devices = get_responsive_uris(filtered_uris, &OnvifQueryImpl::default()).await;
+        info!("simple_onvif_discover - devices: {:?}", devices);
+        Ok(devices)
+    }
+
+    /// Collects the responses received on the socket until the timeout
+    async fn collect_probe_responses(
+        socket: &mut UdpSocket,
+        timeout: Duration,
+    ) -> Result<Vec<String>, anyhow::Error> {
         let mut broadcast_responses = Vec::new();
 
         let start = Instant::now();
@@ -734,31 +972,111 @@ pub mod util {
                 }
                 Err(e) => match e.kind() {
                     ErrorKind::WouldBlock | ErrorKind::TimedOut => {
-                        trace!("simple_onvif_discover - recv_from error ... continue collecting responses {:?}", e);
+                        trace!("collect_probe_responses - recv_from error ... continue collecting responses {:?}", e);
                     }
                     _ => {
-                        error!("simple_onvif_discover - recv_from error: {:?}", e);
+                        error!("collect_probe_responses - recv_from error: {:?}", e);
                         return Err(anyhow::anyhow!(e));
                     }
                 },
             }
         }
+        Ok(broadcast_responses)
+    }
 
-        trace!(
-            "simple_onvif_discover - uris discovered by udp broadcast {:?}",
-            broadcast_responses
-        );
-        let filtered_uris = broadcast_responses
+    /// Announcement of a device received on the WS-Discovery multicast group
+    #[derive(Debug, PartialEq)]
+    pub enum Announcement {
+        /// Uris of a responsive device that joined the network, with its uuid
+        Hello(HashMap<String, String>),
+        /// Uuid of a device that left the network
+        Bye(String),
+    }
+
+    /// Binds a socket to the WS-Discovery multicast group to receive the Hello and Bye announcements
+    /// of the devices. The address is reused, as other WS-Discovery clients may listen on the node.
+    pub fn get_announcement_socket() -> Result<UdpSocket, anyhow::Error> {
+        let socket = socket2::Socket::new(
+            socket2::Domain::IPV4,
+            socket2::Type::DGRAM,
+            Some(socket2::Protocol::UDP),
+        )?;
+        socket.set_reuse_address(true)?;
+        socket.set_nonblocking(true)?;
+        socket
+            .bind(&SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), WS_DISCOVERY_PORT).into())?;
+        socket.join_multicast_v4(&MULTI_IPV4_ADDR, &Ipv4Addr::UNSPECIFIED)?;
+        Ok(UdpSocket::from_std(socket.into())?)
+    }
+
+    /// Parses a message received on the multicast group. A Hello announcement is only returned if
+    /// the device passes the scopes filter and responds, like for the probe matches.
+    async fn get_announcement(
+        message: &str,
+        scopes: Option<&FilterList>,
+        onvif_query: &impl OnvifQuery,
+    ) -> Option<Announcement> {
+        // Probes of the WS-Discovery clients are also multicast and have neither Hello nor Bye
+        let envelope = match yaserde::de::from_str::<to_deserialize::AnnouncementEnvelope>(message)
+        {
+            Ok(envelope) => envelope,
+            Err(e) => {
+                trace!("get_announcement - ignoring message: {}", e);
+                return None;
+            }
+        };
+        if let Some(bye) = envelope.body.bye.first() {
+            if bye.endpoint_reference.address.is_empty() {
+                return None;
+            }
+            return Some(Announcement::Bye(get_onvif_device_id(
+                &bye.endpoint_reference.address,
+            )));
+        }
+        // Devices may omit their uris in the Hello, they are then found by the next probe
+        let uris: HashMap<String, String> = get_scope_filtered_uris(&envelope.body.hello, scopes)
             .into_iter()
-            .flat_map(|r| get_scope_filtered_uris_from_discovery_response(&r, scopes_filters))
-            .collect::<HashMap<String, String>>();
-        trace!(
-            "simple_onvif_discover - uris after filtering by scopes {:?}",
-            filtered_uris
-        );
-        let devices = get_responsive_uris(filtered_uris, &OnvifQueryImpl::default()).await;
-        info!("simple_onvif_discover - devices: {:?}", devices);
-        Ok(devices)
+            .collect();
+        if uris.is_empty() {
+            return None;
+        }
+        let responsive_uris = get_responsive_uris(uris, onvif_query).await;
+        if responsive_uris.is_empty() {
+            None
+        } else {
+            Some(Announcement::Hello(responsive_uris))
+        }
+    }
+
+    /// Listens for the announcements of the devices on the socket, until the receiver is dropped
+    pub async fn listen_for_announcements(
+        socket: UdpSocket,
+        scopes: Option<FilterList>,
+        announcement_sender: mpsc::Sender<Announcement>,
+    ) {
+        let onvif_query = OnvifQueryImpl::default();
+        let mut buf = vec![0; 16 * 1024];
+        loop {
+            let len = tokio::select! {
+                _ = announcement_sender.closed() => break,
+                result = socket.recv(&mut buf) => match result {
+                    Ok(len) => len,
+                    Err(e) => {
+                        error!("listen_for_announcements - recv error: {:?}", e);
+                        break;
+                    }
+                },
+            };
+            let message = String::from_utf8_lossy(&buf[..len]).to_string();
+            if let Some(announcement) =
+                get_announcement(&message, scopes.as_ref(), &onvif_query).await
+            {
+                info!("listen_for_announcements - received {:?}", announcement);
+                if announcement_sender.send(announcement).await.is_err() {
+                    break;
+                }
+            }
+        }
     }
 
     async fn try_recv_string(s: &mut UdpSocket, timeout: Duration) -> std::io::Result<String> {
@@ -788,6 +1106,7 @@ pub mod util {
                 let _ignore = simple_onvif_discover(
                     &mut get_discovery_response_socket().await.unwrap(),
                     None,
+                    None,
                     timeout,
                 )
                 .await
@@ -809,5 +1128,215 @@ pub mod util {
             // we could test for exactly 2 seconds here, but a little wiggle room seems reasonable
             assert!(duration.lock().unwrap().as_millis() <= wait_for_call_millis.into());
         }
+
+        fn get_announcement_message(body: &str) -> String {
+            format!(
+                r#"<?xml version="1.0" encoding="UTF-8"?>
+<SOAP-ENV:Envelope xmlns:SOAP-ENV="http://www.w3.org/2003/05/soap-envelope" xmlns:wsa="http://schemas.xmlsoap.org/ws/2004/08/addressing" xmlns:d="http://schemas.xmlsoap.org/ws/2005/04/discovery" xmlns:dn="http://www.onvif.org/ver10/network/wsdl"><SOAP-ENV:Header><wsa:MessageID>urn:uuid:f9a3c3e8-3c45-4a4e-9bbd-0a4c4f1b2c3d</wsa:MessageID><wsa:To>urn:schemas-xmlsoap-org:ws:2005:04:discovery</wsa:To><wsa:Action>http://schemas.xmlsoap.org/ws/2005/04/discovery/{}</wsa:Action></SOAP-ENV:Header><SOAP-ENV:Body>{}</SOAP-ENV:Body></SOAP-ENV:Envelope>"#,
+                if body.starts_with("<d:Bye>") {
+                    "Bye"
+                } else {
+                    "Hello"
+                },
+                body
+            )
+        }
+
+        fn get_hello_message(scope: &str) -> String {
+            get_announcement_message(&format!(
+                "<d:Hello><wsa:EndpointReference><wsa:Address>urn:uuid:Camera-UUID</wsa:Address></wsa:EndpointReference><d:Types>dn:NetworkVideoTransmitter</d:Types><d:Scopes>{}</d:Scopes><d:XAddrs>http://10.0.0.1/onvif/device_service</d:XAddrs><d:MetadataVersion>1</d:MetadataVersion></d:Hello>",
+                scope
+            ))
+        }
+
+        #[tokio::test]
+        async fn test_get_announcement_hello() {
+            let _ = env_logger::builder().is_test(true).try_init();
+
+            let mut mock = crate::discovery_utils::MockOnvifQuery::new();
+            mock.expect_is_device_responding()
+                .times(1)
+                .returning(|url| Ok(url.to_string()));
+            let scopes = FilterList {
+                action: FilterType::Exclude,
+                items: vec!["onvif://www.onvif.org/name/excluded".to_string()],
+                ..Default::default()
+            };
+            assert_eq!(
+                get_announcement(
+                    &get_hello_message("onvif://www.onvif.org/name/camera"),
+                    Some(&scopes),
+                    &mock
+                )
+                .await,
+                Some(Announcement::Hello(HashMap::from([(
+                    "http://10.0.0.1/onvif/device_service".to_string(),
+                    "camera-uuid".to_string()
+                )])))
+            );
+            // Filtered out devices are not queried
+            assert_eq!(
+                get_announcement(
+                    &get_hello_message("onvif://www.onvif.org/name/excluded"),
+                    Some(&scopes),
+                    &mock
+                )
+                .await,
+                None
+            );
+        }
+
+        #[tokio::test]
+        async fn test_get_announcement_hello_not_responding() {
+            let mut mock = crate::discovery_utils::MockOnvifQuery::new();
+            mock.expect_is_device_responding()
+                .times(1)
+                .returning(|_| Err(anyhow::format_err!("not responding")));
+            assert_eq!(
+                get_announcement(&get_hello_message(""), None, &mock).await,
+                None
+            );
+        }
+
+        #[tokio::test]
+        async fn test_get_announcement_bye() {
+            let mock = crate::discovery_utils::MockOnvifQuery::new();
+            let bye = get_announcement_message(
+                "<d:Bye><wsa:EndpointReference><wsa:Address>urn:uuid:Camera-UUID</wsa:Address></wsa:EndpointReference></d:Bye>",
+            );
+            assert_eq!(
+                get_announcement(&bye, None, &mock).await,
+                Some(Announcement::Bye("camera-uuid".to_string()))
+            );
+            // Probes from other clients are ignored
+            let probe = create_onvif_discovery_message("uuid:probe");
+            assert_eq!(get_announcement(&probe, None, &mock).await, None);
+            assert_eq!(get_announcement("not xml", None, &mock).await, None);
+        }
+
+        #[test]
+        fn test_get_unicast_targets() {
+            let targets = get_unicast_targets(&[
+                "10.0.1.5".to_string(),
+                "10.0.2.0/30".to_string(),
+                "10.0.1.5".to_string(),
+                "10.0.3.7:8702".to_string(),
+                "fd00::1".to_string(),
+                "not-an-address".to_string(),
+                "10.0.4.0/33".to_string(),
+            ]);
+            assert_eq!(
+                targets,
+                vec![
+                    "10.0.1.5:3702".parse::<SocketAddr>().unwrap(),
+                    "10.0.2.1:3702".parse().unwrap(),
+                    "10.0.2.2:3702".parse().unwrap(),
+                    "10.0.3.7:8702".parse().unwrap(),
+                ]
+            );
+            assert_eq!(
+                get_unicast_targets(&["10.0.0.0/8".to_string()]).len(),
+                MAX_UNICAST_TARGETS
+            );
+        }
+
+        #[test]
+        fn test_validate_unicast_target() {
+            for target in ["10.0.1.5", "10.0.3.7:8702", " 10.0.2.0/30 "] {
+                assert_eq!(validate_unicast_target(target), Ok(()), "{}", target);
+            }
+            for target in [
+                "fd00::1",
+                "[fd00::1]:3702",
+                "fd00::/120",
+                "not-an-address",
+                "10.0.4.0/33",
+            ] {
+                assert!(validate_unicast_target(target).is_err(), "{}", target);
+            }
+        }
+
+        #[test]
+        fn test_merge_discovered_uris() {
+            let to_uris = |uris: &[(&str, &str)]| {
+                uris.iter()
+                    .map(|(uri, uuid)| (uri.to_string(), uuid.to_string()))
+                    .collect::<Vec<_>>()
+            };
+            let merged = merge_discovered_uris(vec![
+                to_uris(&[("http://10.0.0.1/onvif", "uuid-1")]),
+                // Same camera answering a directed probe, or listed by the discovery proxy
+                to_uris(&[("http://camera-1/onvif", "uuid-1")]),
+                to_uris(&[
+                    ("http://10.1.0.2/onvif", "uuid-2"),
+                    ("http://[fd00::2]/onvif", "uuid-2"),
+                ]),
+            ]);
+            assert_eq!(
+                merged,
+                HashMap::from([
+                    ("http://10.0.0.1/onvif".to_string(), "uuid-1".to_string()),
+                    ("http://10.1.0.2/onvif".to_string(), "uuid-2".to_string()),
+                    ("http://[fd00::2]/onvif".to_string(), "uuid-2".to_string()),
+                ])
+            );
+        }
+
+        #[tokio::test]
+        async fn test_send_directed_probes() {
+            let camera = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+            let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+            send_directed_probes(&socket, &[camera.local_addr().unwrap()]).await;
+            let mut buf = vec![0; 16 * 1024];
+            let (len, from) = time::timeout(Duration::from_secs(1), camera.recv_from(&mut buf))
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(from, socket.local_addr().unwrap());
+            assert!(String::from_utf8_lossy(&buf[..len])
+                .contains("<d:Types>netwsdl:NetworkVideoTransmitter</d:Types>"));
+        }
+
+        #[tokio::test]
+        async fn test_simple_onvif_discover_discovery_proxy() {
+            use hyper::{
+                service::{make_service_fn, service_fn},
+                Body, Response, Server,
+            };
+            let _ = env_logger::builder().is_test(true).try_init();
+
+            // The mock discovery proxy lists itself as the camera, and answers its
+            // GetSystemDateAndTime request with the same envelope
+            let make_service = make_service_fn(|_| async {
+                Ok::<_, std::convert::Infallible>(service_fn(|req: Request<Body>| async move {
+                    let host = req.headers()["HOST"].to_str().unwrap().to_string();
+                    Ok::<_, std::convert::Infallible>(Response::new(Body::from(format!(
+                        r#"<?xml version="1.0" encoding="UTF-8"?>
+<s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope" xmlns:a="http://schemas.xmlsoap.org/ws/2004/08/addressing" xmlns:d="http://schemas.xmlsoap.org/ws/2005/04/discovery"><s:Header><a:RelatesTo>uuid:probe</a:RelatesTo></s:Header><s:Body><d:ProbeMatches><d:ProbeMatch><a:EndpointReference><a:Address>urn:uuid:Proxied-Camera</a:Address></a:EndpointReference><d:Types>dn:NetworkVideoTransmitter</d:Types><d:Scopes>onvif://www.onvif.org/name/proxied</d:Scopes><d:XAddrs>http://{}/onvif/device_service</d:XAddrs><d:MetadataVersion>1</d:MetadataVersion></d:ProbeMatch></d:ProbeMatches></s:Body></s:Envelope>"#,
+                        host
+                    ))))
+                }))
+            });
+            let server = Server::bind(&"127.0.0.1:0".parse().unwrap()).serve(make_service);
+            let address = server.local_addr();
+            tokio::spawn(server);
+
+            let mut socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+            let devices = simple_onvif_discover(
+                &mut socket,
+                Some(&format!("http://{}/discovery", address)),
+                None,
+                Duration::from_millis(500),
+            )
+            .await
+            .unwrap();
+            assert_eq!(
+                devices,
+                HashMap::from([(
+                    format!("http://{}/onvif/device_service", address),
+                    "proxied-camera".to_string()
+                )])
+            );
+        }
     }
 }
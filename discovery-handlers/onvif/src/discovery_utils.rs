@@ -1,22 +1,88 @@
 use super::credential_store::CredentialStore;
+use super::http_digest::DigestChallenge;
 use super::username_token::UsernameToken;
 use async_trait::async_trait;
 use futures_util::stream::TryStreamExt;
 use hyper::Request;
-use log::trace;
+use log::{error, trace};
 #[cfg(test)]
 use mockall::{automock, predicate::*};
-use std::io::{Error, ErrorKind};
+use std::{
+    collections::{HashMap, HashSet},
+    io::{Error, ErrorKind},
+    sync::Mutex,
+};
 use sxd_document::{parser, Package};
-use sxd_xpath::Value;
+use sxd_xpath::{nodeset::Node, Context, Factory, Value};
 
 pub const ONVIF_DEVICE_SERVICE_URL_LABEL_ID: &str = "ONVIF_DEVICE_SERVICE_URL";
 pub const ONVIF_DEVICE_IP_ADDRESS_LABEL_ID: &str = "ONVIF_DEVICE_IP_ADDRESS";
 pub const ONVIF_DEVICE_MAC_ADDRESS_LABEL_ID: &str = "ONVIF_DEVICE_MAC_ADDRESS";
 pub const ONVIF_DEVICE_UUID_LABEL_ID: &str = "ONVIF_DEVICE_UUID";
+pub const ONVIF_MEDIA_PROFILE_TOKEN_LABEL_ID: &str = "ONVIF_MEDIA_PROFILE_TOKEN";
+pub const ONVIF_MEDIA_PROFILE_NAME_LABEL_ID: &str = "ONVIF_MEDIA_PROFILE_NAME";
+pub const ONVIF_VIDEO_ENCODING_LABEL_ID: &str = "ONVIF_VIDEO_ENCODING";
+pub const ONVIF_VIDEO_RESOLUTION_WIDTH_LABEL_ID: &str = "ONVIF_VIDEO_RESOLUTION_WIDTH";
+pub const ONVIF_VIDEO_RESOLUTION_HEIGHT_LABEL_ID: &str = "ONVIF_VIDEO_RESOLUTION_HEIGHT";
+pub const ONVIF_VIDEO_FRAME_RATE_LABEL_ID: &str = "ONVIF_VIDEO_FRAME_RATE";
+pub const ONVIF_STREAM_URI_LABEL_ID: &str = "ONVIF_STREAM_URI";
+pub const ONVIF_DEVICE_AUTHENTICATION_FAILED_LABEL_ID: &str = "ONVIF_DEVICE_AUTHENTICATION_FAILED";
 pub const MEDIA_WSDL: &str = "http://www.onvif.org/ver10/media/wsdl";
+pub const MEDIA2_WSDL: &str = "http://www.onvif.org/ver20/media/wsdl";
 pub const DEVICE_WSDL: &str = "http://www.onvif.org/ver10/device/wsdl";
 
+/// A media profile of an ONVIF camera, describing one of the video streams it provides
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MediaProfile {
+    pub token: String,
+    pub name: String,
+    pub encoding: Option<String>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub frame_rate: Option<String>,
+    pub stream_uri: Option<String>,
+}
+
+impl MediaProfile {
+    /// Resolution in the `WIDTHxHEIGHT` form, used to filter profiles
+    pub fn resolution(&self) -> Option<String> {
+        Some(format!("{}x{}", self.width?, self.height?))
+    }
+
+    /// Properties describing the profile that are added to the discovered device
+    pub fn properties(&self) -> HashMap<String, String> {
+        let mut properties = HashMap::from([
+            (
+                ONVIF_MEDIA_PROFILE_TOKEN_LABEL_ID.to_string(),
+                self.token.clone(),
+            ),
+            (
+                ONVIF_MEDIA_PROFILE_NAME_LABEL_ID.to_string(),
+                self.name.clone(),
+            ),
+        ]);
+        let optional_properties = [
+            (ONVIF_VIDEO_ENCODING_LABEL_ID, self.encoding.clone()),
+            (
+                ONVIF_VIDEO_RESOLUTION_WIDTH_LABEL_ID,
+                self.width.map(|w| w.to_string()),
+            ),
+            (
+                ONVIF_VIDEO_RESOLUTION_HEIGHT_LABEL_ID,
+                self.height.map(|h| h.to_string()),
+            ),
+            (ONVIF_VIDEO_FRAME_RATE_LABEL_ID, self.frame_rate.clone()),
+            (ONVIF_STREAM_URI_LABEL_ID, self.stream_uri.clone()),
+        ];
+        for (label, value) in optional_properties {
+            if let Some(value) = value {
+                properties.insert(label.to_string(), value);
+            }
+        }
+        properties
+    }
+}
+
 /// OnvifQuery can access ONVIF properties given an ONVIF camera's device service url.
 ///
 /// An implementation of an onvif query can retrieve the camera's ip/mac address, profiles and streaming uri.
@@ -34,25 +100,86 @@ pub trait OnvifQuery {
         url: &str,
         service: &str,
     ) -> Result<String, anyhow::Error>;
-    #[allow(dead_code)]
-    async fn get_device_profiles(&self, url: &str) -> Result<Vec<String>, anyhow::Error>;
-    #[allow(dead_code)]
-    async fn get_device_profile_streaming_uri(
+    async fn get_media_profiles(
         &self,
-        url: &str,
-        profile_token: &str,
-    ) -> Result<String, anyhow::Error>;
+        service_url: &str,
+        device_uuid: &str,
+    ) -> Result<Vec<MediaProfile>, anyhow::Error>;
     async fn is_device_responding(&self, url: &str) -> Result<String, anyhow::Error>;
 }
 
+/// Authentication schemes supported by the cameras
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AuthScheme {
+    None,
+    UsernameToken,
+    HttpDigest,
+}
+
+/// Error returned when a camera does not authorize a request, with the `WWW-Authenticate`
+/// challenges it answered with if any
+#[derive(Debug)]
+pub struct AuthenticationError {
+    pub challenges: Vec<String>,
+}
+
+impl std::fmt::Display for AuthenticationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "the device did not authorize the request")
+    }
+}
+
+impl std::error::Error for AuthenticationError {}
+
+/// Whether a query failed because the camera rejected every authentication scheme
+pub fn is_authentication_error(e: &anyhow::Error) -> bool {
+    e.downcast_ref::<AuthenticationError>().is_some()
+}
+
+/// Credential of a camera along with the authentication scheme it accepted
+#[derive(Default)]
+struct DeviceAuth {
+    credential: Option<(String, Option<String>)>,
+    scheme: Mutex<Option<AuthScheme>>,
+}
+
 #[derive(Default)]
 pub struct OnvifQueryImpl {
     credential_store: CredentialStore,
+    /// Authentication scheme accepted by each camera, by device uuid
+    auth_schemes: Mutex<HashMap<String, AuthScheme>>,
 }
 
 impl OnvifQueryImpl {
     pub fn new(credential_store: CredentialStore) -> Self {
-        Self { credential_store }
+        Self {
+            credential_store,
+            auth_schemes: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn get_device_auth(&self, device_uuid: &str) -> DeviceAuth {
+        DeviceAuth {
+            credential: self.credential_store.get(device_uuid),
+            scheme: Mutex::new(self.auth_schemes.lock().unwrap().get(device_uuid).copied()),
+        }
+    }
+
+    fn cache_auth_scheme(&self, device_uuid: &str, device_auth: DeviceAuth) {
+        let mut auth_schemes = self.auth_schemes.lock().unwrap();
+        match device_auth.scheme.into_inner().unwrap() {
+            Some(scheme) => auth_schemes.insert(device_uuid.to_string(), scheme),
+            None => auth_schemes.remove(device_uuid),
+        };
+    }
+
+    /// Forgets the authentication scheme of the cameras that are not discovered anymore
+    pub fn retain_devices<'a>(&self, device_uuids: impl IntoIterator<Item = &'a String>) {
+        let device_uuids: HashSet<&String> = device_uuids.into_iter().collect();
+        self.auth_schemes
+            .lock()
+            .unwrap()
+            .retain(|device_uuid, _| device_uuids.contains(device_uuid));
     }
 }
 
@@ -64,9 +191,11 @@ impl OnvifQuery for OnvifQueryImpl {
         service_url: &str,
         device_uuid: &str,
     ) -> Result<(String, String), anyhow::Error> {
-        let credential = self.credential_store.get(device_uuid);
+        let device_auth = self.get_device_auth(device_uuid);
         let http = HttpRequest {};
-        inner_get_device_ip_and_mac_address(service_url, credential, &http).await
+        let result = inner_get_device_ip_and_mac_address(service_url, &device_auth, &http).await;
+        self.cache_auth_scheme(device_uuid, device_auth);
+        result
     }
 
     /// Gets specific service, like media, from a given ONVIF camera
@@ -76,23 +205,20 @@ impl OnvifQuery for OnvifQueryImpl {
         service: &str,
     ) -> Result<String, anyhow::Error> {
         let http = HttpRequest {};
-        inner_get_device_service_uri(url, service, &http).await
-    }
-
-    /// Gets the list of streaming profiles for a given ONVIF camera
-    async fn get_device_profiles(&self, url: &str) -> Result<Vec<String>, anyhow::Error> {
-        let http = HttpRequest {};
-        inner_get_device_profiles(url, &http).await
+        inner_get_device_service_uri(url, service, &DeviceAuth::default(), &http).await
     }
 
-    /// Gets the streaming uri for a given ONVIF camera's profile
-    async fn get_device_profile_streaming_uri(
+    /// Gets the media profiles of a given ONVIF camera, with their streaming uri
+    async fn get_media_profiles(
         &self,
-        url: &str,
-        profile_token: &str,
-    ) -> Result<String, anyhow::Error> {
+        service_url: &str,
+        device_uuid: &str,
+    ) -> Result<Vec<MediaProfile>, anyhow::Error> {
+        let device_auth = self.get_device_auth(device_uuid);
         let http = HttpRequest {};
-        inner_get_device_profile_streaming_uri(url, profile_token, &http).await
+        let result = inner_get_media_profiles(service_url, &device_auth, &http).await;
+        self.cache_auth_scheme(device_uuid, device_auth);
+        result
     }
 
     /// Calls the publically accessible GetSystemDateAndTime endpoint to determine
@@ -105,12 +231,18 @@ impl OnvifQuery for OnvifQueryImpl {
 
 /// Http can send an HTTP::Post.
 ///
-/// An implementation of http can send an HTTP::Post.
+/// An implementation of http can send an HTTP::Post, with an optional `Authorization` header.
+/// Requests that are not authorized fail with an `AuthenticationError`.
 #[cfg_attr(test, automock)]
 #[async_trait]
 trait Http {
-    async fn post(&self, url: &str, mime_action: &str, msg: &str)
-        -> Result<Package, anyhow::Error>;
+    async fn post(
+        &self,
+        url: &str,
+        mime_action: &str,
+        msg: &str,
+        authorization: Option<String>,
+    ) -> Result<Package, anyhow::Error>;
 }
 
 struct HttpRequest {}
@@ -138,6 +270,7 @@ impl Http for HttpRequest {
         url: &str,
         mime_action: &str,
         msg: &str,
+        authorization: Option<String>,
     ) -> Result<Package, anyhow::Error> {
         trace!(
             "post - url:{}, mime_action:{}, msg:{}",
@@ -150,21 +283,27 @@ impl Http for HttpRequest {
             "{}; {}; {};",
             "application/soap+xml", "charset=utf-8", mime_action
         );
-        let request = Request::post(url)
-            .header("CONTENT-TYPE", full_mime)
-            .body(msg.to_string().into())
-            .expect("infallible");
+        let mut request = Request::post(url).header("CONTENT-TYPE", full_mime);
+        if let Some(authorization) = authorization {
+            request = request.header("AUTHORIZATION", authorization);
+        }
+        let request = request.body(msg.to_string().into()).expect("infallible");
         // terminate a request if it takes over a second
         let response = tokio::time::timeout(
             std::time::Duration::from_secs(1),
             hyper::Client::new().request(request),
         )
         .await??;
-        if response.status() != 200 {
-            return Err(anyhow::format_err!(
-                "Received a response status of {}, expected 200",
-                response.status()
-            ));
+        let status = response.status();
+        if status == 401 {
+            let challenges = response
+                .headers()
+                .get_all("WWW-AUTHENTICATE")
+                .iter()
+                .filter_map(|challenge| challenge.to_str().ok())
+                .map(|challenge| challenge.to_string())
+                .collect();
+            return Err(AuthenticationError { challenges }.into());
         }
         let response_body = response
             .into_body()
@@ -175,6 +314,16 @@ impl Http for HttpRequest {
             .await?
             .freeze();
         let response_body_str = std::str::from_utf8(&response_body)?;
+        if status != 200 {
+            // Cameras rejecting a WS-UsernameToken answer with a NotAuthorized SOAP fault
+            if is_not_authorized_fault(response_body_str) {
+                return Err(AuthenticationError { challenges: vec![] }.into());
+            }
+            return Err(anyhow::format_err!(
+                "Received a response status of {}, expected 200",
+                status
+            ));
+        }
         match HttpRequest::handle_request_body(response_body_str) {
             Ok(dom) => Ok(dom),
             Err(e) => Err(Error::new(ErrorKind::InvalidData, e).into()),
@@ -182,34 +331,167 @@ impl Http for HttpRequest {
     }
 }
 
+/// Whether a SOAP fault reports that the request was not authorized, with the `ter:NotAuthorized`
+/// subcode
+fn is_not_authorized_fault(body: &str) -> bool {
+    let Ok(package) = HttpRequest::handle_request_body(body) else {
+        return false;
+    };
+    let document = package.as_document();
+    match sxd_xpath::evaluate_xpath(
+        &document,
+        "//*[local-name()='Fault']/*[local-name()='Code']//*[local-name()='Subcode']/*[local-name()='Value'] | //*[local-name()='Fault']/*[local-name()='faultcode']",
+    ) {
+        Ok(Value::Nodeset(codes)) => codes.iter().any(|code| {
+            code.string_value().trim().rsplit(':').next() == Some("NotAuthorized")
+        }),
+        _ => false,
+    }
+}
+
 /// Creates a SOAP mime action
 fn get_action(wsdl: &str, function: &str) -> String {
     format!("action=\"{}/{}\"", wsdl, function)
 }
 
+/// Keeps authentication errors as is so that they can be reported, else adds context to the error
+fn request_error(e: anyhow::Error, context: &str) -> anyhow::Error {
+    if is_authentication_error(&e) {
+        e
+    } else {
+        anyhow::format_err!("{}: {:?}", context, e)
+    }
+}
+
+/// Sends a SOAP request to a camera, negotiating the authentication scheme. The scheme the camera
+/// accepted previously is tried first, then no authentication, and a WS-UsernameToken and HTTP Digest
+/// authentication if the camera has a credential.
+async fn post_authenticated(
+    url: &str,
+    mime_action: &str,
+    message: impl Fn(&Option<UsernameToken>) -> String + Send + Sync,
+    device_auth: &DeviceAuth,
+    http: &impl Http,
+) -> Result<Package, anyhow::Error> {
+    let cached_scheme = *device_auth.scheme.lock().unwrap();
+    let mut schemes = match device_auth.credential {
+        Some(_) => vec![
+            AuthScheme::None,
+            AuthScheme::UsernameToken,
+            AuthScheme::HttpDigest,
+        ],
+        None => vec![AuthScheme::None],
+    };
+    if let Some(cached_scheme) = cached_scheme {
+        schemes.retain(|scheme| *scheme != cached_scheme);
+        schemes.insert(0, cached_scheme);
+    }
+    let mut authentication_error = None;
+    // Digest challenges of a previous rejection, that spare a request to get them
+    let mut challenges = vec![];
+    for scheme in schemes {
+        let result = match scheme {
+            AuthScheme::None => http.post(url, mime_action, &message(&None), None).await,
+            AuthScheme::UsernameToken => {
+                let username_token = get_username_token(&device_auth.credential);
+                http.post(url, mime_action, &message(&username_token), None)
+                    .await
+            }
+            AuthScheme::HttpDigest => {
+                post_http_digest(
+                    url,
+                    mime_action,
+                    &message(&None),
+                    device_auth,
+                    std::mem::take(&mut challenges),
+                    http,
+                )
+                .await
+            }
+        };
+        match result {
+            Ok(xml) => {
+                *device_auth.scheme.lock().unwrap() = Some(scheme);
+                return Ok(xml);
+            }
+            Err(e) if is_authentication_error(&e) => {
+                trace!(
+                    "post_authenticated - {} did not authorize scheme {:?}",
+                    url,
+                    scheme
+                );
+                if let Some(rejection) = e.downcast_ref::<AuthenticationError>() {
+                    if !rejection.challenges.is_empty() {
+                        challenges.clone_from(&rejection.challenges);
+                    }
+                }
+                authentication_error = Some(e);
+            }
+            Err(e) => return Err(e),
+        }
+    }
+    *device_auth.scheme.lock().unwrap() = None;
+    Err(authentication_error.expect("at least one scheme is tried"))
+}
+
+/// Sends a SOAP request answering the HTTP Digest challenge of the camera, the challenges are
+/// requested from the camera unless it already sent them
+async fn post_http_digest(
+    url: &str,
+    mime_action: &str,
+    msg: &str,
+    device_auth: &DeviceAuth,
+    challenges: Vec<String>,
+    http: &impl Http,
+) -> Result<Package, anyhow::Error> {
+    let challenges = match challenges.is_empty() {
+        false => challenges,
+        true => match http.post(url, mime_action, msg, None).await {
+            Ok(xml) => return Ok(xml),
+            Err(e) => match e.downcast::<AuthenticationError>() {
+                Ok(authentication_error) => authentication_error.challenges,
+                Err(e) => return Err(e),
+            },
+        },
+    };
+    let (Some(challenge), Some((username, password))) = (
+        DigestChallenge::select(&challenges),
+        device_auth.credential.as_ref(),
+    ) else {
+        return Err(AuthenticationError { challenges }.into());
+    };
+    let uri = url.parse::<hyper::Uri>()?;
+    let authorization = challenge.authorization(
+        username,
+        password.as_deref().unwrap_or_default(),
+        "POST",
+        uri.path_and_query().map(|p| p.as_str()).unwrap_or("/"),
+        &uuid::Uuid::new_v4().to_simple().to_string(),
+        1,
+    );
+    http.post(url, mime_action, msg, Some(authorization)).await
+}
+
 /// Gets the ip and mac address for a given ONVIF camera
 async fn inner_get_device_ip_and_mac_address(
     service_url: &str,
-    credential: Option<(String, Option<String>)>,
+    device_auth: &DeviceAuth,
     http: &impl Http,
 ) -> Result<(String, String), anyhow::Error> {
-    let username_token = credential.map(|(uname, passwd)| {
-        UsernameToken::new(uname.as_str(), passwd.unwrap_or_default().as_str())
-    });
-    let message = get_network_interfaces_message(&username_token);
-    let network_interfaces_xml = match http
-        .post(
-            service_url,
-            &get_action(DEVICE_WSDL, "GetNetworkInterfaces"),
-            message.as_str(),
-        )
-        .await
+    let network_interfaces_xml = match post_authenticated(
+        service_url,
+        &get_action(DEVICE_WSDL, "GetNetworkInterfaces"),
+        get_network_interfaces_message,
+        device_auth,
+        http,
+    )
+    .await
     {
         Ok(xml) => xml,
         Err(e) => {
-            return Err(anyhow::format_err!(
-                "failed to get network interfaces from device: {:?}",
-                e
+            return Err(request_error(
+                e,
+                "failed to get network interfaces from device",
             ))
         }
     };
@@ -295,27 +577,56 @@ fn get_network_interfaces_message(username_token: &Option<UsernameToken>) -> Str
     )
 }
 
+/// Builds a SOAP request for an ONVIF service, with a WS-UsernameToken security header if credentials are provided
+fn get_soap_message(wsdl: &str, body: &str, username_token: &Option<UsernameToken>) -> String {
+    let security_header = if let Some(username_token) = username_token {
+        get_soap_security_header(username_token)
+    } else {
+        "".to_string()
+    };
+
+    format!(
+        r#"
+<soap:Envelope 
+    xmlns:soap="http://www.w3.org/2003/05/soap-envelope" 
+    xmlns:wsdl="{}"
+    xmlns:sch="http://www.onvif.org/ver10/schema">
+    <soap:Header>
+    {}
+    </soap:Header>
+    <soap:Body>
+        {}
+    </soap:Body>
+</soap:Envelope>"#,
+        wsdl, security_header, body
+    )
+}
+
+/// A new token is created for every request, as cameras reject reused nonces
+fn get_username_token(credential: &Option<(String, Option<String>)>) -> Option<UsernameToken> {
+    credential.as_ref().map(|(uname, passwd)| {
+        UsernameToken::new(uname.as_str(), passwd.as_deref().unwrap_or_default())
+    })
+}
+
 /// Gets a specific service (like media) uri from an ONVIF camera
 async fn inner_get_device_service_uri(
     url: &str,
     service: &str,
+    device_auth: &DeviceAuth,
     http: &impl Http,
 ) -> Result<String, anyhow::Error> {
-    let services_xml = match http
-        .post(
-            url,
-            &get_action(DEVICE_WSDL, "GetServices"),
-            GET_SERVICES_TEMPLATE,
-        )
-        .await
+    let services_xml = match post_authenticated(
+        url,
+        &get_action(DEVICE_WSDL, "GetServices"),
+        get_services_message,
+        device_auth,
+        http,
+    )
+    .await
     {
         Ok(xml) => xml,
-        Err(e) => {
-            return Err(anyhow::format_err!(
-                "failed to get services from device: {:?}",
-                e
-            ))
-        }
+        Err(e) => return Err(request_error(e, "failed to get services from device")),
     };
     let services_doc = services_xml.as_document();
     let service_xpath_query = format!(
@@ -341,39 +652,131 @@ async fn inner_get_device_service_uri(
 }
 
 /// SOAP request body for getting the supported services' uris for an ONVIF camera
-const GET_SERVICES_TEMPLATE: &str = r#"<soap:Envelope xmlns:soap="http://www.w3.org/2003/05/soap-envelope" xmlns:wsdl="http://www.onvif.org/ver10/device/wsdl">
-    <soap:Header/>
-    <soap:Body>
-        <wsdl:GetServices />
-    </soap:Body>
-</soap:Envelope>"#;
+fn get_services_message(username_token: &Option<UsernameToken>) -> String {
+    get_soap_message(
+        DEVICE_WSDL,
+        "<wsdl:GetServices><wsdl:IncludeCapability>false</wsdl:IncludeCapability></wsdl:GetServices>",
+        username_token,
+    )
+}
+
+/// Gets the media profiles of an ONVIF camera along with their streaming uri. The Media2 service is used if the
+/// camera supports it, else the Media service.
+async fn inner_get_media_profiles(
+    service_url: &str,
+    device_auth: &DeviceAuth,
+    http: &impl Http,
+) -> Result<Vec<MediaProfile>, anyhow::Error> {
+    let mut media_wsdl = MEDIA2_WSDL;
+    let mut media_url =
+        inner_get_device_service_uri(service_url, MEDIA2_WSDL, device_auth, http).await?;
+    if media_url.is_empty() {
+        media_wsdl = MEDIA_WSDL;
+        media_url =
+            inner_get_device_service_uri(service_url, MEDIA_WSDL, device_auth, http).await?;
+    }
+    if media_url.is_empty() {
+        return Err(anyhow::format_err!(
+            "device does not provide a media service"
+        ));
+    }
+    let mut profiles = inner_get_device_profiles(&media_url, media_wsdl, device_auth, http).await?;
+    for profile in profiles.iter_mut() {
+        match inner_get_device_profile_streaming_uri(
+            &media_url,
+            media_wsdl,
+            &profile.token,
+            device_auth,
+            http,
+        )
+        .await
+        {
+            Ok(stream_uri) if !stream_uri.is_empty() => profile.stream_uri = Some(stream_uri),
+            Ok(_) => trace!(
+                "inner_get_media_profiles - no streaming uri for profile {}",
+                profile.token
+            ),
+            Err(e) => error!(
+                "inner_get_media_profiles - failed to get streaming uri of profile {}: {}",
+                profile.token, e
+            ),
+        }
+    }
+    Ok(profiles)
+}
+
+/// Evaluates an xpath expression relative to a node, returning `None` if it selects nothing
+fn evaluate_relative_xpath(node: Node, expression: &str) -> Option<String> {
+    let xpath = Factory::new().build(expression).ok()??;
+    let value = xpath.evaluate(&Context::new(), node).ok()?.string();
+    Some(value.trim().to_string()).filter(|value| !value.is_empty())
+}
 
 /// Gets list of media profiles for a given ONVIF camera
 async fn inner_get_device_profiles(
     url: &str,
+    media_wsdl: &str,
+    device_auth: &DeviceAuth,
     http: &impl Http,
-) -> Result<Vec<String>, anyhow::Error> {
-    let action = get_action(MEDIA_WSDL, "GetProfiles");
-    let message = GET_PROFILES_TEMPLATE.to_string();
-    let profiles_xml = match http.post(url, &action, &message).await {
+) -> Result<Vec<MediaProfile>, anyhow::Error> {
+    let profiles_xml = match post_authenticated(
+        url,
+        &get_action(media_wsdl, "GetProfiles"),
+        |username_token| get_profiles_message(media_wsdl, username_token),
+        device_auth,
+        http,
+    )
+    .await
+    {
         Ok(xml) => xml,
-        Err(e) => {
-            return Err(anyhow::format_err!(
-                "failed to get profiles from device: {:?}",
-                e
-            ))
-        }
+        Err(e) => return Err(request_error(e, "failed to get profiles from device")),
     };
     let profiles_doc = profiles_xml.as_document();
     let profiles_query = sxd_xpath::evaluate_xpath(
         &profiles_doc,
-        "//*[local-name()='GetProfilesResponse']/*[local-name()='Profiles']/@token",
+        "//*[local-name()='GetProfilesResponse']/*[local-name()='Profiles']",
     );
+    // Media profiles describe their video encoder in a VideoEncoderConfiguration, and Media2 ones in a VideoEncoder
+    let encoder = ".//*[local-name()='VideoEncoderConfiguration' or local-name()='VideoEncoder']";
     let profiles = match profiles_query {
         Ok(Value::Nodeset(profiles_items)) => profiles_items
-            .iter()
-            .map(|profile_item| profile_item.string_value())
-            .collect::<Vec<String>>(),
+            .document_order()
+            .into_iter()
+            .map(|profile_item| MediaProfile {
+                token: evaluate_relative_xpath(profile_item, "@token").unwrap_or_default(),
+                name: evaluate_relative_xpath(profile_item, "*[local-name()='Name']/text()")
+                    .unwrap_or_default(),
+                encoding: evaluate_relative_xpath(
+                    profile_item,
+                    &format!("{}/*[local-name()='Encoding']/text()", encoder),
+                ),
+                width: evaluate_relative_xpath(
+                    profile_item,
+                    &format!(
+                        "{}/*[local-name()='Resolution']/*[local-name()='Width']/text()",
+                        encoder
+                    ),
+                )
+                .and_then(|width| width.parse().ok()),
+                height: evaluate_relative_xpath(
+                    profile_item,
+                    &format!(
+                        "{}/*[local-name()='Resolution']/*[local-name()='Height']/text()",
+                        encoder
+                    ),
+                )
+                .and_then(|height| height.parse().ok()),
+                frame_rate: evaluate_relative_xpath(
+                    profile_item,
+                    &format!(
+                        "{}/*[local-name()='RateControl']/*[local-name()='FrameRateLimit']/text()",
+                        encoder
+                    ),
+                ),
+                stream_uri: None,
+            })
+            .filter(|profile| !profile.token.is_empty())
+            .collect::<Vec<MediaProfile>>(),
         Ok(Value::Boolean(_)) | Ok(Value::Number(_)) | Ok(Value::String(_)) => {
             return Err(anyhow::format_err!(
                 "Failed to get ONVIF profiles: unexpected type"
@@ -388,35 +791,37 @@ async fn inner_get_device_profiles(
 /// Gets the streaming uri for a given profile for an ONVIF camera
 async fn inner_get_device_profile_streaming_uri(
     url: &str,
+    media_wsdl: &str,
     profile_token: &str,
+    device_auth: &DeviceAuth,
     http: &impl Http,
 ) -> Result<String, anyhow::Error> {
-    let stream_soap = get_stream_uri_message(profile_token);
-    let stream_uri_xml = match http
-        .post(url, &get_action(MEDIA_WSDL, "GetStreamUri"), &stream_soap)
-        .await
+    let stream_uri_xml = match post_authenticated(
+        url,
+        &get_action(media_wsdl, "GetStreamUri"),
+        |username_token| get_stream_uri_message(media_wsdl, profile_token, username_token),
+        device_auth,
+        http,
+    )
+    .await
     {
         Ok(xml) => xml,
+        Err(e) => return Err(request_error(e, "failed to get streaming uri from device")),
+    };
+    let stream_uri_doc = stream_uri_xml.as_document();
+    // The uri is in MediaUri for the Media service, and a direct child of the response for Media2
+    let stream_uri = match sxd_xpath::evaluate_xpath(
+        &stream_uri_doc,
+        "//*[local-name()='GetStreamUriResponse']//*[local-name()='Uri']/text()",
+    ) {
+        Ok(stream) => stream.string(),
         Err(e) => {
             return Err(anyhow::format_err!(
-                "failed to get streaming uri from device: {:?}",
+                "failed to get service uri from response: {:?}",
                 e
             ))
         }
     };
-    let stream_uri_doc = stream_uri_xml.as_document();
-    let stream_uri = match sxd_xpath::evaluate_xpath(
-        &stream_uri_doc,
-        "//*[local-name()='GetStreamUriResponse']/*[local-name()='MediaUri']/*[local-name()='Uri']/text()"
-        ) {
-            Ok(stream) => stream.string(),
-            Err(e) => {
-                return Err(anyhow::format_err!(
-                    "failed to get service uri from response: {:?}",
-                    e
-                ))
-            }
-        };
     Ok(stream_uri)
 }
 
@@ -426,18 +831,29 @@ async fn inner_is_device_responding(url: &str, http: &impl Http) -> Result<Strin
         url,
         &get_action(DEVICE_WSDL, "GetSystemDateAndTime"),
         GET_SYSTEM_DATE_AND_TIME_TEMPLATE,
+        None,
     )
     .await?;
     Ok(url.to_string())
 }
 
 /// Gets SOAP request body for getting the streaming uri for a specific profile for an ONVIF camera
-fn get_stream_uri_message(profile: &str) -> String {
-    format!(
-        r#"<soap:Envelope xmlns:soap="http://www.w3.org/2003/05/soap-envelope" xmlns:wsdl="http://www.onvif.org/ver10/media/wsdl" xmlns:sch="http://www.onvif.org/ver10/schema">
-            <soap:Header/>
-    <soap:Body>
-        <wsdl:GetStreamUri>
+fn get_stream_uri_message(
+    media_wsdl: &str,
+    profile: &str,
+    username_token: &Option<UsernameToken>,
+) -> String {
+    let body = if media_wsdl == MEDIA2_WSDL {
+        format!(
+            r#"<wsdl:GetStreamUri>
+            <wsdl:Protocol>RTSP</wsdl:Protocol>
+            <wsdl:ProfileToken>{}</wsdl:ProfileToken>
+        </wsdl:GetStreamUri>"#,
+            profile
+        )
+    } else {
+        format!(
+            r#"<wsdl:GetStreamUri>
         <wsdl:StreamSetup>
             <sch:Stream>RTP-Unicast</sch:Stream>
             <sch:Transport>
@@ -445,20 +861,23 @@ fn get_stream_uri_message(profile: &str) -> String {
             </sch:Transport>
         </wsdl:StreamSetup>
         <wsdl:ProfileToken>{}</wsdl:ProfileToken>
-        </wsdl:GetStreamUri>
-    </soap:Body>
-</soap:Envelope>;"#,
-        profile
-    )
+        </wsdl:GetStreamUri>"#,
+            profile
+        )
+    };
+    get_soap_message(media_wsdl, &body, username_token)
 }
 
-/// SOAP request body for getting the media profiles for an ONVIF camera
-const GET_PROFILES_TEMPLATE: &str = r#"<soap:Envelope xmlns:soap="http://www.w3.org/2003/05/soap-envelope" xmlns:wsdl="http://www.onvif.org/ver10/media/wsdl">
-    <soap:Header/>
-    <soap:Body>
-        <wsdl:GetProfiles/>
-    </soap:Body>
-</soap:Envelope>"#;
+/// SOAP request body for getting the media profiles for an ONVIF camera. Media2 only returns the
+/// requested configurations, so all of them are requested.
+fn get_profiles_message(media_wsdl: &str, username_token: &Option<UsernameToken>) -> String {
+    let body = if media_wsdl == MEDIA2_WSDL {
+        "<wsdl:GetProfiles><wsdl:Type>All</wsdl:Type></wsdl:GetProfiles>"
+    } else {
+        "<wsdl:GetProfiles/>"
+    };
+    get_soap_message(media_wsdl, body, username_token)
+}
 
 //  const GET_DEVICE_INFORMATION_TEMPLATE: &str = r#"<soap:Envelope xmlns:soap="http://www.w3.org/2003/05/soap-envelope" xmlns:wsdl="http://www.onvif.org/ver10/device/wsdl">
 //     <soap:Header/>
@@ -486,10 +905,13 @@ mod tests {
         trace!("mock.expect_post url:{}, mime:{}, msg:{}", url, mime, msg);
         mock.expect_post()
             .times(1)
-            .withf(move |actual_url, actual_mime, actual_msg| {
-                actual_url == inner_url && actual_mime == inner_mime && actual_msg == inner_msg
+            .withf(move |actual_url, actual_mime, actual_msg, authorization| {
+                actual_url == inner_url
+                    && actual_mime == inner_mime
+                    && actual_msg == inner_msg
+                    && authorization.is_none()
             })
-            .returning(move |_, _, _| {
+            .returning(move |_, _, _, _| {
                 let xml_as_tree = parser::parse(&inner_output_xml).unwrap();
                 Ok(xml_as_tree)
             });
@@ -514,7 +936,7 @@ mod tests {
             ("192.168.1.36".to_string(), "00:12:41:5c:a1:a5".to_string()),
             inner_get_device_ip_and_mac_address(
                 "test_inner_get_device_ip_and_mac_address-url",
-                None,
+                &DeviceAuth::default(),
                 &mock
             )
             .await
@@ -544,7 +966,7 @@ mod tests {
             ),
             inner_get_device_ip_and_mac_address(
                 "test_inner_get_device_ip_and_mac_address-url",
-                None,
+                &DeviceAuth::default(),
                 &mock
             )
             .await
@@ -562,7 +984,7 @@ mod tests {
             &mut mock,
             "test_inner_get_device_service_uri-url",
             &get_action(DEVICE_WSDL, "GetServices"),
-            GET_SERVICES_TEMPLATE,
+            &get_services_message(&None),
             response,
         );
         assert_eq!(
@@ -570,6 +992,7 @@ mod tests {
             inner_get_device_service_uri(
                 "test_inner_get_device_service_uri-url",
                 MEDIA_WSDL,
+                &DeviceAuth::default(),
                 &mock
             )
             .await
@@ -589,18 +1012,39 @@ mod tests {
                 &mut mock,
                 "test_inner_get_device_profiles-url",
                 &get_action(MEDIA_WSDL, "GetProfiles"),
-                GET_PROFILES_TEMPLATE,
+                &get_profiles_message(MEDIA_WSDL, &None),
                 response,
             );
         }
-        let mut actual_profiles =
-            inner_get_device_profiles("test_inner_get_device_profiles-url", &mock)
-                .await
-                .unwrap();
-        actual_profiles.sort();
+        let actual_profiles = inner_get_device_profiles(
+            "test_inner_get_device_profiles-url",
+            MEDIA_WSDL,
+            &DeviceAuth::default(),
+            &mock,
+        )
+        .await
+        .unwrap();
+        let mut actual_tokens = actual_profiles
+            .iter()
+            .map(|profile| profile.token.clone())
+            .collect::<Vec<String>>();
+        actual_tokens.sort();
         assert_eq!(
             vec!["000".to_string(), "001".to_string(), "002".to_string()],
-            actual_profiles,
+            actual_tokens,
+        );
+        let profile = actual_profiles.iter().find(|p| p.token == "000").unwrap();
+        assert_eq!(
+            &MediaProfile {
+                token: "000".to_string(),
+                name: "Profile_000".to_string(),
+                encoding: Some("H264".to_string()),
+                width: Some(1280),
+                height: Some(720),
+                frame_rate: Some("25".to_string()),
+                stream_uri: None,
+            },
+            profile
         );
     }
 
@@ -615,7 +1059,7 @@ mod tests {
         for (i, expected_uri) in expected_result.iter().enumerate().take(3) {
             let mut mock = MockHttp::new();
             let profile = format!("00{}", i).to_string();
-            let message = get_stream_uri_message(&profile);
+            let message = get_stream_uri_message(MEDIA_WSDL, &profile, &None);
             let response = format!(
                 "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<SOAP-ENV:Envelope xmlns:SOAP-ENV=\"http://www.w3.org/2003/05/soap-envelope\" xmlns:SOAP-ENC=\"http://www.w3.org/2003/05/soap-encoding\" xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\" xmlns:xsd=\"http://www.w3.org/2001/XMLSchema\" xmlns:xs=\"http://www.w3.org/2000/10/XMLSchema\" xmlns:wsse=\"http://docs.oasis-open.org/wss/2004/01/oasis-200401-wss-wssecurity-secext-1.0.xsd\" xmlns:wsa5=\"http://www.w3.org/2005/08/addressing\" xmlns:xop=\"http://www.w3.org/2004/08/xop/include\" xmlns:wsa=\"http://schemas.xmlsoap.org/ws/2004/08/addressing\" xmlns:tt=\"http://www.onvif.org/ver10/schema\" xmlns:ns1=\"http://www.w3.org/2005/05/xmlmime\" xmlns:wstop=\"http://docs.oasis-open.org/wsn/t-1\" xmlns:ns7=\"http://docs.oasis-open.org/wsrf/r-2\" xmlns:ns2=\"http://docs.oasis-open.org/wsrf/bf-2\" xmlns:dndl=\"http://www.onvif.org/ver10/network/wsdl/DiscoveryLookupBinding\" xmlns:dnrd=\"http://www.onvif.org/ver10/network/wsdl/RemoteDiscoveryBinding\" xmlns:d=\"http://schemas.xmlsoap.org/ws/2005/04/discovery\" xmlns:dn=\"http://www.onvif.org/ver10/network/wsdl\" xmlns:ns10=\"http://www.onvif.org/ver10/replay/wsdl\" xmlns:ns11=\"http://www.onvif.org/ver10/search/wsdl\" xmlns:ns13=\"http://www.onvif.org/ver20/analytics/wsdl/RuleEngineBinding\" xmlns:ns14=\"http://www.onvif.org/ver20/analytics/wsdl/AnalyticsEngineBinding\" xmlns:tan=\"http://www.onvif.org/ver20/analytics/wsdl\" xmlns:ns15=\"http://www.onvif.org/ver10/events/wsdl/PullPointSubscriptionBinding\" xmlns:ns16=\"http://www.onvif.org/ver10/events/wsdl/EventBinding\" xmlns:tev=\"http://www.onvif.org/ver10/events/wsdl\" xmlns:ns17=\"http://www.onvif.org/ver10/events/wsdl/SubscriptionManagerBinding\" xmlns:ns18=\"http://www.onvif.org/ver10/events/wsdl/NotificationProducerBinding\" xmlns:ns19=\"http://www.onvif.org/ver10/events/wsdl/NotificationConsumerBinding\" xmlns:ns20=\"http://www.onvif.org/ver10/events/wsdl/PullPointBinding\" xmlns:ns21=\"http://www.onvif.org/ver10/events/wsdl/CreatePullPointBinding\" xmlns:ns22=\"http://www.onvif.org/ver10/events/wsdl/PausableSubscriptionManagerBinding\" xmlns:wsnt=\"http://docs.oasis-open.org/wsn/b-2\" xmlns:ns3=\"http://www.onvif.org/ver10/analyticsdevice/wsdl\" xmlns:ns4=\"http://www.onvif.org/ver10/deviceIO/wsdl\" xmlns:ns5=\"http://www.onvif.org/ver10/display/wsdl\" xmlns:ns8=\"http://www.onvif.org/ver10/receiver/wsdl\" xmlns:ns9=\"http://www.onvif.org/ver10/recording/wsdl\" xmlns:tds=\"http://www.onvif.org/ver10/device/wsdl\" xmlns:timg=\"http://www.onvif.org/ver20/imaging/wsdl\" xmlns:tptz=\"http://www.onvif.org/ver20/ptz/wsdl\" xmlns:trt=\"http://www.onvif.org/ver10/media/wsdl\" xmlns:trt2=\"http://www.onvif.org/ver20/media/wsdl\" xmlns:ter=\"http://www.onvif.org/ver10/error\" xmlns:tns1=\"http://www.onvif.org/ver10/topics\" xmlns:tnsn=\"http://www.eventextension.com/2011/event/topics\"><SOAP-ENV:Header></SOAP-ENV:Header><SOAP-ENV:Body><trt:GetStreamUriResponse><trt:MediaUri><tt:Uri>rtsp://192.168.{}.36:554/user=admin_password=tlJwpbo6_channel=1_stream=0.sdp?real_stream</tt:Uri><tt:InvalidAfterConnect>false</tt:InvalidAfterConnect><tt:InvalidAfterReboot>false</tt:InvalidAfterReboot><tt:Timeout>PT10S</tt:Timeout></trt:MediaUri></trt:GetStreamUriResponse></SOAP-ENV:Body></SOAP-ENV:Envelope>",
                 i
@@ -632,7 +1076,9 @@ mod tests {
                 expected_uri.to_string(),
                 inner_get_device_profile_streaming_uri(
                     "test_inner_get_device_profile_streaming_uri-url",
+                    MEDIA_WSDL,
                     &profile,
+                    &DeviceAuth::default(),
                     &mock
                 )
                 .await
@@ -669,4 +1115,347 @@ mod tests {
     fn test_http_handle_request_body_no_panic() {
         assert!(HttpRequest::handle_request_body("\r\n").is_err());
     }
+
+    #[test]
+    fn test_is_not_authorized_fault() {
+        let fault = |code: &str| {
+            soap_response(&format!(
+                "<s:Fault><s:Code><s:Value>s:Sender</s:Value><s:Subcode><s:Value>{}</s:Value></s:Subcode></s:Code><s:Reason><s:Text>NotAuthorized</s:Text></s:Reason></s:Fault>",
+                code
+            ))
+        };
+        assert!(is_not_authorized_fault(&fault("ter:NotAuthorized")));
+        // The fault must carry the subcode, not only mention it
+        assert!(!is_not_authorized_fault(&fault("ter:InvalidArgVal")));
+        assert!(!is_not_authorized_fault("NotAuthorized"));
+    }
+
+    #[test]
+    fn test_retain_devices() {
+        let onvif_query = OnvifQueryImpl::default();
+        onvif_query.auth_schemes.lock().unwrap().extend([
+            ("uuid-a".to_string(), AuthScheme::HttpDigest),
+            ("uuid-b".to_string(), AuthScheme::UsernameToken),
+        ]);
+        onvif_query.retain_devices(&["uuid-b".to_string(), "uuid-c".to_string()]);
+        assert_eq!(
+            *onvif_query.auth_schemes.lock().unwrap(),
+            HashMap::from([("uuid-b".to_string(), AuthScheme::UsernameToken)])
+        );
+    }
+
+    /// Responds to a SOAP request given its head and body, or returns the response failing the request
+    type MockSoapResponder = dyn Fn(&hyper::http::request::Parts, &str) -> Result<String, hyper::Response<hyper::Body>>
+        + Send
+        + Sync;
+
+    /// Digest challenges sent by the mock cameras requiring HTTP Digest authentication
+    const MOCK_DIGEST_CHALLENGES: [&str; 2] = [
+        r#"Digest realm="akri-camera", qop="auth", nonce="mock-nonce", algorithm=MD5"#,
+        r#"Digest realm="akri-camera", qop="auth", nonce="mock-nonce", algorithm=SHA-256"#,
+    ];
+
+    fn bad_request() -> hyper::Response<hyper::Body> {
+        hyper::Response::builder()
+            .status(400)
+            .body(hyper::Body::empty())
+            .unwrap()
+    }
+
+    /// Response of a camera rejecting the WS-UsernameToken of a request
+    fn not_authorized_fault() -> hyper::Response<hyper::Body> {
+        hyper::Response::builder()
+            .status(400)
+            .body(hyper::Body::from(soap_response(
+                r#"<s:Fault><s:Code><s:Value>s:Sender</s:Value><s:Subcode><s:Value>ter:NotAuthorized</s:Value></s:Subcode></s:Code></s:Fault>"#,
+            )))
+            .unwrap()
+    }
+
+    /// Response of a camera requiring HTTP Digest authentication
+    fn digest_challenge() -> hyper::Response<hyper::Body> {
+        let mut response = hyper::Response::builder().status(401);
+        for challenge in MOCK_DIGEST_CHALLENGES {
+            response = response.header("WWW-AUTHENTICATE", challenge);
+        }
+        response.body(hyper::Body::empty()).unwrap()
+    }
+
+    /// Starts a mock ONVIF camera serving SOAP responses over HTTP. `MOCK_ADDRESS` is replaced in
+    /// the responses by the address of the server. Returns the device service url of the camera.
+    async fn start_mock_soap_server(responder: Box<MockSoapResponder>) -> String {
+        use hyper::{
+            service::{make_service_fn, service_fn},
+            Body, Response, Server,
+        };
+        use std::sync::Arc;
+
+        let responder: Arc<MockSoapResponder> = Arc::from(responder);
+        let make_service = make_service_fn(move |_| {
+            let responder = responder.clone();
+            async move {
+                Ok::<_, std::convert::Infallible>(service_fn(move |req: Request<Body>| {
+                    let responder = responder.clone();
+                    async move {
+                        let (parts, body) = req.into_parts();
+                        let host = header_value(&parts, "HOST");
+                        let body = hyper::body::to_bytes(body).await.unwrap();
+                        let body = String::from_utf8_lossy(&body);
+                        let response = match responder(&parts, &body) {
+                            Ok(xml) => {
+                                Response::new(Body::from(xml.replace("MOCK_ADDRESS", &host)))
+                            }
+                            Err(response) => response,
+                        };
+                        Ok::<_, std::convert::Infallible>(response)
+                    }
+                }))
+            }
+        });
+        let server = Server::bind(&"127.0.0.1:0".parse().unwrap()).serve(make_service);
+        let address = server.local_addr();
+        tokio::spawn(server);
+        format!("http://{}/onvif/device_service", address)
+    }
+
+    fn header_value(parts: &hyper::http::request::Parts, name: &str) -> String {
+        parts
+            .headers
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or_default()
+            .to_string()
+    }
+
+    fn soap_response(body: &str) -> String {
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope" xmlns:tds="http://www.onvif.org/ver10/device/wsdl" xmlns:tr2="http://www.onvif.org/ver20/media/wsdl" xmlns:tt="http://www.onvif.org/ver10/schema"><s:Body>{}</s:Body></s:Envelope>"#,
+            body
+        )
+    }
+
+    fn media2_profile(token: &str, encoding: &str, width: u32, height: u32, rate: u32) -> String {
+        format!(
+            r#"<tr2:Profiles token="{token}" fixed="true"><tt:Name>{token}_profile</tt:Name><tr2:Configurations><tr2:VideoSource token="vs0"><tt:Name>VS0</tt:Name><tt:Bounds x="0" y="0" width="3840" height="2160"/></tr2:VideoSource><tr2:VideoEncoder token="ve_{token}" GovLength="50" Profile="Main"><tt:Name>VE_{token}</tt:Name><tt:UseCount>1</tt:UseCount><tt:Encoding>{encoding}</tt:Encoding><tt:Resolution><tt:Width>{width}</tt:Width><tt:Height>{height}</tt:Height></tt:Resolution><tt:RateControl ConstantBitRate="false"><tt:FrameRateLimit>{rate}</tt:FrameRateLimit><tt:BitrateLimit>4096</tt:BitrateLimit></tt:RateControl><tt:Quality>5</tt:Quality></tr2:VideoEncoder></tr2:Configurations></tr2:Profiles>"#
+        )
+    }
+
+    /// Mock camera supporting the Media2 service and requiring a WS-UsernameToken
+    fn media2_camera_responder(
+        parts: &hyper::http::request::Parts,
+        body: &str,
+    ) -> Result<String, hyper::Response<hyper::Body>> {
+        if !body.contains("<wsse:Username>admin</wsse:Username>") {
+            return Err(not_authorized_fault());
+        }
+        let content_type = header_value(parts, "CONTENT-TYPE");
+        if content_type.contains(&get_action(DEVICE_WSDL, "GetServices")) {
+            Ok(soap_response(&format!(
+                r#"<tds:GetServicesResponse><tds:Service><tds:Namespace>{}</tds:Namespace><tds:XAddr>http://MOCK_ADDRESS/onvif/device_service</tds:XAddr></tds:Service><tds:Service><tds:Namespace>{}</tds:Namespace><tds:XAddr>http://MOCK_ADDRESS/onvif/media2</tds:XAddr></tds:Service></tds:GetServicesResponse>"#,
+                DEVICE_WSDL, MEDIA2_WSDL
+            )))
+        } else if content_type.contains(&get_action(MEDIA2_WSDL, "GetProfiles"))
+            && body.contains("<wsdl:Type>All</wsdl:Type>")
+        {
+            Ok(soap_response(&format!(
+                "<tr2:GetProfilesResponse>{}{}</tr2:GetProfilesResponse>",
+                media2_profile("main", "H265", 3840, 2160, 30),
+                media2_profile("sub", "H264", 640, 360, 15)
+            )))
+        } else if content_type.contains(&get_action(MEDIA2_WSDL, "GetStreamUri")) {
+            let token = if body.contains("<wsdl:ProfileToken>main</wsdl:ProfileToken>") {
+                "main"
+            } else {
+                "sub"
+            };
+            Ok(soap_response(&format!(
+                "<tr2:GetStreamUriResponse><tr2:Uri>rtsp://MOCK_ADDRESS/{}</tr2:Uri></tr2:GetStreamUriResponse>",
+                token
+            )))
+        } else {
+            Err(bad_request())
+        }
+    }
+
+    /// Credential store with a default credential for the admin user
+    fn admin_credential_store(password: &str) -> CredentialStore {
+        CredentialStore::new(&HashMap::from([
+            (
+                crate::credential_store::DEVICE_CREDENTIAL_DEFAULT_USERNAME.to_string(),
+                akri_discovery_utils::discovery::v0::ByteData {
+                    vec: Some(b"admin".to_vec()),
+                },
+            ),
+            (
+                crate::credential_store::DEVICE_CREDENTIAL_DEFAULT_PASSWORD.to_string(),
+                akri_discovery_utils::discovery::v0::ByteData {
+                    vec: Some(password.as_bytes().to_vec()),
+                },
+            ),
+        ]))
+    }
+
+    /// Mock camera requiring HTTP Digest authentication of the admin user, counting the requests
+    fn digest_camera_responder(
+        requests: std::sync::Arc<std::sync::atomic::AtomicUsize>,
+    ) -> Box<MockSoapResponder> {
+        Box::new(move |parts: &hyper::http::request::Parts, _body: &str| {
+            requests.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            let authorization = header_value(parts, "AUTHORIZATION");
+            let cnonce = authorization
+                .split("cnonce=\"")
+                .nth(1)
+                .and_then(|cnonce| cnonce.split('"').next())
+                .unwrap_or_default();
+            // The strongest challenge must be answered
+            let expected_authorization = DigestChallenge::parse(MOCK_DIGEST_CHALLENGES[1])
+                .unwrap()
+                .authorization("admin", "password", "POST", parts.uri.path(), cnonce, 1);
+            if authorization != expected_authorization {
+                return Err(digest_challenge());
+            }
+            Ok(soap_response(
+                r#"<tds:GetNetworkInterfacesResponse><tds:NetworkInterfaces token="eth0"><tt:Info><tt:HwAddress>00:12:41:5c:a1:a5</tt:HwAddress></tt:Info><tt:IPv4><tt:Config><tt:Manual><tt:Address>192.168.1.36</tt:Address></tt:Manual></tt:Config></tt:IPv4></tds:NetworkInterfaces></tds:GetNetworkInterfacesResponse>"#,
+            ))
+        })
+    }
+
+    #[tokio::test]
+    async fn test_get_device_ip_and_mac_address_http_digest() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let requests = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let service_url = start_mock_soap_server(digest_camera_responder(requests.clone())).await;
+        let onvif_query = OnvifQueryImpl::new(admin_credential_store("password"));
+        let expected = ("192.168.1.36".to_string(), "00:12:41:5c:a1:a5".to_string());
+
+        // The Digest challenge the camera answered the unauthenticated request with is reused
+        assert_eq!(
+            onvif_query
+                .get_device_ip_and_mac_address(&service_url, "uuid")
+                .await
+                .unwrap(),
+            expected
+        );
+        assert_eq!(requests.load(std::sync::atomic::Ordering::SeqCst), 3);
+        assert_eq!(
+            onvif_query.auth_schemes.lock().unwrap().get("uuid"),
+            Some(&AuthScheme::HttpDigest)
+        );
+
+        // The scheme is cached for the camera
+        assert_eq!(
+            onvif_query
+                .get_device_ip_and_mac_address(&service_url, "uuid")
+                .await
+                .unwrap(),
+            expected
+        );
+        assert_eq!(requests.load(std::sync::atomic::Ordering::SeqCst), 4);
+    }
+
+    #[tokio::test]
+    async fn test_get_device_ip_and_mac_address_no_authentication() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        // A camera that doesn't require authentication, recording the requests it receives
+        let requests = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let recorded = requests.clone();
+        let service_url = start_mock_soap_server(Box::new(move |parts, body| {
+            recorded.lock().unwrap().push((
+                body.contains("<wsse:UsernameToken"),
+                !header_value(parts, "AUTHORIZATION").is_empty(),
+            ));
+            Ok(soap_response(
+                r#"<tds:GetNetworkInterfacesResponse><tds:NetworkInterfaces token="eth0"><tt:Info><tt:HwAddress>00:12:41:5c:a1:a5</tt:HwAddress></tt:Info><tt:IPv4><tt:Config><tt:Manual><tt:Address>192.168.1.36</tt:Address></tt:Manual></tt:Config></tt:IPv4></tds:NetworkInterfaces></tds:GetNetworkInterfacesResponse>"#,
+            ))
+        }))
+        .await;
+
+        // Even with a credential, no authentication is tried first
+        let onvif_query = OnvifQueryImpl::new(admin_credential_store("password"));
+        assert!(onvif_query
+            .get_device_ip_and_mac_address(&service_url, "uuid")
+            .await
+            .is_ok());
+        assert_eq!(*requests.lock().unwrap(), vec![(false, false)]);
+        assert_eq!(
+            onvif_query.auth_schemes.lock().unwrap().get("uuid"),
+            Some(&AuthScheme::None)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_device_ip_and_mac_address_authentication_failure() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let requests = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let service_url = start_mock_soap_server(digest_camera_responder(requests)).await;
+        for onvif_query in [
+            OnvifQueryImpl::default(),
+            OnvifQueryImpl::new(admin_credential_store("wrong-password")),
+        ] {
+            let error = onvif_query
+                .get_device_ip_and_mac_address(&service_url, "uuid")
+                .await
+                .unwrap_err();
+            assert!(is_authentication_error(&error));
+            assert!(onvif_query.auth_schemes.lock().unwrap().is_empty());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_media_profiles_media2() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let service_url = start_mock_soap_server(Box::new(media2_camera_responder)).await;
+        let address = service_url
+            .trim_start_matches("http://")
+            .split('/')
+            .next()
+            .unwrap()
+            .to_string();
+
+        // Without credentials, the camera rejects the requests
+        let onvif_query = OnvifQueryImpl::default();
+        assert!(onvif_query
+            .get_media_profiles(&service_url, "uuid")
+            .await
+            .is_err());
+
+        let onvif_query = OnvifQueryImpl::new(admin_credential_store("password"));
+        let profiles = onvif_query
+            .get_media_profiles(&service_url, "uuid")
+            .await
+            .unwrap();
+        assert_eq!(
+            profiles,
+            vec![
+                MediaProfile {
+                    token: "main".to_string(),
+                    name: "main_profile".to_string(),
+                    encoding: Some("H265".to_string()),
+                    width: Some(3840),
+                    height: Some(2160),
+                    frame_rate: Some("30".to_string()),
+                    stream_uri: Some(format!("rtsp://{}/main", address)),
+                },
+                MediaProfile {
+                    token: "sub".to_string(),
+                    name: "sub_profile".to_string(),
+                    encoding: Some("H264".to_string()),
+                    width: Some(640),
+                    height: Some(360),
+                    frame_rate: Some("15".to_string()),
+                    stream_uri: Some(format!("rtsp://{}/sub", address)),
+                },
+            ]
+        );
+        assert_eq!(profiles[1].resolution(), Some("640x360".to_string()));
+        assert_eq!(
+            profiles[1].properties().get(ONVIF_STREAM_URI_LABEL_ID),
+            Some(&format!("rtsp://{}/sub", address))
+        );
+    }
 }
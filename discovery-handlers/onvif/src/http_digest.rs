@@ -0,0 +1,261 @@
+//! This implements the client side of HTTP Digest Access Authentication as described in
+//! [RFC 7616]: https://www.rfc-editor.org/rfc/rfc7616, which cameras may require on top of or
+//! instead of the WS-UsernameToken.
+use md5::Md5;
+use sha2::{Digest, Sha256};
+
+/// Hash algorithm of a Digest challenge, the `-sess` variants hash the credentials with the nonces
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DigestAlgorithm {
+    Md5,
+    Md5Sess,
+    Sha256,
+    Sha256Sess,
+}
+
+impl DigestAlgorithm {
+    fn parse(algorithm: &str) -> Option<Self> {
+        match algorithm.to_uppercase().as_str() {
+            "MD5" => Some(DigestAlgorithm::Md5),
+            "MD5-SESS" => Some(DigestAlgorithm::Md5Sess),
+            "SHA-256" => Some(DigestAlgorithm::Sha256),
+            "SHA-256-SESS" => Some(DigestAlgorithm::Sha256Sess),
+            _ => None,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            DigestAlgorithm::Md5 => "MD5",
+            DigestAlgorithm::Md5Sess => "MD5-sess",
+            DigestAlgorithm::Sha256 => "SHA-256",
+            DigestAlgorithm::Sha256Sess => "SHA-256-sess",
+        }
+    }
+
+    fn is_session(&self) -> bool {
+        matches!(self, DigestAlgorithm::Md5Sess | DigestAlgorithm::Sha256Sess)
+    }
+
+    fn hash(&self, data: &str) -> String {
+        match self {
+            DigestAlgorithm::Md5 | DigestAlgorithm::Md5Sess => {
+                format!("{:x}", Md5::digest(data.as_bytes()))
+            }
+            DigestAlgorithm::Sha256 | DigestAlgorithm::Sha256Sess => {
+                format!("{:x}", Sha256::digest(data.as_bytes()))
+            }
+        }
+    }
+}
+
+/// Digest challenge sent by a server in a `WWW-Authenticate` header
+#[derive(Clone, Debug, PartialEq)]
+pub struct DigestChallenge {
+    pub realm: String,
+    pub nonce: String,
+    pub opaque: Option<String>,
+    pub algorithm: DigestAlgorithm,
+    /// Whether the server supports the `auth` quality of protection, else the RFC 2069 compatible
+    /// response is computed
+    pub qop_auth: bool,
+}
+
+impl DigestChallenge {
+    /// Parses a `WWW-Authenticate` header value, returning `None` if it is not a supported Digest challenge
+    pub fn parse(header: &str) -> Option<Self> {
+        let header = header.trim();
+        let (scheme, params) = header.split_once(' ')?;
+        if !scheme.eq_ignore_ascii_case("Digest") {
+            return None;
+        }
+        let mut realm = None;
+        let mut nonce = None;
+        let mut opaque = None;
+        let mut algorithm = DigestAlgorithm::Md5;
+        let mut qop_auth = false;
+        for (key, value) in split_params(params) {
+            match key.to_lowercase().as_str() {
+                "realm" => realm = Some(value),
+                "nonce" => nonce = Some(value),
+                "opaque" => opaque = Some(value),
+                "algorithm" => algorithm = DigestAlgorithm::parse(&value)?,
+                "qop" => qop_auth = value.split(',').any(|qop| qop.trim() == "auth"),
+                _ => {}
+            }
+        }
+        Some(DigestChallenge {
+            realm: realm?,
+            nonce: nonce?,
+            opaque,
+            algorithm,
+            qop_auth,
+        })
+    }
+
+    /// Selects the strongest supported challenge among the ones sent by a server
+    pub fn select<'a>(headers: impl IntoIterator<Item = &'a String>) -> Option<Self> {
+        headers
+            .into_iter()
+            .filter_map(|header| DigestChallenge::parse(header))
+            .max_by_key(|challenge| {
+                matches!(
+                    challenge.algorithm,
+                    DigestAlgorithm::Sha256 | DigestAlgorithm::Sha256Sess
+                )
+            })
+    }
+
+    /// Builds the `Authorization` header answering the challenge for a request
+    pub fn authorization(
+        &self,
+        username: &str,
+        password: &str,
+        method: &str,
+        uri: &str,
+        cnonce: &str,
+        nonce_count: u32,
+    ) -> String {
+        let nc = format!("{:08x}", nonce_count);
+        let mut ha1 = self
+            .algorithm
+            .hash(&format!("{}:{}:{}", username, self.realm, password));
+        if self.algorithm.is_session() {
+            ha1 = self
+                .algorithm
+                .hash(&format!("{}:{}:{}", ha1, self.nonce, cnonce));
+        }
+        let ha2 = self.algorithm.hash(&format!("{}:{}", method, uri));
+        let response = if self.qop_auth {
+            self.algorithm.hash(&format!(
+                "{}:{}:{}:{}:auth:{}",
+                ha1, self.nonce, nc, cnonce, ha2
+            ))
+        } else {
+            self.algorithm
+                .hash(&format!("{}:{}:{}", ha1, self.nonce, ha2))
+        };
+        let mut header = format!(
+            r#"Digest username="{}", realm="{}", nonce="{}", uri="{}", algorithm={}, response="{}""#,
+            username,
+            self.realm,
+            self.nonce,
+            uri,
+            self.algorithm.name(),
+            response
+        );
+        if self.qop_auth {
+            header.push_str(&format!(r#", qop=auth, nc={}, cnonce="{}""#, nc, cnonce));
+        }
+        if let Some(opaque) = &self.opaque {
+            header.push_str(&format!(r#", opaque="{}""#, opaque));
+        }
+        header
+    }
+}
+
+/// Splits the comma separated `key=value` parameters of a challenge, values may be quoted and contain commas
+fn split_params(params: &str) -> Vec<(String, String)> {
+    let mut result = Vec::new();
+    let mut rest = params.trim();
+    while let Some((key, value_and_rest)) = rest.split_once('=') {
+        let key = key.trim().trim_start_matches(',').trim().to_string();
+        let value_and_rest = value_and_rest.trim_start();
+        let (value, remaining) = if let Some(quoted) = value_and_rest.strip_prefix('"') {
+            match quoted.find('"') {
+                Some(end) => (quoted[..end].to_string(), &quoted[end + 1..]),
+                None => (quoted.to_string(), ""),
+            }
+        } else {
+            match value_and_rest.find(',') {
+                Some(end) => (
+                    value_and_rest[..end].trim().to_string(),
+                    &value_and_rest[end..],
+                ),
+                None => (value_and_rest.trim().to_string(), ""),
+            }
+        };
+        result.push((key, value));
+        rest = remaining.trim_start().trim_start_matches(',');
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Example of RFC 7616 section 3.9.1
+    const RFC_CHALLENGE_PARAMS: &str = r#"realm="http-auth@example.org", qop="auth, auth-int", nonce="7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v", opaque="FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS""#;
+    const RFC_CNONCE: &str = "f2/wE4q74E6zIJEtWaHKaf5wv/H5QzzpXusqGemxURZJ";
+
+    #[test]
+    fn test_parse_challenge() {
+        let challenge = DigestChallenge::parse(&format!(
+            "Digest {}, algorithm=SHA-256",
+            RFC_CHALLENGE_PARAMS
+        ))
+        .unwrap();
+        assert_eq!(
+            challenge,
+            DigestChallenge {
+                realm: "http-auth@example.org".to_string(),
+                nonce: "7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v".to_string(),
+                opaque: Some("FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS".to_string()),
+                algorithm: DigestAlgorithm::Sha256,
+                qop_auth: true,
+            }
+        );
+        assert!(DigestChallenge::parse(r#"Basic realm="camera""#).is_none());
+        assert!(DigestChallenge::parse(r#"Digest realm="camera""#).is_none());
+        assert!(DigestChallenge::parse(
+            r#"Digest realm="camera", nonce="n", algorithm=SHA-512-256"#
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn test_select_challenge() {
+        let headers = vec![
+            format!("Digest {}, algorithm=MD5", RFC_CHALLENGE_PARAMS),
+            format!("Digest {}, algorithm=SHA-256", RFC_CHALLENGE_PARAMS),
+            r#"Basic realm="camera""#.to_string(),
+        ];
+        assert_eq!(
+            DigestChallenge::select(&headers).unwrap().algorithm,
+            DigestAlgorithm::Sha256
+        );
+    }
+
+    #[test]
+    fn test_authorization_rfc_7616_examples() {
+        for (algorithm, response) in [
+            ("MD5", "8ca523f5e9506fed4657c9700eebdbec"),
+            (
+                "SHA-256",
+                "753927fa0e85d155564e2e272a28d1802ca10daf4496794697cf8db5856cb6c1",
+            ),
+        ] {
+            let challenge = DigestChallenge::parse(&format!(
+                "Digest {}, algorithm={}",
+                RFC_CHALLENGE_PARAMS, algorithm
+            ))
+            .unwrap();
+            let authorization = challenge.authorization(
+                "Mufasa",
+                "Circle of Life",
+                "GET",
+                "/dir/index.html",
+                RFC_CNONCE,
+                1,
+            );
+            assert_eq!(
+                authorization,
+                format!(
+                    r#"Digest username="Mufasa", realm="http-auth@example.org", nonce="7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v", uri="/dir/index.html", algorithm={}, response="{}", qop=auth, nc=00000001, cnonce="{}", opaque="FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS""#,
+                    algorithm, response, RFC_CNONCE
+                )
+            );
+        }
+    }
+}
@@ -2,6 +2,7 @@ mod credential_store;
 pub mod discovery_handler;
 mod discovery_impl;
 mod discovery_utils;
+mod http_digest;
 mod username_token;
 
 #[macro_use]
@@ -9,7 +10,18 @@ extern crate serde_derive;
 #[macro_use]
 extern crate yaserde_derive;
 
+use akri_discovery_utils::discovery::discovery_handler::{
+    DiscoveryHandlerCapabilities, DiscoveryHandlerFeature,
+};
+
 /// Name that onvif discovery handlers use when registering with the Agent
 pub const DISCOVERY_HANDLER_NAME: &str = "onvif";
 /// Defines whether this discovery handler discovers local devices on nodes rather than ones visible to multiple nodes
 pub const SHARED: bool = true;
+
+/// Capabilities onvif discovery handlers advertise when registering with the Agent
+pub fn capabilities() -> DiscoveryHandlerCapabilities {
+    DiscoveryHandlerCapabilities::new(env!("CARGO_PKG_VERSION"))
+        .with_feature(DiscoveryHandlerFeature::DeviceHealth)
+        .with_discovery_details_schema::<discovery_handler::OnvifDiscoveryDetails>()
+}
@@ -0,0 +1,192 @@
+use akri_discovery_utils::discovery::v0::ByteData;
+use log::warn;
+use opcua::client::prelude::*;
+use std::{
+    collections::HashMap,
+    fs::{DirBuilder, OpenOptions, Permissions},
+    io::Write,
+    os::unix::fs::{DirBuilderExt, OpenOptionsExt, PermissionsExt},
+};
+use tempfile::TempDir;
+
+/// Key name of the DER encoded application certificate of the discovery client in discoveryProperties
+pub const OPCUA_CERTIFICATE: &str = "opcua_certificate";
+/// Key name of the PEM encoded private key of the application certificate in discoveryProperties
+pub const OPCUA_PRIVATE_KEY: &str = "opcua_private_key";
+/// Key name of the username used to authenticate to the servers in discoveryProperties
+pub const OPCUA_USERNAME: &str = "opcua_username";
+/// Key name of the password used to authenticate to the servers in discoveryProperties
+pub const OPCUA_PASSWORD: &str = "opcua_password";
+
+/// Path of the application certificate in the PKI directory, as expected by the OPC UA client
+const PKI_CERTIFICATE_PATH: &str = "own/cert.der";
+/// Path of the private key in the PKI directory, as expected by the OPC UA client
+const PKI_PRIVATE_KEY_PATH: &str = "private/private.pem";
+
+/// Credentials used by the discovery client to authenticate to the OPC UA servers, read from the
+/// discoveryProperties of the Configuration.
+#[derive(Clone, Default)]
+pub struct OpcuaCredentials {
+    /// DER encoded certificate and PEM encoded private key of the client application
+    application_certificate: Option<(Vec<u8>, Vec<u8>)>,
+    /// Username and password of the user identity
+    user: Option<(String, String)>,
+}
+
+impl OpcuaCredentials {
+    pub fn new(discovery_properties: &HashMap<String, ByteData>) -> Self {
+        let get = |key: &str| {
+            discovery_properties
+                .get(key)
+                .and_then(|data| data.vec.clone())
+        };
+        let application_certificate = match (get(OPCUA_CERTIFICATE), get(OPCUA_PRIVATE_KEY)) {
+            (Some(certificate), Some(private_key)) => Some((certificate, private_key)),
+            (None, None) => None,
+            _ => {
+                warn!(
+                    "OpcuaCredentials::new - {} and {} must be set together, ignoring the application certificate",
+                    OPCUA_CERTIFICATE, OPCUA_PRIVATE_KEY
+                );
+                None
+            }
+        };
+        let to_string = |data: Vec<u8>| String::from_utf8_lossy(&data).to_string();
+        let user = get(OPCUA_USERNAME).map(|username| {
+            (
+                to_string(username),
+                get(OPCUA_PASSWORD).map(to_string).unwrap_or_default(),
+            )
+        });
+        OpcuaCredentials {
+            application_certificate,
+            user,
+        }
+    }
+
+    /// Whether the discovery client must authenticate to the servers
+    pub fn is_empty(&self) -> bool {
+        self.application_certificate.is_none() && self.user.is_none()
+    }
+
+    /// Type of the user identity token required from the endpoints
+    pub fn user_token_type(&self) -> UserTokenType {
+        match self.user {
+            Some(_) => UserTokenType::UserName,
+            None => UserTokenType::Anonymous,
+        }
+    }
+
+    /// User identity token used when activating a session on the selected endpoint
+    pub fn identity_token(&self) -> IdentityToken {
+        match &self.user {
+            Some((username, password)) => {
+                IdentityToken::UserName(username.clone(), password.clone())
+            }
+            None => IdentityToken::Anonymous,
+        }
+    }
+
+    /// Writes the application certificate in the layout of an OPC UA PKI directory, returning the
+    /// directory if there is a certificate to use. The directory is only accessible by the
+    /// current user, and is removed when dropped.
+    pub fn write_pki_dir(&self) -> std::io::Result<Option<TempDir>> {
+        let Some((certificate, private_key)) = &self.application_certificate else {
+            return Ok(None);
+        };
+        let pki_dir = tempfile::Builder::new()
+            .prefix("akri-opcua-pki-")
+            .tempdir()?;
+        std::fs::set_permissions(pki_dir.path(), Permissions::from_mode(0o700))?;
+        for (path, content, mode) in [
+            (PKI_CERTIFICATE_PATH, certificate, 0o644),
+            (PKI_PRIVATE_KEY_PATH, private_key, 0o600),
+        ] {
+            let path = pki_dir.path().join(path);
+            DirBuilder::new()
+                .recursive(true)
+                .mode(0o700)
+                .create(path.parent().unwrap())?;
+            OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .mode(mode)
+                .open(path)?
+                .write_all(content)?;
+        }
+        Ok(Some(pki_dir))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn byte_data(value: &str) -> ByteData {
+        ByteData {
+            vec: Some(value.as_bytes().to_vec()),
+        }
+    }
+
+    #[test]
+    fn test_credentials_empty() {
+        let credentials = OpcuaCredentials::new(&HashMap::new());
+        assert!(credentials.is_empty());
+        assert_eq!(credentials.user_token_type(), UserTokenType::Anonymous);
+        assert!(matches!(
+            credentials.identity_token(),
+            IdentityToken::Anonymous
+        ));
+    }
+
+    #[test]
+    fn test_credentials_username() {
+        let credentials = OpcuaCredentials::new(&HashMap::from([
+            (OPCUA_USERNAME.to_string(), byte_data("user")),
+            (OPCUA_PASSWORD.to_string(), byte_data("pass")),
+        ]));
+        assert!(!credentials.is_empty());
+        assert_eq!(credentials.user_token_type(), UserTokenType::UserName);
+        assert!(matches!(
+            credentials.identity_token(),
+            IdentityToken::UserName(username, password) if username == "user" && password == "pass"
+        ));
+    }
+
+    #[test]
+    fn test_credentials_certificate_requires_private_key() {
+        let credentials = OpcuaCredentials::new(&HashMap::from([(
+            OPCUA_CERTIFICATE.to_string(),
+            byte_data("cert"),
+        )]));
+        assert!(credentials.is_empty());
+    }
+
+    #[test]
+    fn test_write_pki_dir() {
+        assert!(OpcuaCredentials::default()
+            .write_pki_dir()
+            .unwrap()
+            .is_none());
+        let credentials = OpcuaCredentials::new(&HashMap::from([
+            (OPCUA_CERTIFICATE.to_string(), byte_data("cert")),
+            (OPCUA_PRIVATE_KEY.to_string(), byte_data("key")),
+        ]));
+        let pki_dir = credentials.write_pki_dir().unwrap().unwrap();
+        let mode =
+            |path: &std::path::Path| std::fs::metadata(path).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode(pki_dir.path()), 0o700);
+        assert_eq!(
+            std::fs::read(pki_dir.path().join(PKI_CERTIFICATE_PATH)).unwrap(),
+            b"cert"
+        );
+        assert_eq!(
+            std::fs::read(pki_dir.path().join(PKI_PRIVATE_KEY_PATH)).unwrap(),
+            b"key"
+        );
+        assert_eq!(mode(&pki_dir.path().join(PKI_PRIVATE_KEY_PATH)), 0o600);
+        let path = pki_dir.path().to_path_buf();
+        drop(pki_dir);
+        assert!(!path.exists());
+    }
+}
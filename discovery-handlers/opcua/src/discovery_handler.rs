@@ -1,36 +1,47 @@
-use super::{discovery_impl::do_standard_discovery, OPCUA_DISCOVERY_URL_LABEL};
+use super::{
+    credentials::OpcuaCredentials,
+    discovery_impl::{
+        do_scan_discovery, do_standard_discovery, DiscoveredServer, EndpointCriteria,
+    },
+};
 use akri_discovery_utils::{
     discovery::{
-        discovery_handler::{deserialize_discovery_details, DISCOVERED_DEVICES_CHANNEL_CAPACITY},
-        v0::{
-            discovery_handler_server::DiscoveryHandler, Device, DiscoverRequest, DiscoverResponse,
+        control::{next_update, DiscoveryUpdate, UpdatableDiscoveryHandler},
+        delta::DeltaEncoder,
+        discovery_handler::{
+            deserialize_discovery_details, get_discovery_interval, get_discovery_timeout,
+            run_blocking_scan, DISCOVERED_DEVICES_CHANNEL_CAPACITY,
         },
+        v0::{discovery_handler_server::DiscoveryHandler, ByteData, Device, DiscoverRequest},
         DiscoverStream,
     },
     filtering::FilterList,
 };
 use async_trait::async_trait;
 use log::{error, info, trace};
-use std::time::Duration;
+use opcua::core::constants::DEFAULT_OPC_UA_SERVER_PORT;
+use schemars::JsonSchema;
+use std::{collections::HashMap, sync::Arc, time::Duration};
 use tokio::sync::mpsc;
-use tokio::time::sleep;
+use tokio::time::{sleep_until, Instant};
 use tonic::{Response, Status};
 
-// TODO: make this configurable
+/// Default number of seconds between two discovery scans, used if the Configuration does not set
+/// `discoveryIntervalSeconds`
 pub const DISCOVERY_INTERVAL_SECS: u64 = 10;
 
 /// Methods for discovering OPC UA Servers
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug)]
 #[serde(rename_all = "camelCase")]
 pub enum OpcuaDiscoveryMethod {
     Standard(StandardOpcuaDiscovery),
-    // TODO: add scan
+    Scan(ScanOpcuaDiscovery),
 }
 
 /// Discovers OPC UA Servers and/or LocalDiscoveryServers at specified DiscoveryURLs.
 /// If the DiscoveryURL is for a LocalDiscoveryServer, it will discover all Servers
 /// that have registered with that LocalDiscoveryServer.
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct StandardOpcuaDiscovery {
     #[serde(default = "lds_discovery_url", skip_serializing_if = "Vec::is_empty")]
@@ -43,19 +54,58 @@ fn lds_discovery_url() -> Vec<String> {
     vec!["opc.tcp://localhost:4840/".to_string()]
 }
 
+/// Discovers OPC UA Servers without a LocalDiscoveryServer, by probing every address of the
+/// given IP ranges on the given ports and calling FindServers on the responsive ones.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ScanOpcuaDiscovery {
+    /// CIDR networks (`10.0.0.0/24`), inclusive IP ranges (`10.0.0.10-10.0.0.20`)
+    /// or single IP addresses to scan
+    pub ip_ranges: Vec<String>,
+    /// Ports probed on every address, defaults to the OPC UA default port
+    #[serde(default = "default_scan_ports")]
+    pub ports: Vec<u16>,
+    /// Maximum number of addresses probed concurrently
+    #[serde(default = "default_scan_concurrency")]
+    pub max_concurrency: usize,
+    /// Timeout of the TCP connection to each address and port
+    #[serde(default = "default_scan_connect_timeout_millis")]
+    pub connect_timeout_millis: u64,
+}
+
+fn default_scan_ports() -> Vec<u16> {
+    vec![DEFAULT_OPC_UA_SERVER_PORT]
+}
+
+fn default_scan_concurrency() -> usize {
+    64
+}
+
+fn default_scan_connect_timeout_millis() -> u64 {
+    500
+}
+
 /// This defines the OPC UA data stored in the Configuration
 /// CRD
 ///
 /// The OPC UA discovery handler is designed to support multiple methods
-/// for discovering OPC UA servers and stores a filter list for
-/// application names.
-#[derive(Serialize, Deserialize, Clone, Debug)]
+/// for discovering OPC UA servers and stores filter lists for
+/// application names and endpoint security.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct OpcuaDiscoveryDetails {
+    // A singleton map has the same schema as the default representation of the enum
     #[serde(with = "serde_yaml::with::singleton_map")]
+    #[schemars(with = "OpcuaDiscoveryMethod")]
     pub opcua_discovery_method: OpcuaDiscoveryMethod,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub application_names: Option<FilterList>,
+    /// Filter on the SecurityPolicy of the servers' endpoints, such as `Basic256Sha256` or `None`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub security_policies: Option<FilterList>,
+    /// Filter on the MessageSecurityMode of the servers' endpoints, either `None`, `Sign` or `SignAndEncrypt`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message_security_modes: Option<FilterList>,
 }
 
 /// `DiscoveryHandlerImpl` discovers udev instances by parsing the udev rules in `discovery_handler_config.udev_rules`.
@@ -78,17 +128,72 @@ impl DiscoveryHandler for DiscoveryHandlerImpl {
         request: tonic::Request<DiscoverRequest>,
     ) -> Result<Response<Self::DiscoverStream>, Status> {
         info!("discover - called for OPC UA protocol");
+        self.start_discovery(request.into_inner(), None)
+            .map(Response::new)
+    }
+}
+
+#[async_trait]
+impl UpdatableDiscoveryHandler for DiscoveryHandlerImpl {
+    async fn discover_with_updates(
+        &self,
+        request: DiscoverRequest,
+        updates: mpsc::Receiver<DiscoveryUpdate>,
+    ) -> Result<DiscoverStream, Status> {
+        info!("discover_with_updates - called for OPC UA protocol");
+        self.start_discovery(request, Some(updates))
+    }
+}
+
+/// Builds the criteria the endpoints of the servers must meet, writing the application certificate
+/// of the credentials to a PKI directory
+fn get_endpoint_criteria(
+    discovery_handler_config: &OpcuaDiscoveryDetails,
+    discovery_properties: &HashMap<String, ByteData>,
+) -> std::io::Result<EndpointCriteria> {
+    let credentials = OpcuaCredentials::new(discovery_properties);
+    let pki_dir = credentials.write_pki_dir()?;
+    Ok(EndpointCriteria {
+        security_policies: discovery_handler_config.security_policies.clone(),
+        message_security_modes: discovery_handler_config.message_security_modes.clone(),
+        credentials,
+        pki_dir: pki_dir.map(Arc::new),
+    })
+}
+
+impl DiscoveryHandlerImpl {
+    /// Runs the discovery, applying the updates of the Agent to it when started over the v1 protocol.
+    /// An update or a rescan request starts a new scan right away.
+    fn start_discovery(
+        &self,
+        discover_request: DiscoverRequest,
+        mut updates: Option<mpsc::Receiver<DiscoveryUpdate>>,
+    ) -> Result<DiscoverStream, Status> {
         let register_sender = self.register_sender.clone();
-        let discover_request = request.get_ref();
         let (discovered_devices_sender, discovered_devices_receiver) =
             mpsc::channel(DISCOVERED_DEVICES_CHANNEL_CAPACITY);
-        let discovery_handler_config: OpcuaDiscoveryDetails =
+        let mut discovery_handler_config: OpcuaDiscoveryDetails =
             deserialize_discovery_details(&discover_request.discovery_details)
                 .map_err(|e| tonic::Status::new(tonic::Code::InvalidArgument, format!("{}", e)))?;
-        let mut previously_discovered_devices: Vec<Device> = Vec::new();
+        let discovery_interval = get_discovery_interval(
+            &discover_request,
+            Duration::from_secs(DISCOVERY_INTERVAL_SECS),
+        );
+        let discovery_timeout = get_discovery_timeout(&discover_request);
+        let mut discovery_properties = discover_request.discovery_properties.clone();
+        let mut criteria = get_endpoint_criteria(&discovery_handler_config, &discovery_properties)
+            .map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Internal,
+                    format!("unable to store the application certificate: {}", e),
+                )
+            })?;
+        // Servers can be numerous, only send the ones that changed if the Agent accepts it
+        let mut delta_encoder = DeltaEncoder::new(&discover_request);
         tokio::spawn(async move {
-            let discovery_method = discovery_handler_config.opcua_discovery_method.clone();
-            let application_names = discovery_handler_config.application_names.clone();
+            // A scan that timed out keeps running on its blocking thread as it cannot be
+            // cancelled, it is awaited again instead of starting a new one
+            let mut in_flight_discovery = None;
             loop {
                 // Before each iteration, check if receiver has dropped
                 if discovered_devices_sender.is_closed() {
@@ -99,73 +204,122 @@ impl DiscoveryHandler for DiscoveryHandlerImpl {
                     break;
                 }
 
-                let discovery_urls: Vec<String> = match discovery_method.clone() {
+                let discovery_method = discovery_handler_config.opcua_discovery_method.clone();
+                let application_names = discovery_handler_config.application_names.clone();
+                let scan_criteria = criteria.clone();
+                let scan = move || match discovery_method {
                     OpcuaDiscoveryMethod::Standard(standard_opcua_discovery) => {
-                        let discovery_urls = standard_opcua_discovery.discovery_urls.clone();
-                        let application_names = application_names.clone();
-                        tokio::task::spawn_blocking(move || {
-                            do_standard_discovery(discovery_urls, application_names)
-                        })
-                        .await
-                        .unwrap()
-                    } // No other discovery methods implemented yet
+                        do_standard_discovery(
+                            standard_opcua_discovery.discovery_urls,
+                            application_names,
+                            &scan_criteria,
+                        )
+                    }
+                    OpcuaDiscoveryMethod::Scan(scan_opcua_discovery) => {
+                        do_scan_discovery(scan_opcua_discovery, application_names, &scan_criteria)
+                    }
                 };
-
-                // Build DiscoveryResult for each server discovered
-                let discovered_devices = discovery_urls
-                    .into_iter()
-                    .map(|discovery_url| {
-                        let mut properties = std::collections::HashMap::new();
-                        trace!(
-                            "discover - found OPC UA server at DiscoveryURL {}",
-                            discovery_url
-                        );
-                        properties
-                            .insert(OPCUA_DISCOVERY_URL_LABEL.to_string(), discovery_url.clone());
-                        Device {
-                            id: discovery_url,
-                            properties,
-                            mounts: Vec::default(),
-                            device_specs: Vec::default(),
+                match run_blocking_scan(&mut in_flight_discovery, discovery_timeout, scan).await {
+                    Some(discovered_servers) => {
+                        // Build DiscoveryResult for each server discovered
+                        let discovered_devices = discovered_servers
+                            .into_iter()
+                            .map(|server: DiscoveredServer| {
+                                trace!(
+                                    "discover - found OPC UA server at DiscoveryURL {} with endpoint {:?}",
+                                    server.discovery_url,
+                                    server.endpoint_security
+                                );
+                                let properties = server.properties();
+                                Device {
+                                    id: server.discovery_url,
+                                    properties,
+                                    mounts: Vec::default(),
+                                    device_specs: Vec::default(),
+                                    health: None,
+                                }
+                            })
+                            .collect::<Vec<Device>>();
+                        if let Some(response) = delta_encoder.encode(discovered_devices) {
+                            trace!("discover - for OPC UA, sending updated device list");
+                            if let Err(e) = discovered_devices_sender.send(Ok(response)).await {
+                                error!(
+                                    "discover - for OPC UA failed to send discovery response with error {}",
+                                    e
+                                );
+                                if let Some(sender) = register_sender {
+                                    sender.send(()).await.unwrap();
+                                }
+                                break;
+                            }
                         }
-                    })
-                    .collect::<Vec<Device>>();
-                let mut changed_device_list = false;
-                let mut matching_device_count = 0;
-                discovered_devices.iter().for_each(|device| {
-                    if !previously_discovered_devices.contains(device) {
-                        changed_device_list = true;
-                    } else {
-                        matching_device_count += 1;
                     }
-                });
-                if changed_device_list
-                    || matching_device_count != previously_discovered_devices.len()
-                {
-                    trace!("discover - for OPC UA, sending updated device list");
-                    previously_discovered_devices.clone_from(&discovered_devices);
-                    if let Err(e) = discovered_devices_sender
-                        .send(Ok(DiscoverResponse {
-                            devices: discovered_devices,
-                        }))
-                        .await
-                    {
+                    None => {
+                        // Keep reporting the previously discovered servers until a scan completes in time
                         error!(
-                            "discover - for OPC UA failed to send discovery response with error {}",
-                            e
+                            "discover - for OPC UA, discovery did not complete within {:?}, waiting for it before starting a new one",
+                            discovery_timeout.unwrap_or_default()
                         );
-                        if let Some(sender) = register_sender {
-                            sender.send(()).await.unwrap();
-                        }
-                        break;
                     }
                 }
-                sleep(Duration::from_secs(DISCOVERY_INTERVAL_SECS)).await;
+
+                // Wait for the next scan, or for an update of the Agent that starts one right away
+                let next_scan = Instant::now() + discovery_interval;
+                loop {
+                    tokio::select! {
+                        _ = sleep_until(next_scan) => break,
+                        update = next_update(&mut updates) => match update {
+                            Some(DiscoveryUpdate::Details(details)) => {
+                                let config = match deserialize_discovery_details::<OpcuaDiscoveryDetails>(&details) {
+                                    Ok(config) => config,
+                                    Err(e) => {
+                                        error!(
+                                            "discover - ignoring invalid discovery details update: {}",
+                                            e
+                                        );
+                                        continue;
+                                    }
+                                };
+                                match get_endpoint_criteria(&config, &discovery_properties) {
+                                    Ok(updated) => {
+                                        criteria = updated;
+                                        discovery_handler_config = config;
+                                        break;
+                                    }
+                                    Err(e) => error!(
+                                        "discover - ignoring discovery details update, unable to store the application certificate: {}",
+                                        e
+                                    ),
+                                }
+                            }
+                            Some(DiscoveryUpdate::Properties(properties)) => {
+                                match get_endpoint_criteria(&discovery_handler_config, &properties) {
+                                    Ok(updated) => {
+                                        criteria = updated;
+                                        discovery_properties = properties;
+                                        break;
+                                    }
+                                    Err(e) => error!(
+                                        "discover - ignoring discovery properties update, unable to store the application certificate: {}",
+                                        e
+                                    ),
+                                }
+                            }
+                            // The Agent asks for an immediate scan
+                            Some(DiscoveryUpdate::Rescan) => break,
+                            None => {
+                                // The discovery got stopped, which also closes the devices channel
+                                trace!("discover - control channel closed");
+                                updates = None;
+                            }
+                        },
+                    }
+                }
             }
         });
-        Ok(Response::new(tokio_stream::wrappers::ReceiverStream::new(
+        Ok(tokio_stream::wrappers::ReceiverStream::new(
             discovered_devices_receiver,
-        )))
+        ))
     }
 }
 
@@ -204,4 +358,52 @@ mod tests {
         let expected_serialized = r#"{"opcuaDiscoveryMethod":{"standard":{"discoveryUrls":["opc.tcp://127.0.0.1:4855/"]}},"applicationNames":{"items":["Some application name"],"action":"Include"}}"#;
         assert_eq!(expected_serialized, serialized);
     }
+
+    #[test]
+    fn test_deserialize_discovery_details_scan() {
+        let yaml = r#"
+            opcuaDiscoveryMethod: 
+              scan:
+                ipRanges:
+                - 10.0.0.0/24
+        "#;
+        let dh_config: OpcuaDiscoveryDetails = deserialize_discovery_details(yaml).unwrap();
+        let serialized = serde_json::to_string(&dh_config).unwrap();
+        let expected_serialized = r#"{"opcuaDiscoveryMethod":{"scan":{"ipRanges":["10.0.0.0/24"],"ports":[4840],"maxConcurrency":64,"connectTimeoutMillis":500}}}"#;
+        assert_eq!(expected_serialized, serialized);
+
+        let yaml = r#"
+            opcuaDiscoveryMethod: 
+              scan:
+                ipRanges:
+                - 10.0.0.10-10.0.0.20
+                - 192.168.1.5
+                ports: [4840, 4855]
+                maxConcurrency: 8
+                connectTimeoutMillis: 200
+        "#;
+        let dh_config: OpcuaDiscoveryDetails = deserialize_discovery_details(yaml).unwrap();
+        let serialized = serde_json::to_string(&dh_config).unwrap();
+        let expected_serialized = r#"{"opcuaDiscoveryMethod":{"scan":{"ipRanges":["10.0.0.10-10.0.0.20","192.168.1.5"],"ports":[4840,4855],"maxConcurrency":8,"connectTimeoutMillis":200}}}"#;
+        assert_eq!(expected_serialized, serialized);
+    }
+
+    #[test]
+    fn test_deserialize_discovery_details_security() {
+        let yaml = r#"
+            opcuaDiscoveryMethod: 
+              standard: {}
+            securityPolicies:
+              action: Exclude
+              items:
+              - None
+            messageSecurityModes:
+              items:
+              - SignAndEncrypt
+        "#;
+        let dh_config: OpcuaDiscoveryDetails = deserialize_discovery_details(yaml).unwrap();
+        let serialized = serde_json::to_string(&dh_config).unwrap();
+        let expected_serialized = r#"{"opcuaDiscoveryMethod":{"standard":{"discoveryUrls":["opc.tcp://localhost:4840/"]}},"securityPolicies":{"items":["None"],"action":"Exclude"},"messageSecurityModes":{"items":["SignAndEncrypt"],"action":"Include"}}"#;
+        assert_eq!(expected_serialized, serialized);
+    }
 }
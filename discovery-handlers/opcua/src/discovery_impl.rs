@@ -1,23 +1,104 @@
-use super::wrappers::{
-    opcua_client_wrapper::{create_opcua_discovery_client, OpcuaClient},
-    tcp_stream_wrapper::{TcpStream, TcpStreamImpl},
+use super::{
+    credentials::OpcuaCredentials,
+    discovery_handler::ScanOpcuaDiscovery,
+    wrappers::{
+        opcua_client_wrapper::{create_opcua_discovery_client, OpcuaClient},
+        tcp_stream_wrapper::{TcpStream, TcpStreamImpl},
+    },
+    OPCUA_DISCOVERY_URL_LABEL, OPCUA_ENDPOINT_URL_LABEL, OPCUA_MESSAGE_SECURITY_MODE_LABEL,
+    OPCUA_SECURITY_POLICY_LABEL,
 };
 use ::url::Url;
-use akri_discovery_utils::filtering::{should_include, FilterList};
+use akri_discovery_utils::filtering::{
+    ip_to_u128, parse_ip_range, should_include, u128_to_ip, FilterList,
+};
 use anyhow::Context;
-use log::{error, info, trace};
+use log::{error, info, trace, warn};
 use opcua::client::prelude::*;
 use opcua::core::constants::DEFAULT_OPC_UA_SERVER_PORT;
 use std::{
-    net::{SocketAddr, ToSocketAddrs},
+    collections::HashMap,
+    net::{IpAddr, SocketAddr, ToSocketAddrs},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
     time::Duration,
 };
+use tempfile::TempDir;
 
 /// Timeout for testing TCP connection to OPC UA Server or LDS DiscoveryEndpoint
 /// Used when testing TCP connection before calling FindServers on the endpoint
 const TCP_CONNECTION_TEST_TIMEOUT_SECS: u64 = 3;
 
-/// `standard` is the only `OpcuaDiscoveryMethod` currently implemented, which takes in a set of DiscoveryURLs and discovers all the servers at those DiscoveryURLs.
+/// Maximum number of addresses probed by a scan, bounding the scan of large networks
+const MAX_SCAN_ADDRESSES: usize = 65536;
+
+/// Criteria used to select the endpoint of each discovered server, and credentials used to authenticate to it
+#[derive(Clone, Default)]
+pub struct EndpointCriteria {
+    /// Filter on the name of the endpoints' SecurityPolicy, such as `Basic256Sha256` or `None`
+    pub security_policies: Option<FilterList>,
+    /// Filter on the endpoints' MessageSecurityMode, either `None`, `Sign` or `SignAndEncrypt`
+    pub message_security_modes: Option<FilterList>,
+    /// Credentials the discovery client must successfully authenticate with
+    pub credentials: OpcuaCredentials,
+    /// PKI directory holding the application certificate of the credentials, it is removed once
+    /// the discovery and all the scans using it are over
+    pub pki_dir: Option<Arc<TempDir>>,
+}
+
+impl EndpointCriteria {
+    /// Servers are only required to expose a suitable endpoint if security or credentials are configured
+    fn is_empty(&self) -> bool {
+        self.security_policies.is_none()
+            && self.message_security_modes.is_none()
+            && self.credentials.is_empty()
+    }
+}
+
+/// Security of the endpoint selected for a discovered server
+#[derive(Clone, Debug, PartialEq)]
+pub struct EndpointSecurity {
+    pub endpoint_url: String,
+    pub security_policy: String,
+    pub message_security_mode: String,
+}
+
+/// OPC UA server discovered by the discovery handler
+#[derive(Clone, Debug, PartialEq)]
+pub struct DiscoveredServer {
+    pub discovery_url: String,
+    /// Unknown if the server could not be queried for its endpoints
+    pub endpoint_security: Option<EndpointSecurity>,
+}
+
+impl DiscoveredServer {
+    /// Properties mounted into the broker pods
+    pub fn properties(&self) -> HashMap<String, String> {
+        let mut properties = HashMap::from([(
+            OPCUA_DISCOVERY_URL_LABEL.to_string(),
+            self.discovery_url.clone(),
+        )]);
+        if let Some(security) = &self.endpoint_security {
+            properties.insert(
+                OPCUA_ENDPOINT_URL_LABEL.to_string(),
+                security.endpoint_url.clone(),
+            );
+            properties.insert(
+                OPCUA_SECURITY_POLICY_LABEL.to_string(),
+                security.security_policy.clone(),
+            );
+            properties.insert(
+                OPCUA_MESSAGE_SECURITY_MODE_LABEL.to_string(),
+                security.message_security_mode.clone(),
+            );
+        }
+        properties
+    }
+}
+
+/// `standard` is an `OpcuaDiscoveryMethod` which takes in a set of DiscoveryURLs and discovers all the servers at those DiscoveryURLs.
 ///
 /// Every OPC UA server/application has a DiscoveryEndpoint that Clients can access without establishing a session.
 /// The address for this endpoint is defined by a DiscoveryURL.
@@ -26,23 +107,26 @@ const TCP_CONNECTION_TEST_TIMEOUT_SECS: u64 = 3;
 /// provides mechanisms for Clients to obtain this list" (OPC UA Specification 12). A LocalDiscoveryServer is an implementation
 /// of an OPC UA DiscoveryServer.
 /// `do_standard_discovery` creates an OPC UA Discovery Client and calls get_discovery_urls, passing in the DiscoveryURLs provided
-/// in the OPC UA Configuration.
+/// in the OPC UA Configuration, then selects the endpoint of each server matching the `EndpointCriteria`.
 pub fn do_standard_discovery(
     discovery_urls: Vec<String>,
     filter_list: Option<FilterList>,
-) -> Vec<String> {
+    criteria: &EndpointCriteria,
+) -> Vec<DiscoveredServer> {
     info!(
         "do_standard_discovery - for DiscoveryUrls {:?}",
         discovery_urls
     );
-    let mut discovery_handler_client = create_opcua_discovery_client();
+    let mut discovery_handler_client =
+        create_opcua_discovery_client(criteria.pki_dir.as_ref().map(|dir| dir.path()));
     let tcp_stream = TcpStreamImpl {};
-    get_discovery_urls(
+    let discovery_urls = get_discovery_urls(
         &mut discovery_handler_client,
         discovery_urls,
         filter_list,
         tcp_stream,
-    )
+    );
+    get_discovered_servers(&mut discovery_handler_client, discovery_urls, criteria)
 }
 
 /// This calls FindServers on each DiscoveryURL provided in order to
@@ -96,6 +180,246 @@ fn get_discovery_urls(
     discovery_urls
 }
 
+/// `scan` discovers OPC UA servers on networks without a LocalDiscoveryServer. It probes every address and port
+/// of the `ScanOpcuaDiscovery` with a TCP connection, then calls FindServers on the responsive ones. Servers that
+/// don't answer FindServers are queried with GetEndpoints. The servers are filtered with the `application_names` filter
+/// and the `EndpointCriteria`.
+pub fn do_scan_discovery(
+    scan: ScanOpcuaDiscovery,
+    filter_list: Option<FilterList>,
+    criteria: &EndpointCriteria,
+) -> Vec<DiscoveredServer> {
+    info!("do_scan_discovery - for IP ranges {:?}", scan.ip_ranges);
+    let mut discovery_handler_client =
+        create_opcua_discovery_client(criteria.pki_dir.as_ref().map(|dir| dir.path()));
+    let tcp_stream = TcpStreamImpl {};
+    let discovery_urls = scan_discovery_urls(
+        &mut discovery_handler_client,
+        &scan,
+        filter_list,
+        tcp_stream,
+    );
+    get_discovered_servers(&mut discovery_handler_client, discovery_urls, criteria)
+}
+
+fn scan_discovery_urls(
+    discovery_handler_client: &mut impl OpcuaClient,
+    scan: &ScanOpcuaDiscovery,
+    filter_list: Option<FilterList>,
+    tcp_stream: impl TcpStream + Sync,
+) -> Vec<String> {
+    let targets = get_scan_targets(&scan.ip_ranges, &scan.ports);
+    let responsive_targets = probe_targets(
+        &targets,
+        scan.max_concurrency,
+        Duration::from_millis(scan.connect_timeout_millis),
+        &tcp_stream,
+    );
+    trace!(
+        "scan_discovery_urls - {} of {} addresses are responsive",
+        responsive_targets.len(),
+        targets.len()
+    );
+    let mut discovery_urls: Vec<String> = responsive_targets
+        .into_iter()
+        .flat_map(|target| {
+            let url = format!("{}://{}/", OPC_TCP_SCHEME, target);
+            let servers = match discovery_handler_client.find_servers(&url) {
+                Ok(applications) => applications,
+                Err(err) => {
+                    trace!(
+                        "scan_discovery_urls - FindServers failed on {} with error {:?}, trying GetEndpoints",
+                        url,
+                        err
+                    );
+                    match discovery_handler_client.get_server_endpoints(&url) {
+                        Ok(endpoints) => endpoints.into_iter().map(|e| e.server).collect(),
+                        Err(err) => {
+                            trace!(
+                                "scan_discovery_urls - GetEndpoints failed on {} with error {:?}",
+                                url,
+                                err
+                            );
+                            vec![]
+                        }
+                    }
+                }
+            };
+            servers
+                .iter()
+                .filter_map(|server| {
+                    get_discovery_url_from_application_description(
+                        server,
+                        filter_list.as_ref(),
+                        &url,
+                    )
+                })
+                .collect::<Vec<String>>()
+        })
+        .collect();
+    // A server exposing several endpoints or registered with several discovery urls is reported once
+    discovery_urls.sort();
+    discovery_urls.dedup();
+    discovery_urls
+}
+
+/// Selects the endpoint of every server with GetEndpoints, and authenticates to it if credentials are configured.
+/// Servers without an endpoint matching the `EndpointCriteria` or rejecting the credentials are filtered out.
+fn get_discovered_servers(
+    discovery_handler_client: &mut impl OpcuaClient,
+    discovery_urls: Vec<String>,
+    criteria: &EndpointCriteria,
+) -> Vec<DiscoveredServer> {
+    discovery_urls
+        .into_iter()
+        .filter_map(|discovery_url| {
+            let endpoints = match discovery_handler_client.get_server_endpoints(&discovery_url) {
+                Ok(endpoints) => endpoints,
+                Err(err) => {
+                    trace!(
+                        "get_discovered_servers - GetEndpoints failed on {} with error {:?}",
+                        discovery_url,
+                        err
+                    );
+                    // Without security requirements, the server is reported as before
+                    return criteria.is_empty().then_some(DiscoveredServer {
+                        discovery_url,
+                        endpoint_security: None,
+                    });
+                }
+            };
+            let Some(endpoint) = select_endpoint(&endpoints, criteria) else {
+                trace!(
+                    "get_discovered_servers - Server at {} has no endpoint matching the security filters",
+                    discovery_url
+                );
+                return criteria.is_empty().then_some(DiscoveredServer {
+                    discovery_url,
+                    endpoint_security: None,
+                });
+            };
+            if !criteria.credentials.is_empty() {
+                if let Err(err) = discovery_handler_client
+                    .connect_to_endpoint(endpoint, criteria.credentials.identity_token())
+                {
+                    error!(
+                        "get_discovered_servers - failed to authenticate to endpoint {} with error {:?}",
+                        endpoint.endpoint_url, err
+                    );
+                    return None;
+                }
+            }
+            Some(DiscoveredServer {
+                discovery_url,
+                endpoint_security: Some(EndpointSecurity {
+                    endpoint_url: endpoint.endpoint_url.to_string(),
+                    security_policy: get_security_policy_name(endpoint).to_string(),
+                    message_security_mode: format!("{:?}", endpoint.security_mode),
+                }),
+            })
+        })
+        .collect()
+}
+
+/// Selects the most secure endpoint, according to the security level advertised by the server, that passes
+/// the SecurityPolicy and MessageSecurityMode filters and accepts the user identity of the credentials.
+/// Without any criteria, the endpoints are not filtered.
+fn select_endpoint<'a>(
+    endpoints: &'a [EndpointDescription],
+    criteria: &EndpointCriteria,
+) -> Option<&'a EndpointDescription> {
+    let user_token_type = criteria.credentials.user_token_type();
+    endpoints
+        .iter()
+        .filter(|endpoint| {
+            should_include(
+                criteria.security_policies.as_ref(),
+                get_security_policy_name(endpoint),
+            ) && should_include(
+                criteria.message_security_modes.as_ref(),
+                &format!("{:?}", endpoint.security_mode),
+            ) && (criteria.is_empty()
+                || endpoint.user_identity_tokens.as_ref().map_or(
+                    user_token_type == UserTokenType::Anonymous,
+                    |tokens| {
+                        tokens
+                            .iter()
+                            .any(|token| token.token_type == user_token_type)
+                    },
+                ))
+        })
+        .max_by_key(|endpoint| endpoint.security_level)
+}
+
+/// SecurityPolicy URIs are in the form `http://opcfoundation.org/UA/SecurityPolicy#Basic256Sha256`
+fn get_security_policy_name(endpoint: &EndpointDescription) -> &str {
+    let uri = endpoint.security_policy_uri.as_ref();
+    uri.rsplit_once('#').map_or(uri, |(_, name)| name)
+}
+
+/// Lists the socket addresses to probe from the IP ranges and ports to scan.
+/// Invalid ranges are skipped, and the number of addresses is capped to `MAX_SCAN_ADDRESSES`.
+fn get_scan_targets(ip_ranges: &[String], ports: &[u16]) -> Vec<SocketAddr> {
+    let mut addresses: Vec<IpAddr> = Vec::new();
+    for ip_range in ip_ranges {
+        match parse_ip_range(ip_range) {
+            Some((start, end)) => {
+                let ipv4 = start.is_ipv4();
+                let (mut start, mut end) = (ip_to_u128(start), ip_to_u128(end));
+                // The network and broadcast addresses of IPv4 networks are not scanned
+                if ipv4 && ip_range.contains('/') && end - start > 1 {
+                    start += 1;
+                    end -= 1;
+                }
+                let remaining = MAX_SCAN_ADDRESSES.saturating_sub(addresses.len()) as u128;
+                if end - start >= remaining {
+                    warn!(
+                        "get_scan_targets - only the first {} addresses are scanned",
+                        MAX_SCAN_ADDRESSES
+                    );
+                }
+                let end = end.min(start + remaining.saturating_sub(1));
+                if remaining > 0 {
+                    addresses.extend((start..=end).map(|ip| u128_to_ip(ip, ipv4)));
+                }
+            }
+            None => error!("get_scan_targets - invalid IP range {}", ip_range),
+        }
+    }
+    addresses.sort();
+    addresses.dedup();
+    addresses
+        .into_iter()
+        .flat_map(|ip| ports.iter().map(move |port| SocketAddr::new(ip, *port)))
+        .collect()
+}
+
+/// Tests the TCP connection to every target, with at most `max_concurrency` connections in flight,
+/// and returns the responsive targets.
+fn probe_targets(
+    targets: &[SocketAddr],
+    max_concurrency: usize,
+    timeout: Duration,
+    tcp_stream: &(impl TcpStream + Sync),
+) -> Vec<SocketAddr> {
+    let next_target = AtomicUsize::new(0);
+    let responsive_targets = Mutex::new(Vec::new());
+    std::thread::scope(|scope| {
+        for _ in 0..max_concurrency.clamp(1, targets.len().max(1)) {
+            scope.spawn(|| {
+                while let Some(target) = targets.get(next_target.fetch_add(1, Ordering::Relaxed)) {
+                    if tcp_stream.connect_timeout(target, timeout).is_ok() {
+                        responsive_targets.lock().unwrap().push(*target);
+                    }
+                }
+            });
+        }
+    });
+    let mut responsive_targets = responsive_targets.into_inner().unwrap();
+    responsive_targets.sort();
+    responsive_targets
+}
+
 /// The Rust OPC UA implementation of FindServers does not use a timeout when connecting with a Server over TCP
 /// So, an unsuccessful attempt can take over 2 minutes.
 /// Therefore, this tests the connection using a timeout before calling FindServers on the DiscoveryURL.
@@ -241,6 +565,7 @@ mod tests {
     };
     use super::*;
     use mockall::Sequence;
+    use std::net::Ipv4Addr;
 
     pub fn create_application_description(
         application_uri: &str,
@@ -259,6 +584,22 @@ mod tests {
         }
     }
 
+    pub fn create_endpoint_description(
+        server: ApplicationDescription,
+        security_mode: MessageSecurityMode,
+    ) -> EndpointDescription {
+        EndpointDescription {
+            endpoint_url: UAString::from(""),
+            server,
+            server_certificate: ByteString::null(),
+            security_mode,
+            security_policy_uri: UAString::from(""),
+            user_identity_tokens: None,
+            transport_profile_uri: UAString::from(""),
+            security_level: 0,
+        }
+    }
+
     fn set_up_mock_tcp_stream(
         discovery_url: &'static str,
         discovery_url2: &'static str,
@@ -496,4 +837,347 @@ mod tests {
             "opc.tcp://192.168.0.2:50000/OPCUA/Simluation"
         );
     }
+
+    fn scan(ip_ranges: Vec<&str>, ports: Vec<u16>) -> ScanOpcuaDiscovery {
+        ScanOpcuaDiscovery {
+            ip_ranges: ip_ranges.into_iter().map(|r| r.to_string()).collect(),
+            ports,
+            max_concurrency: 4,
+            connect_timeout_millis: 100,
+        }
+    }
+
+    #[test]
+    fn test_get_scan_targets() {
+        let targets = get_scan_targets(
+            &[
+                "10.0.0.1-10.0.0.2".to_string(),
+                "10.0.0.2".to_string(),
+                "invalid".to_string(),
+                "fd00::1".to_string(),
+            ],
+            &[4840, 4855],
+        );
+        assert_eq!(
+            targets,
+            vec![
+                "10.0.0.1:4840".parse::<SocketAddr>().unwrap(),
+                "10.0.0.1:4855".parse().unwrap(),
+                "10.0.0.2:4840".parse().unwrap(),
+                "10.0.0.2:4855".parse().unwrap(),
+                "[fd00::1]:4840".parse().unwrap(),
+                "[fd00::1]:4855".parse().unwrap(),
+            ]
+        );
+
+        // Large networks are capped
+        let targets = get_scan_targets(&["10.0.0.0/8".to_string()], &[4840]);
+        assert_eq!(targets.len(), MAX_SCAN_ADDRESSES);
+        assert_eq!(targets[0], "10.0.0.1:4840".parse().unwrap());
+    }
+
+    #[test]
+    fn test_probe_targets() {
+        let targets: Vec<SocketAddr> = (1..=20)
+            .map(|i| format!("10.0.0.{}:4840", i).parse().unwrap())
+            .collect();
+        let mut mock_tcp_stream = MockTcpStream::new();
+        mock_tcp_stream
+            .expect_connect_timeout()
+            .times(20)
+            .withf(|_, timeout: &Duration| timeout == &Duration::from_millis(100))
+            .returning(|addr, _| match addr.ip().to_string().as_str() {
+                "10.0.0.3" | "10.0.0.17" => Ok(()),
+                _ => Err(std::io::ErrorKind::TimedOut.into()),
+            });
+        assert_eq!(
+            probe_targets(&targets, 4, Duration::from_millis(100), &mock_tcp_stream),
+            vec![
+                "10.0.0.3:4840".parse::<SocketAddr>().unwrap(),
+                "10.0.0.17:4840".parse().unwrap()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_probe_targets_local_server() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let open_port = listener.local_addr().unwrap().port();
+        let closed_port = {
+            let closed = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            closed.local_addr().unwrap().port()
+        };
+        let targets = get_scan_targets(&["127.0.0.1".to_string()], &[open_port, closed_port]);
+        assert_eq!(
+            probe_targets(&targets, 2, Duration::from_millis(500), &TcpStreamImpl {}),
+            vec![SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), open_port)]
+        );
+    }
+
+    #[test]
+    fn test_scan_discovery_urls() {
+        let mut mock_client = MockOpcuaClient::new();
+        let mut mock_tcp_stream = MockTcpStream::new();
+        mock_tcp_stream
+            .expect_connect_timeout()
+            .times(4)
+            .returning(|addr, _| match addr.ip().to_string().as_str() {
+                "10.0.0.1" | "10.0.0.2" | "10.0.0.3" => Ok(()),
+                _ => Err(std::io::ErrorKind::ConnectionRefused.into()),
+            });
+        mock_client
+            .expect_find_servers()
+            .times(3)
+            .returning(|url| match url {
+                "opc.tcp://10.0.0.1:4840/" => Ok(vec![create_application_description(
+                    "urn:Server1",
+                    "Server 1",
+                    ApplicationType::Server,
+                    "opc.tcp://10.0.0.1:4840/",
+                )]),
+                "opc.tcp://10.0.0.2:4840/" => Ok(vec![create_application_description(
+                    "urn:Filtered",
+                    "Filtered server",
+                    ApplicationType::Server,
+                    "opc.tcp://10.0.0.2:4840/",
+                )]),
+                _ => Err(StatusCode::BadServiceUnsupported),
+            });
+        mock_client
+            .expect_get_server_endpoints()
+            .times(1)
+            .withf(|url: &str| url == "opc.tcp://10.0.0.3:4840/")
+            .returning(|_| {
+                let server = create_application_description(
+                    "urn:Server3",
+                    "Server 3",
+                    ApplicationType::Server,
+                    "opc.tcp://10.0.0.3:4840/",
+                );
+                // A server exposes an endpoint per security policy
+                Ok(vec![
+                    create_endpoint_description(server.clone(), MessageSecurityMode::None),
+                    create_endpoint_description(server, MessageSecurityMode::SignAndEncrypt),
+                ])
+            });
+        let filter_list = Some(FilterList {
+            items: vec!["filtered*".to_string()],
+            action: akri_discovery_utils::filtering::FilterType::Exclude,
+            item_kind: akri_discovery_utils::filtering::FilterItemKind::Glob,
+        });
+
+        let discovery_urls = scan_discovery_urls(
+            &mut mock_client,
+            &scan(vec!["10.0.0.1-10.0.0.4"], vec![4840]),
+            filter_list,
+            mock_tcp_stream,
+        );
+        assert_eq!(
+            discovery_urls,
+            vec![
+                "opc.tcp://10.0.0.1:4840/".to_string(),
+                "opc.tcp://10.0.0.3:4840/".to_string()
+            ]
+        );
+    }
+
+    fn create_secure_endpoint_description(
+        security_policy: &str,
+        security_mode: MessageSecurityMode,
+        security_level: u8,
+        user_token_types: Vec<UserTokenType>,
+    ) -> EndpointDescription {
+        let server = create_application_description(
+            "urn:Server",
+            "Server",
+            ApplicationType::Server,
+            "opc.tcp://127.0.0.1:4855/",
+        );
+        EndpointDescription {
+            endpoint_url: UAString::from(format!(
+                "opc.tcp://127.0.0.1:4855/{}/{:?}",
+                security_policy, security_mode
+            )),
+            security_policy_uri: UAString::from(format!(
+                "http://opcfoundation.org/UA/SecurityPolicy#{}",
+                security_policy
+            )),
+            security_level,
+            user_identity_tokens: Some(
+                user_token_types
+                    .into_iter()
+                    .map(|token_type| UserTokenPolicy {
+                        policy_id: UAString::from(format!("{:?}", token_type)),
+                        token_type,
+                        issued_token_type: UAString::null(),
+                        issuer_endpoint_url: UAString::null(),
+                        security_policy_uri: UAString::null(),
+                    })
+                    .collect(),
+            ),
+            ..create_endpoint_description(server, security_mode)
+        }
+    }
+
+    fn create_endpoints() -> Vec<EndpointDescription> {
+        vec![
+            create_secure_endpoint_description(
+                "None",
+                MessageSecurityMode::None,
+                0,
+                vec![UserTokenType::Anonymous],
+            ),
+            create_secure_endpoint_description(
+                "Basic256Sha256",
+                MessageSecurityMode::Sign,
+                5,
+                vec![UserTokenType::Anonymous, UserTokenType::UserName],
+            ),
+            create_secure_endpoint_description(
+                "Basic256Sha256",
+                MessageSecurityMode::SignAndEncrypt,
+                10,
+                vec![UserTokenType::Certificate],
+            ),
+        ]
+    }
+
+    fn user_credentials() -> OpcuaCredentials {
+        OpcuaCredentials::new(&HashMap::from([(
+            crate::credentials::OPCUA_USERNAME.to_string(),
+            akri_discovery_utils::discovery::v0::ByteData {
+                vec: Some(b"user".to_vec()),
+            },
+        )]))
+    }
+
+    #[test]
+    fn test_select_endpoint() {
+        let endpoints = create_endpoints();
+        let select = |criteria: EndpointCriteria| {
+            select_endpoint(&endpoints, &criteria).map(|e| e.endpoint_url.to_string())
+        };
+        // The most secure endpoint is selected by default, whatever the user identities it accepts
+        assert_eq!(
+            select(EndpointCriteria::default()),
+            Some("opc.tcp://127.0.0.1:4855/Basic256Sha256/SignAndEncrypt".to_string())
+        );
+        // The endpoint must accept anonymous users once a security criteria is configured
+        assert_eq!(
+            select(EndpointCriteria {
+                security_policies: Some(FilterList {
+                    items: vec!["Basic256Sha256".to_string()],
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }),
+            Some("opc.tcp://127.0.0.1:4855/Basic256Sha256/Sign".to_string())
+        );
+        assert_eq!(
+            select(EndpointCriteria {
+                security_policies: Some(FilterList {
+                    items: vec!["basic256sha256".to_string()],
+                    action: akri_discovery_utils::filtering::FilterType::Exclude,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }),
+            Some("opc.tcp://127.0.0.1:4855/None/None".to_string())
+        );
+        assert_eq!(
+            select(EndpointCriteria {
+                message_security_modes: Some(FilterList {
+                    items: vec!["SignAndEncrypt".to_string()],
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }),
+            None
+        );
+        assert_eq!(
+            select(EndpointCriteria {
+                credentials: user_credentials(),
+                ..Default::default()
+            }),
+            Some("opc.tcp://127.0.0.1:4855/Basic256Sha256/Sign".to_string())
+        );
+    }
+
+    #[test]
+    fn test_get_discovered_servers() {
+        let mut mock_client = MockOpcuaClient::new();
+        mock_client
+            .expect_get_server_endpoints()
+            .returning(|url| match url {
+                "opc.tcp://127.0.0.1:4855/" | "opc.tcp://127.0.0.1:4866/" => Ok(create_endpoints()),
+                _ => Err(StatusCode::BadTimeout),
+            });
+        let discovery_urls = vec![
+            "opc.tcp://127.0.0.1:4855/".to_string(),
+            "opc.tcp://127.0.0.1:4866/".to_string(),
+            "opc.tcp://127.0.0.1:4877/".to_string(),
+        ];
+
+        // Without security requirements, servers that don't return their endpoints are kept
+        let servers = get_discovered_servers(
+            &mut mock_client,
+            discovery_urls.clone(),
+            &EndpointCriteria::default(),
+        );
+        assert_eq!(servers.len(), 3);
+        assert_eq!(
+            servers[0].properties(),
+            HashMap::from([
+                (
+                    OPCUA_DISCOVERY_URL_LABEL.to_string(),
+                    "opc.tcp://127.0.0.1:4855/".to_string()
+                ),
+                (
+                    OPCUA_ENDPOINT_URL_LABEL.to_string(),
+                    "opc.tcp://127.0.0.1:4855/Basic256Sha256/SignAndEncrypt".to_string()
+                ),
+                (
+                    OPCUA_SECURITY_POLICY_LABEL.to_string(),
+                    "Basic256Sha256".to_string()
+                ),
+                (
+                    OPCUA_MESSAGE_SECURITY_MODE_LABEL.to_string(),
+                    "SignAndEncrypt".to_string()
+                ),
+            ])
+        );
+        assert_eq!(servers[2].endpoint_security, None);
+
+        // With credentials, servers rejecting them are filtered out
+        let mut connect_seq = Sequence::new();
+        mock_client
+            .expect_connect_to_endpoint()
+            .times(1)
+            .withf(|endpoint, identity_token| {
+                endpoint.security_mode == MessageSecurityMode::Sign
+                    && matches!(identity_token, IdentityToken::UserName(..))
+            })
+            .return_once(|_, _| Ok(()))
+            .in_sequence(&mut connect_seq);
+        mock_client
+            .expect_connect_to_endpoint()
+            .times(1)
+            .return_once(|_, _| Err(StatusCode::BadUserAccessDenied))
+            .in_sequence(&mut connect_seq);
+        let servers = get_discovered_servers(
+            &mut mock_client,
+            discovery_urls,
+            &EndpointCriteria {
+                credentials: user_credentials(),
+                ..Default::default()
+            },
+        );
+        assert_eq!(
+            servers
+                .iter()
+                .map(|s| s.discovery_url.as_str())
+                .collect::<Vec<_>>(),
+            vec!["opc.tcp://127.0.0.1:4855/"]
+        );
+    }
 }
@@ -1,14 +1,35 @@
 #[macro_use]
 extern crate serde_derive;
 
+mod credentials;
 pub mod discovery_handler;
 mod discovery_impl;
 mod wrappers;
 
+use akri_discovery_utils::discovery::discovery_handler::{
+    DiscoveryHandlerCapabilities, DiscoveryHandlerFeature,
+};
+
 /// Name of the environment variable that will be mounted into the OPC UA broker pods.
 /// Holds the DiscoveryURL for the OPC UA Server the broker is to connect to.
 pub const OPCUA_DISCOVERY_URL_LABEL: &str = "OPCUA_DISCOVERY_URL";
+/// Name of the environment variable that will be mounted into the OPC UA broker pods.
+/// Holds the EndpointURL of the endpoint selected by discovery, according to the security filters.
+pub const OPCUA_ENDPOINT_URL_LABEL: &str = "OPCUA_ENDPOINT_URL";
+/// Name of the environment variable that will be mounted into the OPC UA broker pods.
+/// Holds the SecurityPolicy of the selected endpoint, such as `Basic256Sha256` or `None`.
+pub const OPCUA_SECURITY_POLICY_LABEL: &str = "OPCUA_SECURITY_POLICY";
+/// Name of the environment variable that will be mounted into the OPC UA broker pods.
+/// Holds the MessageSecurityMode of the selected endpoint, either `None`, `Sign` or `SignAndEncrypt`.
+pub const OPCUA_MESSAGE_SECURITY_MODE_LABEL: &str = "OPCUA_MESSAGE_SECURITY_MODE";
 /// Name that OPC UA discovery handlers use when registering with the Agent
 pub const DISCOVERY_HANDLER_NAME: &str = "opcua";
 /// Defines whether this discovery handler discovers local devices on nodes rather than ones visible to multiple nodes
 pub const SHARED: bool = true;
+
+/// Capabilities OPC UA discovery handlers advertise when registering with the Agent
+pub fn capabilities() -> DiscoveryHandlerCapabilities {
+    DiscoveryHandlerCapabilities::new(env!("CARGO_PKG_VERSION"))
+        .with_feature(DiscoveryHandlerFeature::DeltaUpdates)
+        .with_discovery_details_schema::<discovery_handler::OpcuaDiscoveryDetails>()
+}
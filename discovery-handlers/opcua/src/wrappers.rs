@@ -3,6 +3,7 @@ pub mod opcua_client_wrapper {
     #[cfg(test)]
     use mockall::{automock, predicate::*};
     use opcua::client::prelude::*;
+    use std::path::Path;
 
     #[cfg_attr(test, automock)]
     pub trait OpcuaClient {
@@ -10,6 +11,18 @@ pub mod opcua_client_wrapper {
             &mut self,
             discovery_endpoint_url: &str,
         ) -> Result<Vec<ApplicationDescription>, StatusCode>;
+
+        fn get_server_endpoints(
+            &mut self,
+            server_url: &str,
+        ) -> Result<Vec<EndpointDescription>, StatusCode>;
+
+        /// Establishes a session on the endpoint with the given user identity, then closes it
+        fn connect_to_endpoint(
+            &mut self,
+            endpoint: &EndpointDescription,
+            identity_token: IdentityToken,
+        ) -> Result<(), StatusCode>;
     }
 
     pub struct OpcuaClientImpl {
@@ -22,15 +35,20 @@ pub mod opcua_client_wrapper {
             application_uri: &str,
             create_sample_keypair: bool,
             session_retry_limit: i32,
+            pki_dir: Option<&Path>,
         ) -> Self {
+            let mut builder = ClientBuilder::new()
+                .application_name(application_name)
+                .application_uri(application_uri)
+                .create_sample_keypair(create_sample_keypair)
+                .session_retry_limit(session_retry_limit);
+            if let Some(pki_dir) = pki_dir {
+                // Discovery only checks that the servers accept the credentials of the client,
+                // the brokers are responsible for establishing trust in the servers
+                builder = builder.pki_dir(pki_dir).trust_server_certs(true);
+            }
             OpcuaClientImpl {
-                inner_opcua_client: ClientBuilder::new()
-                    .application_name(application_name)
-                    .application_uri(application_uri)
-                    .create_sample_keypair(create_sample_keypair)
-                    .session_retry_limit(session_retry_limit)
-                    .client()
-                    .unwrap(),
+                inner_opcua_client: builder.client().unwrap(),
             }
         }
     }
@@ -42,12 +60,33 @@ pub mod opcua_client_wrapper {
         ) -> Result<Vec<ApplicationDescription>, StatusCode> {
             self.inner_opcua_client.find_servers(discovery_endpoint_url)
         }
+
+        fn get_server_endpoints(
+            &mut self,
+            server_url: &str,
+        ) -> Result<Vec<EndpointDescription>, StatusCode> {
+            self.inner_opcua_client
+                .get_server_endpoints_from_url(server_url)
+        }
+
+        fn connect_to_endpoint(
+            &mut self,
+            endpoint: &EndpointDescription,
+            identity_token: IdentityToken,
+        ) -> Result<(), StatusCode> {
+            let session = self
+                .inner_opcua_client
+                .connect_to_endpoint(endpoint.clone(), identity_token)?;
+            session.write().disconnect();
+            Ok(())
+        }
     }
-    /// Returns an OPC UA Client that will only be used to connect to OPC UA Server and Local Discovery Servers' DiscoveryEndpoints
-    pub fn create_opcua_discovery_client() -> impl OpcuaClient {
-        // Automatically create a self-signed private key and public cert.
+    /// Returns an OPC UA Client that will only be used to connect to OPC UA Server and Local Discovery Servers' DiscoveryEndpoints.
+    /// If a PKI directory is given, the client uses the application certificate it holds to authenticate to the servers.
+    pub fn create_opcua_discovery_client(pki_dir: Option<&Path>) -> impl OpcuaClient {
+        // Automatically create a self-signed private key and public cert when no application certificate is provided.
         // This will not be used to authenticate as only public endpoints will be accessed by the discovery client.
-        let create_sample_keypair = true;
+        let create_sample_keypair = pki_dir.is_none();
         // Do not try to create a session again
         let session_retry_limit = 0;
         OpcuaClientImpl::new(
@@ -55,6 +94,7 @@ pub mod opcua_client_wrapper {
             "urn:DiscoveryHandlerClient",
             create_sample_keypair,
             session_retry_limit,
+            pki_dir,
         )
     }
 }
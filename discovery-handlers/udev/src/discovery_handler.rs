@@ -1,9 +1,19 @@
 use super::{
-    discovery_impl::{do_parse_and_find, insert_device_with_relatives, DeviceProperties},
-    wrappers::udev_enumerator,
+    discovery_impl::{
+        do_parse_and_find, do_parse_and_match, insert_device_with_relatives,
+        update_devpaths_with_event, DeviceProperties,
+    },
+    wrappers::{
+        udev_device::{get_devnode, get_devpath},
+        udev_enumerator,
+        udev_monitor::{self, DeviceEventKind},
+    },
 };
 use akri_discovery_utils::discovery::{
-    discovery_handler::{deserialize_discovery_details, DISCOVERED_DEVICES_CHANNEL_CAPACITY},
+    discovery_handler::{
+        deserialize_discovery_details, get_discovery_interval, get_discovery_timeout,
+        run_blocking_scan, DISCOVERED_DEVICES_CHANNEL_CAPACITY,
+    },
     v0::{
         discovery_handler_server::DiscoveryHandler, Device, DeviceSpec, DiscoverRequest,
         DiscoverResponse,
@@ -12,24 +22,45 @@ use akri_discovery_utils::discovery::{
 };
 use async_trait::async_trait;
 use log::{error, info, trace};
+use schemars::JsonSchema;
 use std::collections::{HashMap, HashSet};
 use std::time::Duration;
 use tokio::sync::mpsc;
-use tokio::time::sleep;
+use tokio::task::JoinHandle;
+use tokio::time::{sleep_until, Instant};
 use tonic::{Response, Status};
 
-// TODO: make this configurable
+/// Default number of seconds between two discovery scans, used if the Configuration does not set
+/// `discoveryIntervalSeconds`
 pub const DISCOVERY_INTERVAL_SECS: u64 = 10;
+/// Capacity of the channel carrying events from the udev monitor thread to the discovery loop
+pub const UDEV_EVENT_CHANNEL_CAPACITY: usize = 32;
 
 /// This defines the udev data stored in the Configuration
 /// CRD DiscoveryDetails
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct UdevDiscoveryDetails {
     pub udev_rules: Vec<String>,
 
     #[serde(default)]
     pub group_recursive: bool,
+
+    /// Whether to subscribe to udev events in order to report added and removed devices as soon as they
+    /// happen. Full scans are still run every discovery interval as a safety net.
+    #[serde(default)]
+    pub monitor: bool,
+}
+
+/// Devices that match the udev rules, grouped by the devpath of the device that represents them
+type Devpaths = HashMap<String, HashSet<DeviceProperties>>;
+
+/// A udev monitor event for a device, along with whether the device matches any of the udev rules
+#[derive(Debug)]
+struct UdevEvent {
+    kind: DeviceEventKind,
+    device: DeviceProperties,
+    is_match: bool,
 }
 
 /// `DiscoveryHandlerImpl` discovers udev instances by parsing the udev rules in `discovery_handler_config.udev_rules`.
@@ -58,9 +89,29 @@ impl DiscoveryHandler for DiscoveryHandlerImpl {
         let discovery_handler_config: UdevDiscoveryDetails =
             deserialize_discovery_details(&discover_request.discovery_details)
                 .map_err(|e| tonic::Status::new(tonic::Code::InvalidArgument, format!("{}", e)))?;
+        let discovery_interval = get_discovery_interval(
+            discover_request,
+            Duration::from_secs(DISCOVERY_INTERVAL_SECS),
+        );
+        let discovery_timeout = get_discovery_timeout(discover_request);
         let mut previously_discovered_devices: Vec<Device> = Vec::new();
         tokio::spawn(async move {
             let udev_rules = discovery_handler_config.udev_rules.clone();
+            let group_recursive = discovery_handler_config.group_recursive;
+            let mut udev_event_receiver = discovery_handler_config.monitor.then(|| {
+                let (udev_event_sender, udev_event_receiver) =
+                    mpsc::channel(UDEV_EVENT_CHANNEL_CAPACITY);
+                spawn_udev_monitor(udev_rules.clone(), udev_event_sender);
+                udev_event_receiver
+            });
+            let mut devpaths = Devpaths::new();
+            let mut in_flight_scan = None;
+            let find = {
+                let udev_rules = udev_rules.clone();
+                move || find_devpaths(&udev_rules, group_recursive)
+            };
+            rescan_devpaths(&mut devpaths, &mut in_flight_scan, discovery_timeout, find).await;
+            let mut next_scan = Instant::now() + discovery_interval;
             loop {
                 trace!("discover - for udev rules {:?}", udev_rules);
                 // Before each iteration, check if receiver has dropped
@@ -71,57 +122,11 @@ impl DiscoveryHandler for DiscoveryHandlerImpl {
                     }
                     break;
                 }
-                let mut devpaths: HashMap<String, HashSet<DeviceProperties>> = HashMap::new();
-                udev_rules.iter().for_each(|rule| {
-                    let enumerator = udev_enumerator::create_enumerator();
-                    let paths = do_parse_and_find(enumerator, rule).unwrap();
-                    for path in paths.into_iter() {
-                        if !discovery_handler_config.group_recursive {
-                            devpaths.insert(path.0.clone(), HashSet::from([path]));
-                        } else {
-                            insert_device_with_relatives(&mut devpaths, path);
-                        }
-                    }
-                });
                 trace!(
                     "discover - mapping and returning devices at devpaths {:?}",
                     devpaths
                 );
-                let discovered_devices = devpaths
-                    .into_iter()
-                    .map(|(id, paths)| {
-                        let mut properties = HashMap::new();
-                        let mut device_specs = Vec::new();
-                        for (i, (_, node)) in paths.into_iter().enumerate() {
-                            let property_suffix = discovery_handler_config
-                                .group_recursive
-                                .then(|| format!("_{}", i))
-                                .unwrap_or_default();
-                            if let Some(devnode) = node {
-                                properties.insert(
-                                    super::UDEV_DEVNODE_LABEL_ID.to_string() + &property_suffix,
-                                    devnode.clone(),
-                                );
-                                device_specs.push(DeviceSpec {
-                                    container_path: devnode.clone(),
-                                    host_path: devnode,
-                                    permissions: "rwm".to_string(),
-                                })
-                            }
-                        }
-
-                        //id is the sysfs path of the most top level device so we only need this one
-                        properties.insert(super::UDEV_DEVPATH_LABEL_ID.to_string(), id.clone());
-
-                        // TODO: use device spec
-                        Device {
-                            id,
-                            properties,
-                            mounts: Vec::default(),
-                            device_specs,
-                        }
-                    })
-                    .collect::<Vec<Device>>();
+                let discovered_devices = create_devices(devpaths.clone(), group_recursive);
                 let mut changed_device_list = false;
                 let mut matching_device_count = 0;
                 discovered_devices.iter().for_each(|device| {
@@ -139,6 +144,7 @@ impl DiscoveryHandler for DiscoveryHandlerImpl {
                     if let Err(e) = discovered_devices_sender
                         .send(Ok(DiscoverResponse {
                             devices: discovered_devices,
+                            delta: None,
                         }))
                         .await
                     {
@@ -152,7 +158,44 @@ impl DiscoveryHandler for DiscoveryHandlerImpl {
                         break;
                     }
                 }
-                sleep(Duration::from_secs(DISCOVERY_INTERVAL_SECS)).await;
+                // Wait for either a udev event, which only requires re-evaluating the affected device,
+                // or the next full scan of all devices
+                let udev_event = match udev_event_receiver.as_mut() {
+                    Some(receiver) => tokio::select! {
+                        Some(udev_event) = receiver.recv() => Some(udev_event),
+                        _ = sleep_until(next_scan) => None,
+                    },
+                    None => {
+                        sleep_until(next_scan).await;
+                        None
+                    }
+                };
+                match udev_event {
+                    Some(udev_event) => {
+                        trace!("discover - handling udev event {:?}", udev_event);
+                        update_devpaths_with_event(
+                            &mut devpaths,
+                            udev_event.kind,
+                            udev_event.device,
+                            udev_event.is_match,
+                            group_recursive,
+                        );
+                    }
+                    None => {
+                        let find = {
+                            let udev_rules = udev_rules.clone();
+                            move || find_devpaths(&udev_rules, group_recursive)
+                        };
+                        rescan_devpaths(
+                            &mut devpaths,
+                            &mut in_flight_scan,
+                            discovery_timeout,
+                            find,
+                        )
+                        .await;
+                        next_scan = Instant::now() + discovery_interval;
+                    }
+                }
             }
         });
         Ok(Response::new(tokio_stream::wrappers::ReceiverStream::new(
@@ -161,6 +204,125 @@ impl DiscoveryHandler for DiscoveryHandlerImpl {
     }
 }
 
+/// Runs a full scan with `find` on a blocking thread, waiting at most `timeout` for it to replace `devpaths`.
+/// If the scan times out, the previously found `devpaths` are kept and the scan is awaited again on the next call.
+async fn rescan_devpaths<F>(
+    devpaths: &mut Devpaths,
+    in_flight_scan: &mut Option<JoinHandle<Devpaths>>,
+    timeout: Option<Duration>,
+    find: F,
+) where
+    F: FnOnce() -> Devpaths + Send + 'static,
+{
+    match run_blocking_scan(in_flight_scan, timeout, find).await {
+        Some(found_devpaths) => *devpaths = found_devpaths,
+        None => error!(
+            "rescan_devpaths - scan did not complete within {:?}, keeping previously found devices",
+            timeout.unwrap_or_default()
+        ),
+    }
+}
+
+/// Scans all devices for ones that match the udev rules, returning them grouped by the devpath of the
+/// device that represents them.
+fn find_devpaths(udev_rules: &[String], group_recursive: bool) -> Devpaths {
+    let mut devpaths = Devpaths::new();
+    udev_rules.iter().for_each(|rule| {
+        let enumerator = udev_enumerator::create_enumerator();
+        let paths = do_parse_and_find(enumerator, rule).unwrap();
+        for path in paths.into_iter() {
+            if !group_recursive {
+                devpaths.insert(path.0.clone(), HashSet::from([path]));
+            } else {
+                insert_device_with_relatives(&mut devpaths, path);
+            }
+        }
+    });
+    devpaths
+}
+
+/// Creates a `Device` for each group of discovered devpaths
+fn create_devices(
+    devpaths: HashMap<String, HashSet<DeviceProperties>>,
+    group_recursive: bool,
+) -> Vec<Device> {
+    devpaths
+        .into_iter()
+        .map(|(id, paths)| {
+            let mut properties = HashMap::new();
+            let mut device_specs = Vec::new();
+            for (i, (_, node)) in paths.into_iter().enumerate() {
+                let property_suffix = group_recursive
+                    .then(|| format!("_{}", i))
+                    .unwrap_or_default();
+                if let Some(devnode) = node {
+                    properties.insert(
+                        super::UDEV_DEVNODE_LABEL_ID.to_string() + &property_suffix,
+                        devnode.clone(),
+                    );
+                    device_specs.push(DeviceSpec {
+                        container_path: devnode.clone(),
+                        host_path: devnode,
+                        permissions: "rwm".to_string(),
+                    })
+                }
+            }
+
+            //id is the sysfs path of the most top level device so we only need this one
+            properties.insert(super::UDEV_DEVPATH_LABEL_ID.to_string(), id.clone());
+
+            // TODO: use device spec
+            Device {
+                id,
+                properties,
+                mounts: Vec::default(),
+                device_specs,
+                health: None,
+            }
+        })
+        .collect::<Vec<Device>>()
+}
+
+/// Spawns a thread that listens for udev events and forwards them, along with whether the device matches
+/// any of the udev rules, until the receiving end of `udev_event_sender` is dropped.
+fn spawn_udev_monitor(udev_rules: Vec<String>, udev_event_sender: mpsc::Sender<UdevEvent>) {
+    std::thread::spawn(move || {
+        let result = udev_monitor::listen(
+            || !udev_event_sender.is_closed(),
+            |kind, device| {
+                let is_match = kind != DeviceEventKind::Remove
+                    && udev_rules.iter().any(|rule| {
+                        do_parse_and_match(&device, rule).unwrap_or_else(|e| {
+                            error!(
+                                "spawn_udev_monitor - failed to evaluate rule {} with error {}",
+                                rule, e
+                            );
+                            false
+                        })
+                    });
+                // udev devpaths are relative to /sys, like the ones returned by enumeration
+                let device_properties = (
+                    get_devpath(&device).to_str().unwrap().to_string(),
+                    get_devnode(&device).map(|devnode| devnode.to_str().unwrap().to_string()),
+                );
+                udev_event_sender
+                    .blocking_send(UdevEvent {
+                        kind,
+                        device: device_properties,
+                        is_match,
+                    })
+                    .is_ok()
+            },
+        );
+        if let Err(e) = result {
+            error!(
+                "spawn_udev_monitor - udev monitor failed with error {} ... falling back to periodic scans",
+                e
+            );
+        }
+    });
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -178,7 +340,7 @@ mod tests {
         let udev_dh_config: UdevDiscoveryDetails = deserialize_discovery_details(yaml).unwrap();
         assert!(udev_dh_config.udev_rules.is_empty());
         let serialized = serde_json::to_string(&udev_dh_config).unwrap();
-        let expected_deserialized = r#"{"udevRules":[],"groupRecursive":false}"#;
+        let expected_deserialized = r#"{"udevRules":[],"groupRecursive":false,"monitor":false}"#;
         assert_eq!(expected_deserialized, serialized);
     }
 
@@ -191,5 +353,57 @@ mod tests {
         let udev_dh_config: UdevDiscoveryDetails = deserialize_discovery_details(yaml).unwrap();
         assert_eq!(udev_dh_config.udev_rules.len(), 1);
         assert_eq!(&udev_dh_config.udev_rules[0], "KERNEL==\"video[0-9]*\"");
+        assert!(!udev_dh_config.monitor);
+    }
+
+    #[test]
+    fn test_deserialize_discovery_details_monitor() {
+        let yaml = r#"
+          udevRules:
+          - 'SUBSYSTEM=="video4linux"'
+          monitor: true
+        "#;
+        let udev_dh_config: UdevDiscoveryDetails = deserialize_discovery_details(yaml).unwrap();
+        assert!(udev_dh_config.monitor);
+        assert!(!udev_dh_config.group_recursive);
+    }
+
+    #[tokio::test]
+    async fn test_rescan_devpaths_timeout() {
+        let devpath = |id: &str| {
+            (
+                id.to_string(),
+                HashSet::from([(id.to_string(), Some(format!("/dev/{}", id)))]),
+            )
+        };
+        let mut devpaths = Devpaths::from([devpath("video0")]);
+        let mut in_flight_scan = None;
+        let (release_sender, release_receiver) = std::sync::mpsc::channel::<()>();
+        // The scan hangs until released, the previously found devices are kept meanwhile
+        let slow_find = move || {
+            release_receiver.recv().unwrap();
+            Devpaths::from([devpath("video1")])
+        };
+        rescan_devpaths(
+            &mut devpaths,
+            &mut in_flight_scan,
+            Some(Duration::from_millis(10)),
+            slow_find,
+        )
+        .await;
+        assert_eq!(devpaths, Devpaths::from([devpath("video0")]));
+        assert!(in_flight_scan.is_some());
+
+        // The next rescan waits for the one that timed out instead of starting another
+        release_sender.send(()).unwrap();
+        rescan_devpaths(
+            &mut devpaths,
+            &mut in_flight_scan,
+            Some(Duration::from_secs(5)),
+            || Devpaths::from([devpath("video2")]),
+        )
+        .await;
+        assert_eq!(devpaths, Devpaths::from([devpath("video1")]));
+        assert!(in_flight_scan.is_none());
     }
 }
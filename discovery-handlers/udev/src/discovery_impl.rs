@@ -1,4 +1,4 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 use super::wrappers::{
     udev_device::{
@@ -6,6 +6,7 @@ use super::wrappers::{
         get_subsystem, get_sysname, DeviceExt,
     },
     udev_enumerator::Enumerator,
+    udev_monitor::DeviceEventKind,
 };
 use log::{error, info, trace};
 use pest::iterators::Pair;
@@ -42,6 +43,18 @@ pub fn do_parse_and_find(
     Ok(devices)
 }
 
+/// This parses the udev rule into UdevFilters and checks whether a single device matches all of them.
+/// Used to re-evaluate a device reported by a udev monitor event without scanning all sys devices.
+pub fn do_parse_and_match(
+    device: &impl DeviceExt,
+    udev_rule_string: &str,
+) -> Result<bool, anyhow::Error> {
+    let udev_filters = parse_udev_rule(udev_rule_string)?;
+    Ok(udev_filters
+        .iter()
+        .all(|udev_filter| device_matches_udev_filter(device, udev_filter)))
+}
+
 /// This parses a udev rule and returns a list of UdevFilter objects that specify which devices to search for.
 /// This returns an error if the udev rule parameter does not fit the format specified in udev
 /// man pages/wiki and therefore does not match the grammar specified in udev_rule_grammar.pest
@@ -50,7 +63,7 @@ pub fn do_parse_and_find(
 /// Udev discovery is only interested in match operations ("==",  "!="), so all action ("=" , "+=" , "-=" , ":=") operations
 /// will be ignored.
 /// Udev discovery is only interested in match fields, so all action fields, such as TEST, are ignored
-fn parse_udev_rule(udev_rule_string: &str) -> Result<Vec<UdevFilter>, anyhow::Error> {
+pub fn parse_udev_rule(udev_rule_string: &str) -> Result<Vec<UdevFilter>, anyhow::Error> {
     info!(
         "parse_udev_rule - enter for udev rule string {}",
         udev_rule_string
@@ -392,6 +405,78 @@ fn filter_by_remaining_udev_filters(
     mutable_devices
 }
 
+/// Checks whether a single device satisfies a UdevFilter. Unlike `find_devices`, which delegates some
+/// filters to the Enumerator, this inspects every field on the device itself.
+fn device_matches_udev_filter(device: &impl DeviceExt, udev_filter: &UdevFilter) -> bool {
+    let value_regex = match Regex::new(&udev_filter.value) {
+        Ok(value_regex) => value_regex,
+        Err(e) => {
+            error!(
+                "device_matches_udev_filter - invalid value {} with error {}",
+                udev_filter.value, e
+            );
+            return false;
+        }
+    };
+    let is_equality = udev_filter.operation == Rule::equality;
+    let is_match = match udev_filter.field.as_rule() {
+        Rule::devpath => is_regex_match(get_devpath(device).to_str().unwrap(), &value_regex),
+        Rule::kernel => is_regex_match(get_sysname(device).to_str().unwrap(), &value_regex),
+        Rule::tag => match get_property_value(device, TAGS) {
+            Some(tags) => tags
+                .to_str()
+                .unwrap()
+                .split(':')
+                .any(|tag| is_regex_match(tag, &value_regex)),
+            None => false,
+        },
+        Rule::subsystem => match get_subsystem(device) {
+            Some(subsystem) => is_regex_match(subsystem.to_str().unwrap(), &value_regex),
+            None => false,
+        },
+        Rule::attribute => match get_attribute_value(device, get_udev_filter_key(udev_filter)) {
+            Some(attribute_value) => {
+                is_regex_match(attribute_value.to_str().unwrap(), &value_regex)
+            }
+            None => false,
+        },
+        Rule::property => match get_property_value(device, get_udev_filter_key(udev_filter)) {
+            Some(property_value) => is_regex_match(property_value.to_str().unwrap(), &value_regex),
+            None => false,
+        },
+        Rule::driver => match get_driver(device) {
+            Some(driver) => is_regex_match(driver.to_str().unwrap(), &value_regex),
+            None => false,
+        },
+        Rule::subsystems => device_or_parents_have_subsystem(device, &value_regex),
+        Rule::attributes => {
+            device_or_parents_have_attribute(device, get_udev_filter_key(udev_filter), &value_regex)
+        }
+        Rule::drivers => device_or_parents_have_driver(device, &value_regex),
+        Rule::kernels => device_or_parents_have_sysname(device, &value_regex),
+        Rule::tags => device_or_parents_have_tag(device, &value_regex),
+        _ => {
+            error!("device_matches_udev_filter - encountered unsupported field");
+            return false;
+        }
+    };
+    filter_equality_check(is_equality, is_match)
+}
+
+/// Get the key of a UdevFilter with a keyed field, such as ATTR{key} or ENV{key}
+fn get_udev_filter_key<'a>(udev_filter: &UdevFilter<'a>) -> &'a str {
+    udev_filter
+        .field
+        .clone()
+        .into_inner()
+        .next()
+        .unwrap()
+        .into_inner()
+        .next()
+        .unwrap()
+        .as_str()
+}
+
 /// Check whether the device should be selected based on equality and field matching
 fn filter_equality_check(is_equality: bool, is_match: bool) -> bool {
     (is_equality && is_match) || (!is_equality && !is_match)
@@ -553,12 +638,38 @@ pub fn insert_device_with_relatives(
     }
 }
 
+/// Applies a udev monitor event to the map of discovered devices. Devices that were added or changed and still
+/// match a udev rule are (re)inserted, while removed devices and devices that no longer match are dropped.
+pub fn update_devpaths_with_event(
+    devpaths: &mut HashMap<String, HashSet<DeviceProperties>>,
+    kind: DeviceEventKind,
+    device: DeviceProperties,
+    is_match: bool,
+    group_recursive: bool,
+) {
+    match kind {
+        DeviceEventKind::Add | DeviceEventKind::Change if is_match => {
+            if group_recursive {
+                insert_device_with_relatives(devpaths, device);
+            } else {
+                devpaths.insert(device.0.clone(), HashSet::from([device]));
+            }
+        }
+        _ => {
+            if devpaths.remove(&device.0).is_none() {
+                devpaths
+                    .values_mut()
+                    .for_each(|group| group.retain(|(devpath, _)| devpath != &device.0));
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod discovery_tests {
     use super::super::wrappers::udev_enumerator::{create_enumerator, MockEnumerator};
     use super::*;
     use std::{
-        collections::HashMap,
         ffi::OsStr,
         fs::File,
         io::{prelude::*, BufReader},
@@ -1323,6 +1434,147 @@ mod discovery_tests {
         assert_eq!(do_parse_and_find(mock, rule).unwrap().len(), 0);
     }
 
+    #[test]
+    fn test_do_parse_and_match() {
+        let rule = "KERNEL==\"video[0-9]*\", SUBSYSTEM==\"video4linux\", ATTR{idVendor}==\"05a9\", ENV{ID}!=\"id_num\", SUBSYSTEMS==\"usb\"";
+        let usb_parent = create_mock_device(
+            "/devices/usb0",
+            "",
+            "usb0",
+            HashMap::new(),
+            HashMap::new(),
+            None,
+            Some(OsStr::new("usb")),
+            None,
+        );
+        let mut attributes = HashMap::new();
+        attributes.insert("idVendor".to_string(), "05a9".to_string());
+        let matching_device = create_mock_device(
+            "/devices/usb0/video0",
+            "/dev/video0",
+            "video0",
+            HashMap::new(),
+            attributes.clone(),
+            None,
+            Some(OsStr::new("video4linux")),
+            Some(usb_parent.clone()),
+        );
+        assert!(do_parse_and_match(&matching_device, rule).unwrap());
+
+        // Excluded by ENV{ID}!="id_num"
+        let mut id_exclude_properties = HashMap::new();
+        id_exclude_properties.insert("ID".to_string(), "id_num".to_string());
+        let excluded_device = create_mock_device(
+            "/devices/usb0/video1",
+            "/dev/video1",
+            "video1",
+            id_exclude_properties,
+            attributes.clone(),
+            None,
+            Some(OsStr::new("video4linux")),
+            Some(usb_parent),
+        );
+        assert!(!do_parse_and_match(&excluded_device, rule).unwrap());
+
+        // No usb ancestor
+        let orphan_device = create_mock_device(
+            "/devices/video2",
+            "/dev/video2",
+            "video2",
+            HashMap::new(),
+            attributes,
+            None,
+            Some(OsStr::new("video4linux")),
+            None,
+        );
+        assert!(!do_parse_and_match(&orphan_device, rule).unwrap());
+
+        assert!(do_parse_and_match(&orphan_device, "KERNEL=\"video2\"").is_err());
+    }
+
+    #[test]
+    fn test_update_devpaths_with_event() {
+        let mut devpaths: HashMap<String, HashSet<DeviceProperties>> = HashMap::default();
+        let parent = ("/sys/device/parent".to_string(), None);
+        let child = (
+            "/sys/device/parent/child".to_string(),
+            Some("/dev/child".to_string()),
+        );
+
+        // Non matching devices are not added
+        update_devpaths_with_event(
+            &mut devpaths,
+            DeviceEventKind::Add,
+            parent.clone(),
+            false,
+            false,
+        );
+        assert!(devpaths.is_empty());
+
+        // Matching devices are added in their own group if not grouping recursively
+        update_devpaths_with_event(
+            &mut devpaths,
+            DeviceEventKind::Add,
+            parent.clone(),
+            true,
+            false,
+        );
+        update_devpaths_with_event(
+            &mut devpaths,
+            DeviceEventKind::Add,
+            child.clone(),
+            true,
+            false,
+        );
+        assert_eq!(devpaths.len(), 2);
+
+        // Devices that changed and no longer match are removed
+        update_devpaths_with_event(
+            &mut devpaths,
+            DeviceEventKind::Change,
+            child.clone(),
+            false,
+            false,
+        );
+        assert_eq!(
+            devpaths,
+            HashMap::from([(parent.0.clone(), HashSet::from([parent.clone()]))])
+        );
+
+        // Matching children are grouped with their parent if grouping recursively
+        update_devpaths_with_event(
+            &mut devpaths,
+            DeviceEventKind::Add,
+            child.clone(),
+            true,
+            true,
+        );
+        assert_eq!(
+            devpaths,
+            HashMap::from([(
+                parent.0.clone(),
+                HashSet::from([parent.clone(), child.clone()])
+            )])
+        );
+
+        // Removed children are removed from their group
+        update_devpaths_with_event(
+            &mut devpaths,
+            DeviceEventKind::Remove,
+            child.clone(),
+            true,
+            true,
+        );
+        assert_eq!(
+            devpaths,
+            HashMap::from([(parent.0.clone(), HashSet::from([parent.clone()]))])
+        );
+
+        // Removed group roots remove the whole group
+        update_devpaths_with_event(&mut devpaths, DeviceEventKind::Remove, parent, true, true);
+        assert!(devpaths.is_empty());
+    }
+
     #[test]
     fn test_get_device_relatives() {
         let device_path = "/devices/pci0/usb0/0-1/0-1.1";
@@ -9,6 +9,10 @@ pub mod discovery_handler;
 mod discovery_impl;
 mod wrappers;
 
+pub use discovery_impl::parse_udev_rule;
+
+use akri_discovery_utils::discovery::discovery_handler::DiscoveryHandlerCapabilities;
+
 /// Name of environment variable that is set in udev brokers. Contains devnode for udev device
 /// the broker should use.
 pub const UDEV_DEVNODE_LABEL_ID: &str = "UDEV_DEVNODE";
@@ -19,3 +23,9 @@ pub const UDEV_DEVPATH_LABEL_ID: &str = "UDEV_DEVPATH";
 pub const DISCOVERY_HANDLER_NAME: &str = "udev";
 /// Defines whether this discovery handler discovers local devices on nodes rather than ones visible to multiple nodes
 pub const SHARED: bool = false;
+
+/// Capabilities udev discovery handlers advertise when registering with the Agent
+pub fn capabilities() -> DiscoveryHandlerCapabilities {
+    DiscoveryHandlerCapabilities::new(env!("CARGO_PKG_VERSION"))
+        .with_discovery_details_schema::<discovery_handler::UdevDiscoveryDetails>()
+}
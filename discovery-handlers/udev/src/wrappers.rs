@@ -144,3 +144,62 @@ pub mod udev_enumerator {
         }
     }
 }
+
+pub mod udev_monitor {
+    extern crate udev;
+    use log::trace;
+    use std::os::unix::io::AsRawFd;
+
+    /// How long a blocking poll on the monitor socket waits before checking whether listening should continue
+    const MONITOR_POLL_TIMEOUT_MS: libc::c_int = 1000;
+
+    /// Kind of udev event relevant to discovery. Bind and unbind events are reported as changes.
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub enum DeviceEventKind {
+        Add,
+        Change,
+        Remove,
+    }
+
+    /// Listens for udev events on a monitor socket, calling `handle_event` for each add, change or remove event.
+    /// Blocks the calling thread until `keep_listening` or `handle_event` returns false.
+    pub fn listen(
+        keep_listening: impl Fn() -> bool,
+        mut handle_event: impl FnMut(DeviceEventKind, udev::Device) -> bool,
+    ) -> std::io::Result<()> {
+        let mut socket = udev::MonitorBuilder::new()?.listen()?;
+        let mut poll_fd = libc::pollfd {
+            fd: socket.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        while keep_listening() {
+            // The monitor socket is non-blocking, so wait for it to become readable before draining it
+            let result = unsafe { libc::poll(&mut poll_fd, 1, MONITOR_POLL_TIMEOUT_MS) };
+            if result < 0 {
+                let error = std::io::Error::last_os_error();
+                if error.kind() == std::io::ErrorKind::Interrupted {
+                    continue;
+                }
+                return Err(error);
+            }
+            for event in socket.by_ref() {
+                let kind = match event.event_type() {
+                    udev::EventType::Add => DeviceEventKind::Add,
+                    udev::EventType::Change | udev::EventType::Bind | udev::EventType::Unbind => {
+                        DeviceEventKind::Change
+                    }
+                    udev::EventType::Remove => DeviceEventKind::Remove,
+                    _ => {
+                        trace!("listen - ignoring udev event {:?}", event.event_type());
+                        continue;
+                    }
+                };
+                if !handle_event(kind, event.device()) {
+                    return Ok(());
+                }
+            }
+        }
+        Ok(())
+    }
+}
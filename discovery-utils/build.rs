@@ -2,6 +2,9 @@ fn main() {
     tonic_build::configure()
         .out_dir("./src/discovery")
         .protoc_arg("--experimental_allow_proto3_optional")
-        .compile(&["proto/discovery.proto"], &["proto"])
+        .compile(
+            &["proto/discovery.proto", "proto/discovery_v1.proto"],
+            &["proto"],
+        )
         .expect("failed to compile protos");
 }
@@ -0,0 +1,287 @@
+//! Support for the v1 `DiscoveryHandler` protocol, where the Agent keeps a control channel open with the
+//! `DiscoveryHandler` to update a running discovery.
+//! A `DiscoveryHandler` supporting it implements [UpdatableDiscoveryHandler] on top of the v0
+//! `DiscoveryHandler` trait, and gets served over v1 by wrapping it in a [ControlledDiscoveryHandler].
+use std::{collections::HashMap, sync::Arc};
+
+use log::{trace, warn};
+use tokio::sync::mpsc;
+use tokio_stream::{wrappers::ReceiverStream, Stream, StreamExt};
+
+use super::{
+    discovery_handler::DISCOVERED_DEVICES_CHANNEL_CAPACITY,
+    v0::{ByteData, DiscoverRequest, DiscoverResponse},
+    v1::{
+        discover_control::Command, discovery_handler_server::DiscoveryHandler, DiscoverControl,
+        Rescan, Stop, UpdateDetails, UpdateProperties,
+    },
+    DiscoverStream,
+};
+
+/// Capacity of channel over which the updates of a running discovery are passed to the `DiscoveryHandler`.
+pub const DISCOVERY_UPDATES_CHANNEL_CAPACITY: usize = 4;
+
+/// Update of a running discovery, sent by the Agent over the control channel.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DiscoveryUpdate {
+    /// Replaces the discovery details
+    Details(String),
+    /// Replaces the discovery properties
+    Properties(HashMap<String, ByteData>),
+    /// Asks for an immediate discovery scan
+    Rescan,
+}
+
+impl From<DiscoveryUpdate> for DiscoverControl {
+    fn from(update: DiscoveryUpdate) -> Self {
+        let command = match update {
+            DiscoveryUpdate::Details(discovery_details) => {
+                Command::UpdateDetails(UpdateDetails { discovery_details })
+            }
+            DiscoveryUpdate::Properties(discovery_properties) => {
+                Command::UpdateProperties(UpdateProperties {
+                    discovery_properties,
+                })
+            }
+            DiscoveryUpdate::Rescan => Command::Rescan(Rescan {}),
+        };
+        DiscoverControl {
+            command: Some(command),
+        }
+    }
+}
+
+impl DiscoverControl {
+    /// Control message starting a discovery, it must be the first message of the control channel.
+    pub fn start(request: DiscoverRequest) -> Self {
+        DiscoverControl {
+            command: Some(Command::Start(request)),
+        }
+    }
+
+    /// Control message stopping a discovery.
+    pub fn stop() -> Self {
+        DiscoverControl {
+            command: Some(Command::Stop(Stop {})),
+        }
+    }
+}
+
+/// Trait implemented by `DiscoveryHandlers` that can apply updates to a running discovery.
+#[tonic::async_trait]
+pub trait UpdatableDiscoveryHandler: Send + Sync + 'static {
+    /// Starts a discovery just like `DiscoveryHandler::discover`, then applies the updates received over
+    /// `updates` to it. `updates` gets closed along with the returned stream once the discovery is stopped.
+    async fn discover_with_updates(
+        &self,
+        request: DiscoverRequest,
+        updates: mpsc::Receiver<DiscoveryUpdate>,
+    ) -> Result<DiscoverStream, tonic::Status>;
+}
+
+/// Waits for the next update of the Agent, never resolves for a discovery started over the v0 protocol
+/// (without updates) or once the control channel got closed and `updates` reset.
+pub async fn next_update(
+    updates: &mut Option<mpsc::Receiver<DiscoveryUpdate>>,
+) -> Option<DiscoveryUpdate> {
+    match updates {
+        Some(updates) => updates.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Serves an [UpdatableDiscoveryHandler] over the v1 `DiscoveryHandler` service.
+pub struct ControlledDiscoveryHandler<T>(pub Arc<T>);
+
+#[tonic::async_trait]
+impl<T: UpdatableDiscoveryHandler> DiscoveryHandler for ControlledDiscoveryHandler<T> {
+    type DiscoverStream = DiscoverStream;
+    async fn discover(
+        &self,
+        request: tonic::Request<tonic::Streaming<DiscoverControl>>,
+    ) -> Result<tonic::Response<Self::DiscoverStream>, tonic::Status> {
+        let mut control = request.into_inner();
+        let discover_request = match control.message().await? {
+            Some(DiscoverControl {
+                command: Some(Command::Start(discover_request)),
+            }) => discover_request,
+            _ => {
+                return Err(tonic::Status::invalid_argument(
+                    "first message of the control channel must start the discovery",
+                ))
+            }
+        };
+        let (update_sender, update_receiver) = mpsc::channel(DISCOVERY_UPDATES_CHANNEL_CAPACITY);
+        let devices = self
+            .0
+            .discover_with_updates(discover_request, update_receiver)
+            .await?;
+        let (response_sender, response_receiver) =
+            mpsc::channel(DISCOVERED_DEVICES_CHANNEL_CAPACITY);
+        tokio::spawn(forward_discovery(
+            control,
+            update_sender,
+            devices,
+            response_sender,
+        ));
+        Ok(tonic::Response::new(ReceiverStream::new(response_receiver)))
+    }
+}
+
+/// Passes the control messages of the Agent as updates to the `DiscoveryHandler` and the discovered devices
+/// back to the Agent, until the discovery gets stopped by either side.
+async fn forward_discovery(
+    mut control: impl Stream<Item = Result<DiscoverControl, tonic::Status>> + Unpin,
+    update_sender: mpsc::Sender<DiscoveryUpdate>,
+    mut devices: DiscoverStream,
+    response_sender: mpsc::Sender<Result<DiscoverResponse, tonic::Status>>,
+) {
+    loop {
+        tokio::select! {
+            message = control.next() => {
+                let update = match message {
+                    Some(Ok(DiscoverControl { command: Some(command) })) => match command {
+                        Command::UpdateDetails(u) => DiscoveryUpdate::Details(u.discovery_details),
+                        Command::UpdateProperties(u) => {
+                            DiscoveryUpdate::Properties(u.discovery_properties)
+                        }
+                        Command::Rescan(_) => DiscoveryUpdate::Rescan,
+                        Command::Stop(_) => {
+                            trace!("forward_discovery - discovery stopped by the Agent");
+                            return;
+                        }
+                        Command::Start(_) => {
+                            warn!("forward_discovery - ignoring start message for a running discovery");
+                            continue;
+                        }
+                    },
+                    Some(Ok(_)) => continue,
+                    Some(Err(e)) => {
+                        warn!("forward_discovery - control channel failed with error {}", e);
+                        return;
+                    }
+                    None => {
+                        trace!("forward_discovery - control channel closed by the Agent");
+                        return;
+                    }
+                };
+                if update_sender.send(update).await.is_err() {
+                    return;
+                }
+            },
+            response = devices.next() => match response {
+                Some(response) => {
+                    if response_sender.send(response).await.is_err() {
+                        return;
+                    }
+                }
+                None => return,
+            },
+            _ = response_sender.closed() => return,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::v0::Device;
+    use super::*;
+
+    #[test]
+    fn test_discovery_update_to_discover_control() {
+        assert_eq!(
+            DiscoverControl::from(DiscoveryUpdate::Details("details".to_string())),
+            DiscoverControl {
+                command: Some(Command::UpdateDetails(UpdateDetails {
+                    discovery_details: "details".to_string()
+                }))
+            }
+        );
+        let properties = HashMap::from([(
+            "key".to_string(),
+            ByteData {
+                vec: Some(b"value".to_vec()),
+            },
+        )]);
+        assert_eq!(
+            DiscoverControl::from(DiscoveryUpdate::Properties(properties.clone())),
+            DiscoverControl {
+                command: Some(Command::UpdateProperties(UpdateProperties {
+                    discovery_properties: properties
+                }))
+            }
+        );
+        assert_eq!(
+            DiscoverControl::from(DiscoveryUpdate::Rescan),
+            DiscoverControl {
+                command: Some(Command::Rescan(Rescan {}))
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_forward_discovery() {
+        let (control_sender, control_receiver) = mpsc::channel(4);
+        let (update_sender, mut update_receiver) = mpsc::channel(4);
+        let (devices_sender, devices_receiver) = mpsc::channel(4);
+        let (response_sender, mut response_receiver) = mpsc::channel(4);
+        let task = tokio::spawn(forward_discovery(
+            ReceiverStream::new(control_receiver),
+            update_sender,
+            ReceiverStream::new(devices_receiver),
+            response_sender,
+        ));
+
+        control_sender
+            .send(Ok(DiscoveryUpdate::Details("details".to_string()).into()))
+            .await
+            .unwrap();
+        assert_eq!(
+            update_receiver.recv().await,
+            Some(DiscoveryUpdate::Details("details".to_string()))
+        );
+        control_sender
+            .send(Ok(DiscoveryUpdate::Rescan.into()))
+            .await
+            .unwrap();
+        assert_eq!(update_receiver.recv().await, Some(DiscoveryUpdate::Rescan));
+
+        let response = DiscoverResponse {
+            devices: vec![Device {
+                id: "device".to_string(),
+                ..Default::default()
+            }],
+            delta: None,
+        };
+        devices_sender.send(Ok(response.clone())).await.unwrap();
+        assert_eq!(response_receiver.recv().await.unwrap().unwrap(), response);
+
+        // Stopping the discovery closes the updates channel, telling the handler to end the discovery
+        control_sender
+            .send(Ok(DiscoverControl::stop()))
+            .await
+            .unwrap();
+        task.await.unwrap();
+        assert_eq!(update_receiver.recv().await, None);
+        assert!(response_receiver.recv().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_forward_discovery_handler_ended() {
+        let (_control_sender, control_receiver) = mpsc::channel(4);
+        let (update_sender, mut update_receiver) = mpsc::channel(4);
+        let (devices_sender, devices_receiver) = mpsc::channel(4);
+        let (response_sender, mut response_receiver) = mpsc::channel(4);
+        let task = tokio::spawn(forward_discovery(
+            ReceiverStream::new(control_receiver),
+            update_sender,
+            ReceiverStream::new(devices_receiver),
+            response_sender,
+        ));
+
+        drop(devices_sender);
+        task.await.unwrap();
+        assert_eq!(update_receiver.recv().await, None);
+        assert!(response_receiver.recv().await.is_none());
+    }
+}
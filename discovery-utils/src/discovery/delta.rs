@@ -0,0 +1,182 @@
+//! Support for incremental updates of the device list, allowing `DiscoveryHandlers` that track many devices to only
+//! send the devices that changed. The Agent advertises it supports them with `DiscoverRequest::accept_deltas`.
+use std::collections::HashMap;
+
+use super::v0::{Device, DevicesDelta, DiscoverRequest, DiscoverResponse};
+
+/// Default number of responses between two full snapshots of the device list in delta mode.
+pub const DELTA_SNAPSHOT_INTERVAL: u64 = 50;
+
+/// Builds the responses of a discovery from the successive device lists found by a `DiscoveryHandler`. It sends
+/// deltas with periodic snapshots if the Agent accepts them, and full device lists otherwise.
+pub struct DeltaEncoder {
+    accept_deltas: bool,
+    snapshot_interval: u64,
+    /// Sequence number of the next response
+    sequence: u64,
+    /// Devices as last sent to the Agent, by id
+    devices: Option<HashMap<String, Device>>,
+}
+
+impl DeltaEncoder {
+    pub fn new(request: &DiscoverRequest) -> Self {
+        DeltaEncoder {
+            accept_deltas: request.accept_deltas,
+            snapshot_interval: DELTA_SNAPSHOT_INTERVAL,
+            sequence: 0,
+            devices: None,
+        }
+    }
+
+    /// Sets the number of responses between two full snapshots, a snapshot is always sent first.
+    pub fn with_snapshot_interval(mut self, snapshot_interval: u64) -> Self {
+        self.snapshot_interval = snapshot_interval.max(1);
+        self
+    }
+
+    /// Returns the response to send for the devices currently discovered, or None if they didn't change since the
+    /// last response.
+    pub fn encode(&mut self, devices: Vec<Device>) -> Option<DiscoverResponse> {
+        let current: HashMap<String, Device> =
+            devices.iter().map(|d| (d.id.clone(), d.clone())).collect();
+        let (changed, removed_ids) = match self.devices.as_ref() {
+            Some(previous) => {
+                let changed: Vec<Device> = devices
+                    .iter()
+                    .filter(|d| previous.get(&d.id) != Some(*d))
+                    .cloned()
+                    .collect();
+                let removed_ids: Vec<String> = previous
+                    .keys()
+                    .filter(|id| !current.contains_key(*id))
+                    .cloned()
+                    .collect();
+                if changed.is_empty() && removed_ids.is_empty() {
+                    return None;
+                }
+                (changed, removed_ids)
+            }
+            None => (devices.clone(), Vec::new()),
+        };
+        self.devices = Some(current);
+        if !self.accept_deltas {
+            return Some(DiscoverResponse {
+                devices,
+                delta: None,
+            });
+        }
+        let sequence = self.sequence;
+        self.sequence += 1;
+        let response = if sequence % self.snapshot_interval == 0 {
+            DiscoverResponse {
+                devices,
+                delta: Some(DevicesDelta {
+                    sequence,
+                    snapshot: true,
+                    removed_ids: Vec::new(),
+                }),
+            }
+        } else {
+            DiscoverResponse {
+                devices: changed,
+                delta: Some(DevicesDelta {
+                    sequence,
+                    snapshot: false,
+                    removed_ids,
+                }),
+            }
+        };
+        Some(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn device(id: &str, value: &str) -> Device {
+        Device {
+            id: id.to_string(),
+            properties: HashMap::from([("KEY".to_string(), value.to_string())]),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_encode_full_lists() {
+        let mut encoder = DeltaEncoder::new(&DiscoverRequest::default());
+        let devices = vec![device("a", "1"), device("b", "1")];
+        assert_eq!(
+            encoder.encode(devices.clone()),
+            Some(DiscoverResponse {
+                devices: devices.clone(),
+                delta: None
+            })
+        );
+        assert_eq!(encoder.encode(devices), None);
+        let devices = vec![device("b", "1")];
+        assert_eq!(
+            encoder.encode(devices.clone()),
+            Some(DiscoverResponse {
+                devices,
+                delta: None
+            })
+        );
+    }
+
+    #[test]
+    fn test_encode_deltas() {
+        let mut encoder = DeltaEncoder::new(&DiscoverRequest {
+            accept_deltas: true,
+            ..Default::default()
+        })
+        .with_snapshot_interval(3);
+
+        // The first response is a snapshot, even without any device
+        assert_eq!(
+            encoder.encode(vec![]),
+            Some(DiscoverResponse {
+                devices: vec![],
+                delta: Some(DevicesDelta {
+                    sequence: 0,
+                    snapshot: true,
+                    removed_ids: vec![],
+                })
+            })
+        );
+        assert_eq!(encoder.encode(vec![]), None);
+        assert_eq!(
+            encoder.encode(vec![device("a", "1"), device("b", "1")]),
+            Some(DiscoverResponse {
+                devices: vec![device("a", "1"), device("b", "1")],
+                delta: Some(DevicesDelta {
+                    sequence: 1,
+                    snapshot: false,
+                    removed_ids: vec![],
+                })
+            })
+        );
+        assert_eq!(
+            encoder.encode(vec![device("b", "2")]),
+            Some(DiscoverResponse {
+                devices: vec![device("b", "2")],
+                delta: Some(DevicesDelta {
+                    sequence: 2,
+                    snapshot: false,
+                    removed_ids: vec!["a".to_string()],
+                })
+            })
+        );
+        assert_eq!(
+            encoder.encode(vec![device("b", "2"), device("c", "1")]),
+            Some(DiscoverResponse {
+                devices: vec![device("b", "2"), device("c", "1")],
+                delta: Some(DevicesDelta {
+                    sequence: 3,
+                    snapshot: true,
+                    removed_ids: vec![],
+                })
+            })
+        );
+    }
+}
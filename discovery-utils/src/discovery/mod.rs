@@ -1,5 +1,10 @@
 /// Akri's Discovery API code, which is auto-generated by `build.rs` from `proto/discovery.proto`
 pub mod v0;
+/// Version 1 of Akri's Discovery API, auto-generated by `build.rs` from `proto/discovery_v1.proto`
+pub mod v1;
+
+pub mod control;
+pub mod delta;
 
 /// Definition of the DiscoverStream type expected for supported embedded Akri DiscoveryHandlers
 pub type DiscoverStream =
@@ -10,14 +15,20 @@ pub mod discovery_handler {
         register_discovery_handler, register_discovery_handler_again,
     };
     use super::{
-        server::run_discovery_server,
+        control::UpdatableDiscoveryHandler,
+        server::{run_discovery_server, run_updatable_discovery_server},
         v0::{
             discovery_handler_server::DiscoveryHandler,
-            register_discovery_handler_request::EndpointType, RegisterDiscoveryHandlerRequest,
+            register_discovery_handler_request::{EndpointType, Feature, ProtocolVersion},
+            DiscoverRequest, RegisterDiscoveryHandlerRequest,
         },
     };
-    use log::trace;
-    use tokio::sync::mpsc;
+    pub use akri_shared::akri::discovery_handler::{
+        DiscoveryHandlerCapabilities, DiscoveryHandlerFeature,
+    };
+    use log::{trace, warn};
+    use std::time::Duration;
+    use tokio::{sync::mpsc, task::JoinHandle};
 
     const DISCOVERY_PORT: i16 = 10000;
 
@@ -36,40 +47,120 @@ pub mod discovery_handler {
         protocol_name: &str,
         shared: bool,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
-        let mut use_uds = true;
-        let mut endpoint: String = match std::env::var("POD_IP") {
-            Ok(pod_ip) => {
-                trace!("run_discovery_handler - registering with Agent with IP endpoint");
-                use_uds = false;
-                format!("{}:{}", pod_ip, DISCOVERY_PORT)
-            }
-            Err(_) => {
-                trace!("run_discovery_handler - registering with Agent with uds endpoint");
-                format!(
-                    "{}/{}.sock",
-                    std::env::var(super::super::DISCOVERY_HANDLERS_DIRECTORY_LABEL).unwrap(),
-                    protocol_name
-                )
-            }
-        };
+        run_discovery_handler_with_capabilities(
+            discovery_handler,
+            register_receiver,
+            protocol_name,
+            shared,
+            DiscoveryHandlerCapabilities::default(),
+        )
+        .await
+    }
+
+    /// Runs a `DiscoveryHandler` that advertises its version, features and the JSON Schema of its discovery details
+    /// when registering with the Agent.
+    pub async fn run_discovery_handler_with_capabilities(
+        discovery_handler: impl DiscoveryHandler,
+        register_receiver: mpsc::Receiver<()>,
+        protocol_name: &str,
+        shared: bool,
+        capabilities: DiscoveryHandlerCapabilities,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+        let (endpoint, endpoint_type) = get_discovery_handler_endpoint(protocol_name);
         let endpoint_clone = endpoint.clone();
         let discovery_handle = tokio::spawn(async move {
             run_discovery_server(discovery_handler, &endpoint_clone)
                 .await
                 .unwrap();
         });
-        let endpoint_type = if !use_uds {
-            endpoint.insert_str(0, "http://");
-            EndpointType::Network
-        } else {
-            EndpointType::Uds
-        };
-        let register_request = RegisterDiscoveryHandlerRequest {
-            name: protocol_name.to_string(),
+        register_and_run(
+            discovery_handle,
+            register_receiver,
+            protocol_name,
             endpoint,
-            endpoint_type: endpoint_type as i32,
+            endpoint_type,
             shared,
-        };
+            capabilities,
+        )
+        .await
+    }
+
+    /// Runs a `DiscoveryHandler` that also supports the v1 protocol, allowing the Agent to update its running
+    /// discoveries. It gets served over both v0 and v1, and registers as supporting v1 and the control channel.
+    pub async fn run_updatable_discovery_handler(
+        discovery_handler: impl DiscoveryHandler + UpdatableDiscoveryHandler,
+        register_receiver: mpsc::Receiver<()>,
+        protocol_name: &str,
+        shared: bool,
+        mut capabilities: DiscoveryHandlerCapabilities,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+        if !capabilities
+            .protocol_versions
+            .contains(&(ProtocolVersion::V1 as u32))
+        {
+            capabilities
+                .protocol_versions
+                .push(ProtocolVersion::V1 as u32);
+        }
+        let capabilities = capabilities.with_feature(DiscoveryHandlerFeature::ControlChannel);
+        let (endpoint, endpoint_type) = get_discovery_handler_endpoint(protocol_name);
+        let endpoint_clone = endpoint.clone();
+        let discovery_handle = tokio::spawn(async move {
+            run_updatable_discovery_server(discovery_handler, &endpoint_clone)
+                .await
+                .unwrap();
+        });
+        register_and_run(
+            discovery_handle,
+            register_receiver,
+            protocol_name,
+            endpoint,
+            endpoint_type,
+            shared,
+            capabilities,
+        )
+        .await
+    }
+
+    /// Gets the endpoint the `DiscoveryHandler` should be served on, a network one if running in a Pod with an IP,
+    /// a Unix socket in the discovery handlers directory otherwise.
+    fn get_discovery_handler_endpoint(protocol_name: &str) -> (String, EndpointType) {
+        match std::env::var("POD_IP") {
+            Ok(pod_ip) => {
+                trace!("run_discovery_handler - registering with Agent with IP endpoint");
+                (
+                    format!("{}:{}", pod_ip, DISCOVERY_PORT),
+                    EndpointType::Network,
+                )
+            }
+            Err(_) => {
+                trace!("run_discovery_handler - registering with Agent with uds endpoint");
+                (
+                    format!(
+                        "{}/{}.sock",
+                        std::env::var(super::super::DISCOVERY_HANDLERS_DIRECTORY_LABEL).unwrap(),
+                        protocol_name
+                    ),
+                    EndpointType::Uds,
+                )
+            }
+        }
+    }
+
+    async fn register_and_run(
+        discovery_handle: tokio::task::JoinHandle<()>,
+        register_receiver: mpsc::Receiver<()>,
+        protocol_name: &str,
+        mut endpoint: String,
+        endpoint_type: EndpointType,
+        shared: bool,
+        capabilities: DiscoveryHandlerCapabilities,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+        if endpoint_type == EndpointType::Network {
+            endpoint.insert_str(0, "http://");
+        }
+        let register_request =
+            build_register_request(protocol_name, endpoint, endpoint_type, shared, capabilities);
         register_discovery_handler(&register_request).await?;
         let registration_handle = tokio::spawn(async move {
             register_discovery_handler_again(register_receiver, &register_request).await;
@@ -78,6 +169,90 @@ pub mod discovery_handler {
         Ok(())
     }
 
+    fn build_register_request(
+        protocol_name: &str,
+        endpoint: String,
+        endpoint_type: EndpointType,
+        shared: bool,
+        capabilities: DiscoveryHandlerCapabilities,
+    ) -> RegisterDiscoveryHandlerRequest {
+        RegisterDiscoveryHandlerRequest {
+            name: protocol_name.to_string(),
+            endpoint,
+            endpoint_type: endpoint_type as i32,
+            shared,
+            protocol_versions: capabilities
+                .protocol_versions
+                .iter()
+                .map(|v| *v as i32)
+                .collect(),
+            version: capabilities.version,
+            features: capabilities
+                .features
+                .into_iter()
+                .map(|f| Feature::from(f) as i32)
+                .collect(),
+            discovery_details_schema: capabilities
+                .discovery_details_schema
+                .map(|schema| schema.to_string())
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Gets the capabilities a `DiscoveryHandler` advertised in its registration request. Unknown features are
+    /// skipped and an invalid discovery details schema is ignored.
+    pub fn get_capabilities(
+        request: &RegisterDiscoveryHandlerRequest,
+    ) -> DiscoveryHandlerCapabilities {
+        let discovery_details_schema = if request.discovery_details_schema.is_empty() {
+            None
+        } else {
+            serde_json::from_str(&request.discovery_details_schema)
+                .map_err(|e| {
+                    warn!(
+                        "get_capabilities - ignoring invalid discovery details schema of {}: {}",
+                        request.name, e
+                    )
+                })
+                .ok()
+        };
+        DiscoveryHandlerCapabilities {
+            version: request.version.clone(),
+            protocol_versions: request
+                .protocol_versions
+                .iter()
+                .filter_map(|v| u32::try_from(*v).ok())
+                .collect(),
+            features: request
+                .features
+                .iter()
+                .filter_map(|f| Feature::try_from(*f).ok())
+                .map(DiscoveryHandlerFeature::from)
+                .collect(),
+            discovery_details_schema,
+        }
+    }
+
+    impl From<DiscoveryHandlerFeature> for Feature {
+        fn from(feature: DiscoveryHandlerFeature) -> Self {
+            match feature {
+                DiscoveryHandlerFeature::DeviceHealth => Feature::DeviceHealth,
+                DiscoveryHandlerFeature::DeltaUpdates => Feature::DeltaUpdates,
+                DiscoveryHandlerFeature::ControlChannel => Feature::ControlChannel,
+            }
+        }
+    }
+
+    impl From<Feature> for DiscoveryHandlerFeature {
+        fn from(feature: Feature) -> Self {
+            match feature {
+                Feature::DeviceHealth => DiscoveryHandlerFeature::DeviceHealth,
+                Feature::DeltaUpdates => DiscoveryHandlerFeature::DeltaUpdates,
+                Feature::ControlChannel => DiscoveryHandlerFeature::ControlChannel,
+            }
+        }
+    }
+
     /// This obtains the expected type `T` from a discovery details String by running it through function `f` which will
     /// attempt to deserialize the String.
     pub fn deserialize_discovery_details<T>(discovery_details: &str) -> Result<T, anyhow::Error>
@@ -92,10 +267,173 @@ pub mod discovery_handler {
         })?;
         Ok(discovery_handler_config)
     }
+
+    /// This gets the time a `DiscoveryHandler` should wait between two discovery scans, as set in the
+    /// `DiscoverRequest` schedule, falling back to `default_interval` if none was requested.
+    pub fn get_discovery_interval(
+        discover_request: &DiscoverRequest,
+        default_interval: Duration,
+    ) -> Duration {
+        discover_request
+            .schedule
+            .as_ref()
+            .and_then(|schedule| schedule.interval_seconds)
+            .map(Duration::from_secs)
+            .unwrap_or(default_interval)
+    }
+
+    /// This gets the maximum time a single discovery scan may take, as set in the `DiscoverRequest` schedule.
+    /// Returns `None` if no deadline was requested.
+    pub fn get_discovery_timeout(discover_request: &DiscoverRequest) -> Option<Duration> {
+        discover_request
+            .schedule
+            .as_ref()
+            .and_then(|schedule| schedule.timeout_seconds)
+            .map(Duration::from_secs)
+    }
+
+    /// Runs a blocking discovery `scan` on a blocking thread and waits at most `timeout` for it to complete.
+    /// A scan that timed out keeps running as it cannot be cancelled: it is stored in `in_flight_scan` and
+    /// awaited again by the next call instead of starting a new one. Returns `None` if the scan timed out.
+    pub async fn run_blocking_scan<T, F>(
+        in_flight_scan: &mut Option<JoinHandle<T>>,
+        timeout: Option<Duration>,
+        scan: F,
+    ) -> Option<T>
+    where
+        T: Send + 'static,
+        F: FnOnce() -> T + Send + 'static,
+    {
+        let mut handle = in_flight_scan
+            .take()
+            .unwrap_or_else(|| tokio::task::spawn_blocking(scan));
+        match timeout {
+            Some(timeout) => match tokio::time::timeout(timeout, &mut handle).await {
+                Ok(result) => Some(result.unwrap()),
+                Err(_) => {
+                    *in_flight_scan = Some(handle);
+                    None
+                }
+            },
+            None => Some(handle.await.unwrap()),
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::super::v0::DiscoverySchedule;
+        use super::*;
+
+        #[test]
+        fn test_get_discovery_interval_and_timeout() {
+            let default_interval = Duration::from_secs(10);
+            let mut discover_request = DiscoverRequest::default();
+            assert_eq!(
+                get_discovery_interval(&discover_request, default_interval),
+                default_interval
+            );
+            assert_eq!(get_discovery_timeout(&discover_request), None);
+
+            discover_request.schedule = Some(DiscoverySchedule {
+                interval_seconds: Some(2),
+                timeout_seconds: None,
+            });
+            assert_eq!(
+                get_discovery_interval(&discover_request, default_interval),
+                Duration::from_secs(2)
+            );
+            assert_eq!(get_discovery_timeout(&discover_request), None);
+
+            discover_request.schedule = Some(DiscoverySchedule {
+                interval_seconds: None,
+                timeout_seconds: Some(30),
+            });
+            assert_eq!(
+                get_discovery_interval(&discover_request, default_interval),
+                default_interval
+            );
+            assert_eq!(
+                get_discovery_timeout(&discover_request),
+                Some(Duration::from_secs(30))
+            );
+        }
+
+        #[tokio::test]
+        async fn test_run_blocking_scan_timeout() {
+            let (release_sender, release_receiver) = std::sync::mpsc::channel::<()>();
+            let mut in_flight_scan = None;
+            // The scan blocks until released so it cannot complete within the timeout
+            let scan = move || {
+                release_receiver.recv().unwrap();
+                1
+            };
+            assert_eq!(
+                run_blocking_scan(&mut in_flight_scan, Some(Duration::from_millis(10)), scan).await,
+                None
+            );
+            assert!(in_flight_scan.is_some());
+
+            // The timed out scan is awaited again rather than starting the new one
+            release_sender.send(()).unwrap();
+            assert_eq!(
+                run_blocking_scan(&mut in_flight_scan, Some(Duration::from_secs(5)), || 2).await,
+                Some(1)
+            );
+            assert!(in_flight_scan.is_none());
+            assert_eq!(
+                run_blocking_scan(&mut in_flight_scan, None, || 3).await,
+                Some(3)
+            );
+        }
+
+        #[test]
+        fn test_register_request_capabilities() {
+            let request = build_register_request(
+                "protocol",
+                "/tmp/protocol.sock".to_string(),
+                EndpointType::Uds,
+                false,
+                DiscoveryHandlerCapabilities::default(),
+            );
+            assert!(request.version.is_empty());
+            assert!(request.discovery_details_schema.is_empty());
+            assert_eq!(
+                get_capabilities(&request),
+                DiscoveryHandlerCapabilities::default()
+            );
+
+            let capabilities = DiscoveryHandlerCapabilities {
+                version: "1.2.3".to_string(),
+                protocol_versions: vec![ProtocolVersion::V1 as u32],
+                features: vec![
+                    DiscoveryHandlerFeature::DeltaUpdates,
+                    DiscoveryHandlerFeature::ControlChannel,
+                ],
+                discovery_details_schema: Some(serde_json::json!({"type": "object"})),
+            };
+            let mut request = build_register_request(
+                "protocol",
+                "/tmp/protocol.sock".to_string(),
+                EndpointType::Uds,
+                false,
+                capabilities.clone(),
+            );
+            assert_eq!(request.features, vec![1, 2]);
+            assert_eq!(get_capabilities(&request), capabilities);
+
+            // Unknown features and invalid schemas are ignored
+            request.features.push(42);
+            request.discovery_details_schema = "{".to_string();
+            let parsed = get_capabilities(&request);
+            assert_eq!(parsed.features, capabilities.features);
+            assert_eq!(parsed.discovery_details_schema, None);
+        }
+    }
 }
 
 #[cfg(any(feature = "mock-discovery-handler", test))]
 pub mod mock_discovery_handler {
+    use super::control::{DiscoveryUpdate, UpdatableDiscoveryHandler};
     use super::v0::{
         discovery_handler_server::DiscoveryHandler, Device, DiscoverRequest, DiscoverResponse,
     };
@@ -103,6 +441,7 @@ pub mod mock_discovery_handler {
     use async_trait::async_trait;
     use tempfile::Builder;
     use tokio::sync::mpsc;
+    use tokio_stream::StreamExt;
 
     /// Simple discovery handler for tests
     /// Has fields for specifying that it return an error or a set of devices.
@@ -123,7 +462,10 @@ pub mod mock_discovery_handler {
             let devices = self.devices.clone();
             tokio::spawn(async move {
                 discovered_devices_sender
-                    .send(Ok(DiscoverResponse { devices }))
+                    .send(Ok(DiscoverResponse {
+                        devices,
+                        delta: None,
+                    }))
                     .await
                     .unwrap();
             });
@@ -140,6 +482,50 @@ pub mod mock_discovery_handler {
         }
     }
 
+    #[async_trait]
+    impl UpdatableDiscoveryHandler for MockDiscoveryHandler {
+        /// Sends the set of devices, then a device per discovery details update
+        async fn discover_with_updates(
+            &self,
+            request: DiscoverRequest,
+            mut updates: mpsc::Receiver<DiscoveryUpdate>,
+        ) -> Result<super::DiscoverStream, tonic::Status> {
+            let (discovered_devices_sender, discovered_devices_receiver) =
+                mpsc::channel(super::discovery_handler::DISCOVERED_DEVICES_CHANNEL_CAPACITY);
+            let stream = self
+                .discover(tonic::Request::new(request))
+                .await?
+                .into_inner();
+            tokio::spawn(async move {
+                let mut stream = stream;
+                if let Some(response) = stream.next().await {
+                    discovered_devices_sender.send(response).await.unwrap();
+                }
+                while let Some(update) = updates.recv().await {
+                    if let DiscoveryUpdate::Details(id) = update {
+                        let devices = vec![Device {
+                            id,
+                            ..Default::default()
+                        }];
+                        if discovered_devices_sender
+                            .send(Ok(DiscoverResponse {
+                                devices,
+                                delta: None,
+                            }))
+                            .await
+                            .is_err()
+                        {
+                            return;
+                        }
+                    }
+                }
+            });
+            Ok(tokio_stream::wrappers::ReceiverStream::new(
+                discovered_devices_receiver,
+            ))
+        }
+    }
+
     pub fn get_mock_discovery_handler_dir_and_endpoint(socket_name: &str) -> (String, String) {
         let discovery_handler_temp_dir = Builder::new()
             .prefix("discovery-handlers")
@@ -190,13 +576,16 @@ pub mod mock_discovery_handler {
 }
 
 pub mod server {
+    use super::control::{ControlledDiscoveryHandler, UpdatableDiscoveryHandler};
     use super::v0::discovery_handler_server::{DiscoveryHandler, DiscoveryHandlerServer};
+    use super::v1::discovery_handler_server::DiscoveryHandlerServer as DiscoveryHandlerServerV1;
     use akri_shared::uds::unix_stream;
     use futures::TryFutureExt;
     use log::info;
     use std::path::Path;
+    use std::sync::Arc;
     use tokio::net::UnixListener;
-    use tonic::transport::Server;
+    use tonic::transport::{server::Router, Server};
 
     pub async fn run_discovery_server(
         discovery_handler: impl DiscoveryHandler,
@@ -210,6 +599,18 @@ pub mod server {
         .await
     }
 
+    pub async fn run_updatable_discovery_server(
+        discovery_handler: impl DiscoveryHandler + UpdatableDiscoveryHandler,
+        discovery_endpoint: &str,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+        internal_run_updatable_discovery_server(
+            discovery_handler,
+            discovery_endpoint,
+            &std::env::var(super::super::DISCOVERY_HANDLERS_DIRECTORY_LABEL).unwrap(),
+        )
+        .await
+    }
+
     /// Creates a DiscoveryHandlerServer for the given Discovery Handler at the specified endpoint Verifies the endpoint
     /// by checking that it is in the discovery handler directory if it is UDS or that it is a valid IP address and
     /// port.
@@ -219,7 +620,38 @@ pub mod server {
         discovery_handler_directory: &str,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
         info!("internal_run_discovery_server - entered");
+        let router = Server::builder().add_service(DiscoveryHandlerServer::new(discovery_handler));
+        serve(router, discovery_endpoint, discovery_handler_directory).await?;
+        info!("internal_run_discovery_server - finished");
+        Ok(())
+    }
 
+    /// Same as [internal_run_discovery_server], serving the Discovery Handler over both the v0 and v1 protocols.
+    pub async fn internal_run_updatable_discovery_server<T>(
+        discovery_handler: T,
+        discovery_endpoint: &str,
+        discovery_handler_directory: &str,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>>
+    where
+        T: DiscoveryHandler + UpdatableDiscoveryHandler,
+    {
+        info!("internal_run_updatable_discovery_server - entered");
+        let discovery_handler = Arc::new(discovery_handler);
+        let router = Server::builder()
+            .add_service(DiscoveryHandlerServer::from_arc(discovery_handler.clone()))
+            .add_service(DiscoveryHandlerServerV1::new(ControlledDiscoveryHandler(
+                discovery_handler,
+            )));
+        serve(router, discovery_endpoint, discovery_handler_directory).await?;
+        info!("internal_run_updatable_discovery_server - finished");
+        Ok(())
+    }
+
+    async fn serve(
+        router: Router,
+        discovery_endpoint: &str,
+        discovery_handler_directory: &str,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
         if discovery_endpoint.starts_with(discovery_handler_directory) {
             tokio::fs::create_dir_all(Path::new(discovery_endpoint).parent().unwrap()).await?;
             // Delete socket if it already exists
@@ -234,19 +666,12 @@ pub mod server {
                     }
                 }
             };
-            Server::builder()
-                .add_service(DiscoveryHandlerServer::new(discovery_handler))
-                .serve_with_incoming(incoming)
-                .await?;
+            router.serve_with_incoming(incoming).await?;
             std::fs::remove_file(discovery_endpoint).unwrap_or(());
         } else {
             let addr = discovery_endpoint.parse()?;
-            Server::builder()
-                .add_service(DiscoveryHandlerServer::new(discovery_handler))
-                .serve(addr)
-                .await?;
+            router.serve(addr).await?;
         }
-        info!("internal_run_discovery_server - finished");
         Ok(())
     }
 
@@ -258,12 +683,18 @@ pub mod server {
                 MockDiscoveryHandler,
             },
             v0::{discovery_handler_client::DiscoveryHandlerClient, DiscoverRequest},
+            v1::{
+                discovery_handler_client::DiscoveryHandlerClient as DiscoveryHandlerClientV1,
+                DiscoverControl,
+            },
         };
         use super::*;
+        use crate::discovery::control::DiscoveryUpdate;
         use std::collections::HashMap;
         use std::convert::TryFrom;
         use tempfile::Builder;
         use tokio::net::UnixStream;
+        use tokio_stream::wrappers::ReceiverStream;
         use tonic::{
             transport::{Endpoint, Uri},
             Request,
@@ -292,11 +723,70 @@ pub mod server {
                 .discover(Request::new(DiscoverRequest {
                     discovery_details: String::new(),
                     discovery_properties: HashMap::new(),
+                    schedule: None,
+                    accept_deltas: false,
+                }))
+                .await
+                .unwrap()
+                .into_inner();
+            assert!(stream.message().await.unwrap().unwrap().devices.is_empty());
+        }
+
+        #[tokio::test]
+        async fn test_run_updatable_discovery_server_uds() {
+            let (discovery_handler_dir, discovery_handler_socket) =
+                get_mock_discovery_handler_dir_and_endpoint("protocol.sock");
+            let discovery_handler = MockDiscoveryHandler {
+                return_error: false,
+                devices: Vec::new(),
+            };
+            let dir = discovery_handler_dir.clone();
+            let socket = discovery_handler_socket.clone();
+            let _handle = tokio::spawn(async move {
+                internal_run_updatable_discovery_server(discovery_handler, &socket, &dir)
+                    .await
+                    .unwrap();
+            });
+            unix_stream::try_connect(&discovery_handler_socket)
+                .await
+                .unwrap();
+            let channel = Endpoint::try_from("http://[::1]:50051")
+                .unwrap()
+                .connect_with_connector(tower::service_fn(move |_: Uri| {
+                    UnixStream::connect(discovery_handler_socket.clone())
                 }))
                 .await
+                .unwrap();
+
+            // The v0 protocol is still served
+            let mut stream = DiscoveryHandlerClient::new(channel.clone())
+                .discover(Request::new(DiscoverRequest::default()))
+                .await
+                .unwrap()
+                .into_inner();
+            assert!(stream.message().await.unwrap().unwrap().devices.is_empty());
+
+            let (control_sender, control_receiver) = tokio::sync::mpsc::channel(4);
+            control_sender
+                .send(DiscoverControl::start(DiscoverRequest::default()))
+                .await
+                .unwrap();
+            let mut stream = DiscoveryHandlerClientV1::new(channel)
+                .discover(ReceiverStream::new(control_receiver))
+                .await
                 .unwrap()
                 .into_inner();
             assert!(stream.message().await.unwrap().unwrap().devices.is_empty());
+            control_sender
+                .send(DiscoveryUpdate::Details("updated".to_string()).into())
+                .await
+                .unwrap();
+            assert_eq!(
+                stream.message().await.unwrap().unwrap().devices[0].id,
+                "updated"
+            );
+            control_sender.send(DiscoverControl::stop()).await.unwrap();
+            assert!(stream.message().await.unwrap().is_none());
         }
 
         // Test when improper socket path or IP address is given as an endpoint
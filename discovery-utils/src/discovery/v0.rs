@@ -18,6 +18,25 @@ pub struct RegisterDiscoveryHandlerRequest {
     /// or can only be ever be discovered by a single node (e.g. a local USB device)
     #[prost(bool, tag = "4")]
     pub shared: bool,
+    #[prost(
+        enumeration = "register_discovery_handler_request::ProtocolVersion",
+        repeated,
+        tag = "5"
+    )]
+    pub protocol_versions: ::prost::alloc::vec::Vec<i32>,
+    /// Version of the `DiscoveryHandler`, for information purposes.
+    #[prost(string, tag = "6")]
+    pub version: ::prost::alloc::string::String,
+    #[prost(
+        enumeration = "register_discovery_handler_request::Feature",
+        repeated,
+        tag = "7"
+    )]
+    pub features: ::prost::alloc::vec::Vec<i32>,
+    /// JSON Schema of the discovery details expected by the `DiscoveryHandler`, once parsed
+    /// from YAML. Left empty if the `DiscoveryHandler` does not advertise one.
+    #[prost(string, tag = "8")]
+    pub discovery_details_schema: ::prost::alloc::string::String,
 }
 /// Nested message and enum types in `RegisterDiscoveryHandlerRequest`.
 pub mod register_discovery_handler_request {
@@ -48,6 +67,65 @@ pub mod register_discovery_handler_request {
             }
         }
     }
+    /// Versions of the `DiscoveryHandler` service served on the endpoint, v0 is always served.
+    /// The Agent uses the highest version it also supports, handlers that don't set this
+    /// field are only queried with v0.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+    #[repr(i32)]
+    pub enum ProtocolVersion {
+        V0 = 0,
+        V1 = 1,
+    }
+    impl ProtocolVersion {
+        /// String value of the enum field names used in the ProtoBuf definition.
+        ///
+        /// The values are not transformed in any way and thus are considered stable
+        /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+        pub fn as_str_name(&self) -> &'static str {
+            match self {
+                ProtocolVersion::V0 => "V0",
+                ProtocolVersion::V1 => "V1",
+            }
+        }
+        /// Creates an enum from field names used in the ProtoBuf definition.
+        pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+            match value {
+                "V0" => Some(Self::V0),
+                "V1" => Some(Self::V1),
+                _ => None,
+            }
+        }
+    }
+    /// Optional features of the discovery protocol supported by the `DiscoveryHandler`.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+    #[repr(i32)]
+    pub enum Feature {
+        DeviceHealth = 0,
+        DeltaUpdates = 1,
+        ControlChannel = 2,
+    }
+    impl Feature {
+        /// String value of the enum field names used in the ProtoBuf definition.
+        ///
+        /// The values are not transformed in any way and thus are considered stable
+        /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+        pub fn as_str_name(&self) -> &'static str {
+            match self {
+                Feature::DeviceHealth => "DEVICE_HEALTH",
+                Feature::DeltaUpdates => "DELTA_UPDATES",
+                Feature::ControlChannel => "CONTROL_CHANNEL",
+            }
+        }
+        /// Creates an enum from field names used in the ProtoBuf definition.
+        pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+            match value {
+                "DEVICE_HEALTH" => Some(Self::DeviceHealth),
+                "DELTA_UPDATES" => Some(Self::DeltaUpdates),
+                "CONTROL_CHANNEL" => Some(Self::ControlChannel),
+                _ => None,
+            }
+        }
+    }
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -69,13 +147,52 @@ pub struct DiscoverRequest {
     /// for the 'DiscoveryHandler' to discover devices
     #[prost(map = "string, message", tag = "2")]
     pub discovery_properties: ::std::collections::HashMap<::prost::alloc::string::String, ByteData>,
+    /// Optional settings controlling how often and for how long the 'DiscoveryHandler'
+    /// looks for devices. Handlers use their own defaults for any unset value.
+    #[prost(message, optional, tag = "3")]
+    pub schedule: ::core::option::Option<DiscoverySchedule>,
+    /// Whether the Agent can apply incremental updates of the device list, see `DevicesDelta`.
+    /// Handlers must only send full device lists when it is not set.
+    #[prost(bool, tag = "4")]
+    pub accept_deltas: bool,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct DiscoverySchedule {
+    /// Number of seconds to wait between two discovery scans
+    #[prost(uint64, optional, tag = "1")]
+    pub interval_seconds: ::core::option::Option<u64>,
+    /// Maximum number of seconds a single discovery scan may take
+    #[prost(uint64, optional, tag = "2")]
+    pub timeout_seconds: ::core::option::Option<u64>,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct DiscoverResponse {
-    /// List of discovered devices
+    /// List of discovered devices, or only the added and modified ones if `delta` is set and
+    /// is not a snapshot
     #[prost(message, repeated, tag = "1")]
     pub devices: ::prost::alloc::vec::Vec<Device>,
+    /// Set when the response is part of an incremental update of the device list
+    #[prost(message, optional, tag = "2")]
+    pub delta: ::core::option::Option<DevicesDelta>,
+}
+/// Incremental update of the device list, devices are identified by their id
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct DevicesDelta {
+    /// Sequence number of the response in the stream, it starts with a snapshot and is
+    /// increased by one with every response. A gap makes the Agent ignore the deltas until
+    /// the next snapshot.
+    #[prost(uint64, tag = "1")]
+    pub sequence: u64,
+    /// Whether `devices` holds all the discovered devices, Discovery Handlers periodically
+    /// send snapshots to recover from lost updates
+    #[prost(bool, tag = "2")]
+    pub snapshot: bool,
+    /// Ids of the devices that are no longer discovered
+    #[prost(string, repeated, tag = "3")]
+    pub removed_ids: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -95,6 +212,54 @@ pub struct Device {
     /// Optionally specify device information to be mounted for Pods that request this device as a resource
     #[prost(message, repeated, tag = "4")]
     pub device_specs: ::prost::alloc::vec::Vec<DeviceSpec>,
+    /// Optionally report the health of the device. Devices that do not set it are considered healthy
+    #[prost(message, optional, tag = "5")]
+    pub health: ::core::option::Option<DeviceHealth>,
+}
+/// Health of a discovered device, as seen by its Discovery Handler
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct DeviceHealth {
+    #[prost(enumeration = "device_health::State", tag = "1")]
+    pub state: i32,
+    /// Human readable explanation of the state
+    #[prost(string, tag = "2")]
+    pub reason: ::prost::alloc::string::String,
+}
+/// Nested message and enum types in `DeviceHealth`.
+pub mod device_health {
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+    #[repr(i32)]
+    pub enum State {
+        /// The device is working as expected
+        Healthy = 0,
+        /// The device is still visible but cannot be used (e.g. it doesn't answer queries)
+        Unhealthy = 1,
+        /// The device can be used but is not fully functional
+        Degraded = 2,
+    }
+    impl State {
+        /// String value of the enum field names used in the ProtoBuf definition.
+        ///
+        /// The values are not transformed in any way and thus are considered stable
+        /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+        pub fn as_str_name(&self) -> &'static str {
+            match self {
+                State::Healthy => "HEALTHY",
+                State::Unhealthy => "UNHEALTHY",
+                State::Degraded => "DEGRADED",
+            }
+        }
+        /// Creates an enum from field names used in the ProtoBuf definition.
+        pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+            match value {
+                "HEALTHY" => Some(Self::Healthy),
+                "UNHEALTHY" => Some(Self::Unhealthy),
+                "DEGRADED" => Some(Self::Degraded),
+                _ => None,
+            }
+        }
+    }
 }
 /// From Device Plugin  API
 /// Mount specifies a host volume to mount into a container.
@@ -1,11 +1,19 @@
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::sync::{Mutex, OnceLock};
+
+use regex::{Regex, RegexBuilder};
+use schemars::JsonSchema;
+
 /// This defines the types of supported filters
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, PartialEq, Default)]
 pub enum FilterType {
     /// If the filter type is Exclude, any items NOT found in the
     /// list are accepted
     Exclude,
     /// If the filter type is Include, only items found in the
     /// list are accepted
+    #[default]
     Include,
 }
 
@@ -14,12 +22,152 @@ fn default_action() -> FilterType {
     FilterType::Include
 }
 
+/// This defines how the items of a filter list are matched against a value.
+/// All the matches are case-insensitive.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, Debug, PartialEq, Default)]
+pub enum FilterItemKind {
+    /// The item is the exact expected value
+    #[default]
+    Exact,
+    /// The item is a glob pattern, where `*` matches any sequence of characters
+    /// and `?` matches any single character
+    Glob,
+    /// The item is a regular expression, that must match a part of the value
+    Regex,
+    /// The item is an IP address, a CIDR network (`10.0.0.0/24`) or an inclusive
+    /// IP range (`10.0.0.10-10.0.0.20`), that the value must be part of
+    Cidr,
+}
+
+impl FilterItemKind {
+    fn is_exact(&self) -> bool {
+        *self == FilterItemKind::Exact
+    }
+
+    /// Tests whether a value matches a filter item of this kind, an invalid item never matches
+    pub fn matches(&self, item: &str, value: &str) -> bool {
+        match self {
+            FilterItemKind::Exact => item.to_lowercase() == value.to_lowercase(),
+            FilterItemKind::Glob => glob_to_regex(item).is_some_and(|r| r.is_match(value)),
+            FilterItemKind::Regex => build_regex(item).is_some_and(|r| r.is_match(value)),
+            FilterItemKind::Cidr => match value.trim().parse::<IpAddr>() {
+                Ok(ip) => ip_in_range(item, ip).unwrap_or_else(|| {
+                    log::warn!("Invalid IP address, network or range in filter: {}", item);
+                    false
+                }),
+                Err(_) => false,
+            },
+        }
+    }
+}
+
+/// Maximum number of regular expressions kept in the cache
+const REGEX_CACHE_SIZE: usize = 256;
+
+/// Regular expressions built from the filter items, the filters are evaluated against every
+/// discovered device so each item only gets compiled once
+static REGEX_CACHE: OnceLock<Mutex<HashMap<String, Option<Regex>>>> = OnceLock::new();
+
+fn build_regex(pattern: &str) -> Option<Regex> {
+    let mut cache = REGEX_CACHE
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(|e| e.into_inner());
+    if let Some(regex) = cache.get(pattern) {
+        return regex.clone();
+    }
+    let regex = match RegexBuilder::new(pattern).case_insensitive(true).build() {
+        Ok(regex) => Some(regex),
+        Err(e) => {
+            log::warn!("Invalid regular expression in filter {}: {}", pattern, e);
+            None
+        }
+    };
+    // Items of removed filters are never evicted otherwise
+    if cache.len() >= REGEX_CACHE_SIZE {
+        cache.clear();
+    }
+    cache.insert(pattern.to_string(), regex.clone());
+    regex
+}
+
+fn glob_to_regex(glob: &str) -> Option<Regex> {
+    let pattern = glob
+        .split('*')
+        .map(|part| {
+            part.split('?')
+                .map(regex::escape)
+                .collect::<Vec<_>>()
+                .join(".")
+        })
+        .collect::<Vec<_>>()
+        .join(".*");
+    build_regex(&format!("^{}$", pattern))
+}
+
+/// Converts an IP address to a number, within the address space of its family
+pub fn ip_to_u128(ip: IpAddr) -> u128 {
+    match ip {
+        IpAddr::V4(ip) => u32::from(ip) as u128,
+        IpAddr::V6(ip) => u128::from(ip),
+    }
+}
+
+/// Converts a number back to an IP address of the given family
+pub fn u128_to_ip(ip: u128, ipv4: bool) -> IpAddr {
+    match ipv4 {
+        true => IpAddr::V4(Ipv4Addr::from(ip as u32)),
+        false => IpAddr::V6(Ipv6Addr::from(ip)),
+    }
+}
+
+/// Parses an IP address, a CIDR network (`10.0.0.0/24`) or an inclusive IP range
+/// (`10.0.0.10-10.0.0.20`) into the first and last addresses it holds,
+/// returns None if the item is invalid
+pub fn parse_ip_range(item: &str) -> Option<(IpAddr, IpAddr)> {
+    let item = item.trim();
+    if let Some((network, prefix)) = item.split_once('/') {
+        let network: IpAddr = network.trim().parse().ok()?;
+        let prefix: u32 = prefix.trim().parse().ok()?;
+        let bits = if network.is_ipv4() { 32 } else { 128 };
+        if prefix > bits {
+            return None;
+        }
+        let host_mask = u128::MAX.checked_shr(128 - bits + prefix).unwrap_or(0);
+        let start = ip_to_u128(network) & !host_mask;
+        Some((
+            u128_to_ip(start, network.is_ipv4()),
+            u128_to_ip(start | host_mask, network.is_ipv4()),
+        ))
+    } else if let Some((start, end)) = item.split_once('-') {
+        let start: IpAddr = start.trim().parse().ok()?;
+        let end: IpAddr = end.trim().parse().ok()?;
+        if start.is_ipv4() != end.is_ipv4() || ip_to_u128(start) > ip_to_u128(end) {
+            return None;
+        }
+        Some((start, end))
+    } else {
+        let ip: IpAddr = item.parse().ok()?;
+        Some((ip, ip))
+    }
+}
+
+/// Tests whether the IP is part of the address, network or range described by the item,
+/// returns None if the item is invalid
+fn ip_in_range(item: &str, ip: IpAddr) -> Option<bool> {
+    let (start, end) = parse_ip_range(item)?;
+    Some(
+        start.is_ipv4() == ip.is_ipv4()
+            && (ip_to_u128(start)..=ip_to_u128(end)).contains(&ip_to_u128(ip)),
+    )
+}
+
 /// This defines a filter list.
 ///
 /// The items list can either define the only acceptable
 /// items (Include) or can define the only unacceptable items
 /// (Exclude)
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct FilterList {
     /// This defines a list of items that will be evaluated as part
@@ -30,15 +178,28 @@ pub struct FilterList {
     /// is `Include`
     #[serde(default = "default_action")]
     pub action: FilterType,
+    /// This defines how the items are matched against a value.  The default
+    /// is `Exact`
+    #[serde(default, skip_serializing_if = "FilterItemKind::is_exact")]
+    pub item_kind: FilterItemKind,
+}
+
+impl FilterList {
+    /// Tests whether a value matches any of the items of the list
+    pub fn matches(&self, value: &str) -> bool {
+        self.items
+            .iter()
+            .any(|item| self.item_kind.matches(item, value))
+    }
 }
 
 /// This tests whether an item should be included according to the `FilterList`
 pub fn should_include(filter_list: Option<&FilterList>, item: &str) -> bool {
-    if filter_list.is_none() {
+    let Some(filter_list) = filter_list else {
         return true;
-    }
-    let item_contained = filter_list.unwrap().items.contains(&item.to_string());
-    if filter_list.as_ref().unwrap().action == FilterType::Include {
+    };
+    let item_contained = filter_list.matches(item);
+    if filter_list.action == FilterType::Include {
         item_contained
     } else {
         !item_contained
@@ -56,6 +217,7 @@ mod tests {
         let exclude_filter_list = Some(FilterList {
             items: exclude_items,
             action: FilterType::Exclude,
+            ..Default::default()
         });
         assert!(!should_include(exclude_filter_list.as_ref(), "beep"));
         assert!(!should_include(exclude_filter_list.as_ref(), "bop"));
@@ -66,6 +228,7 @@ mod tests {
         let empty_exclude_filter_list = Some(FilterList {
             items: empty_exclude_items,
             action: FilterType::Exclude,
+            ..Default::default()
         });
         assert!(should_include(empty_exclude_filter_list.as_ref(), "beep"));
 
@@ -74,6 +237,7 @@ mod tests {
         let include_filter_list = Some(FilterList {
             items: include_items,
             action: FilterType::Include,
+            ..Default::default()
         });
         assert!(should_include(include_filter_list.as_ref(), "beep"));
         assert!(should_include(include_filter_list.as_ref(), "bop"));
@@ -84,10 +248,114 @@ mod tests {
         let empty_include_filter_list = Some(FilterList {
             items: empty_include_items,
             action: FilterType::Include,
+            ..Default::default()
         });
         assert!(!should_include(empty_include_filter_list.as_ref(), "beep"));
 
         // Test when None
         assert!(should_include(None, "beep"));
     }
+
+    #[test]
+    fn test_should_include_case_insensitive() {
+        let filter_list = Some(FilterList {
+            items: vec!["Beep".to_string()],
+            action: FilterType::Include,
+            item_kind: FilterItemKind::Exact,
+        });
+        assert!(should_include(filter_list.as_ref(), "beep"));
+        assert!(should_include(filter_list.as_ref(), "BEEP"));
+        assert!(!should_include(filter_list.as_ref(), "beeps"));
+    }
+
+    #[test]
+    fn test_filter_item_kind_glob() {
+        let kind = FilterItemKind::Glob;
+        assert!(kind.matches(
+            "onvif://www.onvif.org/name/*",
+            "onvif://www.onvif.org/name/NVT"
+        ));
+        assert!(kind.matches("camera-??", "Camera-01"));
+        assert!(!kind.matches("camera-??", "camera-001"));
+        assert!(!kind.matches("*.onvif.org", "www.onvif.org.evil"));
+        assert!(kind.matches("a.b", "A.B"));
+        assert!(!kind.matches("a.b", "axb"));
+    }
+
+    #[test]
+    fn test_filter_item_kind_regex() {
+        let kind = FilterItemKind::Regex;
+        assert!(kind.matches("^00:11:22", "00:11:22:33:44:55"));
+        assert!(kind.matches("aa:bb", "00:AA:BB:cc:dd:ee"));
+        assert!(!kind.matches("^aa:bb", "00:aa:bb:cc:dd:ee"));
+        // An invalid regex never matches
+        assert!(!kind.matches("(", "("));
+    }
+
+    #[test]
+    fn test_filter_item_kind_cidr() {
+        let kind = FilterItemKind::Cidr;
+        assert!(kind.matches("10.0.0.0/24", "10.0.0.12"));
+        assert!(!kind.matches("10.0.0.0/24", "10.0.1.12"));
+        assert!(kind.matches("0.0.0.0/0", "192.168.1.1"));
+        assert!(!kind.matches("0.0.0.0/0", "fe80::1"));
+        assert!(kind.matches("fe80::/64", "FE80::1234"));
+        assert!(!kind.matches("fe80::/64", "fe81::1"));
+        assert!(kind.matches("10.0.0.10-10.0.0.20", "10.0.0.15"));
+        assert!(kind.matches("10.0.0.10 - 10.0.0.20", "10.0.0.20"));
+        assert!(!kind.matches("10.0.0.10-10.0.0.20", "10.0.0.21"));
+        assert!(kind.matches("10.0.0.1", "10.0.0.1"));
+        assert!(kind.matches("FE80::1", "fe80::1"));
+        assert!(!kind.matches("10.0.0.1", "10.0.0.2"));
+        // Invalid items or values never match
+        assert!(!kind.matches("10.0.0.0/33", "10.0.0.1"));
+        assert!(!kind.matches("not-an-ip", "10.0.0.1"));
+        assert!(!kind.matches("10.0.0.0/24", "not-an-ip"));
+    }
+
+    #[test]
+    fn test_parse_ip_range() {
+        let range = |start: &str, end: &str| Some((start.parse().unwrap(), end.parse().unwrap()));
+        assert_eq!(
+            parse_ip_range("10.0.0.0/24"),
+            range("10.0.0.0", "10.0.0.255")
+        );
+        assert_eq!(parse_ip_range("10.0.0.7/32"), range("10.0.0.7", "10.0.0.7"));
+        assert_eq!(
+            parse_ip_range("10.0.0.10 - 10.0.0.20"),
+            range("10.0.0.10", "10.0.0.20")
+        );
+        assert_eq!(
+            parse_ip_range("192.168.1.5"),
+            range("192.168.1.5", "192.168.1.5")
+        );
+        assert_eq!(parse_ip_range("fd00::/126"), range("fd00::", "fd00::3"));
+        assert_eq!(parse_ip_range("10.0.0.0/33"), None);
+        assert_eq!(parse_ip_range("10.0.0.20-10.0.0.10"), None);
+        assert_eq!(parse_ip_range("10.0.0.1-fd00::1"), None);
+        assert_eq!(parse_ip_range("not-an-ip"), None);
+    }
+
+    #[test]
+    fn test_filter_list_deserialization() {
+        // Filter lists without item kind keep their exact matching behavior
+        let filter_list: FilterList = serde_yaml::from_str("items: [foo, bar]").unwrap();
+        assert_eq!(filter_list.action, FilterType::Include);
+        assert_eq!(filter_list.item_kind, FilterItemKind::Exact);
+        assert_eq!(
+            serde_json::to_string(&filter_list).unwrap(),
+            r#"{"items":["foo","bar"],"action":"Include"}"#
+        );
+
+        let filter_list: FilterList =
+            serde_yaml::from_str("items: [10.0.0.0/24]\naction: Exclude\nitemKind: Cidr").unwrap();
+        assert_eq!(filter_list.action, FilterType::Exclude);
+        assert_eq!(filter_list.item_kind, FilterItemKind::Cidr);
+        assert_eq!(
+            serde_json::to_string(&filter_list).unwrap(),
+            r#"{"items":["10.0.0.0/24"],"action":"Exclude","itemKind":"Cidr"}"#
+        );
+        assert!(!should_include(Some(&filter_list), "10.0.0.1"));
+        assert!(should_include(Some(&filter_list), "10.0.1.1"));
+    }
 }
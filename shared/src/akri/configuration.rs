@@ -7,17 +7,27 @@
 use k8s_openapi::api::batch::v1::JobSpec;
 use k8s_openapi::api::core::v1::PodSpec;
 use k8s_openapi::api::core::v1::ServiceSpec;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::{Condition, Time};
 use kube::CustomResource;
 use kube::{
     api::{Api, ListParams, ObjectList},
     client::Client,
 };
+use regex::Regex;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+use super::status::{ssa_conditions_map, ssa_nodes_map};
+use super::API_NAMESPACE;
+
 pub type ConfigurationList = ObjectList<Configuration>;
 
+/// Number of nodes that can schedule workloads for a capability, if the Configuration does not set `capacity`
+pub const DEFAULT_CAPACITY: usize = 1;
+/// Finalizer added to Configurations on creation, removed by the Controller once the Agents released them
+pub const CONFIGURATION_FINALIZER: &str = API_NAMESPACE;
+
 /// Selects a key from a ConfigMap or Secret
 #[derive(Serialize, Deserialize, PartialEq, Clone, Debug, JsonSchema)]
 pub struct DiscoveryPropertyKeySelector {
@@ -78,6 +88,16 @@ pub struct DiscoveryHandlerInfo {
 
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub discovery_properties: Option<Vec<DiscoveryProperty>>,
+
+    /// Number of seconds the Discovery Handler waits between two discovery scans.
+    /// The Discovery Handler's default is used if not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub discovery_interval_seconds: Option<u64>,
+
+    /// Maximum number of seconds a single discovery scan may take.
+    /// The Discovery Handler's default is used if not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub discovery_timeout_seconds: Option<u64>,
 }
 
 /// This defines a workload that should be scheduled to nodes
@@ -95,6 +115,167 @@ pub enum BrokerSpec {
     BrokerJobSpec(Box<JobSpec>),
 }
 
+/// This defines how the Configuration level device plugin chooses among the
+/// available virtual devices when a container requests several of them
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Copy, Debug, Default, JsonSchema)]
+pub enum AllocationStrategy {
+    /// Prefer the Instances that have the fewest slots in use
+    #[default]
+    Spread,
+    /// Prefer the Instances that have the most slots in use
+    Pack,
+    /// Prefer Instances that share the same value for a given broker property
+    Affinity,
+}
+
+/// This defines the policy used to prefer some virtual devices over others
+/// when allocating several of them to the same container
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug, Default, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AllocationPolicy {
+    #[serde(default)]
+    pub strategy: AllocationStrategy,
+
+    /// Name of the broker property used to group Instances with the `Affinity` strategy
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub property: Option<String>,
+}
+
+/// This defines the relation between a device property and the values of a selector requirement
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Copy, Debug, JsonSchema)]
+pub enum DeviceSelectorOperator {
+    /// The property exists and its value is one of the values
+    In,
+    /// The property doesn't exist or its value is none of the values
+    NotIn,
+    /// The property exists, the values must be empty
+    Exists,
+    /// The property doesn't exist, the values must be empty
+    DoesNotExist,
+    /// The property exists and its whole value matches one of the values as regular expressions
+    Matches,
+}
+
+/// This defines a requirement on a property of the discovered devices
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DeviceSelectorRequirement {
+    /// Name of the device property the requirement applies to
+    pub key: String,
+    pub operator: DeviceSelectorOperator,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub values: Vec<String>,
+}
+
+/// This defines a selector over the properties of the discovered devices,
+/// only the devices matching all the requirements are turned into Instances and CDI devices
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug, Default, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DeviceSelector {
+    #[serde(default)]
+    pub match_expressions: Vec<DeviceSelectorRequirement>,
+}
+
+/// Compiles a `Matches` value, anchored so that it has to match the whole property value
+fn compile_regex(value: &str) -> Result<Regex, regex::Error> {
+    Regex::new(&format!("^(?:{})$", value))
+}
+
+impl DeviceSelector {
+    /// Checks that the values of the `Matches` requirements are valid regular expressions
+    pub fn validate(&self) -> Result<(), String> {
+        let errors: Vec<String> = self
+            .match_expressions
+            .iter()
+            .enumerate()
+            .filter(|(_, requirement)| requirement.operator == DeviceSelectorOperator::Matches)
+            .flat_map(|(i, requirement)| {
+                requirement
+                    .values
+                    .iter()
+                    .enumerate()
+                    .filter_map(move |(j, value)| {
+                        compile_regex(value).err().map(|e| {
+                            format!("selector.matchExpressions[{}].values[{}]: {}", i, j, e)
+                        })
+                    })
+            })
+            .collect();
+        match errors.is_empty() {
+            true => Ok(()),
+            false => Err(errors.join(", ")),
+        }
+    }
+
+    /// Compiles the regular expressions of the selector, invalid ones never match
+    pub fn compile(&self) -> CompiledDeviceSelector {
+        let requirements = self
+            .match_expressions
+            .iter()
+            .map(|requirement| {
+                let regexes = match requirement.operator {
+                    DeviceSelectorOperator::Matches => requirement
+                        .values
+                        .iter()
+                        .filter_map(|r| match compile_regex(r) {
+                            Ok(regex) => Some(regex),
+                            Err(e) => {
+                                log::warn!(
+                                    "Invalid regular expression {} for {}: {}",
+                                    r,
+                                    requirement.key,
+                                    e
+                                );
+                                None
+                            }
+                        })
+                        .collect(),
+                    _ => vec![],
+                };
+                (requirement.clone(), regexes)
+            })
+            .collect();
+        CompiledDeviceSelector { requirements }
+    }
+}
+
+/// A device selector ready to be matched against the properties of the discovered devices
+#[derive(Clone, Debug)]
+pub struct CompiledDeviceSelector {
+    requirements: Vec<(DeviceSelectorRequirement, Vec<Regex>)>,
+}
+
+impl CompiledDeviceSelector {
+    /// Checks whether a device with the given properties is selected
+    pub fn matches(&self, properties: &HashMap<String, String>) -> bool {
+        self.requirements.iter().all(|(requirement, regexes)| {
+            let value = properties.get(&requirement.key);
+            match requirement.operator {
+                DeviceSelectorOperator::In => value.is_some_and(|v| requirement.values.contains(v)),
+                DeviceSelectorOperator::NotIn => {
+                    !value.is_some_and(|v| requirement.values.contains(v))
+                }
+                DeviceSelectorOperator::Exists => value.is_some(),
+                DeviceSelectorOperator::DoesNotExist => value.is_none(),
+                DeviceSelectorOperator::Matches => {
+                    value.is_some_and(|v| regexes.iter().any(|regex| regex.is_match(v)))
+                }
+            }
+        })
+    }
+}
+
+impl PartialEq for CompiledDeviceSelector {
+    fn eq(&self, other: &Self) -> bool {
+        self.requirements.len() == other.requirements.len()
+            && self
+                .requirements
+                .iter()
+                .zip(other.requirements.iter())
+                .all(|((a, _), (b, _))| a == b)
+    }
+}
+
 /// Defines the information in the Akri Configuration CRD
 ///
 /// A Configuration is the primary method for users to describe anticipated
@@ -103,7 +284,13 @@ pub enum BrokerSpec {
 /// is created.
 #[derive(CustomResource, Serialize, Deserialize, Clone, Debug, JsonSchema)]
 // group = API_NAMESPACE and version = API_VERSION
-#[kube(group = "akri.sh", version = "v0", kind = "Configuration", namespaced)]
+#[kube(
+    group = "akri.sh",
+    version = "v0",
+    kind = "Configuration",
+    namespaced,
+    status = "ConfigurationStatus"
+)]
 #[serde(rename_all = "camelCase")]
 pub struct ConfigurationSpec {
     /// This defines the `DiscoveryHandler` that should be used to
@@ -149,6 +336,56 @@ pub struct ConfigurationSpec {
     /// that represent the discovered resources.
     #[serde(default)]
     pub broker_properties: HashMap<String, String>,
+
+    /// This defines how virtual devices are preferred when a
+    /// container requests several of them from the Configuration
+    /// level device plugin. Defaults to the `Spread` strategy.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allocation_policy: Option<AllocationPolicy>,
+
+    /// This defines requirements on the properties of the
+    /// discovered devices, only matching devices are turned into
+    /// Instances. This applies to every Discovery Handler.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub selector: Option<DeviceSelector>,
+}
+
+/// Defines the observed state of a Configuration
+#[derive(Serialize, Deserialize, Clone, Debug, Default, JsonSchema, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigurationStatus {
+    /// This contains the discovery state on every node running an Agent
+    /// that handles this Configuration, each entry is written by the
+    /// Agent of its node
+    #[serde(default)]
+    #[schemars(schema_with = "ssa_nodes_map::<NodeDiscoveryStatus>")]
+    pub nodes: Vec<NodeDiscoveryStatus>,
+}
+
+/// Defines the discovery state of a Configuration on a node
+#[derive(Serialize, Deserialize, Clone, Debug, Default, JsonSchema, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeDiscoveryStatus {
+    /// This contains the name of the node
+    pub node: String,
+
+    /// This contains the `DiscoveryActive` and `HandlerRegistered` conditions
+    #[serde(default)]
+    #[schemars(schema_with = "ssa_conditions_map")]
+    pub conditions: Vec<Condition>,
+
+    /// This contains the Discovery Handler endpoints that answered the
+    /// last discovery
+    #[serde(default)]
+    pub handler_endpoints: Vec<String>,
+
+    /// This contains the number of devices discovered on the node
+    #[serde(default)]
+    pub discovered_instances: usize,
+
+    /// This contains the time of the last discovery result
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_discovery_time: Option<Time>,
 }
 
 fn immutable_dh_info(gen: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
@@ -248,7 +485,7 @@ pub async fn find_configuration(
     }
 }
 fn default_capacity() -> usize {
-    1
+    DEFAULT_CAPACITY
 }
 
 #[cfg(test)]
@@ -285,6 +522,220 @@ mod crd_serialization_tests {
         assert_eq!(None, deserialized.instance_service_spec);
         assert_eq!(None, deserialized.configuration_service_spec);
         assert_eq!(0, deserialized.broker_properties.len());
+        assert_eq!(None, deserialized.allocation_policy);
+        assert_eq!(
+            None,
+            deserialized.discovery_handler.discovery_interval_seconds
+        );
+        assert_eq!(
+            None,
+            deserialized.discovery_handler.discovery_timeout_seconds
+        );
+    }
+
+    #[test]
+    fn test_config_serialization_discovery_schedule() {
+        let _ = env_logger::builder().is_test(true).try_init();
+        let json = r#"{"discoveryHandler":{"name":"opcua", "discoveryDetails":"", "discoveryIntervalSeconds":60, "discoveryTimeoutSeconds":30}}"#;
+        let deserialized: ConfigurationSpec = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            Some(60),
+            deserialized.discovery_handler.discovery_interval_seconds
+        );
+        assert_eq!(
+            Some(30),
+            deserialized.discovery_handler.discovery_timeout_seconds
+        );
+        let serialized = serde_json::to_string(&deserialized.discovery_handler).unwrap();
+        let expected_serialized = r#"{"name":"opcua","discoveryDetails":"","discoveryIntervalSeconds":60,"discoveryTimeoutSeconds":30}"#;
+        assert_eq!(expected_serialized, serialized);
+    }
+
+    #[test]
+    fn test_config_serialization_allocation_policy() {
+        let _ = env_logger::builder().is_test(true).try_init();
+        let json = r#"{"discoveryHandler":{"name":"udev"}, "allocationPolicy":{"strategy":"Affinity", "property":"UDEV_BUS"}}"#;
+        let deserialized: ConfigurationSpec = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            Some(AllocationPolicy {
+                strategy: AllocationStrategy::Affinity,
+                property: Some("UDEV_BUS".to_string()),
+            }),
+            deserialized.allocation_policy
+        );
+
+        let json = r#"{"discoveryHandler":{"name":"udev"}, "allocationPolicy":{}}"#;
+        let deserialized: ConfigurationSpec = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            Some(AllocationPolicy::default()),
+            deserialized.allocation_policy
+        );
+        let serialized = serde_json::to_string(&deserialized.allocation_policy).unwrap();
+        assert_eq!(r#"{"strategy":"Spread"}"#, serialized);
+    }
+
+    #[test]
+    fn test_config_serialization_selector() {
+        let _ = env_logger::builder().is_test(true).try_init();
+        let json = r#"{"discoveryHandler":{"name":"udev"}, "selector":{"matchExpressions":[{"key":"UDEV_DEVPATH", "operator":"Matches", "values":["^/dev/video[0-9]+$"]}, {"key":"UDEV_BUS", "operator":"Exists"}]}}"#;
+        let deserialized: ConfigurationSpec = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            Some(DeviceSelector {
+                match_expressions: vec![
+                    DeviceSelectorRequirement {
+                        key: "UDEV_DEVPATH".to_string(),
+                        operator: DeviceSelectorOperator::Matches,
+                        values: vec!["^/dev/video[0-9]+$".to_string()],
+                    },
+                    DeviceSelectorRequirement {
+                        key: "UDEV_BUS".to_string(),
+                        operator: DeviceSelectorOperator::Exists,
+                        values: vec![],
+                    },
+                ],
+            }),
+            deserialized.selector
+        );
+        let serialized = serde_json::to_string(&deserialized.selector).unwrap();
+        assert_eq!(
+            r#"{"matchExpressions":[{"key":"UDEV_DEVPATH","operator":"Matches","values":["^/dev/video[0-9]+$"]},{"key":"UDEV_BUS","operator":"Exists"}]}"#,
+            serialized
+        );
+
+        let json = r#"{"discoveryHandler":{"name":"udev"}}"#;
+        let deserialized: ConfigurationSpec = serde_json::from_str(json).unwrap();
+        assert_eq!(None, deserialized.selector);
+    }
+
+    #[test]
+    fn test_device_selector_matches() {
+        let _ = env_logger::builder().is_test(true).try_init();
+        let requirement = |key: &str, operator: DeviceSelectorOperator, values: Vec<&str>| {
+            DeviceSelectorRequirement {
+                key: key.to_string(),
+                operator,
+                values: values.into_iter().map(|v| v.to_string()).collect(),
+            }
+        };
+        let properties = HashMap::from([
+            (
+                "ONVIF_DEVICE_MAC_ADDRESS".to_string(),
+                "00:11:22:33:44:55".to_string(),
+            ),
+            ("UDEV_DEVPATH".to_string(), "/dev/video0".to_string()),
+        ]);
+        let tests = vec![
+            (
+                requirement(
+                    "ONVIF_DEVICE_MAC_ADDRESS",
+                    DeviceSelectorOperator::In,
+                    vec!["00:11:22:33:44:55", "66:77:88:99:aa:bb"],
+                ),
+                true,
+            ),
+            (
+                requirement("MISSING", DeviceSelectorOperator::In, vec!["a"]),
+                false,
+            ),
+            (
+                requirement(
+                    "ONVIF_DEVICE_MAC_ADDRESS",
+                    DeviceSelectorOperator::NotIn,
+                    vec!["00:11:22:33:44:55"],
+                ),
+                false,
+            ),
+            (
+                requirement("MISSING", DeviceSelectorOperator::NotIn, vec!["a"]),
+                true,
+            ),
+            (
+                requirement("UDEV_DEVPATH", DeviceSelectorOperator::Exists, vec![]),
+                true,
+            ),
+            (
+                requirement("UDEV_DEVPATH", DeviceSelectorOperator::DoesNotExist, vec![]),
+                false,
+            ),
+            (
+                requirement(
+                    "UDEV_DEVPATH",
+                    DeviceSelectorOperator::Matches,
+                    vec!["^/dev/sd.*", "^/dev/video[0-9]+$"],
+                ),
+                true,
+            ),
+            (
+                requirement(
+                    "UDEV_DEVPATH",
+                    DeviceSelectorOperator::Matches,
+                    vec!["/dev/video[0-9]+"],
+                ),
+                true,
+            ),
+            // The values have to match the whole property value
+            (
+                requirement(
+                    "UDEV_DEVPATH",
+                    DeviceSelectorOperator::Matches,
+                    vec!["video"],
+                ),
+                false,
+            ),
+            (
+                requirement(
+                    "UDEV_DEVPATH",
+                    DeviceSelectorOperator::Matches,
+                    vec!["/dev|foo"],
+                ),
+                false,
+            ),
+            (
+                requirement("UDEV_DEVPATH", DeviceSelectorOperator::Matches, vec!["("]),
+                false,
+            ),
+            (
+                requirement("MISSING", DeviceSelectorOperator::Matches, vec![".*"]),
+                false,
+            ),
+        ];
+        for (requirement, expected) in tests {
+            let selector = DeviceSelector {
+                match_expressions: vec![requirement.clone()],
+            }
+            .compile();
+            assert_eq!(expected, selector.matches(&properties), "{:?}", requirement);
+        }
+        assert!(DeviceSelector::default().compile().matches(&properties));
+    }
+
+    #[test]
+    fn test_device_selector_validate() {
+        let selector = |operator: DeviceSelectorOperator, values: Vec<&str>| DeviceSelector {
+            match_expressions: vec![DeviceSelectorRequirement {
+                key: "UDEV_DEVPATH".to_string(),
+                operator,
+                values: values.into_iter().map(|v| v.to_string()).collect(),
+            }],
+        };
+        assert!(DeviceSelector::default().validate().is_ok());
+        assert!(
+            selector(DeviceSelectorOperator::Matches, vec!["/dev/video[0-9]+"])
+                .validate()
+                .is_ok()
+        );
+        // Only the values of Matches requirements are regular expressions
+        assert!(selector(DeviceSelectorOperator::In, vec!["("])
+            .validate()
+            .is_ok());
+        let error = selector(DeviceSelectorOperator::Matches, vec![".*", "("])
+            .validate()
+            .unwrap_err();
+        assert!(
+            error.starts_with("selector.matchExpressions[0].values[1]"),
+            "{}",
+            error
+        );
     }
 
     #[test]
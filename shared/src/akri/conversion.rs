@@ -0,0 +1,561 @@
+//! Conversion of Configurations and Instances between the versions of the Akri API.
+//!
+//! The API server calls the conversion webhook whenever an object is read or written in a version other than the
+//! one it is stored in. Objects are converted as JSON, only the fields that differ between the versions are
+//! rewritten so that everything else, including fields unknown to this version of Akri, is kept as is.
+//!
+//! Converting an object to another version and back must give the original object, except for the slots of `v0`
+//! Instances written in their structured form while their legacy string encoding can hold them, which come back
+//! in this encoding that reads the same. The discovery details of a `v0` Configuration are a YAML string that may not be valid, may not be a mapping or may hold comments and
+//! formatting that structured data can't represent: in these cases the original string is kept in the
+//! [`V0_DISCOVERY_DETAILS_ANNOTATION`] of the `v1` Configuration and restored when converting it back to `v0`,
+//! unless the discovery details changed in the meantime.
+use anyhow::{anyhow, bail};
+use serde_json::{Map, Value};
+
+use super::{v1::instance::SlotUsage, API_NAMESPACE};
+
+/// Annotation of `v1` Configurations holding the `v0` discovery details they were converted from,
+/// when these can't be rebuilt from the structured discovery details
+pub const V0_DISCOVERY_DETAILS_ANNOTATION: &str = "akri.sh/v0-discovery-details";
+
+/// Versions of the Akri API
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApiVersion {
+    V0,
+    V1,
+}
+
+impl ApiVersion {
+    /// Parses a full API version, e.g. `akri.sh/v1`
+    pub fn parse(api_version: &str) -> Result<Self, anyhow::Error> {
+        match api_version.split_once('/') {
+            Some((API_NAMESPACE, super::API_VERSION)) => Ok(Self::V0),
+            Some((API_NAMESPACE, super::v1::API_VERSION)) => Ok(Self::V1),
+            _ => Err(anyhow!("unsupported API version {}", api_version)),
+        }
+    }
+
+    /// Returns the full API version, e.g. `akri.sh/v1`
+    pub fn api_version(&self) -> String {
+        let version = match self {
+            Self::V0 => super::API_VERSION,
+            Self::V1 => super::v1::API_VERSION,
+        };
+        format!("{}/{}", API_NAMESPACE, version)
+    }
+}
+
+/// Converts a Configuration or an Instance to the desired API version
+pub fn convert(object: &Value, desired_api_version: &str) -> Result<Value, anyhow::Error> {
+    let from = ApiVersion::parse(
+        object["apiVersion"]
+            .as_str()
+            .ok_or_else(|| anyhow!("object has no apiVersion"))?,
+    )?;
+    let to = ApiVersion::parse(desired_api_version)?;
+    let mut converted = object.clone();
+    match (object["kind"].as_str(), from, to) {
+        (_, from, to) if from == to => {}
+        (Some("Configuration"), ApiVersion::V0, ApiVersion::V1) => {
+            configuration_to_v1(&mut converted)?
+        }
+        (Some("Configuration"), ApiVersion::V1, ApiVersion::V0) => {
+            configuration_to_v0(&mut converted)?
+        }
+        (Some("Instance"), ApiVersion::V0, ApiVersion::V1) => instance_to_v1(&mut converted)?,
+        (Some("Instance"), ApiVersion::V1, ApiVersion::V0) => instance_to_v0(&mut converted)?,
+        (kind, _, _) => bail!("unsupported kind {:?}", kind),
+    }
+    converted["apiVersion"] = Value::String(to.api_version());
+    Ok(converted)
+}
+
+fn configuration_to_v1(configuration: &mut Value) -> Result<(), anyhow::Error> {
+    let details = match configuration
+        .pointer_mut("/spec/discoveryHandler")
+        .and_then(Value::as_object_mut)
+    {
+        Some(info) => match info.remove("discoveryDetails") {
+            None => return Ok(()),
+            Some(Value::String(details)) => {
+                if let Some(structured) = structured_details(&details) {
+                    let unchanged = serde_yaml::to_string(&structured)? == details;
+                    info.insert("discoveryDetails".to_owned(), Value::Object(structured));
+                    if unchanged {
+                        return Ok(());
+                    }
+                }
+                details
+            }
+            Some(_) => bail!("discoveryDetails of a v0 Configuration must be a string"),
+        },
+        None => return Ok(()),
+    };
+    let metadata = configuration["metadata"]
+        .as_object_mut()
+        .ok_or_else(|| anyhow!("Configuration has no metadata"))?;
+    let annotations = metadata
+        .entry("annotations")
+        .or_insert_with(|| Value::Object(Map::new()));
+    annotations
+        .as_object_mut()
+        .ok_or_else(|| anyhow!("annotations of the Configuration must be an object"))?
+        .insert(
+            V0_DISCOVERY_DETAILS_ANNOTATION.to_owned(),
+            Value::String(details),
+        );
+    Ok(())
+}
+
+fn configuration_to_v0(configuration: &mut Value) -> Result<(), anyhow::Error> {
+    let original = take_annotation(configuration, V0_DISCOVERY_DETAILS_ANNOTATION);
+    let info = match configuration
+        .pointer_mut("/spec/discoveryHandler")
+        .and_then(Value::as_object_mut)
+    {
+        Some(info) => info,
+        None => return Ok(()),
+    };
+    let details = match info.remove("discoveryDetails") {
+        Some(Value::Object(structured)) => match original {
+            Some(original) if structured_details(&original).as_ref() == Some(&structured) => {
+                original
+            }
+            _ => serde_yaml::to_string(&structured)?,
+        },
+        // Structured discovery details removed since the conversion to v1 are not restored
+        None | Some(Value::Null) => match original {
+            Some(original) if structured_details(&original).is_none() => original,
+            _ => return Ok(()),
+        },
+        Some(_) => bail!("discoveryDetails of a v1 Configuration must be an object"),
+    };
+    info.insert("discoveryDetails".to_owned(), Value::String(details));
+    Ok(())
+}
+
+/// Returns the structured form of v0 discovery details, if they are a YAML mapping
+fn structured_details(details: &str) -> Option<Map<String, Value>> {
+    match serde_yaml::from_str(details) {
+        Ok(Value::Object(structured)) => Some(structured),
+        _ => None,
+    }
+}
+
+fn take_annotation(object: &mut Value, annotation: &str) -> Option<String> {
+    let annotations = object
+        .pointer_mut("/metadata/annotations")?
+        .as_object_mut()?;
+    let value = annotations.remove(annotation)?;
+    if annotations.is_empty() {
+        if let Some(metadata) = object["metadata"].as_object_mut() {
+            metadata.remove("annotations");
+        }
+    }
+    value.as_str().map(str::to_owned)
+}
+
+fn instance_to_v1(instance: &mut Value) -> Result<(), anyhow::Error> {
+    for_each_slot(instance, |usage| match usage {
+        Value::String(usage) => Ok(serde_json::to_value(SlotUsage::from_v0(usage))?),
+        Value::Object(_) => {
+            serde_json::from_value::<SlotUsage>(usage.clone())?;
+            Ok(usage.clone())
+        }
+        _ => bail!("slots of a v0 Instance must be strings or objects"),
+    })
+}
+
+/// Slots are written in their `v0` string encoding, as Akri versions prior to the structured usage
+/// only read this one, unless it can't hold them
+fn instance_to_v0(instance: &mut Value) -> Result<(), anyhow::Error> {
+    for_each_slot(instance, |usage| {
+        let slot: SlotUsage = serde_json::from_value(usage.clone())?;
+        match slot.to_v0() {
+            Some(legacy) if serde_json::to_value(&slot)? == *usage => Ok(Value::String(legacy)),
+            _ => Ok(usage.clone()),
+        }
+    })
+}
+
+fn for_each_slot(
+    instance: &mut Value,
+    convert: impl Fn(&Value) -> Result<Value, anyhow::Error>,
+) -> Result<(), anyhow::Error> {
+    if let Some(slots) = instance
+        .pointer_mut("/spec/deviceUsage")
+        .and_then(Value::as_object_mut)
+    {
+        for usage in slots.values_mut() {
+            *usage = convert(usage)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::akri::{configuration, instance, v1};
+    use serde_json::json;
+
+    fn v0_configuration(discovery_details: Option<&str>) -> Value {
+        let mut configuration = json!({
+            "apiVersion": "akri.sh/v0",
+            "kind": "Configuration",
+            "metadata": {"name": "config-a", "namespace": "default", "uid": "e9a2d3c4"},
+            "spec": {
+                "discoveryHandler": {"name": "debugEcho", "discoveryTimeoutSeconds": 10},
+                "capacity": 2,
+                "brokerSpec": {"brokerPodSpec": {"containers": [{"name": "broker", "image": "nginx"}]}},
+                "brokerProperties": {"KEY": "value"}
+            },
+            "status": {"nodes": [{"node": "node-a", "conditions": [], "handlerEndpoints": [], "discoveredInstances": 1}]}
+        });
+        if let Some(details) = discovery_details {
+            configuration["spec"]["discoveryHandler"]["discoveryDetails"] = json!(details);
+        }
+        configuration
+    }
+
+    fn v0_instance(device_usage: Value) -> Value {
+        json!({
+            "apiVersion": "akri.sh/v0",
+            "kind": "Instance",
+            "metadata": {"name": "config-a-b494b6", "namespace": "default"},
+            "spec": {
+                "configurationName": "config-a",
+                "cdiName": "akri.sh/config-a=b494b6",
+                "capacity": 3,
+                "nodes": ["node-a", "node-b"],
+                "shared": true,
+                "deviceUsage": device_usage,
+            },
+            "status": {"conditions": [], "nodes": [{"node": "node-a"}]}
+        })
+    }
+
+    fn round_trip(object: &Value, via: ApiVersion) -> Value {
+        let original = object["apiVersion"].as_str().unwrap();
+        let converted = convert(object, &via.api_version()).unwrap();
+        assert_eq!(converted["apiVersion"], json!(via.api_version()));
+        convert(&converted, original).unwrap()
+    }
+
+    #[test]
+    fn test_api_version() {
+        assert_eq!(ApiVersion::parse("akri.sh/v0").unwrap(), ApiVersion::V0);
+        assert_eq!(ApiVersion::parse("akri.sh/v1").unwrap(), ApiVersion::V1);
+        assert!(ApiVersion::parse("akri.sh/v2").is_err());
+        assert!(ApiVersion::parse("example.com/v1").is_err());
+        assert!(ApiVersion::parse("v1").is_err());
+        assert_eq!(ApiVersion::V1.api_version(), "akri.sh/v1");
+    }
+
+    #[test]
+    fn test_convert_unsupported() {
+        let mut object = v0_configuration(None);
+        assert!(convert(&object, "akri.sh/v2").is_err());
+        object["kind"] = json!("Pod");
+        assert!(convert(&object, "akri.sh/v1").is_err());
+        object["apiVersion"] = json!("v1");
+        assert!(convert(&object, "akri.sh/v1").is_err());
+        // Converting to the same version leaves any object untouched
+        let object = json!({"apiVersion": "akri.sh/v1", "kind": "Pod"});
+        assert_eq!(convert(&object, "akri.sh/v1").unwrap(), object);
+    }
+
+    #[test]
+    fn test_configuration_to_v1() {
+        let v0 = v0_configuration(Some("descriptions:\n- foo0\n- foo1\n"));
+        let v1 = convert(&v0, "akri.sh/v1").unwrap();
+        assert_eq!(
+            v1["spec"]["discoveryHandler"]["discoveryDetails"],
+            json!({"descriptions": ["foo0", "foo1"]})
+        );
+        // Details written as the conversion would render them need no annotation
+        assert_eq!(v1["metadata"].get("annotations"), None);
+        // Everything else is kept as is
+        let mut expected = v0.clone();
+        expected["apiVersion"] = json!("akri.sh/v1");
+        expected["spec"]["discoveryHandler"]["discoveryDetails"] =
+            json!({"descriptions": ["foo0", "foo1"]});
+        assert_eq!(v1, expected);
+
+        let typed: v1::configuration::Configuration = serde_json::from_value(v1).unwrap();
+        assert_eq!(typed.spec.capacity, 2);
+        assert_eq!(
+            typed.spec.discovery_handler.discovery_details,
+            Some(
+                json!({"descriptions": ["foo0", "foo1"]})
+                    .as_object()
+                    .unwrap()
+                    .clone()
+            )
+        );
+    }
+
+    #[test]
+    fn test_configuration_to_v1_annotation() {
+        let cases = [
+            // Comments, ordering and formatting can't be represented
+            ("# cameras\ndescriptions: [foo0]\n", true),
+            // Neither can anything but a mapping
+            ("", false),
+            ("~", false),
+            ("just a string", false),
+            ("- foo0\n- foo1\n", false),
+            ("descriptions: [foo0", false),
+            ("{\"onvif\":{}}", true),
+        ];
+        for (details, structured) in cases {
+            let v1 = convert(&v0_configuration(Some(details)), "akri.sh/v1").unwrap();
+            assert_eq!(
+                v1["metadata"]["annotations"][V0_DISCOVERY_DETAILS_ANNOTATION],
+                json!(details),
+                "{:?}",
+                details
+            );
+            assert_eq!(
+                v1["spec"]["discoveryHandler"]
+                    .get("discoveryDetails")
+                    .is_some(),
+                structured,
+                "{:?}",
+                details
+            );
+            serde_json::from_value::<v1::configuration::Configuration>(v1).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_configuration_round_trip_from_v0() {
+        let details = [
+            None,
+            Some(""),
+            Some("~"),
+            Some("descriptions:\n- foo0\n"),
+            Some("descriptions: [foo0]"),
+            Some("# cameras\ndescriptions:\n  - foo0 # first\n"),
+            Some("b: 1\na: {c: [1, 2.5, true, null]}\n"),
+            Some("{\"onvif\":{}}"),
+            Some("just a string"),
+            Some("- foo0\n- foo1\n"),
+            Some("descriptions: [foo0"),
+            Some("udevRules:\n- KERNEL==\"video[0-9]*\"\n"),
+        ];
+        for details in details {
+            let v0 = v0_configuration(details);
+            assert_eq!(round_trip(&v0, ApiVersion::V1), v0, "{:?}", details);
+
+            // Other annotations are kept
+            let mut annotated = v0.clone();
+            annotated["metadata"]["annotations"] = json!({"team": "a"});
+            assert_eq!(
+                round_trip(&annotated, ApiVersion::V1),
+                annotated,
+                "{:?}",
+                details
+            );
+
+            // The result is still a valid v0 Configuration
+            serde_json::from_value::<configuration::Configuration>(round_trip(&v0, ApiVersion::V1))
+                .unwrap();
+        }
+
+        // Without discovery handler or spec
+        let mut v0 = v0_configuration(None);
+        v0["spec"]
+            .as_object_mut()
+            .unwrap()
+            .remove("discoveryHandler");
+        assert_eq!(round_trip(&v0, ApiVersion::V1), v0);
+        v0.as_object_mut().unwrap().remove("spec");
+        assert_eq!(round_trip(&v0, ApiVersion::V1), v0);
+    }
+
+    #[test]
+    fn test_configuration_round_trip_from_v1() {
+        let details = [
+            None,
+            Some(json!({})),
+            Some(json!({"descriptions": ["foo0", "foo1"]})),
+            Some(json!({"onvif": {"ipAddresses": {"action": "Exclude", "items": []}}})),
+            Some(json!({"b": 1, "a": {"c": [1, 2.5, true, null, "text: with: colons"]}})),
+        ];
+        for details in details {
+            let mut v1 = v0_configuration(None);
+            v1["apiVersion"] = json!("akri.sh/v1");
+            if let Some(details) = &details {
+                v1["spec"]["discoveryHandler"]["discoveryDetails"] = details.clone();
+            }
+            assert_eq!(round_trip(&v1, ApiVersion::V0), v1, "{:?}", details);
+            serde_json::from_value::<configuration::Configuration>(
+                convert(&v1, "akri.sh/v0").unwrap(),
+            )
+            .unwrap();
+        }
+    }
+
+    #[test]
+    fn test_configuration_to_v0_changed_details() {
+        let v1 = convert(
+            &v0_configuration(Some("# cameras\ndescriptions: [foo0]\n")),
+            "akri.sh/v1",
+        )
+        .unwrap();
+
+        // Unchanged details are restored with their comments
+        let v0 = convert(&v1, "akri.sh/v0").unwrap();
+        assert_eq!(
+            v0["spec"]["discoveryHandler"]["discoveryDetails"],
+            json!("# cameras\ndescriptions: [foo0]\n")
+        );
+        assert_eq!(v0["metadata"].get("annotations"), None);
+
+        // Changed details are rendered from the structured ones
+        let mut changed = v1.clone();
+        changed["spec"]["discoveryHandler"]["discoveryDetails"] = json!({"descriptions": ["foo1"]});
+        let v0 = convert(&changed, "akri.sh/v0").unwrap();
+        assert_eq!(
+            v0["spec"]["discoveryHandler"]["discoveryDetails"],
+            json!("descriptions:\n- foo1\n")
+        );
+
+        // Removed details stay removed
+        let mut removed = v1;
+        removed["spec"]["discoveryHandler"]
+            .as_object_mut()
+            .unwrap()
+            .remove("discoveryDetails");
+        let v0 = convert(&removed, "akri.sh/v0").unwrap();
+        assert_eq!(v0["spec"]["discoveryHandler"].get("discoveryDetails"), None);
+        assert_eq!(v0["metadata"].get("annotations"), None);
+    }
+
+    #[test]
+    fn test_configuration_invalid() {
+        let mut v0 = v0_configuration(None);
+        v0["spec"]["discoveryHandler"]["discoveryDetails"] = json!({"descriptions": []});
+        assert!(convert(&v0, "akri.sh/v1").is_err());
+        let mut v1 = v0_configuration(None);
+        v1["apiVersion"] = json!("akri.sh/v1");
+        v1["spec"]["discoveryHandler"]["discoveryDetails"] = json!("descriptions: []");
+        assert!(convert(&v1, "akri.sh/v0").is_err());
+    }
+
+    #[test]
+    fn test_instance_to_v1() {
+        let v0 = v0_instance(json!({
+            "config-a-b494b6-0": "",
+            "config-a-b494b6-1": "node-a",
+            "config-a-b494b6-2": "C:1:node-b",
+            "config-a-b494b6-3": {"kind": "Instance", "node": "node:c", "claimTime": "2024-01-01T00:00:00Z"},
+        }));
+        let v1 = convert(&v0, "akri.sh/v1").unwrap();
+        assert_eq!(
+            v1["spec"]["deviceUsage"],
+            json!({
+                "config-a-b494b6-0": {"kind": "Free"},
+                "config-a-b494b6-1": {"kind": "Instance", "node": "node-a"},
+                "config-a-b494b6-2": {"kind": "Configuration", "node": "node-b", "vdev": "1"},
+                "config-a-b494b6-3": {"kind": "Instance", "node": "node:c", "claimTime": "2024-01-01T00:00:00Z"},
+            })
+        );
+        let typed: v1::instance::Instance = serde_json::from_value(v1).unwrap();
+        assert_eq!(typed.spec.nodes, vec!["node-a", "node-b"]);
+        assert_eq!(
+            typed.spec.device_usage["config-a-b494b6-2"].kind,
+            v1::instance::SlotUsageKind::Configuration
+        );
+    }
+
+    #[test]
+    fn test_instance_round_trip_from_v0() {
+        let usages = [
+            json!({}),
+            json!({"config-a-b494b6-0": ""}),
+            json!({"config-a-b494b6-0": "node-a"}),
+            json!({"config-a-b494b6-0": "C:0:node-a"}),
+            json!({"config-a-b494b6-0": "C:vdev:with:colons"}),
+            json!({"config-a-b494b6-0": "C:0:"}),
+            json!({"config-a-b494b6-0": "C:"}),
+            json!({"config-a-b494b6-0": ":"}),
+            json!({
+                "config-a-b494b6-0": "",
+                "config-a-b494b6-1": "node-a",
+                "config-a-b494b6-2": "C:1:node-b",
+            }),
+            json!({"config-a-b494b6-0": {"kind": "Instance", "node": "node-a", "podUid": "8d7e6f"}}),
+            json!({"config-a-b494b6-0": {"kind": "Instance", "node": "C:0:node-a"}}),
+            json!({"config-a-b494b6-0": {"node": "node-a"}}),
+        ];
+        for usage in usages {
+            let v0 = v0_instance(usage.clone());
+            assert_eq!(round_trip(&v0, ApiVersion::V1), v0, "{:?}", usage);
+            serde_json::from_value::<instance::Instance>(round_trip(&v0, ApiVersion::V1)).unwrap();
+        }
+
+        let mut v0 = v0_instance(json!({}));
+        v0["spec"].as_object_mut().unwrap().remove("deviceUsage");
+        assert_eq!(round_trip(&v0, ApiVersion::V1), v0);
+    }
+
+    #[test]
+    fn test_instance_structured_slots_to_v0() {
+        let v0 = v0_instance(json!({
+            "config-a-b494b6-0": {"kind": "Free"},
+            "config-a-b494b6-1": {"kind": "Instance", "node": "node-a"},
+            "config-a-b494b6-2": {"kind": "Configuration", "node": "node-b", "vdev": "1"},
+        }));
+        let converted = round_trip(&v0, ApiVersion::V1);
+        // Structured slots the string encoding can hold come back in this encoding, read the same way
+        assert_eq!(
+            converted["spec"]["deviceUsage"],
+            json!({
+                "config-a-b494b6-0": "",
+                "config-a-b494b6-1": "node-a",
+                "config-a-b494b6-2": "C:1:node-b",
+            })
+        );
+        assert_eq!(
+            serde_json::from_value::<instance::Instance>(converted).unwrap(),
+            serde_json::from_value::<instance::Instance>(v0).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_instance_round_trip_from_v1() {
+        let usages = [
+            json!({}),
+            json!({"config-a-b494b6-0": {"kind": "Free"}}),
+            json!({"config-a-b494b6-0": {"kind": "Instance", "node": "node-a"}}),
+            json!({"config-a-b494b6-0": {"kind": "Configuration", "node": "node-a", "vdev": "0"}}),
+            json!({
+                "config-a-b494b6-0": {"kind": "Free"},
+                "config-a-b494b6-1": {"kind": "Instance", "node": "node-a", "podUid": "8d7e6f", "claimTime": "2024-01-01T00:00:00Z"},
+                "config-a-b494b6-2": {"kind": "Configuration", "node": "node-b", "vdev": "1"},
+            }),
+        ];
+        for usage in usages {
+            let mut v1 = v0_instance(usage.clone());
+            v1["apiVersion"] = json!("akri.sh/v1");
+            assert_eq!(round_trip(&v1, ApiVersion::V0), v1, "{:?}", usage);
+            serde_json::from_value::<v1::instance::Instance>(v1.clone()).unwrap();
+            serde_json::from_value::<instance::Instance>(convert(&v1, "akri.sh/v0").unwrap())
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn test_instance_invalid() {
+        for usage in [json!({"kind": "Unknown"}), json!(1), json!(["node-a"])] {
+            let v0 = v0_instance(json!({ "config-a-b494b6-0": usage }));
+            assert!(convert(&v0, "akri.sh/v1").is_err(), "{:?}", usage);
+        }
+        let mut v1 = v0_instance(json!({"config-a-b494b6-0": "node-a"}));
+        v1["apiVersion"] = json!("akri.sh/v1");
+        assert!(convert(&v1, "akri.sh/v0").is_err());
+    }
+}
@@ -0,0 +1,204 @@
+//! Capabilities advertised by Discovery Handlers when registering with the Agent.
+//!
+//! The Agents publish the capabilities of every registered Discovery Handler in a ConfigMap of their namespace,
+//! each under its own node key, so that other components, such as the validating webhook, can use them.
+use k8s_openapi::api::core::v1::ConfigMap;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::k8s::api::IntoApi;
+
+/// Prefix of the name of the ConfigMaps holding the capabilities of the Discovery Handlers
+pub const CAPABILITIES_CONFIG_MAP_PREFIX: &str = "akri-dh-capabilities-";
+/// Suffix of the keys of the serialized capabilities in the data of their ConfigMap, prefixed by the node name
+pub const CAPABILITIES_CONFIG_MAP_KEY_SUFFIX: &str = ".capabilities.json";
+/// Maximum length of a ConfigMap name
+const MAX_CONFIG_MAP_NAME_LENGTH: usize = 253;
+
+/// Optional features of the discovery protocol a Discovery Handler supports
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum DiscoveryHandlerFeature {
+    /// Reports the health of the discovered devices
+    DeviceHealth,
+    /// Sends incremental updates of the device list when the Agent accepts them
+    DeltaUpdates,
+    /// Applies the updates sent over the control channel of the v1 protocol
+    ControlChannel,
+}
+
+/// Capabilities of a Discovery Handler
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DiscoveryHandlerCapabilities {
+    /// Version of the Discovery Handler, empty if not advertised
+    #[serde(default)]
+    pub version: String,
+    /// Versions of the discovery protocol served by the Discovery Handler, besides v0
+    #[serde(default)]
+    pub protocol_versions: Vec<u32>,
+    #[serde(default)]
+    pub features: Vec<DiscoveryHandlerFeature>,
+    /// JSON Schema of the discovery details, once parsed from YAML
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub discovery_details_schema: Option<serde_json::Value>,
+}
+
+impl DiscoveryHandlerCapabilities {
+    pub fn new(version: &str) -> Self {
+        DiscoveryHandlerCapabilities {
+            version: version.to_string(),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_feature(mut self, feature: DiscoveryHandlerFeature) -> Self {
+        if !self.supports(feature) {
+            self.features.push(feature);
+        }
+        self
+    }
+
+    /// Advertises the JSON Schema of `T`, the type the discovery details get deserialized to
+    pub fn with_discovery_details_schema<T: JsonSchema>(mut self) -> Self {
+        self.discovery_details_schema = Some(
+            serde_json::to_value(schemars::schema_for!(T)).expect("JSON Schema is serializable"),
+        );
+        self
+    }
+
+    pub fn supports(&self, feature: DiscoveryHandlerFeature) -> bool {
+        self.features.contains(&feature)
+    }
+}
+
+/// Name of the ConfigMap holding the capabilities of a Discovery Handler. Discovery Handler names aren't always
+/// valid object names, so they get lowercased, any other character than letters and digits is replaced, and
+/// the result is trimmed to a valid DNS-1123 name.
+pub fn capabilities_config_map_name(discovery_handler_name: &str) -> String {
+    let name: String = discovery_handler_name
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .take(MAX_CONFIG_MAP_NAME_LENGTH - CAPABILITIES_CONFIG_MAP_PREFIX.len())
+        .collect();
+    let name = name.trim_matches('-');
+    if name.is_empty() {
+        return CAPABILITIES_CONFIG_MAP_PREFIX
+            .trim_end_matches('-')
+            .to_string();
+    }
+    format!("{}{}", CAPABILITIES_CONFIG_MAP_PREFIX, name)
+}
+
+/// Key of the capabilities published by the Agent of the given node in the data of their ConfigMap. Every
+/// Agent applies its own key, so that Agents of different nodes don't overwrite each other's capabilities.
+pub fn capabilities_config_map_key(node_name: &str) -> String {
+    format!("{}{}", node_name, CAPABILITIES_CONFIG_MAP_KEY_SUFFIX)
+}
+
+/// Gets the capabilities of a Discovery Handler as published by the Agents in the default namespace of `client`.
+/// If the Agents of several nodes published them, the ones of the first node by name are used.
+/// Returns None if no Agent published them, e.g. if no such Discovery Handler ever registered.
+pub async fn get_capabilities(
+    client: &dyn IntoApi<ConfigMap>,
+    discovery_handler_name: &str,
+) -> Result<Option<DiscoveryHandlerCapabilities>, anyhow::Error> {
+    let config_map = client
+        .default_namespaced()
+        .get(&capabilities_config_map_name(discovery_handler_name))
+        .await?;
+    match config_map.and_then(|cm| cm.data).and_then(|data| {
+        data.into_iter()
+            .find(|(key, _)| key.ends_with(CAPABILITIES_CONFIG_MAP_KEY_SUFFIX))
+    }) {
+        Some((_, capabilities)) => Ok(Some(serde_json::from_str(&capabilities)?)),
+        None => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::k8s::api::{MockApi, MockIntoApi};
+    use std::collections::BTreeMap;
+
+    #[derive(Deserialize, JsonSchema)]
+    #[allow(dead_code)]
+    struct Details {
+        descriptions: Vec<String>,
+    }
+
+    #[test]
+    fn test_capabilities_config_map_name() {
+        assert_eq!(
+            capabilities_config_map_name("debugEcho"),
+            "akri-dh-capabilities-debugecho"
+        );
+        assert_eq!(
+            capabilities_config_map_name("my_handler.v2"),
+            "akri-dh-capabilities-my-handler-v2"
+        );
+        assert_eq!(
+            capabilities_config_map_name("_handler_"),
+            "akri-dh-capabilities-handler"
+        );
+        assert_eq!(capabilities_config_map_name("_"), "akri-dh-capabilities");
+        let name = capabilities_config_map_name(&"a".repeat(300));
+        assert_eq!(name.len(), MAX_CONFIG_MAP_NAME_LENGTH);
+        assert_eq!(
+            capabilities_config_map_name(&format!("{}_b", "a".repeat(232))),
+            format!("akri-dh-capabilities-{}", "a".repeat(232))
+        );
+    }
+
+    #[test]
+    fn test_capabilities_serialization() {
+        let capabilities = DiscoveryHandlerCapabilities::new("1.0.0")
+            .with_feature(DiscoveryHandlerFeature::DeltaUpdates)
+            .with_feature(DiscoveryHandlerFeature::DeltaUpdates)
+            .with_discovery_details_schema::<Details>();
+        assert_eq!(
+            capabilities.features,
+            vec![DiscoveryHandlerFeature::DeltaUpdates]
+        );
+        let schema = capabilities.discovery_details_schema.as_ref().unwrap();
+        assert_eq!(schema["required"], serde_json::json!(["descriptions"]));
+
+        let serialized = serde_json::to_string(&capabilities).unwrap();
+        assert!(serialized.contains(r#""features":["deltaUpdates"]"#));
+        let deserialized: DiscoveryHandlerCapabilities = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(deserialized, capabilities);
+        let deserialized: DiscoveryHandlerCapabilities = serde_json::from_str("{}").unwrap();
+        assert_eq!(deserialized, DiscoveryHandlerCapabilities::default());
+    }
+
+    #[tokio::test]
+    async fn test_get_capabilities() {
+        let capabilities = DiscoveryHandlerCapabilities::new("1.0.0");
+        let data = serde_json::to_string(&capabilities).unwrap();
+        let mut client = MockIntoApi::<ConfigMap>::new();
+        client.expect_default_namespaced().returning(move || {
+            let data = data.clone();
+            let mut api = MockApi::<ConfigMap>::new();
+            api.expect_get().returning(move |name| {
+                if name != "akri-dh-capabilities-debugecho" {
+                    return Ok(None);
+                }
+                Ok(Some(ConfigMap {
+                    data: Some(BTreeMap::from([
+                        ("other-key".to_string(), "{}".to_string()),
+                        (capabilities_config_map_key("node-a"), data.clone()),
+                    ])),
+                    ..Default::default()
+                }))
+            });
+            Box::new(api)
+        });
+        assert_eq!(
+            get_capabilities(&client, "debugEcho").await.unwrap(),
+            Some(capabilities)
+        );
+        assert_eq!(get_capabilities(&client, "unknown").await.unwrap(), None);
+    }
+}
@@ -1,15 +1,20 @@
+use super::status::{ssa_conditions_map, ssa_nodes_map};
 use super::{API_NAMESPACE, API_VERSION};
+use crate::k8s::api::Api as _;
 use kube::{
     api::{Api, DeleteParams, ListParams, ObjectList, ObjectMeta, Patch, PatchParams, PostParams},
     Client, CustomResource,
 };
 
-use k8s_openapi::apimachinery::pkg::apis::meta::v1::OwnerReference;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::{Condition, OwnerReference, Time};
 use schemars::JsonSchema;
 use std::collections::HashMap;
 
 pub type InstanceList = ObjectList<Instance>;
 
+/// Field manager used by the Controller to apply the conditions of Instances
+pub const CONTROLLER_FIELD_MANAGER: &str = "akri-controller";
+
 /// Defines the information in the Instance CRD
 ///
 /// An Instance is a specific instance described by
@@ -43,11 +48,37 @@ pub type InstanceList = ObjectList<Instance>;
         "jsonPath": ".spec.nodes",
         "description": "Nodes that expose this Instance"
     }"#,
+    printcolumn = r#"{
+        "name": "Pods",
+        "type": "string",
+        "jsonPath": ".spec.deviceUsage.*.podName",
+        "description": "Pods the slots of this Instance are allocated to, only recorded with the Agent's structuredSlotUsage enabled"
+    }"#,
+    printcolumn = r#"{
+        "name": "Pod Namespaces",
+        "type": "string",
+        "jsonPath": ".spec.deviceUsage.*.podNamespace",
+        "description": "Namespaces of the Pods the slots of this Instance are allocated to, only recorded with the Agent's structuredSlotUsage enabled",
+        "priority": 1
+    }"#,
+    printcolumn = r#"{
+        "name": "Health",
+        "type": "string",
+        "jsonPath": ".status.nodes[*].health.state",
+        "description": "Health of the device as seen by each node"
+    }"#,
+    printcolumn = r#"{
+        "name": "Brokers",
+        "type": "string",
+        "jsonPath": ".status.conditions[?(@.type==\"BrokersReady\")].status",
+        "description": "Whether every broker Pod of this Instance is ready"
+    }"#,
     printcolumn = r#"{
         "name": "Age",
         "type": "date",
         "jsonPath": ".metadata.creationTimestamp"
     }"#,
+    status = "InstanceStatus",
     derive = "PartialEq"
 )]
 pub struct InstanceSpec {
@@ -79,14 +110,251 @@ pub struct InstanceSpec {
     #[schemars(schema_with = "ssa_nodes_set")]
     pub nodes: Vec<String>,
 
-    /// This contains a map of capability slots to node names.  The number of
-    /// slots corresponds to the associated Configuration.capacity
-    /// field.  Each slot will either map to an empty string (if the slot has not
-    /// been claimed) or to a node name (corresponding to the node that has claimed
-    /// the slot)
-    #[serde(default)]
+    /// This contains the usage of each slot of the Instance.  The number of
+    /// slots corresponds to the associated Configuration.capacity field.
+    /// Slots are either their legacy string encoding or their structured form,
+    /// both being read as their structured equivalent.
+    #[serde(
+        default,
+        deserialize_with = "deserialize_device_usage",
+        serialize_with = "serialize_device_usage"
+    )]
     #[schemars(schema_with = "ssa_usage_granular")]
-    pub device_usage: HashMap<String, String>,
+    pub device_usage: HashMap<String, SlotUsage>,
+}
+
+/// Defines what holds a slot of an Instance
+#[derive(Deserialize, Serialize, Clone, Debug, Default, JsonSchema, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SlotUsage {
+    /// This contains the kind of device plugin that claimed the slot
+    #[serde(default)]
+    pub kind: SlotUsageKind,
+
+    /// This contains the node that claimed the slot, set unless the slot is free
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub node: Option<String>,
+
+    /// This contains the virtual device of the Configuration level device plugin
+    /// the slot is allocated to, only set for the `Configuration` kind
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vdev: Option<String>,
+
+    /// This contains the namespace of the Pod the slot is allocated to, if known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pod_namespace: Option<String>,
+
+    /// This contains the name of the Pod the slot is allocated to, if known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pod_name: Option<String>,
+
+    /// This contains the UID of the Pod the slot is allocated to, if known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pod_uid: Option<String>,
+
+    /// This contains the time the slot was claimed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub claim_time: Option<Time>,
+
+    /// This contains the UID of the DRA resource claim the slot is prepared for,
+    /// only set for the `Dra` kind
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub claim_uid: Option<String>,
+}
+
+/// Kind of device plugin that claimed a slot of an Instance
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, Default, JsonSchema, PartialEq, Eq)]
+pub enum SlotUsageKind {
+    /// The slot is free
+    #[default]
+    Free,
+    /// The slot is reserved by the Instance level device plugin
+    Instance,
+    /// The slot is reserved by the Configuration level device plugin
+    Configuration,
+    /// The slot is reserved by the DRA plugin for a prepared resource claim
+    Dra,
+}
+
+impl SlotUsage {
+    /// Builds the usage of a slot from its legacy string encoding: an empty string for a free
+    /// slot, `C:<vdev>:<node>` for the Configuration level device plugin, the node name otherwise.
+    /// A value that can't be parsed is kept as the node name.
+    pub fn from_legacy(usage: &str) -> Self {
+        if usage.is_empty() {
+            return Self::default();
+        }
+        match usage.strip_prefix("C:").and_then(|s| s.split_once(':')) {
+            Some((vdev, node)) if !node.is_empty() => Self {
+                kind: SlotUsageKind::Configuration,
+                node: Some(node.to_string()),
+                vdev: Some(vdev.to_string()),
+                ..Default::default()
+            },
+            _ => Self {
+                kind: SlotUsageKind::Instance,
+                node: Some(usage.to_string()),
+                ..Default::default()
+            },
+        }
+    }
+
+    /// Returns the legacy string encoding of the slot, it doesn't hold the Pod
+    /// the slot is allocated to nor its claim time, and a DRA slot reads as an
+    /// Instance level one
+    pub fn to_legacy(&self) -> String {
+        let node = self.node.as_deref().unwrap_or_default();
+        match self.kind {
+            SlotUsageKind::Free => String::new(),
+            SlotUsageKind::Instance | SlotUsageKind::Dra => node.to_string(),
+            SlotUsageKind::Configuration => {
+                format!("C:{}:{}", self.vdev.as_deref().unwrap_or_default(), node)
+            }
+        }
+    }
+
+    /// Returns true if the slot is claimed by the given node
+    pub fn is_owned_by(&self, node: &str) -> bool {
+        self.kind != SlotUsageKind::Free && self.node.as_deref() == Some(node)
+    }
+}
+
+/// Usage of a slot as written in a `v0` Instance, Akri versions prior to the structured
+/// usage only read the legacy string encoding
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+#[serde(untagged)]
+pub enum AnySlotUsage {
+    Legacy(String),
+    Structured(SlotUsage),
+}
+
+impl AnySlotUsage {
+    /// Returns the legacy string encoding of the slot when it holds everything
+    /// the slot does, its structured form otherwise
+    pub fn compact(usage: &SlotUsage) -> Self {
+        let legacy = usage.to_legacy();
+        if SlotUsage::from_legacy(&legacy) == *usage {
+            Self::Legacy(legacy)
+        } else {
+            Self::Structured(usage.clone())
+        }
+    }
+}
+
+impl From<AnySlotUsage> for SlotUsage {
+    fn from(usage: AnySlotUsage) -> Self {
+        match usage {
+            AnySlotUsage::Legacy(usage) => SlotUsage::from_legacy(&usage),
+            AnySlotUsage::Structured(usage) => usage,
+        }
+    }
+}
+
+/// Deserializes the slots of an Instance, accepting their legacy string encoding
+fn deserialize_device_usage<'de, D>(deserializer: D) -> Result<HashMap<String, SlotUsage>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let slots: HashMap<String, AnySlotUsage> = serde::Deserialize::deserialize(deserializer)?;
+    Ok(slots
+        .into_iter()
+        .map(|(slot, usage)| (slot, usage.into()))
+        .collect())
+}
+
+/// Serializes the slots of an Instance in their legacy string encoding, unless it would
+/// lose part of the slot, so that Akri versions prior to the structured usage can read them
+fn serialize_device_usage<S>(
+    slots: &HashMap<String, SlotUsage>,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    serializer.collect_map(
+        slots
+            .iter()
+            .map(|(slot, usage)| (slot, AnySlotUsage::compact(usage))),
+    )
+}
+
+/// Defines the observed state of an Instance
+#[derive(Deserialize, Serialize, Clone, Debug, Default, JsonSchema, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct InstanceStatus {
+    /// This contains the `BrokersReady` and `Degraded` conditions,
+    /// written by the Controller
+    #[serde(default)]
+    #[schemars(schema_with = "ssa_conditions_map")]
+    pub conditions: Vec<Condition>,
+
+    /// This contains when the device was last discovered by each node and its
+    /// health as seen by that node, each entry is written by the Agent of its node
+    #[serde(default)]
+    #[schemars(schema_with = "ssa_nodes_map::<NodeInstanceStatus>")]
+    pub nodes: Vec<NodeInstanceStatus>,
+}
+
+/// Defines the state of an Instance as seen by a node
+#[derive(Deserialize, Serialize, Clone, Debug, Default, JsonSchema, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeInstanceStatus {
+    /// This contains the name of the node
+    pub node: String,
+
+    /// This contains the last time the node discovered the device
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_seen_time: Option<Time>,
+
+    /// This contains the health of the device as reported by the
+    /// Discovery Handler of the node
+    #[serde(default)]
+    pub health: InstanceHealth,
+}
+
+impl Instance {
+    /// Returns the health of the device as seen by the given node, a node
+    /// that didn't report it yet considers the device healthy
+    pub fn node_health(&self, node: &str) -> InstanceHealth {
+        self.status
+            .as_ref()
+            .and_then(|s| s.nodes.iter().find(|n| n.node == node))
+            .map(|n| n.health.clone())
+            .unwrap_or_default()
+    }
+}
+
+/// Defines the health of the device an Instance represents
+#[derive(Deserialize, Serialize, Clone, Debug, Default, JsonSchema, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct InstanceHealth {
+    /// This contains the health state of the device
+    #[serde(default)]
+    pub state: HealthState,
+
+    /// This contains a human readable reason for the current state
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+impl InstanceHealth {
+    /// Returns true if the device can be allocated to workloads, i.e. if it
+    /// is either healthy or degraded
+    pub fn is_usable(&self) -> bool {
+        self.state != HealthState::Unhealthy
+    }
+}
+
+/// Health state of a device
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, Default, JsonSchema, PartialEq, Eq)]
+pub enum HealthState {
+    /// The device is working as expected
+    #[default]
+    Healthy,
+    /// The device is visible but cannot be used
+    Unhealthy,
+    /// The device can be used but is not fully functional
+    Degraded,
 }
 
 fn ssa_nodes_set(gen: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
@@ -98,9 +366,23 @@ fn ssa_nodes_set(gen: &mut schemars::gen::SchemaGenerator) -> schemars::schema::
     schema.into()
 }
 
+/// Slots hold either their legacy string encoding or their structured form. Kubernetes doesn't allow
+/// typed alternatives in a structural schema, so the slot schema has no type and declares the
+/// properties of the structured form, which only apply to objects. Each slot is owned by the device
+/// plugin that claims it.
 fn ssa_usage_granular(gen: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
     let mut schema: schemars::schema::SchemaObject =
-        <HashMap<String, String>>::json_schema(gen).into();
+        <HashMap<String, SlotUsage>>::json_schema(gen).into();
+    let mut slot: schemars::schema::SchemaObject = <SlotUsage>::json_schema(gen).into();
+    slot.instance_type = None;
+    slot.metadata().description = Some(
+        "Usage of a slot, either its structured form or its legacy string encoding".to_owned(),
+    );
+    slot.extensions.insert(
+        "x-kubernetes-preserve-unknown-fields".to_owned(),
+        serde_json::Value::Bool(true),
+    );
+    schema.object().additional_properties = Some(Box::new(slot.into()));
     schema.extensions.insert(
         "x-kubernetes-map-type".to_owned(),
         serde_json::Value::String("granular".to_owned()),
@@ -353,7 +635,9 @@ pub async fn update_instance(
 ) -> Result<(), anyhow::Error> {
     log::trace!("update_instance enter");
     let instances_client: Api<Instance> = Api::namespaced(kube_client.clone(), namespace);
-    let modified_instance = Instance::new(name, instance_to_update.clone());
+    let mut modified_instance =
+        serde_json::to_value(Instance::new(name, instance_to_update.clone()))?;
+    null_unset_slot_fields(&mut modified_instance);
     match instances_client
         .patch(
             name,
@@ -380,10 +664,79 @@ pub async fn update_instance(
     }
 }
 
+/// A merge patch keeps the fields it doesn't mention, the optional fields of the slots are
+/// thus explicitly nulled so that they don't outlive the claim they belong to
+fn null_unset_slot_fields(instance: &mut serde_json::Value) {
+    if let Some(slots) = instance
+        .pointer_mut("/spec/deviceUsage")
+        .and_then(serde_json::Value::as_object_mut)
+    {
+        for usage in slots
+            .values_mut()
+            .filter_map(serde_json::Value::as_object_mut)
+        {
+            for field in [
+                "node",
+                "vdev",
+                "podNamespace",
+                "podName",
+                "podUid",
+                "claimTime",
+                "claimUid",
+            ] {
+                usage.entry(field).or_insert(serde_json::Value::Null);
+            }
+        }
+    }
+}
+
+/// Update the conditions of an Instance status
+///
+/// The conditions are Server Side Applied by the Controller, leaving the
+/// per-node entries of the status to the Agents.
+///
+/// Example:
+///
+/// ```no_run
+/// use akri_shared::akri::instance;
+/// use kube::client::Client;
+/// use kube::config;
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let api_client = Client::try_default().await.unwrap();
+/// instance::update_instance_conditions(
+///     &[],
+///     "instance-1",
+///     "default",
+///     &api_client).await.unwrap();
+/// # }
+/// ```
+pub async fn update_instance_conditions(
+    conditions: &[Condition],
+    name: &str,
+    namespace: &str,
+    kube_client: &Client,
+) -> Result<(), anyhow::Error> {
+    log::trace!("update_instance_conditions enter");
+    let instances_client: Api<Instance> = Api::namespaced(kube_client.clone(), namespace);
+    instances_client
+        .apply_status(
+            name,
+            serde_json::json!({ "conditions": conditions }),
+            CONTROLLER_FIELD_MANAGER,
+        )
+        .await?;
+    log::trace!("update_instance_conditions return");
+    Ok(())
+}
+
 fn default_shared() -> bool {
     false
 }
 
+/// Legacy string encoding of the usage of a slot
+#[deprecated(note = "use `SlotUsage`, `SlotUsage::from_legacy` and `SlotUsage::to_legacy` instead")]
 pub mod device_usage {
     #[derive(PartialEq, Clone, Debug, Default)]
     pub enum DeviceUsageKind {
@@ -553,6 +906,144 @@ mod crd_serializeation_tests {
         let _ = serde_json::to_string(&deserialized).unwrap();
     }
 
+    #[test]
+    fn test_instance_node_health() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let json = r#"{"apiVersion": "akri.sh/v0", "kind": "Instance", "metadata": {"name": "foo"}, "spec": {"configurationName": "foo", "cdiName": "akri.sh/foo=bar", "capacity": 1}, "status": {"nodes": [{"node": "n1", "health": {"state": "Unhealthy", "reason": "unreachable"}}, {"node": "n2"}]}}"#;
+        let deserialized: Instance = serde_json::from_str(json).unwrap();
+        let health = deserialized.node_health("n1");
+        assert_eq!(HealthState::Unhealthy, health.state);
+        assert_eq!(Some("unreachable".to_string()), health.reason);
+        assert!(!health.is_usable());
+        assert_eq!(InstanceHealth::default(), deserialized.node_health("n2"));
+        assert_eq!(InstanceHealth::default(), deserialized.node_health("n3"));
+    }
+
+    #[test]
+    fn test_slot_usage_from_legacy() {
+        let node = |kind, node: &str, vdev: Option<&str>| SlotUsage {
+            kind,
+            node: Some(node.to_string()),
+            vdev: vdev.map(str::to_string),
+            ..Default::default()
+        };
+        for (legacy, expected) in [
+            ("", SlotUsage::default()),
+            ("node-a", node(SlotUsageKind::Instance, "node-a", None)),
+            (
+                "C:vdev0:node-a",
+                node(SlotUsageKind::Configuration, "node-a", Some("vdev0")),
+            ),
+            // Only the virtual device can't hold a colon
+            (
+                "C:vdev0:node:a",
+                node(SlotUsageKind::Configuration, "node:a", Some("vdev0")),
+            ),
+            ("node:a", node(SlotUsageKind::Instance, "node:a", None)),
+            // Values that can't be parsed are kept as the node name
+            ("C:vdev0:", node(SlotUsageKind::Instance, "C:vdev0:", None)),
+        ] {
+            assert_eq!(SlotUsage::from_legacy(legacy), expected, "{:?}", legacy);
+            assert_eq!(expected.to_legacy(), legacy);
+        }
+        // The legacy encoding doesn't hold the Pod nor the claim time
+        let claimed = SlotUsage {
+            pod_namespace: Some("default".to_string()),
+            pod_name: Some("broker-a".to_string()),
+            ..node(SlotUsageKind::Instance, "node-a", None)
+        };
+        assert_eq!(claimed.to_legacy(), "node-a");
+        assert_eq!(
+            AnySlotUsage::compact(&claimed),
+            AnySlotUsage::Structured(claimed.clone())
+        );
+        assert_eq!(
+            AnySlotUsage::compact(&node(SlotUsageKind::Instance, "C:vdev0:node-a", None)),
+            AnySlotUsage::Structured(node(SlotUsageKind::Instance, "C:vdev0:node-a", None))
+        );
+        assert_eq!(
+            AnySlotUsage::compact(&node(SlotUsageKind::Instance, "node-a", None)),
+            AnySlotUsage::Legacy("node-a".to_string())
+        );
+        // A DRA slot reads as an Instance level one in the legacy encoding, which loses its claim
+        let dra = SlotUsage {
+            claim_uid: Some("claim-uid".to_string()),
+            ..node(SlotUsageKind::Dra, "node-a", None)
+        };
+        assert_eq!(dra.to_legacy(), "node-a");
+        assert_eq!(
+            AnySlotUsage::compact(&dra),
+            AnySlotUsage::Structured(dra.clone())
+        );
+        assert!(dra.is_owned_by("node-a"));
+        assert!(node(SlotUsageKind::Instance, "node-a", None).is_owned_by("node-a"));
+        assert!(!node(SlotUsageKind::Instance, "node-a", None).is_owned_by("node-b"));
+        assert!(!SlotUsage::default().is_owned_by(""));
+    }
+
+    #[test]
+    fn test_instance_legacy_and_structured_slots() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let json = r#"{"configurationName": "foo", "cdiName": "akri.sh/foo=bar", "capacity": 3, "deviceUsage": {"foo-0": "C:vdev0:node-a", "foo-1": {"kind": "Instance", "node": "node-b", "podNamespace": "default", "podName": "broker-a", "podUid": "8d7e6f", "claimTime": "2024-01-01T00:00:00Z"}, "foo-2": {}}}"#;
+        let deserialized: InstanceSpec = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            SlotUsage::from_legacy("C:vdev0:node-a"),
+            deserialized.device_usage["foo-0"]
+        );
+        assert_eq!(
+            SlotUsageKind::Instance,
+            deserialized.device_usage["foo-1"].kind
+        );
+        assert_eq!(
+            Some("broker-a".to_string()),
+            deserialized.device_usage["foo-1"].pod_name
+        );
+        assert_eq!(
+            Some("8d7e6f".to_string()),
+            deserialized.device_usage["foo-1"].pod_uid
+        );
+        assert!(deserialized.device_usage["foo-1"].claim_time.is_some());
+        assert_eq!(SlotUsage::default(), deserialized.device_usage["foo-2"]);
+        // Slots are written in their legacy encoding unless it can't hold them
+        let serialized = serde_json::to_value(&deserialized).unwrap();
+        assert_eq!(
+            serde_json::json!("C:vdev0:node-a"),
+            serialized["deviceUsage"]["foo-0"]
+        );
+        assert_eq!(
+            serde_json::json!({"kind": "Instance", "node": "node-b", "podNamespace": "default", "podName": "broker-a", "podUid": "8d7e6f", "claimTime": "2024-01-01T00:00:00Z"}),
+            serialized["deviceUsage"]["foo-1"]
+        );
+        assert_eq!(serde_json::json!(""), serialized["deviceUsage"]["foo-2"]);
+        assert_eq!(
+            deserialized,
+            serde_json::from_value::<InstanceSpec>(serialized).unwrap()
+        );
+
+        let json = r#"{"configurationName": "foo", "cdiName": "akri.sh/foo=bar", "capacity": 1, "deviceUsage": {"foo-0": {"kind": "Unknown"}}}"#;
+        assert!(serde_json::from_str::<InstanceSpec>(json).is_err());
+    }
+
+    #[test]
+    fn test_null_unset_slot_fields() {
+        let mut instance = serde_json::json!({"spec": {"deviceUsage": {
+            "foo-0": {"kind": "Free"},
+            "foo-1": {"kind": "Instance", "node": "node-a"},
+            "foo-2": "node-a",
+        }}});
+        null_unset_slot_fields(&mut instance);
+        assert_eq!(
+            serde_json::json!({"spec": {"deviceUsage": {
+                "foo-0": {"kind": "Free", "node": null, "vdev": null, "podNamespace": null, "podName": null, "podUid": null, "claimTime": null},
+                "foo-1": {"kind": "Instance", "node": "node-a", "vdev": null, "podNamespace": null, "podName": null, "podUid": null, "claimTime": null},
+                "foo-2": "node-a",
+            }}}),
+            instance
+        );
+    }
+
     #[test]
     fn test_real_instance() {
         let _ = env_logger::builder().is_test(true).try_init();
@@ -15,8 +15,12 @@ pub const AKRI_PREFIX: &str = "akri.sh";
 pub const AKRI_SLOT_ANNOTATION_NAME_PREFIX: &str = "akri.agent.slot-";
 
 pub mod configuration;
+pub mod conversion;
+pub mod discovery_handler;
 pub mod instance;
 pub mod metrics;
+pub mod status;
+pub mod v1;
 
 pub mod retry {
     use rand::random;
@@ -0,0 +1,108 @@
+//! Conditions reported in the status of Configurations and Instances.
+//!
+//! The status of a Configuration is written by the Agents, each owning the entry of its node, while the
+//! conditions of an Instance are written by the Controller and its per-node entries by the Agents.
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::{Condition, Time};
+use schemars::JsonSchema;
+
+/// The Agent runs discovery for the Configuration on its node
+pub const DISCOVERY_ACTIVE_CONDITION: &str = "DiscoveryActive";
+/// A Discovery Handler with the name used by the Configuration is registered on the node
+pub const HANDLER_REGISTERED_CONDITION: &str = "HandlerRegistered";
+/// Every broker Pod of the Instance is running and ready
+pub const BROKERS_READY_CONDITION: &str = "BrokersReady";
+/// The device of the Instance is not fully functional, as reported by its Discovery Handler
+pub const DEGRADED_CONDITION: &str = "Degraded";
+
+/// Sets a condition, keeping its last transition time unless its status changes.
+pub fn set_condition(
+    conditions: &mut Vec<Condition>,
+    type_: &str,
+    status: bool,
+    reason: &str,
+    message: &str,
+    observed_generation: Option<i64>,
+) {
+    let status = if status { "True" } else { "False" }.to_string();
+    let last_transition_time = match conditions.iter().find(|c| c.type_ == type_) {
+        Some(current) if current.status == status => current.last_transition_time.clone(),
+        _ => Time(k8s_openapi::chrono::Utc::now()),
+    };
+    let condition = Condition {
+        type_: type_.to_string(),
+        status,
+        reason: reason.to_string(),
+        message: message.to_string(),
+        observed_generation,
+        last_transition_time,
+    };
+    match conditions.iter_mut().find(|c| c.type_ == type_) {
+        Some(current) => *current = condition,
+        None => conditions.push(condition),
+    }
+}
+
+/// Returns true if the condition of this type is set and true
+pub fn is_condition_true(conditions: &[Condition], type_: &str) -> bool {
+    conditions
+        .iter()
+        .any(|c| c.type_ == type_ && c.status == "True")
+}
+
+/// Conditions are a map keyed by type for Server Side Apply, as in the core types
+pub(crate) fn ssa_conditions_map(
+    gen: &mut schemars::gen::SchemaGenerator,
+) -> schemars::schema::Schema {
+    ssa_list_map::<Condition>(gen, "type")
+}
+
+/// Per-node entries are a map keyed by node for Server Side Apply, so each Agent owns its own entry
+pub(crate) fn ssa_nodes_map<T: JsonSchema>(
+    gen: &mut schemars::gen::SchemaGenerator,
+) -> schemars::schema::Schema {
+    ssa_list_map::<T>(gen, "node")
+}
+
+fn ssa_list_map<T: JsonSchema>(
+    gen: &mut schemars::gen::SchemaGenerator,
+    key: &str,
+) -> schemars::schema::Schema {
+    let mut schema: schemars::schema::SchemaObject = <Vec<T>>::json_schema(gen).into();
+    schema.extensions.insert(
+        "x-kubernetes-list-type".to_owned(),
+        serde_json::Value::String("map".to_owned()),
+    );
+    schema.extensions.insert(
+        "x-kubernetes-list-map-keys".to_owned(),
+        serde_json::json!([key]),
+    );
+    schema.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_condition() {
+        let mut conditions = Vec::new();
+        set_condition(&mut conditions, "Ready", false, "Pending", "", Some(1));
+        assert_eq!(conditions.len(), 1);
+        assert!(!is_condition_true(&conditions, "Ready"));
+        let first_transition = conditions[0].last_transition_time.clone();
+
+        // Same status, the transition time is kept
+        set_condition(&mut conditions, "Ready", false, "Failing", "oops", Some(2));
+        assert_eq!(conditions.len(), 1);
+        assert_eq!(conditions[0].reason, "Failing");
+        assert_eq!(conditions[0].observed_generation, Some(2));
+        assert_eq!(conditions[0].last_transition_time, first_transition);
+
+        set_condition(&mut conditions, "Other", true, "Reason", "", None);
+        set_condition(&mut conditions, "Ready", true, "Running", "", Some(2));
+        assert_eq!(conditions.len(), 2);
+        assert!(is_condition_true(&conditions, "Ready"));
+        assert!(is_condition_true(&conditions, "Other"));
+        assert!(conditions[0].last_transition_time >= first_transition);
+    }
+}
@@ -0,0 +1,269 @@
+use k8s_openapi::api::core::v1::ServiceSpec;
+use kube::CustomResource;
+use schemars::JsonSchema;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+
+use crate::akri::configuration::{
+    AllocationPolicy, BrokerSpec, ConfigurationStatus, DeviceSelector, DiscoveryProperty,
+    DEFAULT_CAPACITY,
+};
+
+/// This specifies which `DiscoveryHandler` should be used for discovery
+/// and any details that need to be sent to the `DiscoveryHandler`.
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DiscoveryHandlerInfo {
+    pub name: String,
+    /// The discovery details of the Discovery Handler, following the schema the Discovery Handler
+    /// advertises, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(schema_with = "discovery_details_schema")]
+    pub discovery_details: Option<Map<String, Value>>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub discovery_properties: Option<Vec<DiscoveryProperty>>,
+
+    /// Number of seconds the Discovery Handler waits between two discovery scans.
+    /// The Discovery Handler's default is used if not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub discovery_interval_seconds: Option<u64>,
+
+    /// Maximum number of seconds a single discovery scan may take.
+    /// The Discovery Handler's default is used if not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub discovery_timeout_seconds: Option<u64>,
+}
+
+impl DiscoveryHandlerInfo {
+    /// Deserializes the discovery details into the type of the Discovery Handler, missing details
+    /// being deserialized from an empty object
+    pub fn details<T: DeserializeOwned>(&self) -> Result<T, serde_json::Error> {
+        serde_json::from_value(Value::Object(
+            self.discovery_details.clone().unwrap_or_default(),
+        ))
+    }
+}
+
+/// Defines the information in the Akri Configuration CRD
+///
+/// A Configuration is the primary method for users to describe anticipated
+/// capabilities.  For any specific capability found that is described by this
+/// configuration, an Instance
+/// is created.
+#[derive(CustomResource, Serialize, Deserialize, Clone, Debug, JsonSchema)]
+#[kube(
+    group = "akri.sh",
+    version = "v1",
+    kind = "Configuration",
+    namespaced,
+    status = "ConfigurationStatus"
+)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigurationSpec {
+    /// This defines the `DiscoveryHandler` that should be used to
+    /// discover the capability and any information needed by the `DiscoveryHandler`.
+    #[schemars(schema_with = "immutable_dh_info")]
+    pub discovery_handler: DiscoveryHandlerInfo,
+
+    /// This defines the number of nodes that can schedule workloads for
+    /// any given capability that is found
+    #[serde(default = "default_capacity")]
+    pub capacity: usize,
+
+    /// This defines a workload that should be scheduled to any
+    /// node that can access any capability described by this
+    /// configuration
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "serde_yaml::with::singleton_map::deserialize",
+        serialize_with = "serde_yaml::with::singleton_map::serialize"
+    )]
+    pub broker_spec: Option<BrokerSpec>,
+
+    /// This defines a service that should be created to access
+    /// any specific capability found that is described by this
+    /// configuration. For each Configuration, several Instances
+    /// can be found.  For each Instance, there is at most 1
+    /// instance service.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instance_service_spec: Option<ServiceSpec>,
+
+    /// This defines a service that should be created to access
+    /// all of the capabilities found that are described by this
+    /// configuration. For each Configuration, there is at most
+    /// 1 device capability service.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub configuration_service_spec: Option<ServiceSpec>,
+
+    /// This defines some properties that will be set as
+    /// environment variables in broker Pods that request
+    /// resources discovered in response to this Configuration.
+    /// These properties are also propagated in the Instances
+    /// that represent the discovered resources.
+    #[serde(default)]
+    pub broker_properties: HashMap<String, String>,
+
+    /// This defines how virtual devices are preferred when a
+    /// container requests several of them from the Configuration
+    /// level device plugin. Defaults to the `Spread` strategy.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allocation_policy: Option<AllocationPolicy>,
+
+    /// This defines requirements on the properties of the
+    /// discovered devices, only matching devices are turned into
+    /// Instances. This applies to every Discovery Handler.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub selector: Option<DeviceSelector>,
+}
+
+/// Names of the Discovery Handlers shipped with Akri, whose discovery details are typed in the CRD
+const BUILT_IN_DISCOVERY_HANDLERS: [&str; 4] = ["debugEcho", "onvif", "opcua", "udev"];
+
+/// The discovery details are validated by the webhook against the schema of their Discovery Handler.
+/// The CRD types the fields of the Discovery Handlers shipped with Akri, other fields are preserved
+/// for the other Discovery Handlers.
+fn discovery_details_schema(_: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+    let filter_list = serde_json::json!({
+        "type": "object",
+        "properties": {
+            "items": { "type": "array", "items": { "type": "string" } },
+            "action": { "type": "string", "enum": ["Include", "Exclude"] },
+            "itemKind": { "type": "string", "enum": ["Exact", "Glob", "Regex", "Cidr"] },
+        },
+    });
+    let strings = serde_json::json!({ "type": "array", "items": { "type": "string" } });
+    serde_json::from_value(serde_json::json!({
+        "nullable": true,
+        "type": "object",
+        "x-kubernetes-preserve-unknown-fields": true,
+        "properties": {
+            "descriptions": strings,
+            "ipAddresses": filter_list,
+            "macAddresses": filter_list,
+            "scopes": filter_list,
+            "uuids": filter_list,
+            "discoveryTimeoutSeconds": { "type": "integer" },
+            "mediaProfiles": {
+                "type": "object",
+                "properties": {
+                    "devicePerProfile": { "type": "boolean" },
+                    "encodings": filter_list,
+                    "resolutions": filter_list,
+                    "frameRates": filter_list,
+                },
+            },
+            "unicastTargets": strings,
+            "discoveryProxy": { "type": "string" },
+            "opcuaDiscoveryMethod": {
+                "type": "object",
+                "x-kubernetes-preserve-unknown-fields": true,
+            },
+            "applicationNames": filter_list,
+            "securityPolicies": filter_list,
+            "messageSecurityModes": filter_list,
+            "udevRules": strings,
+            "groupRecursive": { "type": "boolean" },
+            "monitor": { "type": "boolean" },
+        },
+    }))
+    .unwrap()
+}
+
+/// One schema per Discovery Handler shipped with Akri, keyed by its name, requiring the discovery
+/// details it can't do without. Any discovery details are accepted for the other Discovery Handlers.
+fn discovery_handler_one_of() -> serde_json::Value {
+    let requiring = |name: &str, field: &str| {
+        serde_json::json!({
+            "properties": {
+                "name": { "enum": [name] },
+                "discoveryDetails": { "required": [field] },
+            },
+            "required": ["discoveryDetails"],
+        })
+    };
+    serde_json::json!([
+        requiring("debugEcho", "descriptions"),
+        { "properties": { "name": { "enum": ["onvif"] } } },
+        requiring("opcua", "opcuaDiscoveryMethod"),
+        requiring("udev", "udevRules"),
+        { "properties": { "name": { "not": { "enum": BUILT_IN_DISCOVERY_HANDLERS } } } },
+    ])
+}
+
+fn immutable_dh_info(gen: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+    let mut schema: schemars::schema::SchemaObject =
+        <DiscoveryHandlerInfo>::json_schema(gen).into();
+    schema.extensions.insert(
+        "x-kubernetes-validations".to_owned(),
+        serde_json::from_str(r#"[{"message": "Value is immutable", "rule": "self == oldSelf"}]"#)
+            .unwrap(),
+    );
+    schema.subschemas().one_of = Some(serde_json::from_value(discovery_handler_one_of()).unwrap());
+    schema.into()
+}
+
+fn default_capacity() -> usize {
+    DEFAULT_CAPACITY
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::super::os::file;
+    use super::*;
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    #[serde(rename_all = "camelCase")]
+    struct DebugEchoDiscoveryDetails {
+        descriptions: Vec<String>,
+    }
+
+    #[test]
+    fn test_config_structured_discovery_details() {
+        let json = r#"{"discoveryHandler":{"name":"debugEcho", "discoveryDetails":{"descriptions":["foo0"]}}}"#;
+        let deserialized: ConfigurationSpec = serde_json::from_str(json).unwrap();
+        assert_eq!(DEFAULT_CAPACITY, deserialized.capacity);
+        assert_eq!(
+            DebugEchoDiscoveryDetails {
+                descriptions: vec!["foo0".to_string()]
+            },
+            deserialized.discovery_handler.details().unwrap()
+        );
+
+        let json = r#"{"discoveryHandler":{"name":"debugEcho"}}"#;
+        let deserialized: ConfigurationSpec = serde_json::from_str(json).unwrap();
+        assert_eq!(None, deserialized.discovery_handler.discovery_details);
+        assert!(deserialized
+            .discovery_handler
+            .details::<DebugEchoDiscoveryDetails>()
+            .is_err());
+
+        // v0 discovery details are not accepted
+        let json = r#"{"discoveryHandler":{"name":"debugEcho", "discoveryDetails":"descriptions: [foo0]"}}"#;
+        assert!(serde_json::from_str::<ConfigurationSpec>(json).is_err());
+    }
+
+    #[test]
+    fn test_discovery_handler_schema_matches_crd() {
+        let crd: Value = serde_yaml::from_str(&file::read_file_to_string(
+            "../deployment/helm/crds/akri-configuration-crd.yaml",
+        ))
+        .unwrap();
+        let v1 = crd["spec"]["versions"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|version| version["name"] == "v1")
+            .unwrap();
+        let dh_info = &v1["schema"]["openAPIV3Schema"]["properties"]["spec"]["properties"]
+            ["discoveryHandler"];
+        let details_schema = discovery_details_schema(&mut Default::default());
+        assert_eq!(
+            serde_json::to_value(details_schema).unwrap(),
+            dh_info["properties"]["discoveryDetails"]
+        );
+        assert_eq!(discovery_handler_one_of(), dh_info["oneOf"]);
+    }
+}
@@ -0,0 +1,241 @@
+use kube::CustomResource;
+use schemars::JsonSchema;
+use std::collections::HashMap;
+
+use crate::akri::instance::InstanceStatus;
+pub use crate::akri::instance::{SlotUsage, SlotUsageKind};
+
+/// Defines the information in the Instance CRD
+///
+/// An Instance is a specific instance described by
+/// a Configuration.  For example, a Configuration
+/// may describe many cameras, each camera will be represented by a
+/// Instance.
+#[derive(CustomResource, Deserialize, Serialize, Clone, Debug, JsonSchema, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[kube(
+    group = "akri.sh",
+    version = "v1",
+    kind = "Instance",
+    namespaced,
+    shortname = "akrii",
+    printcolumn = r#"{
+        "name": "Config",
+        "type": "string",
+        "jsonPath": ".spec.configurationName",
+        "description": "The Configuration this Instance belongs to"
+    }"#,
+    printcolumn = r#"{
+        "name": "Shared",
+        "type": "boolean",
+        "jsonPath": ".spec.shared",
+        "description": "Describes whether this Instance is shared"
+    }"#,
+    printcolumn = r#"{
+        "name": "Nodes",
+        "type": "string",
+        "jsonPath": ".spec.nodes",
+        "description": "Nodes that expose this Instance"
+    }"#,
+    printcolumn = r#"{
+        "name": "Pods",
+        "type": "string",
+        "jsonPath": ".spec.deviceUsage.*.podName",
+        "description": "Pods the slots of this Instance are allocated to, only recorded with the Agent's structuredSlotUsage enabled"
+    }"#,
+    printcolumn = r#"{
+        "name": "Pod Namespaces",
+        "type": "string",
+        "jsonPath": ".spec.deviceUsage.*.podNamespace",
+        "description": "Namespaces of the Pods the slots of this Instance are allocated to, only recorded with the Agent's structuredSlotUsage enabled",
+        "priority": 1
+    }"#,
+    printcolumn = r#"{
+        "name": "Health",
+        "type": "string",
+        "jsonPath": ".status.nodes[*].health.state",
+        "description": "Health of the device as seen by each node"
+    }"#,
+    printcolumn = r#"{
+        "name": "Brokers",
+        "type": "string",
+        "jsonPath": ".status.conditions[?(@.type==\"BrokersReady\")].status",
+        "description": "Whether every broker Pod of this Instance is ready"
+    }"#,
+    printcolumn = r#"{
+        "name": "Age",
+        "type": "date",
+        "jsonPath": ".metadata.creationTimestamp"
+    }"#,
+    status = "InstanceStatus",
+    derive = "PartialEq"
+)]
+pub struct InstanceSpec {
+    /// This contains the name of the corresponding Configuration
+    pub configuration_name: String,
+
+    /// This contains the CDI fully qualified name of the device linked to the Instance
+    pub cdi_name: String,
+
+    /// This contains the number of slots for the Instance
+    pub capacity: usize,
+
+    /// This defines some properties that will be set as
+    /// environment variables in broker Pods that request
+    /// the resource this Instance represents.
+    /// It contains the `Configuration.broker_properties` from
+    /// this Instance's Configuration and the `Device.properties`
+    /// set by the Discovery Handler that discovered the resource
+    /// this Instance represents.
+    #[serde(default)]
+    pub broker_properties: HashMap<String, String>,
+
+    /// This defines whether the capability is to be shared by multiple nodes
+    #[serde(default)]
+    pub shared: bool,
+
+    /// This contains a list of the nodes that can access this capability instance
+    #[serde(default)]
+    #[schemars(schema_with = "ssa_nodes_set")]
+    pub nodes: Vec<String>,
+
+    /// This contains the usage of each slot of the Instance.  The number of
+    /// slots corresponds to the associated Configuration.capacity field.
+    #[serde(default)]
+    #[schemars(schema_with = "ssa_usage_granular")]
+    pub device_usage: HashMap<String, SlotUsage>,
+}
+
+impl SlotUsage {
+    /// Builds the usage of a slot from its `v0` string encoding, see [`SlotUsage::from_legacy`]
+    pub fn from_v0(usage: &str) -> Self {
+        Self::from_legacy(usage)
+    }
+
+    /// Returns the `v0` string encoding of the usage of the slot, unless it can't hold the
+    /// whole slot, in which case the slot is kept in its structured form that `v0` also accepts
+    pub fn to_v0(&self) -> Option<String> {
+        match AnySlotUsage::compact(self) {
+            AnySlotUsage::Legacy(usage) => Some(usage),
+            AnySlotUsage::Structured(_) => None,
+        }
+    }
+}
+
+fn ssa_nodes_set(gen: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+    let mut schema: schemars::schema::SchemaObject = <Vec<String>>::json_schema(gen).into();
+    schema.extensions.insert(
+        "x-kubernetes-list-type".to_owned(),
+        serde_json::Value::String("set".to_owned()),
+    );
+    schema.into()
+}
+
+/// Each slot is owned as a whole by the device plugin that claims it, and its fields must be
+/// consistent with its kind
+fn ssa_usage_granular(gen: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+    let mut slot: schemars::schema::SchemaObject = <SlotUsage>::json_schema(gen).into();
+    slot.extensions.insert(
+        "x-kubernetes-map-type".to_owned(),
+        serde_json::Value::String("atomic".to_owned()),
+    );
+    slot.extensions.insert(
+        "x-kubernetes-validations".to_owned(),
+        serde_json::json!([
+            {
+                "message": "node must be set unless the slot is free",
+                "rule": "self.kind == 'Free' ? !has(self.node) : has(self.node)"
+            },
+            {
+                "message": "vdev must be set for, and only for, the Configuration kind",
+                "rule": "self.kind == 'Configuration' ? has(self.vdev) : !has(self.vdev)"
+            },
+            {
+                "message": "claimUid must be set for, and only for, the Dra kind",
+                "rule": "self.kind == 'Dra' ? has(self.claimUid) : !has(self.claimUid)"
+            },
+            {
+                "message": "a free slot has no claim",
+                "rule": "self.kind != 'Free' || (!has(self.podNamespace) && !has(self.podName) && !has(self.podUid) && !has(self.claimTime))"
+            },
+            {
+                "message": "podNamespace and podName must be set together, and before podUid",
+                "rule": "has(self.podNamespace) == has(self.podName) && (!has(self.podUid) || has(self.podName))"
+            }
+        ]),
+    );
+    let mut schema: schemars::schema::SchemaObject =
+        <HashMap<String, SlotUsage>>::json_schema(gen).into();
+    schema.object().additional_properties = Some(Box::new(slot.into()));
+    schema.extensions.insert(
+        "x-kubernetes-map-type".to_owned(),
+        serde_json::Value::String("granular".to_owned()),
+    );
+    schema.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slot_usage_v0() {
+        let free = SlotUsage::default();
+        let instance = SlotUsage {
+            kind: SlotUsageKind::Instance,
+            node: Some("node-a".to_string()),
+            ..Default::default()
+        };
+        let configuration = SlotUsage {
+            kind: SlotUsageKind::Configuration,
+            node: Some("node-a".to_string()),
+            vdev: Some("vdev0".to_string()),
+            ..Default::default()
+        };
+        for (v0, v1) in [
+            ("", &free),
+            ("node-a", &instance),
+            ("C:vdev0:node-a", &configuration),
+        ] {
+            assert_eq!(&SlotUsage::from_v0(v0), v1);
+            assert_eq!(v1.to_v0().as_deref(), Some(v0));
+        }
+
+        // Values that can't be parsed are kept as the node name
+        let invalid = SlotUsage::from_v0("C:vdev0:");
+        assert_eq!(invalid.kind, SlotUsageKind::Instance);
+        assert_eq!(invalid.to_v0().as_deref(), Some("C:vdev0:"));
+
+        // Slots the string encoding can't hold have none
+        let claimed = SlotUsage {
+            pod_namespace: Some("default".to_string()),
+            pod_name: Some("broker-a".to_string()),
+            ..instance.clone()
+        };
+        assert_eq!(claimed.to_v0(), None);
+        let ambiguous = SlotUsage {
+            node: Some("C:vdev0:node-a".to_string()),
+            ..instance
+        };
+        assert_eq!(ambiguous.to_v0(), None);
+    }
+
+    #[test]
+    fn test_slot_usage_serialization() {
+        let json = r#"{"configurationName": "foo", "cdiName": "akri.sh/foo=bar", "capacity": 2, "deviceUsage": {"foo-0": {"kind": "Configuration", "node": "node-a", "vdev": "vdev0"}, "foo-1": {}}}"#;
+        let deserialized: InstanceSpec = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            deserialized.device_usage["foo-0"],
+            SlotUsage::from_legacy("C:vdev0:node-a")
+        );
+        assert_eq!(deserialized.device_usage["foo-1"], SlotUsage::default());
+        assert_eq!(
+            serde_json::to_value(&deserialized.device_usage["foo-1"]).unwrap(),
+            serde_json::json!({"kind": "Free"})
+        );
+
+        // Unlike v0, legacy string slots are not accepted
+        let json = r#"{"configurationName": "foo", "cdiName": "akri.sh/foo=bar", "capacity": 1, "deviceUsage": {"foo-0": "node-a"}}"#;
+        assert!(serde_json::from_str::<InstanceSpec>(json).is_err());
+    }
+}
@@ -0,0 +1,17 @@
+//! Version `v1` of the Akri API.
+//!
+//! It is served alongside `v0`, which remains the storage version and the version used by the Agent and the
+//! Controller, the API server converting objects between both versions through the conversion webhook (see
+//! [`super::conversion`]). Compared to `v0`:
+//! - the discovery details of a Configuration are structured data, following the schema of its Discovery Handler,
+//!   rather than an opaque string
+//! - the usage of each slot of an Instance is always a structure, `v0` still accepting the string encoding of its
+//!   owner written by older Agents
+//!
+//! The status of both resources is the same in both versions.
+
+/// Akri API Version of this module
+pub const API_VERSION: &str = "v1";
+
+pub mod configuration;
+pub mod instance;
@@ -1,9 +1,15 @@
-use akri_shared::akri::instance;
-use kube::CustomResourceExt;
+use akri_shared::akri::{instance, v1, API_VERSION};
+use kube::{core::crd::merge_crds, CustomResourceExt};
 
 pub fn main() {
-    println!(
-        "{}",
-        serde_yaml::to_string(&instance::Instance::crd()).unwrap()
-    );
+    let mut crd = merge_crds(
+        vec![instance::Instance::crd(), v1::instance::Instance::crd()],
+        API_VERSION,
+    )
+    .unwrap();
+    // Versions other than the storage one are served once the conversion webhook is set up
+    for version in crd.spec.versions.iter_mut() {
+        version.served = version.name == API_VERSION;
+    }
+    println!("{}", serde_yaml::to_string(&crd).unwrap());
 }
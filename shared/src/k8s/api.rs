@@ -18,6 +18,13 @@ use super::KubeImpl;
 pub trait Api<T: Clone + Send + Sync + Resource>: Send + Sync {
     fn as_inner(&self) -> kube::Api<T>;
     async fn apply(&self, obj: T, field_manager: &str) -> Result<T, Error>;
+    /// Server Side Applies the given status to the status subresource of the named object
+    async fn apply_status(
+        &self,
+        name: &str,
+        status: Value,
+        field_manager: &str,
+    ) -> Result<T, Error>;
     async fn raw_patch(
         &self,
         name: &str,
@@ -67,6 +74,21 @@ where
         let patch = kube::api::Patch::Apply(obj);
         self.patch(&name, &pp, &patch).await
     }
+    async fn apply_status(
+        &self,
+        name: &str,
+        status: Value,
+        field_manager: &str,
+    ) -> Result<T, Error> {
+        let patch = Patch::Apply(serde_json::json!({
+            "apiVersion": T::api_version(&()),
+            "kind": T::kind(&()),
+            "metadata": { "name": name },
+            "status": status,
+        }));
+        self.patch_status(name, &PatchParams::apply(field_manager), &patch)
+            .await
+    }
     async fn raw_patch(
         &self,
         name: &str,
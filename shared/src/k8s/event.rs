@@ -0,0 +1,471 @@
+//! Kubernetes Events recorded by the Agent and the Controller about Akri objects.
+//!
+//! Like the event recorder of client-go, events are rate limited per object and
+//! aggregated: repeated events update the count of a single Event, and many
+//! similar events with distinct messages are combined into one.
+use super::api::IntoApi;
+use async_trait::async_trait;
+use k8s_openapi::{
+    api::core::v1::{Event, EventSource, ObjectReference},
+    apimachinery::pkg::apis::meta::v1::{ObjectMeta, Time},
+    chrono::{DateTime, Utc},
+};
+use log::{trace, warn};
+use mockall::automock;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex},
+};
+
+/// Number of events that can be recorded at once about an object
+const BURST: f64 = 25.0;
+/// Seconds it takes to allow one more event about an object once the burst is spent
+const REFILL_SECS: f64 = 300.0;
+/// Number of distinct messages of similar events recorded before they get combined
+const MAX_SIMILAR_EVENTS: usize = 10;
+/// Seconds during which similar events are counted
+const SIMILAR_EVENTS_WINDOW_SECS: i64 = 600;
+/// Seconds during which a repeated event updates the previous Event, matching the
+/// default time to live of Events
+const DEDUPLICATION_WINDOW_SECS: i64 = 3600;
+const COMBINED_PREFIX: &str = "(combined from similar events): ";
+
+/// Type of an Event, warnings being for anything that needs attention
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum EventType {
+    Normal,
+    Warning,
+}
+
+impl EventType {
+    fn as_str(&self) -> &'static str {
+        match self {
+            EventType::Normal => "Normal",
+            EventType::Warning => "Warning",
+        }
+    }
+}
+
+/// What happened to an object
+#[derive(Clone, Debug, PartialEq)]
+pub struct EventInfo {
+    pub type_: EventType,
+    /// Short, machine understandable, reason of the event in UpperCamelCase
+    pub reason: String,
+    /// Action taken or failed regarding the object
+    pub action: String,
+    /// Human readable description of the event
+    pub message: String,
+}
+
+impl EventInfo {
+    pub fn normal(reason: &str, action: &str, message: impl Into<String>) -> Self {
+        EventInfo {
+            type_: EventType::Normal,
+            reason: reason.to_string(),
+            action: action.to_string(),
+            message: message.into(),
+        }
+    }
+
+    pub fn warning(reason: &str, action: &str, message: impl Into<String>) -> Self {
+        EventInfo {
+            type_: EventType::Warning,
+            reason: reason.to_string(),
+            action: action.to_string(),
+            message: message.into(),
+        }
+    }
+}
+
+#[automock]
+#[async_trait]
+pub trait EventRecorder: Send + Sync {
+    /// Records an event regarding the given object. Events are best effort, failures are only logged.
+    async fn record(&self, regarding: &ObjectReference, event: EventInfo);
+}
+
+/// Records events as core/v1 Events
+pub struct KubeEventRecorder {
+    client: Arc<dyn IntoApi<Event>>,
+    component: String,
+    instance: String,
+    correlator: Mutex<EventCorrelator>,
+}
+
+impl KubeEventRecorder {
+    /// Create a recorder reporting events as coming from `instance` (e.g. the node
+    /// name of an Agent) of `component`
+    pub fn new(client: Arc<dyn IntoApi<Event>>, component: &str, instance: &str) -> Self {
+        KubeEventRecorder {
+            client,
+            component: component.to_string(),
+            instance: instance.to_string(),
+            correlator: Mutex::new(EventCorrelator::default()),
+        }
+    }
+
+    fn build_event(
+        &self,
+        namespace: &str,
+        regarding: &ObjectReference,
+        event: &EventInfo,
+        correlated: CorrelatedEvent,
+    ) -> Event {
+        Event {
+            metadata: ObjectMeta {
+                name: Some(correlated.name),
+                namespace: Some(namespace.to_string()),
+                ..Default::default()
+            },
+            involved_object: regarding.clone(),
+            type_: Some(event.type_.as_str().to_string()),
+            reason: Some(event.reason.clone()),
+            action: Some(event.action.clone()),
+            message: Some(correlated.message),
+            count: Some(correlated.count),
+            first_timestamp: Some(Time(correlated.first_timestamp)),
+            last_timestamp: Some(Time(correlated.last_timestamp)),
+            source: Some(EventSource {
+                component: Some(self.component.clone()),
+                host: Some(self.instance.clone()),
+            }),
+            reporting_component: Some(self.component.clone()),
+            reporting_instance: Some(self.instance.clone()),
+            ..Default::default()
+        }
+    }
+}
+
+#[async_trait]
+impl EventRecorder for KubeEventRecorder {
+    async fn record(&self, regarding: &ObjectReference, event: EventInfo) {
+        let correlated = self
+            .correlator
+            .lock()
+            .unwrap()
+            .correlate(regarding, &event, Utc::now());
+        let correlated = match correlated {
+            Some(correlated) => correlated,
+            None => {
+                trace!(
+                    "record - dropping event {} about {:?}, too many events",
+                    event.reason,
+                    regarding.name
+                );
+                return;
+            }
+        };
+        let namespace = regarding.namespace.as_deref().unwrap_or("default");
+        let k8s_event = self.build_event(namespace, regarding, &event, correlated);
+        if let Err(e) = self
+            .client
+            .namespaced(namespace)
+            .apply(k8s_event, &self.component)
+            .await
+        {
+            warn!(
+                "record - unable to record event {} about {:?}: {:?}",
+                event.reason, regarding.name, e
+            );
+        }
+    }
+}
+
+/// Event to create, or update if an Event with that name exists
+#[derive(Debug, PartialEq)]
+struct CorrelatedEvent {
+    name: String,
+    message: String,
+    count: i32,
+    first_timestamp: DateTime<Utc>,
+    last_timestamp: DateTime<Utc>,
+}
+
+struct TokenBucket {
+    tokens: f64,
+    last_refill: DateTime<Utc>,
+}
+
+struct SimilarEvents {
+    first_seen: DateTime<Utc>,
+    messages: HashSet<String>,
+}
+
+struct RecordedEvent {
+    name: String,
+    count: i32,
+    first_timestamp: DateTime<Utc>,
+    last_timestamp: DateTime<Utc>,
+}
+
+/// Rate limits, aggregates and deduplicates events
+#[derive(Default)]
+struct EventCorrelator {
+    buckets: HashMap<String, TokenBucket>,
+    similar: HashMap<(String, EventType, String), SimilarEvents>,
+    recorded: HashMap<(String, EventType, String, String), RecordedEvent>,
+    /// Timestamp used in the name of the last created Event, so names are unique
+    last_name_nanos: i64,
+}
+
+impl EventCorrelator {
+    /// Returns the Event to write for this event, or None if it is dropped by the rate limit
+    fn correlate(
+        &mut self,
+        regarding: &ObjectReference,
+        event: &EventInfo,
+        now: DateTime<Utc>,
+    ) -> Option<CorrelatedEvent> {
+        self.purge(now);
+        let object = object_key(regarding);
+        if !self.take_token(&object, now) {
+            return None;
+        }
+
+        let similar = self
+            .similar
+            .entry((object.clone(), event.type_, event.reason.clone()))
+            .or_insert_with(|| SimilarEvents {
+                first_seen: now,
+                messages: HashSet::new(),
+            });
+        let message = if similar.messages.contains(&event.message)
+            || similar.messages.len() < MAX_SIMILAR_EVENTS
+        {
+            similar.messages.insert(event.message.clone());
+            event.message.clone()
+        } else {
+            format!("{}{}", COMBINED_PREFIX, event.message)
+        };
+        // Combined events share a single Event whatever their message
+        let message_key = if message.starts_with(COMBINED_PREFIX) {
+            COMBINED_PREFIX.to_string()
+        } else {
+            message.clone()
+        };
+
+        let last_name_nanos = &mut self.last_name_nanos;
+        let recorded = self
+            .recorded
+            .entry((object, event.type_, event.reason.clone(), message_key))
+            .and_modify(|recorded| {
+                recorded.count += 1;
+                recorded.last_timestamp = now;
+            })
+            .or_insert_with(|| {
+                *last_name_nanos =
+                    (*last_name_nanos + 1).max(now.timestamp_nanos_opt().unwrap_or_default());
+                RecordedEvent {
+                    name: format!(
+                        "{}.{:x}",
+                        regarding.name.as_deref().unwrap_or_default(),
+                        last_name_nanos
+                    ),
+                    count: 1,
+                    first_timestamp: now,
+                    last_timestamp: now,
+                }
+            });
+        Some(CorrelatedEvent {
+            name: recorded.name.clone(),
+            message,
+            count: recorded.count,
+            first_timestamp: recorded.first_timestamp,
+            last_timestamp: recorded.last_timestamp,
+        })
+    }
+
+    fn take_token(&mut self, object: &str, now: DateTime<Utc>) -> bool {
+        let bucket = self
+            .buckets
+            .entry(object.to_string())
+            .or_insert(TokenBucket {
+                tokens: BURST,
+                last_refill: now,
+            });
+        let elapsed = (now - bucket.last_refill).num_milliseconds().max(0) as f64 / 1000.0;
+        bucket.tokens = (bucket.tokens + elapsed / REFILL_SECS).min(BURST);
+        bucket.last_refill = now;
+        if bucket.tokens < 1.0 {
+            return false;
+        }
+        bucket.tokens -= 1.0;
+        true
+    }
+
+    /// Forgets about events that no longer affect the correlation
+    fn purge(&mut self, now: DateTime<Utc>) {
+        self.similar
+            .retain(|_, s| (now - s.first_seen).num_seconds() < SIMILAR_EVENTS_WINDOW_SECS);
+        self.recorded
+            .retain(|_, r| (now - r.last_timestamp).num_seconds() < DEDUPLICATION_WINDOW_SECS);
+        self.buckets.retain(|_, b| {
+            b.tokens + (now - b.last_refill).num_seconds() as f64 / REFILL_SECS < BURST
+        });
+    }
+}
+
+fn object_key(regarding: &ObjectReference) -> String {
+    format!(
+        "{}/{}/{}/{}",
+        regarding.kind.as_deref().unwrap_or_default(),
+        regarding.namespace.as_deref().unwrap_or_default(),
+        regarding.name.as_deref().unwrap_or_default(),
+        regarding.uid.as_deref().unwrap_or_default()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::api::{MockApi, MockIntoApi};
+    use super::*;
+    use k8s_openapi::chrono::Duration;
+
+    fn regarding(name: &str) -> ObjectReference {
+        ObjectReference {
+            api_version: Some("akri.sh/v0".to_string()),
+            kind: Some("Instance".to_string()),
+            name: Some(name.to_string()),
+            namespace: Some("ns".to_string()),
+            uid: Some(format!("{}-uid", name)),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_correlate_deduplicates() {
+        let mut correlator = EventCorrelator::default();
+        let now = Utc::now();
+        let event = EventInfo::normal("SlotClaimed", "Claim", "claimed slot 0");
+        let first = correlator.correlate(&regarding("a"), &event, now).unwrap();
+        assert_eq!(first.count, 1);
+        let second = correlator
+            .correlate(&regarding("a"), &event, now + Duration::seconds(1))
+            .unwrap();
+        assert_eq!(second.name, first.name);
+        assert_eq!(second.count, 2);
+        assert_eq!(second.first_timestamp, now);
+        assert_eq!(second.last_timestamp, now + Duration::seconds(1));
+
+        // Another message or object is another Event
+        let other = correlator
+            .correlate(
+                &regarding("a"),
+                &EventInfo::normal("SlotClaimed", "Claim", "claimed slot 1"),
+                now,
+            )
+            .unwrap();
+        assert_ne!(other.name, first.name);
+        let other = correlator
+            .correlate(&regarding("b"), &event, now + Duration::seconds(2))
+            .unwrap();
+        assert_eq!(other.count, 1);
+
+        // Repeating after the deduplication window creates a new Event
+        let later = correlator
+            .correlate(
+                &regarding("a"),
+                &event,
+                now + Duration::seconds(DEDUPLICATION_WINDOW_SECS + 2),
+            )
+            .unwrap();
+        assert_ne!(later.name, first.name);
+        assert_eq!(later.count, 1);
+    }
+
+    #[test]
+    fn test_correlate_aggregates_similar_events() {
+        let mut correlator = EventCorrelator::default();
+        let now = Utc::now();
+        for i in 0..MAX_SIMILAR_EVENTS {
+            let correlated = correlator
+                .correlate(
+                    &regarding("a"),
+                    &EventInfo::warning("BrokerFailing", "Deploy", format!("pod {}", i)),
+                    now,
+                )
+                .unwrap();
+            assert_eq!(correlated.message, format!("pod {}", i));
+        }
+        let combined = correlator
+            .correlate(
+                &regarding("a"),
+                &EventInfo::warning("BrokerFailing", "Deploy", "pod 10"),
+                now,
+            )
+            .unwrap();
+        assert_eq!(combined.message, "(combined from similar events): pod 10");
+        let combined_again = correlator
+            .correlate(
+                &regarding("a"),
+                &EventInfo::warning("BrokerFailing", "Deploy", "pod 11"),
+                now,
+            )
+            .unwrap();
+        assert_eq!(combined_again.name, combined.name);
+        assert_eq!(combined_again.count, 2);
+
+        // Known messages are still recorded on their own
+        let known = correlator
+            .correlate(
+                &regarding("a"),
+                &EventInfo::warning("BrokerFailing", "Deploy", "pod 0"),
+                now,
+            )
+            .unwrap();
+        assert_eq!(known.message, "pod 0");
+        assert_eq!(known.count, 2);
+    }
+
+    #[test]
+    fn test_correlate_rate_limits() {
+        let mut correlator = EventCorrelator::default();
+        let now = Utc::now();
+        let event = EventInfo::normal("SlotFreed", "Free", "freed slot 0");
+        for _ in 0..BURST as usize {
+            assert!(correlator.correlate(&regarding("a"), &event, now).is_some());
+        }
+        assert!(correlator.correlate(&regarding("a"), &event, now).is_none());
+        // Other objects have their own limit
+        assert!(correlator.correlate(&regarding("b"), &event, now).is_some());
+        // A token is available again after the refill period
+        let later = now + Duration::seconds(REFILL_SECS as i64);
+        assert!(correlator
+            .correlate(&regarding("a"), &event, later)
+            .is_some());
+        assert!(correlator
+            .correlate(&regarding("a"), &event, later)
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_record() {
+        let mut client = MockIntoApi::<Event>::new();
+        client
+            .expect_namespaced()
+            .withf(|namespace| namespace == "ns")
+            .returning(|_| {
+                let mut api = MockApi::<Event>::new();
+                api.expect_apply()
+                    .withf(|event, field_manager| {
+                        field_manager == "akri-agent"
+                            && event.involved_object == regarding("a")
+                            && event.type_.as_deref() == Some("Warning")
+                            && event.reason.as_deref() == Some("SlotConflict")
+                            && event.count == Some(1)
+                            && event.reporting_instance.as_deref() == Some("node-a")
+                            && event.metadata.name.as_ref().unwrap().starts_with("a.")
+                    })
+                    .returning(|event, _| Ok(event));
+                Box::new(api)
+            });
+        let recorder = KubeEventRecorder::new(Arc::new(client), "akri-agent", "node-a");
+        recorder
+            .record(
+                &regarding("a"),
+                EventInfo::warning("SlotConflict", "Claim", "slot 0 is in use"),
+            )
+            .await;
+    }
+}
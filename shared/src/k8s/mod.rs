@@ -9,10 +9,12 @@ use super::akri::{
 use async_trait::async_trait;
 use k8s_openapi::api::batch::v1::Job;
 use k8s_openapi::api::core::v1::{Node, Pod, Service};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::Condition;
 use kube::{api::ObjectList, client::Client};
 use mockall::{automock, predicate::*};
 
 pub mod api;
+pub mod event;
 pub mod job;
 pub mod node;
 pub mod pod;
@@ -147,6 +149,12 @@ pub trait KubeInterface: Send + Sync {
         name: &str,
         namespace: &str,
     ) -> Result<(), anyhow::Error>;
+    async fn update_instance_conditions(
+        &self,
+        conditions: &[Condition],
+        name: &str,
+        namespace: &str,
+    ) -> Result<(), anyhow::Error>;
 }
 
 #[derive(Clone)]
@@ -598,6 +606,32 @@ impl KubeInterface for KubeImpl {
         instance::update_instance(instance_to_update, name, namespace, &self.get_kube_client())
             .await
     }
+
+    /// Update the conditions of an Akri Instance status
+    ///
+    /// Example:
+    ///
+    /// ```no_run
+    /// use akri_shared::k8s;
+    /// use akri_shared::k8s::KubeInterface;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let kube = k8s::KubeImpl::new().await.unwrap();
+    /// kube.update_instance_conditions(&[], "instance-1", "instance-namespace")
+    ///     .await
+    ///     .unwrap();
+    /// # }
+    /// ```
+    async fn update_instance_conditions(
+        &self,
+        conditions: &[Condition],
+        name: &str,
+        namespace: &str,
+    ) -> Result<(), anyhow::Error> {
+        instance::update_instance_conditions(conditions, name, namespace, &self.get_kube_client())
+            .await
+    }
 }
 
 /// This deletes an Instance unless it has already been deleted by another node
@@ -0,0 +1,128 @@
+//! Conversion webhook of the Configuration and Instance CRDs: the API server sends a ConversionReview with the
+//! objects to convert whenever they are read or written in a version other than the one they are stored in.
+//!
+//! The conversion itself is done by `akri_shared::akri::conversion`, a single object that can't be converted fails
+//! the whole review, as the API server expects.
+use akri_shared::akri::conversion::convert;
+use serde_json::{json, Value};
+
+/// Answers a `apiextensions.k8s.io/v1` ConversionReview, None if it holds no request
+pub fn review_conversion(review: &Value) -> Option<Value> {
+    let request = review.get("request")?;
+    let uid = request["uid"].as_str()?;
+    let desired_api_version = request["desiredAPIVersion"].as_str()?;
+    let objects = request["objects"].as_array().cloned().unwrap_or_default();
+    let response = match objects
+        .iter()
+        .map(|object| convert(object, desired_api_version))
+        .collect::<Result<Vec<_>, _>>()
+    {
+        Ok(converted) => json!({
+            "uid": uid,
+            "convertedObjects": converted,
+            "result": { "status": "Success" },
+        }),
+        Err(e) => {
+            println!(
+                "review_conversion - conversion to {} failed: {}",
+                desired_api_version, e
+            );
+            json!({
+                "uid": uid,
+                "convertedObjects": [],
+                "result": { "status": "Failure", "message": e.to_string() },
+            })
+        }
+    };
+    Some(json!({
+        "apiVersion": "apiextensions.k8s.io/v1",
+        "kind": "ConversionReview",
+        "response": response,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn conversion_review(desired_api_version: &str, objects: Value) -> Value {
+        json!({
+            "apiVersion": "apiextensions.k8s.io/v1",
+            "kind": "ConversionReview",
+            "request": {
+                "uid": "705ab4f5-6393-11e8-b7cc-42010a800002",
+                "desiredAPIVersion": desired_api_version,
+                "objects": objects,
+            }
+        })
+    }
+
+    #[test]
+    fn test_review_conversion() {
+        let review = conversion_review(
+            "akri.sh/v1",
+            json!([
+                {
+                    "apiVersion": "akri.sh/v0",
+                    "kind": "Configuration",
+                    "metadata": {"name": "config-a"},
+                    "spec": {"discoveryHandler": {"name": "debugEcho", "discoveryDetails": "descriptions:\n- foo0\n"}}
+                },
+                {
+                    "apiVersion": "akri.sh/v0",
+                    "kind": "Instance",
+                    "metadata": {"name": "config-a-b494b6"},
+                    "spec": {"configurationName": "config-a", "cdiName": "akri.sh/config-a=b494b6", "capacity": 1, "deviceUsage": {"config-a-b494b6-0": "node-a"}}
+                }
+            ]),
+        );
+        let response = review_conversion(&review).unwrap();
+        assert_eq!(response["kind"], "ConversionReview");
+        let response = &response["response"];
+        assert_eq!(response["uid"], "705ab4f5-6393-11e8-b7cc-42010a800002");
+        assert_eq!(response["result"]["status"], "Success");
+        let converted = response["convertedObjects"].as_array().unwrap();
+        assert_eq!(converted.len(), 2);
+        assert!(converted
+            .iter()
+            .all(|object| object["apiVersion"] == "akri.sh/v1"));
+        assert_eq!(
+            converted[0]["spec"]["discoveryHandler"]["discoveryDetails"],
+            json!({"descriptions": ["foo0"]})
+        );
+        assert_eq!(
+            converted[1]["spec"]["deviceUsage"]["config-a-b494b6-0"],
+            json!({"kind": "Instance", "node": "node-a"})
+        );
+    }
+
+    #[test]
+    fn test_review_conversion_failure() {
+        let review = conversion_review(
+            "akri.sh/v0",
+            json!([
+                {
+                    "apiVersion": "akri.sh/v1",
+                    "kind": "Instance",
+                    "metadata": {"name": "config-a-b494b6"},
+                    "spec": {"deviceUsage": {"config-a-b494b6-0": {"kind": "Free"}}}
+                },
+                {"apiVersion": "akri.sh/v2", "kind": "Instance", "metadata": {"name": "config-a-b494b6"}}
+            ]),
+        );
+        let response = review_conversion(&review).unwrap();
+        let response = &response["response"];
+        assert_eq!(response["result"]["status"], "Failure");
+        assert_eq!(response["convertedObjects"], json!([]));
+    }
+
+    #[test]
+    fn test_review_conversion_no_request() {
+        assert_eq!(
+            review_conversion(
+                &json!({"apiVersion": "apiextensions.k8s.io/v1", "kind": "ConversionReview"})
+            ),
+            None
+        );
+    }
+}
@@ -0,0 +1,247 @@
+//! Validation of the discovery details of the Discovery Handlers embedded in the webhook, by deserializing them the
+//! way these Discovery Handlers do. Unlike the Discovery Handlers, unknown fields are rejected, as they most likely
+//! are typos that would otherwise be silently ignored.
+use serde::de::DeserializeOwned;
+
+/// Validates the discovery details of an embedded Discovery Handler. Returns None if the Discovery Handler is not
+/// embedded, in which case its details have to be validated some other way.
+// The arguments are unused if the webhook is built without any Discovery Handler feature
+#[allow(unused_variables)]
+pub fn validate_embedded_discovery_details(
+    discovery_handler_name: &str,
+    discovery_details: &str,
+) -> Option<Result<(), String>> {
+    match discovery_handler_name {
+        #[cfg(feature = "debug-echo-feat")]
+        akri_debug_echo::DISCOVERY_HANDLER_NAME => Some(
+            deserialize::<akri_debug_echo::discovery_handler::DebugEchoDiscoveryDetails>(
+                discovery_details,
+            )
+            .map(|_| ()),
+        ),
+        #[cfg(feature = "onvif-feat")]
+        akri_onvif::DISCOVERY_HANDLER_NAME => Some(
+            deserialize::<akri_onvif::discovery_handler::OnvifDiscoveryDetails>(discovery_details)
+                .map(|_| ()),
+        ),
+        #[cfg(feature = "opcua-feat")]
+        akri_opcua::DISCOVERY_HANDLER_NAME => Some(
+            deserialize::<akri_opcua::discovery_handler::OpcuaDiscoveryDetails>(discovery_details)
+                .map(|_| ()),
+        ),
+        #[cfg(feature = "udev-feat")]
+        akri_udev::DISCOVERY_HANDLER_NAME => {
+            Some(validate_udev_discovery_details(discovery_details))
+        }
+        _ => None,
+    }
+}
+
+/// Besides being well formed, every udev rule must be supported by the udev Discovery Handler
+#[cfg(feature = "udev-feat")]
+fn validate_udev_discovery_details(discovery_details: &str) -> Result<(), String> {
+    let details: akri_udev::discovery_handler::UdevDiscoveryDetails =
+        deserialize(discovery_details)?;
+    let errors: Vec<String> = details
+        .udev_rules
+        .iter()
+        .enumerate()
+        .filter_map(|(i, rule)| {
+            akri_udev::parse_udev_rule(rule)
+                .err()
+                .map(|e| format!("discoveryDetails.udevRules[{}]: {}", i, e))
+        })
+        .collect();
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors.join(", "))
+    }
+}
+
+/// Deserializes YAML discovery details, reporting the path of the invalid or unknown fields in the errors.
+#[allow(dead_code)]
+fn deserialize<T: DeserializeOwned>(discovery_details: &str) -> Result<T, String> {
+    let mut unknown_fields = Vec::new();
+    let deserializer = serde_ignored::Deserializer::new(
+        serde_yaml::Deserializer::from_str(discovery_details),
+        |path| unknown_fields.push(format_ignored_path(&path)),
+    );
+    let details: T = serde_path_to_error::deserialize(deserializer).map_err(|e| {
+        let path = e.path().to_string();
+        if path == "." {
+            format!("discoveryDetails: {}", e.inner())
+        } else {
+            format!("discoveryDetails.{}: {}", path, e.inner())
+        }
+    })?;
+    if !unknown_fields.is_empty() {
+        return Err(unknown_fields
+            .iter()
+            .map(|path| format!("discoveryDetails{}: unknown field", path))
+            .collect::<Vec<String>>()
+            .join(", "));
+    }
+    Ok(details)
+}
+
+/// Formats the path of an unknown field the same way `serde_path_to_error` does, without the optional and newtype
+/// wrappers that don't show in YAML.
+#[allow(dead_code)]
+fn format_ignored_path(path: &serde_ignored::Path) -> String {
+    match path {
+        serde_ignored::Path::Root => String::new(),
+        serde_ignored::Path::Seq { parent, index } => {
+            format!("{}[{}]", format_ignored_path(parent), index)
+        }
+        serde_ignored::Path::Map { parent, key } => {
+            format!("{}.{}", format_ignored_path(parent), key)
+        }
+        serde_ignored::Path::Some { parent }
+        | serde_ignored::Path::NewtypeStruct { parent }
+        | serde_ignored::Path::NewtypeVariant { parent } => format_ignored_path(parent),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unknown_discovery_handler() {
+        assert!(validate_embedded_discovery_details("custom", "anything: [").is_none());
+    }
+
+    #[cfg(feature = "debug-echo-feat")]
+    #[test]
+    fn test_validate_debug_echo_discovery_details() {
+        assert_eq!(
+            validate_embedded_discovery_details("debugEcho", "descriptions: [foo0, foo1]"),
+            Some(Ok(()))
+        );
+        let err = validate_embedded_discovery_details("debugEcho", "descriptions: foo")
+            .unwrap()
+            .unwrap_err();
+        assert!(
+            err.starts_with("discoveryDetails.descriptions: "),
+            "{}",
+            err
+        );
+        let err = validate_embedded_discovery_details("debugEcho", "description: [foo]")
+            .unwrap()
+            .unwrap_err();
+        assert!(
+            err.starts_with("discoveryDetails: missing field"),
+            "{}",
+            err
+        );
+    }
+
+    #[cfg(feature = "onvif-feat")]
+    #[test]
+    fn test_validate_onvif_discovery_details() {
+        let yaml = r#"
+            ipAddresses:
+              action: Exclude
+              items: [10.0.0.1]
+            mediaProfiles:
+              encodings:
+                items: [H264]
+        "#;
+        assert_eq!(
+            validate_embedded_discovery_details("onvif", yaml),
+            Some(Ok(()))
+        );
+
+        let yaml = r#"
+            ipAddresses:
+              action: Exclud
+              items: [10.0.0.1]
+        "#;
+        let err = validate_embedded_discovery_details("onvif", yaml)
+            .unwrap()
+            .unwrap_err();
+        assert!(
+            err.starts_with("discoveryDetails.ipAddresses.action: "),
+            "{}",
+            err
+        );
+
+        let yaml = r#"
+            ipAdresses:
+              items: [10.0.0.1]
+            mediaProfiles:
+              encoding:
+                items: [H264]
+        "#;
+        let err = validate_embedded_discovery_details("onvif", yaml)
+            .unwrap()
+            .unwrap_err();
+        assert_eq!(
+            err,
+            "discoveryDetails.ipAdresses: unknown field, \
+             discoveryDetails.mediaProfiles.encoding: unknown field"
+        );
+    }
+
+    #[cfg(feature = "opcua-feat")]
+    #[test]
+    fn test_validate_opcua_discovery_details() {
+        let yaml = r#"
+            opcuaDiscoveryMethod:
+              standard:
+                discoveryUrls: ["opc.tcp://10.0.0.1:4840/"]
+        "#;
+        assert_eq!(
+            validate_embedded_discovery_details("opcua", yaml),
+            Some(Ok(()))
+        );
+
+        let yaml = r#"
+            opcuaDiscoveryMethod:
+              scan:
+                ipRanges: [10.0.0.0/24]
+                ports: [not-a-port]
+        "#;
+        let err = validate_embedded_discovery_details("opcua", yaml)
+            .unwrap()
+            .unwrap_err();
+        assert!(err.contains("ports"), "{}", err);
+    }
+
+    #[cfg(feature = "udev-feat")]
+    #[test]
+    fn test_validate_udev_discovery_details() {
+        let yaml = r#"
+            udevRules:
+            - 'KERNEL=="video[0-9]*"'
+            - 'SUBSYSTEM=="video4linux", ATTR{index}=="0"'
+        "#;
+        assert_eq!(
+            validate_embedded_discovery_details("udev", yaml),
+            Some(Ok(()))
+        );
+
+        let yaml = r#"
+            udevRules:
+            - 'KERNEL=="video[0-9]*"'
+            - 'KERNEL=="video[0-9]*", TYPO=="blah"'
+            - ' KERNEL=="video[0-9]*"'
+        "#;
+        let err = validate_embedded_discovery_details("udev", yaml)
+            .unwrap()
+            .unwrap_err();
+        assert!(!err.contains("udevRules[0]"), "{}", err);
+        assert!(err.contains("discoveryDetails.udevRules[1]: "), "{}", err);
+        assert!(err.contains("discoveryDetails.udevRules[2]: "), "{}", err);
+
+        let err = validate_embedded_discovery_details("udev", "udevRule: []")
+            .unwrap()
+            .unwrap_err();
+        assert!(
+            err.starts_with("discoveryDetails: missing field"),
+            "{}",
+            err
+        );
+    }
+}
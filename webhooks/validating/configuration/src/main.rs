@@ -1,7 +1,20 @@
+mod conversion;
+mod discovery_details;
+mod mutation;
+
 use actix_web::{post, web, App, HttpResponse, HttpServer, Responder};
-use akri_shared::akri::configuration::Configuration;
+use akri_shared::{
+    akri::{
+        configuration::Configuration,
+        discovery_handler::{get_capabilities, DiscoveryHandlerCapabilities},
+    },
+    k8s::KubeImpl,
+};
 use clap::Arg;
+use conversion::review_conversion;
+use discovery_details::validate_embedded_discovery_details;
 use k8s_openapi::apimachinery::pkg::runtime::RawExtension;
+use mutation::{mutate_configuration, ConfigurationDefaults};
 use openapi::models::{
     V1AdmissionRequest as AdmissionRequest, V1AdmissionResponse as AdmissionResponse,
     V1AdmissionReview as AdmissionReview, V1Status as Status,
@@ -108,7 +121,68 @@ fn filter_configuration(mut v: Value) -> Value {
     v
 }
 
-fn validate_configuration(rqst: &AdmissionRequest) -> AdmissionResponse {
+/// Validates the discovery details of a Configuration. The details of embedded Discovery Handlers are deserialized
+/// as these Discovery Handlers do, others are validated against the JSON Schema advertised by their Discovery Handler.
+/// Empty details are then left for the Discovery Handler to reject, as are all details if no schema is known.
+fn validate_discovery_details(
+    discovery_handler_name: &str,
+    discovery_details: &str,
+    capabilities: Option<&DiscoveryHandlerCapabilities>,
+) -> Result<(), String> {
+    if let Some(result) =
+        validate_embedded_discovery_details(discovery_handler_name, discovery_details)
+    {
+        return result;
+    }
+    let schema = match capabilities.and_then(|c| c.discovery_details_schema.as_ref()) {
+        Some(schema) => schema,
+        None => return Ok(()),
+    };
+    if discovery_details.trim().is_empty() {
+        return Ok(());
+    }
+    let schema = match jsonschema::JSONSchema::compile(schema) {
+        Ok(schema) => schema,
+        Err(e) => {
+            println!(
+                "validate_discovery_details - ignoring invalid discovery details schema: {}",
+                e
+            );
+            return Ok(());
+        }
+    };
+    let details: Value = serde_yaml::from_str(discovery_details)
+        .map_err(|e| format!("discoveryDetails is not valid YAML: {}", e))?;
+    schema.validate(&details).map_err(|errors| {
+        errors
+            .map(|e| {
+                format!(
+                    "discoveryDetails{}: {}",
+                    format_instance_path(&e.instance_path),
+                    e
+                )
+            })
+            .collect::<Vec<String>>()
+            .join(", ")
+    })
+}
+
+/// Formats the path of an invalid value like the errors of embedded Discovery Handlers, e.g. `.udevRules[0]`
+fn format_instance_path(instance_path: &jsonschema::paths::JSONPointer) -> String {
+    instance_path
+        .iter()
+        .map(|chunk| match chunk {
+            jsonschema::paths::PathChunk::Index(index) => format!("[{}]", index),
+            jsonschema::paths::PathChunk::Property(property) => format!(".{}", property),
+            jsonschema::paths::PathChunk::Keyword(keyword) => format!(".{}", keyword),
+        })
+        .collect()
+}
+
+fn validate_configuration(
+    rqst: &AdmissionRequest,
+    capabilities: Option<&DiscoveryHandlerCapabilities>,
+) -> AdmissionResponse {
     println!("Validating Configuration");
     match &rqst.object {
         Some(raw) => {
@@ -130,7 +204,20 @@ fn validate_configuration(rqst: &AdmissionRequest) -> AdmissionResponse {
             );
 
             // Do they match?
-            match check(&val, &deserialized) {
+            let validation = check(&val, &deserialized)
+                .map_err(|e| e.to_string())
+                .and_then(|_| match &config.spec.selector {
+                    Some(selector) => selector.validate(),
+                    None => Ok(()),
+                })
+                .and_then(|_| {
+                    validate_discovery_details(
+                        &config.spec.discovery_handler.name,
+                        &config.spec.discovery_handler.discovery_details,
+                        capabilities,
+                    )
+                });
+            match validation {
                 Ok(_) => AdmissionResponse::new(true, rqst.uid.to_owned()),
                 Err(e) => AdmissionResponse {
                     allowed: false,
@@ -142,7 +229,7 @@ fn validate_configuration(rqst: &AdmissionRequest) -> AdmissionResponse {
                         code: None,
                         details: None,
                         kind: None,
-                        message: Some(e.to_string()),
+                        message: Some(e),
                         metadata: None,
                         reason: None,
                         status: None,
@@ -173,13 +260,37 @@ fn validate_configuration(rqst: &AdmissionRequest) -> AdmissionResponse {
     }
 }
 
+/// Gets the capabilities of the Discovery Handler a Configuration uses, as published by the Agents.
+/// Returns None if they are unknown, in which case the discovery details are not validated.
+async fn get_discovery_handler_capabilities(
+    kube_client: Option<&KubeImpl>,
+    rqst: &AdmissionRequest,
+) -> Option<DiscoveryHandlerCapabilities> {
+    let name = rqst.object.as_ref()?["spec"]["discoveryHandler"]["name"].as_str()?;
+    match get_capabilities(kube_client?, name).await {
+        Ok(capabilities) => capabilities,
+        Err(e) => {
+            println!(
+                "get_discovery_handler_capabilities - failed to get capabilities of {}: {}",
+                name, e
+            );
+            None
+        }
+    }
+}
+
 #[post("/validate")]
-async fn validate(rqst: web::Json<AdmissionReview>) -> impl Responder {
+async fn validate(
+    rqst: web::Json<AdmissionReview>,
+    kube_client: Option<web::Data<KubeImpl>>,
+) -> impl Responder {
     println!("Handler invoked");
     match &rqst.request {
         Some(rqst) => {
             println!("Handler received: AdmissionRequest");
-            let resp = validate_configuration(rqst);
+            let capabilities =
+                get_discovery_handler_capabilities(kube_client.as_deref(), rqst).await;
+            let resp = validate_configuration(rqst, capabilities.as_ref());
             let resp: AdmissionReview = AdmissionReview {
                 api_version: Some("admission.k8s.io/v1".to_owned()),
                 kind: Some("AdmissionReview".to_owned()),
@@ -196,6 +307,47 @@ async fn validate(rqst: web::Json<AdmissionReview>) -> impl Responder {
     }
 }
 
+#[post("/mutate")]
+async fn mutate(
+    rqst: web::Json<AdmissionReview>,
+    defaults: web::Data<ConfigurationDefaults>,
+) -> impl Responder {
+    println!("Mutation handler invoked");
+    match &rqst.request {
+        Some(rqst) => {
+            println!("Mutation handler received: AdmissionRequest");
+            let resp = mutate_configuration(rqst, &defaults);
+            let resp: AdmissionReview = AdmissionReview {
+                api_version: Some("admission.k8s.io/v1".to_owned()),
+                kind: Some("AdmissionReview".to_owned()),
+                request: None,
+                response: Some(resp),
+            };
+            let body = serde_json::to_string(&resp).expect("Valid AdmissionReview");
+            HttpResponse::Ok().body(body)
+        }
+        None => {
+            println!("Mutation handler received: Nothing");
+            HttpResponse::BadRequest().body("")
+        }
+    }
+}
+
+#[post("/convert")]
+async fn convert(review: web::Json<Value>) -> impl Responder {
+    println!("Conversion handler invoked");
+    match review_conversion(&review) {
+        Some(resp) => {
+            let body = serde_json::to_string(&resp).expect("Valid ConversionReview");
+            HttpResponse::Ok().body(body)
+        }
+        None => {
+            println!("Conversion handler received: Nothing");
+            HttpResponse::BadRequest().body("")
+        }
+    }
+}
+
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
     let matches = clap::Command::new("Akri Webhook")
@@ -219,6 +371,12 @@ async fn main() -> std::io::Result<()> {
                 .required(true)
                 .help("port"),
         )
+        .arg(
+            Arg::new("default_discovery_timeout_seconds")
+                .long("default-discovery-timeout-seconds")
+                .value_parser(clap::value_parser!(u64))
+                .help("discoveryTimeoutSeconds set in the Configurations that don't set it"),
+        )
         .get_matches();
 
     let crt_file = matches
@@ -234,14 +392,37 @@ async fn main() -> std::io::Result<()> {
         .get_one::<u16>("port")
         .expect("valid port [0-65535]");
 
+    let defaults = web::Data::new(ConfigurationDefaults {
+        discovery_timeout_seconds: matches
+            .get_one::<u64>("default_discovery_timeout_seconds")
+            .copied(),
+    });
+
     let endpoint = format!("0.0.0.0:{}", port);
     println!("Started Webhook server: {}", endpoint);
 
+    // Without access to the cluster, discovery details are not validated against the Discovery Handlers' schemas
+    let kube_client = match KubeImpl::new().await {
+        Ok(kube_client) => Some(web::Data::new(kube_client)),
+        Err(e) => {
+            println!("Failed to create Kubernetes client: {}", e);
+            None
+        }
+    };
     let builder = get_builder(key_file, crt_file);
-    HttpServer::new(|| App::new().service(validate))
-        .bind_openssl(endpoint, builder)?
-        .run()
-        .await
+    HttpServer::new(move || {
+        let app = App::new().app_data(defaults.clone());
+        match kube_client.clone() {
+            Some(kube_client) => app.app_data(kube_client),
+            None => app,
+        }
+        .service(validate)
+        .service(mutate)
+        .service(convert)
+    })
+    .bind_openssl(endpoint, builder)?
+    .run()
+    .await
 }
 
 #[cfg(test)]
@@ -805,7 +986,7 @@ mod tests {
             serde_json::from_str(&get_valid_admission_review_with_broker_pod_spec())
                 .expect("v1.AdmissionReview JSON");
         let rqst = valid.request.expect("v1.AdmissionRequest JSON");
-        let resp = validate_configuration(&rqst);
+        let resp = validate_configuration(&rqst, None);
         assert!(resp.allowed);
     }
 
@@ -815,7 +996,7 @@ mod tests {
             serde_json::from_str(&get_valid_admission_review_with_broker_job_spec())
                 .expect("v1.AdmissionReview JSON");
         let rqst = valid.request.expect("v1.AdmissionRequest JSON");
-        let resp = validate_configuration(&rqst);
+        let resp = validate_configuration(&rqst, None);
         assert!(resp.allowed);
     }
 
@@ -825,7 +1006,7 @@ mod tests {
             serde_json::from_str(&get_invalid_admission_review_with_broker_pod_spec())
                 .expect("v1.AdmissionReview JSON");
         let rqst = invalid.request.expect("v1.AdmissionRequest JSON");
-        let resp = validate_configuration(&rqst);
+        let resp = validate_configuration(&rqst, None);
         assert!(!resp.allowed);
     }
 
@@ -835,7 +1016,7 @@ mod tests {
             serde_json::from_str(&get_invalid_admission_review_with_broker_job_spec())
                 .expect("v1.AdmissionReview JSON");
         let rqst = invalid.request.expect("v1.AdmissionRequest JSON");
-        let resp = validate_configuration(&rqst);
+        let resp = validate_configuration(&rqst, None);
         assert!(!resp.allowed);
     }
 
@@ -846,7 +1027,7 @@ mod tests {
             serde_json::from_str(&get_invalid_admission_review_with_broker_job_and_pod_spec())
                 .expect("v1.AdmissionReview JSON");
         let rqst = invalid.request.expect("v1.AdmissionRequest JSON");
-        validate_configuration(&rqst);
+        validate_configuration(&rqst, None);
     }
 
     #[test]
@@ -855,7 +1036,7 @@ mod tests {
             serde_json::from_str(&get_extended_admission_review_with_broker_pod_spec())
                 .expect("v1.AdmissionReview JSON");
         let rqst = valid.request.expect("v1.AdmissionRequest JSON");
-        let resp = validate_configuration(&rqst);
+        let resp = validate_configuration(&rqst, None);
         assert!(resp.allowed);
     }
 
@@ -1076,6 +1257,136 @@ mod tests {
         run_validate_configuration_discovery_properties(discovery_properties);
     }
 
+    fn debug_echo_capabilities() -> DiscoveryHandlerCapabilities {
+        DiscoveryHandlerCapabilities {
+            discovery_details_schema: Some(json!({
+                "type": "object",
+                "required": ["descriptions"],
+                "properties": {
+                    "descriptions": {
+                        "type": "array",
+                        "items": { "type": "string" }
+                    }
+                }
+            })),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_validate_discovery_details() {
+        let capabilities = debug_echo_capabilities();
+        let validate = |details: &str, capabilities: Option<&DiscoveryHandlerCapabilities>| {
+            validate_discovery_details("custom", details, capabilities)
+        };
+        assert!(validate("descriptions: [foo]", Some(&capabilities)).is_ok());
+        // Nothing to validate against
+        assert!(validate("descriptions: 1", None).is_ok());
+        assert!(validate(
+            "descriptions: 1",
+            Some(&DiscoveryHandlerCapabilities::default())
+        )
+        .is_ok());
+        // Empty details are left to the Discovery Handler
+        assert!(validate("", Some(&capabilities)).is_ok());
+
+        let err = validate("descriptions: [foo, 1]", Some(&capabilities)).unwrap_err();
+        assert!(
+            err.starts_with("discoveryDetails.descriptions[1]: "),
+            "{}",
+            err
+        );
+        let err = validate("other: true", Some(&capabilities)).unwrap_err();
+        assert!(err.contains("descriptions"), "{}", err);
+        let err = validate("descriptions: [", Some(&capabilities)).unwrap_err();
+        assert!(
+            err.starts_with("discoveryDetails is not valid YAML"),
+            "{}",
+            err
+        );
+    }
+
+    #[cfg(feature = "debug-echo-feat")]
+    #[test]
+    fn test_validate_discovery_details_embedded() {
+        // The details of embedded Discovery Handlers are validated even without a schema
+        let err = validate_discovery_details("debugEcho", "descriptions: foo", None).unwrap_err();
+        assert!(
+            err.starts_with("discoveryDetails.descriptions: "),
+            "{}",
+            err
+        );
+        // and regardless of the schema, their Discovery Handler being the authority
+        let capabilities = DiscoveryHandlerCapabilities {
+            discovery_details_schema: Some(json!({"required": ["udevRules"]})),
+            ..Default::default()
+        };
+        assert!(validate_discovery_details(
+            "debugEcho",
+            "descriptions: [foo]",
+            Some(&capabilities)
+        )
+        .is_ok());
+        // Unknown fields are rejected with their path
+        assert_eq!(
+            validate_discovery_details("debugEcho", "descriptions: [foo]\ndescription: foo", None),
+            Err("discoveryDetails.description: unknown field".to_string())
+        );
+    }
+
+    #[test]
+    fn test_validate_configuration_discovery_details_schema() {
+        // A Discovery Handler the webhook does not embed
+        let valid: AdmissionReview = serde_json::from_str(
+            &get_valid_admission_review_with_broker_pod_spec()
+                .replace(r#""name": "debugEcho""#, r#""name": "custom""#),
+        )
+        .expect("v1.AdmissionReview JSON");
+        let rqst = valid.request.expect("v1.AdmissionRequest JSON");
+        let resp = validate_configuration(&rqst, Some(&debug_echo_capabilities()));
+        assert!(resp.allowed);
+
+        let capabilities = DiscoveryHandlerCapabilities {
+            discovery_details_schema: Some(json!({
+                "type": "object",
+                "required": ["udevRules"]
+            })),
+            ..Default::default()
+        };
+        let resp = validate_configuration(&rqst, Some(&capabilities));
+        assert!(!resp.allowed);
+        assert!(resp.status.unwrap().message.unwrap().contains("udevRules"));
+    }
+
+    #[test]
+    fn test_validate_configuration_selector() {
+        let review = |values: &str| -> AdmissionRequest {
+            let review: AdmissionReview = serde_json::from_str(
+                &get_valid_admission_review_with_broker_pod_spec().replace(
+                    r#""brokerSpec": {"#,
+                    &format!(
+                        r#""selector": {{"matchExpressions": [{{"key": "DEBUG_ECHO_DESCRIPTION", "operator": "Matches", "values": {}}}]}},
+                    "brokerSpec": {{"#,
+                        values
+                    ),
+                ),
+            )
+            .expect("v1.AdmissionReview JSON");
+            review.request.expect("v1.AdmissionRequest JSON")
+        };
+        let resp = validate_configuration(&review(r#"["foo[0-9]"]"#), None);
+        assert!(resp.allowed);
+
+        let resp = validate_configuration(&review(r#"["foo[0-9]", "foo("]"#), None);
+        assert!(!resp.allowed);
+        assert!(resp
+            .status
+            .unwrap()
+            .message
+            .unwrap()
+            .starts_with("selector.matchExpressions[0].values[1]"));
+    }
+
     fn run_validate_configuration_discovery_properties(
         discovery_properties: &str,
     ) -> AdmissionResponse {
@@ -1084,7 +1395,7 @@ mod tests {
         )
         .expect("v1.AdmissionReview JSON");
         let rqst = valid.request.expect("v1.AdmissionRequest JSON");
-        validate_configuration(&rqst)
+        validate_configuration(&rqst, None)
     }
 
     #[actix_web::test]
@@ -1142,4 +1453,61 @@ mod tests {
         let resp = actix_web::test::call_service(&app, rqst).await;
         assert!(resp.status().is_success());
     }
+
+    #[actix_web::test]
+    async fn test_mutate_valid_podspec() {
+        let app = actix_web::test::init_service(
+            App::new()
+                .app_data(web::Data::new(ConfigurationDefaults::default()))
+                .service(mutate),
+        )
+        .await;
+        let valid: AdmissionReview =
+            serde_json::from_str(&get_valid_admission_review_with_broker_pod_spec())
+                .expect("v1.AdmissionReview JSON");
+        let rqst = actix_web::test::TestRequest::post()
+            .uri("/mutate")
+            .set_json(&valid)
+            .to_request();
+        let resp: AdmissionReview = actix_web::test::call_and_read_body_json(&app, rqst).await;
+        let resp = resp.response.expect("v1.AdmissionResponse");
+        assert!(resp.allowed);
+        assert_eq!(resp.patch_type.as_deref(), Some("JSONPatch"));
+    }
+
+    #[actix_web::test]
+    async fn test_convert_instance() {
+        let app = actix_web::test::init_service(App::new().service(convert)).await;
+        let review = json!({
+            "apiVersion": "apiextensions.k8s.io/v1",
+            "kind": "ConversionReview",
+            "request": {
+                "uid": "705ab4f5-6393-11e8-b7cc-42010a800002",
+                "desiredAPIVersion": "akri.sh/v1",
+                "objects": [{
+                    "apiVersion": "akri.sh/v0",
+                    "kind": "Instance",
+                    "metadata": {"name": "config-a-b494b6"},
+                    "spec": {"deviceUsage": {"config-a-b494b6-0": "C:0:node-a"}}
+                }]
+            }
+        });
+        let rqst = actix_web::test::TestRequest::post()
+            .uri("/convert")
+            .set_json(&review)
+            .to_request();
+        let resp: Value = actix_web::test::call_and_read_body_json(&app, rqst).await;
+        assert_eq!(resp["response"]["result"]["status"], "Success");
+        assert_eq!(
+            resp["response"]["convertedObjects"][0]["spec"]["deviceUsage"]["config-a-b494b6-0"],
+            json!({"kind": "Configuration", "node": "node-a", "vdev": "0"})
+        );
+
+        let rqst = actix_web::test::TestRequest::post()
+            .uri("/convert")
+            .set_json(json!({"kind": "ConversionReview"}))
+            .to_request();
+        let resp = actix_web::test::call_service(&app, rqst).await;
+        assert!(resp.status().is_client_error());
+    }
 }
@@ -0,0 +1,397 @@
+//! Mutation of the Configurations before they get validated and persisted: defaults are made explicit, discovery
+//! details are normalized and broker workloads get the labels and resources Akri expects.
+//!
+//! The changes are returned to the API server as a JSONPatch computed between the received and the mutated object.
+use akri_shared::{
+    akri::configuration::{CONFIGURATION_FINALIZER, DEFAULT_CAPACITY},
+    k8s::{pod::AKRI_CONFIGURATION_LABEL_NAME, RESOURCE_REQUIREMENTS_KEY},
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use openapi::models::{
+    V1AdmissionRequest as AdmissionRequest, V1AdmissionResponse as AdmissionResponse,
+};
+use serde_json::{json, Map, Value};
+
+/// Defaults the webhook fills in Configurations that don't set them
+#[derive(Clone, Debug, Default)]
+pub struct ConfigurationDefaults {
+    /// Maximum duration of a discovery scan, left to the Discovery Handlers if None
+    pub discovery_timeout_seconds: Option<u64>,
+}
+
+/// Mutates a Configuration, returning the changes as a JSONPatch.
+///
+/// The `discoveryHandler` is immutable, so it is only defaulted and normalized on creation, as is the finalizer
+/// added since it must not be added back once the Controller removed it.
+pub fn mutate_configuration(
+    rqst: &AdmissionRequest,
+    defaults: &ConfigurationDefaults,
+) -> AdmissionResponse {
+    println!("Mutating Configuration");
+    let mut response = AdmissionResponse::new(true, rqst.uid.to_owned());
+    let original = match &rqst.object {
+        Some(object) => object,
+        None => return response,
+    };
+    let mut mutated = original.clone();
+    let create = rqst.operation == "CREATE";
+    if create {
+        add_finalizer(&mut mutated);
+        default_discovery_handler(&mut mutated, defaults);
+    }
+    default_spec(&mut mutated);
+    let name = mutated["metadata"]["name"]
+        .as_str()
+        .or(rqst.name.as_deref())
+        .map(|name| name.to_string());
+    mutate_broker_spec(&mut mutated, name.as_deref());
+
+    let mut patch = Vec::new();
+    diff("", original, &mutated, &mut patch);
+    if !patch.is_empty() {
+        println!("mutate_configuration - patch: {:?}", patch);
+        response.patch = Some(STANDARD.encode(Value::Array(patch).to_string()));
+        response.patch_type = Some("JSONPatch".to_owned());
+    }
+    response
+}
+
+fn add_finalizer(configuration: &mut Value) {
+    let finalizers = &mut configuration["metadata"]["finalizers"];
+    if !finalizers.is_array() {
+        *finalizers = json!([]);
+    }
+    let finalizers = finalizers.as_array_mut().unwrap();
+    if !finalizers.contains(&json!(CONFIGURATION_FINALIZER)) {
+        finalizers.push(json!(CONFIGURATION_FINALIZER));
+    }
+}
+
+// The mutated objects are only ever indexed mutably once known to exist, as indexing a missing key inserts it
+fn default_spec(configuration: &mut Value) {
+    if let Some(spec) = configuration
+        .get_mut("spec")
+        .filter(|spec| spec.is_object())
+    {
+        if spec["capacity"].is_null() {
+            spec["capacity"] = json!(DEFAULT_CAPACITY);
+        }
+    }
+}
+
+fn default_discovery_handler(configuration: &mut Value, defaults: &ConfigurationDefaults) {
+    let discovery_handler = match configuration
+        .pointer_mut("/spec/discoveryHandler")
+        .filter(|discovery_handler| discovery_handler.is_object())
+    {
+        Some(discovery_handler) => discovery_handler,
+        None => return,
+    };
+    if let Some(timeout) = defaults.discovery_timeout_seconds {
+        if discovery_handler["discoveryTimeoutSeconds"].is_null() {
+            discovery_handler["discoveryTimeoutSeconds"] = json!(timeout);
+        }
+    }
+    let name = discovery_handler["name"].as_str().unwrap_or_default();
+    let normalized = discovery_handler["discoveryDetails"]
+        .as_str()
+        .and_then(|details| normalize_discovery_details(name, details));
+    if let Some(normalized) = normalized {
+        discovery_handler["discoveryDetails"] = json!(normalized);
+    }
+}
+
+/// Rewrites discovery details as canonical YAML, with sorted keys and the defaults of the udev Discovery Handler made
+/// explicit. Returns None if the details are empty or not valid YAML, invalid details being left for validation.
+fn normalize_discovery_details(discovery_handler_name: &str, details: &str) -> Option<String> {
+    if details.trim().is_empty() {
+        return None;
+    }
+    let mut details: serde_yaml::Value = serde_yaml::from_str(details).ok()?;
+    if discovery_handler_name == "udev" {
+        if let Some(details) = details.as_mapping_mut() {
+            details
+                .entry("groupRecursive".into())
+                .or_insert(serde_yaml::Value::Bool(false));
+        }
+    }
+    sort_keys(&mut details);
+    serde_yaml::to_string(&details).ok()
+}
+
+fn sort_keys(value: &mut serde_yaml::Value) {
+    match value {
+        serde_yaml::Value::Mapping(mapping) => {
+            let mut entries: Vec<(serde_yaml::Value, serde_yaml::Value)> =
+                std::mem::take(mapping).into_iter().collect();
+            entries.sort_by_cached_key(|(key, _)| serde_yaml::to_string(key).unwrap_or_default());
+            for (key, mut value) in entries {
+                sort_keys(&mut value);
+                mapping.insert(key, value);
+            }
+        }
+        serde_yaml::Value::Sequence(sequence) => sequence.iter_mut().for_each(sort_keys),
+        serde_yaml::Value::Tagged(tagged) => sort_keys(&mut tagged.value),
+        _ => {}
+    }
+}
+
+/// Labels the Pods of a broker Job with the Configuration, and makes sure a container of the broker requests the
+/// device. The Pods of a broker PodSpec get labeled by the Controller when it creates them.
+fn mutate_broker_spec(configuration: &mut Value, configuration_name: Option<&str>) {
+    if let Some(pod_spec) = configuration
+        .pointer_mut("/spec/brokerSpec/brokerPodSpec")
+        .filter(|pod_spec| pod_spec.is_object())
+    {
+        request_device(pod_spec);
+    } else if let Some(template) = configuration
+        .pointer_mut("/spec/brokerSpec/brokerJobSpec/template")
+        .filter(|template| template.is_object())
+    {
+        if let Some(name) = configuration_name {
+            let labels = &mut template["metadata"]["labels"];
+            if labels[AKRI_CONFIGURATION_LABEL_NAME].is_null() {
+                labels[AKRI_CONFIGURATION_LABEL_NAME] = json!(name);
+            }
+        }
+        if let Some(pod_spec) = template.get_mut("spec") {
+            request_device(pod_spec);
+        }
+    }
+}
+
+/// Requests one device in the first container of a broker, unless one of its containers already does
+fn request_device(pod_spec: &mut Value) {
+    let requests_device = |container: &Value| {
+        ["limits", "requests"]
+            .iter()
+            .any(|kind| !container["resources"][*kind][RESOURCE_REQUIREMENTS_KEY].is_null())
+    };
+    let containers = pod_spec["containers"].as_array().into_iter().flatten();
+    let init_containers = pod_spec["initContainers"].as_array().into_iter().flatten();
+    if containers.chain(init_containers).any(requests_device) {
+        return;
+    }
+    if let Some(container) = pod_spec
+        .get_mut("containers")
+        .and_then(|containers| containers.as_array_mut())
+        .and_then(|containers| containers.first_mut())
+    {
+        for kind in ["limits", "requests"] {
+            container["resources"][kind][RESOURCE_REQUIREMENTS_KEY] = json!("1");
+        }
+    }
+}
+
+/// Appends to `patch` the JSONPatch operations turning `original` into `mutated`. Arrays of the same length are
+/// compared item by item, others replaced as a whole.
+fn diff(path: &str, original: &Value, mutated: &Value, patch: &mut Vec<Value>) {
+    match (original, mutated) {
+        (Value::Object(original), Value::Object(mutated)) => {
+            diff_objects(path, original, mutated, patch)
+        }
+        (Value::Array(original), Value::Array(mutated)) if original.len() == mutated.len() => {
+            for (i, (original, mutated)) in original.iter().zip(mutated).enumerate() {
+                diff(&format!("{}/{}", path, i), original, mutated, patch);
+            }
+        }
+        (original, mutated) if original != mutated => {
+            patch.push(json!({"op": "replace", "path": path, "value": mutated}))
+        }
+        _ => {}
+    }
+}
+
+fn diff_objects(
+    path: &str,
+    original: &Map<String, Value>,
+    mutated: &Map<String, Value>,
+    patch: &mut Vec<Value>,
+) {
+    for (key, original_value) in original {
+        let key_path = format!("{}/{}", path, escape_key(key));
+        match mutated.get(key) {
+            Some(mutated_value) => diff(&key_path, original_value, mutated_value, patch),
+            None => patch.push(json!({"op": "remove", "path": key_path})),
+        }
+    }
+    for (key, mutated_value) in mutated {
+        if !original.contains_key(key) {
+            let key_path = format!("{}/{}", path, escape_key(key));
+            patch.push(json!({"op": "add", "path": key_path, "value": mutated_value}));
+        }
+    }
+}
+
+/// Escapes a key as a JSON Pointer reference token (RFC 6901)
+fn escape_key(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(operation: &str, object: Value) -> AdmissionRequest {
+        serde_json::from_value(json!({
+            "uid": "00000000-0000-0000-0000-000000000000",
+            "kind": {"group": "akri.sh", "version": "v0", "kind": "Configuration"},
+            "resource": {"group": "akri.sh", "version": "v0", "resource": "configurations"},
+            "name": "name",
+            "namespace": "default",
+            "operation": operation,
+            "userInfo": {"username": "admin", "uid": "admin", "groups": []},
+            "object": object,
+        }))
+        .expect("v1.AdmissionRequest JSON")
+    }
+
+    fn decode_patch(response: &AdmissionResponse) -> Value {
+        assert_eq!(response.patch_type.as_deref(), Some("JSONPatch"));
+        let patch = STANDARD
+            .decode(response.patch.as_ref().expect("patch"))
+            .expect("base64 patch");
+        serde_json::from_slice(&patch).expect("JSON patch")
+    }
+
+    #[test]
+    fn test_diff() {
+        let original = json!({"a": 1, "b": {"c": [1, 2]}, "d": [1], "e": "x"});
+        let mutated = json!({"a": 1, "b": {"c": [1, 3], "f/g": true}, "d": [1, 2]});
+        let mut patch = Vec::new();
+        diff("", &original, &mutated, &mut patch);
+        assert_eq!(
+            Value::Array(patch),
+            json!([
+                {"op": "replace", "path": "/b/c/1", "value": 3},
+                {"op": "add", "path": "/b/f~1g", "value": true},
+                {"op": "replace", "path": "/d", "value": [1, 2]},
+                {"op": "remove", "path": "/e"},
+            ])
+        );
+    }
+
+    #[test]
+    fn test_mutate_configuration_create() {
+        let configuration = json!({
+            "apiVersion": "akri.sh/v0",
+            "kind": "Configuration",
+            "metadata": {"name": "name", "namespace": "default"},
+            "spec": {
+                "discoveryHandler": {
+                    "name": "udev",
+                    "discoveryDetails": "udevRules:\n- 'KERNEL==\"video[0-9]*\"'\n"
+                },
+                "brokerSpec": {
+                    "brokerPodSpec": {
+                        "containers": [{"name": "broker", "image": "nginx"}]
+                    }
+                }
+            }
+        });
+        let defaults = ConfigurationDefaults {
+            discovery_timeout_seconds: Some(30),
+        };
+        let response = mutate_configuration(&request("CREATE", configuration), &defaults);
+        assert!(response.allowed);
+        assert_eq!(
+            decode_patch(&response),
+            json!([
+                {"op": "add", "path": "/metadata/finalizers", "value": ["akri.sh"]},
+                {
+                    "op": "add",
+                    "path": "/spec/brokerSpec/brokerPodSpec/containers/0/resources",
+                    "value": {
+                        "limits": {"{{PLACEHOLDER}}": "1"},
+                        "requests": {"{{PLACEHOLDER}}": "1"}
+                    }
+                },
+                {
+                    "op": "replace",
+                    "path": "/spec/discoveryHandler/discoveryDetails",
+                    "value": "groupRecursive: false\nudevRules:\n- KERNEL==\"video[0-9]*\"\n"
+                },
+                {"op": "add", "path": "/spec/discoveryHandler/discoveryTimeoutSeconds", "value": 30},
+                {"op": "add", "path": "/spec/capacity", "value": 1},
+            ])
+        );
+    }
+
+    #[test]
+    fn test_mutate_configuration_job() {
+        let configuration = json!({
+            "metadata": {"name": "name", "finalizers": ["other"]},
+            "spec": {
+                "discoveryHandler": {"name": "debugEcho", "discoveryDetails": ""},
+                "capacity": 2,
+                "brokerSpec": {
+                    "brokerJobSpec": {
+                        "template": {
+                            "spec": {
+                                "containers": [
+                                    {"name": "a"},
+                                    {"name": "b", "resources": {"limits": {"{{PLACEHOLDER}}": "1"}}}
+                                ]
+                            }
+                        }
+                    }
+                }
+            }
+        });
+        let response = mutate_configuration(
+            &request("CREATE", configuration),
+            &ConfigurationDefaults::default(),
+        );
+        assert_eq!(
+            decode_patch(&response),
+            json!([
+                {"op": "replace", "path": "/metadata/finalizers", "value": ["other", "akri.sh"]},
+                {
+                    "op": "add",
+                    "path": "/spec/brokerSpec/brokerJobSpec/template/metadata",
+                    "value": {"labels": {"akri.sh/configuration": "name"}}
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn test_mutate_configuration_update() {
+        // Neither the discovery handler nor the finalizers are touched on update
+        let configuration = json!({
+            "metadata": {"name": "name"},
+            "spec": {
+                "discoveryHandler": {"name": "udev", "discoveryDetails": "udevRules: []"},
+                "capacity": 1
+            }
+        });
+        let response = mutate_configuration(
+            &request("UPDATE", configuration),
+            &ConfigurationDefaults {
+                discovery_timeout_seconds: Some(30),
+            },
+        );
+        assert!(response.allowed);
+        assert_eq!(response.patch, None);
+        assert_eq!(response.patch_type, None);
+    }
+
+    #[test]
+    fn test_normalize_discovery_details() {
+        assert_eq!(normalize_discovery_details("debugEcho", ""), None);
+        assert_eq!(normalize_discovery_details("debugEcho", "a: ["), None);
+        assert_eq!(
+            normalize_discovery_details("custom", "b: {d: 1, c: 2}\na: [x, y]").as_deref(),
+            Some("a:\n- x\n- y\nb:\n  c: 2\n  d: 1\n")
+        );
+        // Only udev details get a default groupRecursive
+        assert_eq!(
+            normalize_discovery_details("udev", "groupRecursive: true\nudevRules: []").as_deref(),
+            Some("groupRecursive: true\nudevRules: []\n")
+        );
+        assert_eq!(
+            normalize_discovery_details("custom", "udevRules: []").as_deref(),
+            Some("udevRules: []\n")
+        );
+    }
+}